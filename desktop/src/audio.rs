@@ -2,6 +2,7 @@ use std::f32::consts::PI;
 
 use anyhow::anyhow;
 use anyhow::Result;
+use nes::apu::expansion::SAMPLE_RATE;
 use nes::apu::AudioChannel;
 use web_audio_api::context::AudioContext;
 use web_audio_api::context::BaseAudioContext;
@@ -23,6 +24,9 @@ pub struct NesAudio {
     p1_gain: GainNode,
     p2_gain: GainNode,
     tri_gain: GainNode,
+    output_gain: GainNode,
+    // when the last queued batch of expansion audio ends
+    samples_end: f64,
 }
 
 impl Default for NesAudio {
@@ -72,6 +76,8 @@ impl Default for NesAudio {
             p1_gain,
             p2_gain,
             tri_gain,
+            output_gain,
+            samples_end: 0.,
         }
     }
 }
@@ -145,6 +151,21 @@ impl ::nes::nesaudio::NesAudio for NesAudio {
         }
         Ok(())
     }
+
+    fn play_samples(&mut self, samples: &[f32]) -> Result<()> {
+        let mut buffer = self
+            .context
+            .create_buffer(1, samples.len(), SAMPLE_RATE as f32);
+        buffer.copy_to_channel(samples, 0);
+        let source = self.context.create_buffer_source();
+        source.set_buffer(buffer);
+        source.connect(&self.output_gain);
+        // play right after the previous batch, or now if we fell behind
+        let start = self.samples_end.max(self.context.current_time());
+        source.start_at(start);
+        self.samples_end = start + samples.len() as f64 / SAMPLE_RATE as f64;
+        Ok(())
+    }
 }
 
 fn set_duty_cycle(cx: &AudioContext, pulse: &OscillatorNode, dc: f32) {
@@ -1,3 +1,6 @@
+use std::fs;
+use std::path::Path;
+
 use ::nes::nesaudio::NesAudio;
 use ::nes::nesscreen::NesScreen;
 use ::nes::Nes;
@@ -6,10 +9,23 @@ use anyhow::Context;
 use anyhow::Result;
 use nes::buscpu;
 use nes::busppu;
+use nes::cartridge;
+use nes::cdl;
+use nes::cheats;
+use nes::cheats::GameGenie;
+use nes::cheats::RamCheat;
+use nes::cheats::SearchFilter;
+use nes::cheats::Watch;
+use nes::cheats::WatchType;
 use nes::cpu::Cpu;
 use nes::ppu::Ppu;
 use regex::Regex;
-use rs6502::Disassembler;
+
+use crate::dbg::debugger::Breakpoint;
+use crate::dbg::debugger::Debugger;
+
+// Candidates of the cheat search, only printed once there are few of them
+const SEARCH_LIST_MAX: usize = 32;
 
 #[derive(Debug)]
 pub enum Command {
@@ -18,6 +34,40 @@ pub enum Command {
     CpuMemory(u16, u16),
     PpuMemory(u16, u16),
     PpuOam,
+    Banks,
+    LoadSymbols(String),
+    Break(String),
+    Breakpoints,
+    Delete(usize),
+    Continue,
+    Pause,
+    Step,
+    Next,
+    Finish,
+    Backtrace,
+    Trace,
+    CdlStatus,
+    CdlStart,
+    CdlStop,
+    CdlSave(String),
+    CdlLoad(String),
+    DiskStatus,
+    DiskEject,
+    DiskInsert(usize),
+    GenieList,
+    GenieAdd(String),
+    GenieEnable(usize, bool),
+    GenieDelete(usize),
+    RamList,
+    RamAdd(String),
+    RamEnable(usize, bool),
+    RamDelete(usize),
+    SearchList,
+    SearchStart,
+    SearchFilter(SearchFilter),
+    WatchList,
+    WatchAdd(u16, WatchType, String),
+    WatchDelete(usize),
 }
 
 pub fn parse(s: &str) -> Result<Command> {
@@ -58,12 +108,122 @@ pub fn parse(s: &str) -> Result<Command> {
         Ok(Command::PpuMemory(addr_start, addr_end))
     } else if Regex::new(r"^oam\n?$")?.is_match(s) {
         Ok(Command::PpuOam)
+    } else if Regex::new(r"^banks\n?$")?.is_match(s) {
+        Ok(Command::Banks)
+    } else if Regex::new(r"^sym \S.*\n?$")?.is_match(s) {
+        Ok(Command::LoadSymbols(s[4..].trim().to_string()))
+    } else if Regex::new(r"^break \S+\n?$")?.is_match(s) {
+        Ok(Command::Break(s[6..].trim().to_string()))
+    } else if Regex::new(r"^breaks\n?$")?.is_match(s) {
+        Ok(Command::Breakpoints)
+    } else if Regex::new(r"^delete \d+\n?$")?.is_match(s) {
+        let idx = s[7..].trim().parse().context("Invalid delete args")?;
+        Ok(Command::Delete(idx))
+    } else if Regex::new(r"^(c|continue)\n?$")?.is_match(s) {
+        Ok(Command::Continue)
+    } else if Regex::new(r"^pause\n?$")?.is_match(s) {
+        Ok(Command::Pause)
+    } else if Regex::new(r"^(s|step)\n?$")?.is_match(s) {
+        Ok(Command::Step)
+    } else if Regex::new(r"^(n|next)\n?$")?.is_match(s) {
+        Ok(Command::Next)
+    } else if Regex::new(r"^finish\n?$")?.is_match(s) {
+        Ok(Command::Finish)
+    } else if Regex::new(r"^bt\n?$")?.is_match(s) {
+        Ok(Command::Backtrace)
+    } else if Regex::new(r"^trace\n?$")?.is_match(s) {
+        Ok(Command::Trace)
+    } else if Regex::new(r"^cdl\n?$")?.is_match(s) {
+        Ok(Command::CdlStatus)
+    } else if Regex::new(r"^cdl on\n?$")?.is_match(s) {
+        Ok(Command::CdlStart)
+    } else if Regex::new(r"^cdl off\n?$")?.is_match(s) {
+        Ok(Command::CdlStop)
+    } else if Regex::new(r"^cdl save \S.*\n?$")?.is_match(s) {
+        Ok(Command::CdlSave(s[9..].trim().to_string()))
+    } else if Regex::new(r"^cdl load \S.*\n?$")?.is_match(s) {
+        Ok(Command::CdlLoad(s[9..].trim().to_string()))
+    } else if Regex::new(r"^disk\n?$")?.is_match(s) {
+        Ok(Command::DiskStatus)
+    } else if Regex::new(r"^disk eject\n?$")?.is_match(s) {
+        Ok(Command::DiskEject)
+    } else if Regex::new(r"^disk insert \d+\n?$")?.is_match(s) {
+        let side = s[12..].trim().parse().context("Invalid disk insert args")?;
+        Ok(Command::DiskInsert(side))
+    } else if Regex::new(r"^genie\n?$")?.is_match(s) {
+        Ok(Command::GenieList)
+    } else if Regex::new(r"^genie add [a-zA-Z]+\n?$")?.is_match(s) {
+        Ok(Command::GenieAdd(s[10..].trim().to_string()))
+    } else if Regex::new(r"^genie (on|off) \d+\n?$")?.is_match(s) {
+        let args = s.split_whitespace().collect::<Vec<&str>>();
+        let idx = args[2].parse().context("Invalid genie args")?;
+        Ok(Command::GenieEnable(idx, args[1] == "on"))
+    } else if Regex::new(r"^genie delete \d+\n?$")?.is_match(s) {
+        let idx = s[13..]
+            .trim()
+            .parse()
+            .context("Invalid genie delete args")?;
+        Ok(Command::GenieDelete(idx))
+    } else if Regex::new(r"^ram\n?$")?.is_match(s) {
+        Ok(Command::RamList)
+    } else if Regex::new(r"^ram add [a-fA-F\d]{4}:?[a-fA-F\d]{2}\n?$")?.is_match(s) {
+        Ok(Command::RamAdd(s[8..].trim().to_string()))
+    } else if Regex::new(r"^ram (on|off) \d+\n?$")?.is_match(s) {
+        let args = s.split_whitespace().collect::<Vec<&str>>();
+        let idx = args[2].parse().context("Invalid ram args")?;
+        Ok(Command::RamEnable(idx, args[1] == "on"))
+    } else if Regex::new(r"^ram delete \d+\n?$")?.is_match(s) {
+        let idx = s[11..].trim().parse().context("Invalid ram delete args")?;
+        Ok(Command::RamDelete(idx))
+    } else if Regex::new(r"^search\n?$")?.is_match(s) {
+        Ok(Command::SearchList)
+    } else if Regex::new(r"^search start\n?$")?.is_match(s) {
+        Ok(Command::SearchStart)
+    } else if Regex::new(r"^search (equal|changed|greater|less)\n?$")?.is_match(s) {
+        Ok(Command::SearchFilter(match s[7..].trim() {
+            "equal" => SearchFilter::Equal,
+            "changed" => SearchFilter::Changed,
+            "greater" => SearchFilter::Greater,
+            _ => SearchFilter::Less,
+        }))
+    } else if Regex::new(r"^search value [a-fA-F\d]{1,2}\n?$")?.is_match(s) {
+        let value = u8::from_str_radix(s[13..].trim(), 16).context("Invalid search value")?;
+        Ok(Command::SearchFilter(SearchFilter::Value(value)))
+    } else if Regex::new(r"^watch\n?$")?.is_match(s) {
+        Ok(Command::WatchList)
+    } else if Regex::new(r"^watch add [a-fA-F\d]{1,4}( (u8|i8|u16|i16|bcd8|bcd16))?( .+)?\n?$")?
+        .is_match(s)
+    {
+        let (addr, rest) = s[10..]
+            .trim()
+            .split_once(' ')
+            .unwrap_or((s[10..].trim(), ""));
+        let addr = u16::from_str_radix(addr, 16).context("Invalid watch args")?;
+        let (kind, label) = rest.split_once(' ').unwrap_or((rest, ""));
+        let (kind, label) = match kind {
+            "i8" => (WatchType::I8, label),
+            "u16" => (WatchType::U16, label),
+            "i16" => (WatchType::I16, label),
+            "bcd8" => (WatchType::Bcd8, label),
+            "bcd16" => (WatchType::Bcd16, label),
+            "u8" => (WatchType::U8, label),
+            // no type given, all of it is the label
+            _ => (WatchType::U8, rest),
+        };
+        let label = label.to_string();
+        Ok(Command::WatchAdd(addr, kind, label))
+    } else if Regex::new(r"^watch delete \d+\n?$")?.is_match(s) {
+        let idx = s[13..]
+            .trim()
+            .parse()
+            .context("Invalid watch delete args")?;
+        Ok(Command::WatchDelete(idx))
     } else {
         Err(anyhow!("Invalid command: {}", s))
     }
 }
 
-pub fn exec<S, A>(cmd: Command, nes: &mut Nes<S, A>) -> Result<()>
+pub fn exec<S, A>(cmd: Command, nes: &mut Nes<S, A>, dbg: &mut Debugger) -> Result<()>
 where
     S: NesScreen,
     A: NesAudio,
@@ -71,10 +231,139 @@ where
     println!("EXEC... {:#x?}", &cmd);
     match cmd {
         Command::CpuRegs => cpuregs(&nes.cpu),
-        Command::Disassemble(addr_start, addr_end) => disassemble(addr_start, addr_end, nes)?,
+        Command::Disassemble(addr_start, addr_end) => disassemble(addr_start, addr_end, nes, dbg)?,
         Command::CpuMemory(addr_start, addr_end) => cpumem(addr_start, addr_end, nes),
         Command::PpuMemory(addr_start, addr_end) => ppumem(addr_start, addr_end, nes),
         Command::PpuOam => oam(&nes.ppu),
+        Command::Banks => banks(nes)?,
+        Command::LoadSymbols(path) => {
+            let count = dbg.load_symbols(nes, Path::new(&path))?;
+            println!("Loaded {} symbols from {}", count, path);
+        }
+        Command::Break(target) => {
+            let bp = dbg.resolve(&target)?;
+            println!("Breakpoint {} at {}", dbg.breakpoints.len(), target);
+            dbg.breakpoints.push(bp);
+        }
+        Command::Breakpoints => breakpoints(nes, dbg),
+        Command::Delete(idx) => {
+            if idx >= dbg.breakpoints.len() {
+                Err(anyhow!("No breakpoint number {}", idx))?;
+            }
+            dbg.breakpoints.remove(idx);
+        }
+        Command::Continue => dbg.resume(),
+        Command::Pause => dbg.pause(),
+        Command::Step => {
+            if !dbg.paused {
+                Err(anyhow!("Cannot step while running, pause first"))?;
+            }
+            dbg.step(nes)?;
+        }
+        Command::Next => {
+            if !dbg.paused {
+                Err(anyhow!("Cannot step while running, pause first"))?;
+            }
+            dbg.step_over(nes)?;
+        }
+        Command::Finish => dbg.step_out(nes)?,
+        Command::Backtrace => dbg
+            .backtrace(nes)
+            .iter()
+            .for_each(|frame| println!("{}", frame)),
+        Command::Trace => {
+            dbg.trace = !dbg.trace;
+            println!("Trace {}", if dbg.trace { "enabled" } else { "disabled" });
+        }
+        Command::CdlStatus => cdl_status(nes),
+        Command::CdlStart => {
+            cdl::start(nes);
+            println!("Code/data logging enabled");
+        }
+        Command::CdlStop => {
+            cdl::stop(nes);
+            println!("Code/data logging disabled");
+        }
+        Command::CdlSave(path) => {
+            fs::write(&path, cdl::export(nes))
+                .with_context(|| format!("Cannot write CDL file {}", path))?;
+            println!("Saved code/data log to {}", path);
+        }
+        Command::CdlLoad(path) => {
+            let bytes =
+                fs::read(&path).with_context(|| format!("Cannot read CDL file {}", path))?;
+            cdl::import(nes, &bytes)?;
+            println!("Merged code/data log from {}", path);
+        }
+        Command::DiskStatus => disk_status(nes),
+        Command::DiskEject => {
+            nes.cartridge
+                .disk
+                .as_mut()
+                .context("No disk image loaded")?
+                .eject();
+            println!("Disk ejected");
+        }
+        Command::DiskInsert(side) => {
+            // ejects first and inserts a moment later, like swapping by hand
+            nes.cartridge
+                .disk
+                .as_mut()
+                .context("No disk image loaded")?
+                .swap(side)?;
+            println!("Inserting {}", side_name(side));
+        }
+        Command::GenieList => genie_list(nes),
+        Command::GenieAdd(code) => {
+            let idx = cheats::add_game_genie(nes, &code)?;
+            println!("{}: {}", idx, genie_describe(&nes.cheats.game_genie[idx]));
+        }
+        Command::GenieEnable(idx, enabled) => {
+            cheats::enable_game_genie(nes, idx, enabled)?;
+            println!("{}: {}", idx, genie_describe(&nes.cheats.game_genie[idx]));
+        }
+        Command::GenieDelete(idx) => {
+            let genie = cheats::remove_game_genie(nes, idx)?;
+            println!("Deleted {}", genie.code);
+        }
+        Command::RamList => ram_list(nes),
+        Command::RamAdd(code) => {
+            let idx = cheats::add_ram_cheat(nes, &code)?;
+            println!("{}: {}", idx, ram_describe(&nes.cheats.ram[idx]));
+        }
+        Command::RamEnable(idx, enabled) => {
+            cheats::enable_ram_cheat(nes, idx, enabled)?;
+            println!("{}: {}", idx, ram_describe(&nes.cheats.ram[idx]));
+        }
+        Command::RamDelete(idx) => {
+            let cheat = cheats::remove_ram_cheat(nes, idx)?;
+            println!("Deleted {}", cheat.code);
+        }
+        Command::SearchList => search_list(nes),
+        Command::SearchStart => {
+            let count = cheats::search_start(nes)?;
+            println!("{} candidates", count);
+        }
+        Command::SearchFilter(filter) => {
+            let count = cheats::search_filter(nes, filter)?;
+            println!("{} candidates left", count);
+            if count <= SEARCH_LIST_MAX {
+                search_list(nes);
+            }
+        }
+        Command::WatchList => watch_list(nes)?,
+        Command::WatchAdd(addr, kind, label) => {
+            let idx = cheats::add_watch(nes, addr, kind, &label);
+            println!(
+                "{}: {}",
+                idx,
+                watch_describe(nes, &nes.cheats.watches[idx])?
+            );
+        }
+        Command::WatchDelete(idx) => {
+            let watch = cheats::remove_watch(nes, idx)?;
+            println!("Deleted watch at {:04X}", watch.addr);
+        }
     }
     Ok(())
 }
@@ -87,31 +376,199 @@ fn cpuregs(cpu: &Cpu) {
     );
 }
 
-// Disassemble
-fn disassemble<S, A>(addr_start: u16, addr_end: u16, nes: &mut Nes<S, A>) -> Result<()>
+// Disassemble, naming addresses with the loaded symbols
+fn disassemble<S, A>(addr_start: u16, addr_end: u16, nes: &Nes<S, A>, dbg: &Debugger) -> Result<()>
 where
     S: NesScreen,
     A: NesAudio,
 {
-    let dasm = Disassembler::with_offset(addr_start);
-    let code = (addr_start..addr_end)
-        .map(|addr| buscpu::read(nes, addr))
-        .collect::<Result<Vec<u8>>>()?;
-    let asm = dasm.disassemble(&code);
-    println!("{}", asm);
+    let mut addr = addr_start;
+    while addr < addr_end {
+        if let Some(label) = dbg.label(nes, addr) {
+            println!("{}:", label);
+        }
+        let inst = dbg.disassemble(nes, addr)?;
+        println!("{:04X}  {}", inst.addr, inst.text);
+        addr = match addr.checked_add(inst.bytes.len() as u16) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    Ok(())
+}
+
+// List breakpoints
+fn breakpoints<S, A>(nes: &Nes<S, A>, dbg: &Debugger) {
+    dbg.breakpoints.iter().enumerate().for_each(|(i, bp)| {
+        let (addr, bank) = match bp {
+            Breakpoint::Addr(addr) => (*addr, String::new()),
+            Breakpoint::Prg(prg, addr) => (*addr, format!(" (PRG {:#07x})", prg)),
+        };
+        println!("{}: {}{}", i, dbg.describe(nes, addr), bank);
+    })
+}
+
+// Print which PRG and CHR banks the mapper has selected
+fn banks<S, A>(nes: &Nes<S, A>) -> Result<()> {
+    bank_lines(nes)?
+        .iter()
+        .for_each(|line| println!("{}", line));
+    Ok(())
+}
+
+// The mapper name then one line per mapped bank
+pub fn bank_lines<S, A>(nes: &Nes<S, A>) -> Result<Vec<String>> {
+    let mut lines = vec![format!(
+        "Mapper: {}",
+        nes.cartridge.mapper.try_borrow()?.name()
+    )];
+    let prg = cartridge::prg_layout(nes)?;
+    let chr = cartridge::chr_layout(nes)?;
+    for (bus, bank) in prg
+        .iter()
+        .map(|bank| ("CPU", bank))
+        .chain(chr.iter().map(|bank| ("PPU", bank)))
+    {
+        lines.push(format!(
+            "{} {:04X}-{:04X}: {:?} bank {} (offset {:#07x})",
+            bus,
+            bank.addr,
+            bank.addr as usize + bank.size - 1,
+            bank.memory,
+            bank.number(),
+            bank.offset
+        ));
+    }
+    Ok(lines)
+}
+
+// Print how much of PRG ROM has been logged as code or data
+fn cdl_status<S, A>(nes: &Nes<S, A>) {
+    let coverage = cdl::coverage(nes);
+    let total = nes.cdl.prg.len().max(1) as f32;
+    println!(
+        "CDL {}: code {} ({:.1}%), data {} ({:.1}%), unused {} ({:.1}%)",
+        if nes.cdl.enabled { "on" } else { "off" },
+        coverage.code,
+        coverage.code as f32 * 100. / total,
+        coverage.data,
+        coverage.data as f32 * 100. / total,
+        coverage.unused,
+        coverage.unused as f32 * 100. / total,
+    );
+}
+
+// Disk sides as printed on the labels, two per disk
+fn side_name(side: usize) -> String {
+    format!(
+        "disk {} side {}",
+        side / 2 + 1,
+        if side & 1 == 0 { 'A' } else { 'B' }
+    )
+}
+
+// Print which disk side is in the drive
+fn disk_status<S, A>(nes: &Nes<S, A>) {
+    match nes.cartridge.disk.as_ref() {
+        Some(disk) => {
+            for side in 0..disk.sides.len() {
+                let inserted = if disk.inserted == Some(side) {
+                    " (inserted)"
+                } else {
+                    ""
+                };
+                println!("{}: {}{}", side, side_name(side), inserted);
+            }
+            if disk.inserted.is_none() {
+                println!("No disk inserted");
+            }
+        }
+        None => println!("No disk image loaded"),
+    }
+}
+
+fn genie_describe(genie: &GameGenie) -> String {
+    let compare = match genie.compare {
+        Some(compare) => format!(" if {:02X}", compare),
+        None => String::new(),
+    };
+    format!(
+        "{} {:04X}={:02X}{} ({})",
+        genie.code,
+        genie.addr,
+        genie.value,
+        compare,
+        if genie.enabled { "on" } else { "off" }
+    )
+}
+
+// List Game Genie codes
+fn genie_list<S, A>(nes: &Nes<S, A>) {
+    if nes.cheats.game_genie.is_empty() {
+        println!("No Game Genie codes");
+    }
+    for (i, genie) in nes.cheats.game_genie.iter().enumerate() {
+        println!("{}: {}", i, genie_describe(genie));
+    }
+}
+
+fn ram_describe(cheat: &RamCheat) -> String {
+    format!(
+        "{} ({})",
+        cheat.code,
+        if cheat.enabled { "on" } else { "off" }
+    )
+}
+
+// List RAM freeze codes
+fn ram_list<S, A>(nes: &Nes<S, A>) {
+    if nes.cheats.ram.is_empty() {
+        println!("No RAM codes");
+    }
+    for (i, cheat) in nes.cheats.ram.iter().enumerate() {
+        println!("{}: {}", i, ram_describe(cheat));
+    }
+}
+
+fn search_list<S, A>(nes: &Nes<S, A>) {
+    match &nes.cheats.search {
+        Some(search) if search.candidates.len() > SEARCH_LIST_MAX => {
+            println!("{} candidates", search.candidates.len())
+        }
+        Some(search) => {
+            for (addr, value) in search.candidates.iter() {
+                println!("{:04X}: {:02X}", addr, value);
+            }
+        }
+        None => println!("No search started"),
+    }
+}
+
+fn watch_describe<S, A>(nes: &Nes<S, A>, watch: &Watch) -> Result<String> {
+    let value = cheats::watch_value(nes, watch)?;
+    Ok(format!(
+        "{:04X} {:?} = {} {}",
+        watch.addr, watch.kind, value, watch.label
+    ))
+}
+
+// Print the current values of the RAM watches
+fn watch_list<S, A>(nes: &Nes<S, A>) -> Result<()> {
+    if nes.cheats.watches.is_empty() {
+        println!("No watches");
+    }
+    for (i, watch) in nes.cheats.watches.iter().enumerate() {
+        println!("{}: {}", i, watch_describe(nes, watch)?);
+    }
     Ok(())
 }
 
 // Print raw memory as seen by the CPU bus
-fn cpumem<S, A>(addr_start: u16, addr_end: u16, nes: &mut Nes<S, A>)
-where
-    S: NesScreen,
-    A: NesAudio,
-{
+fn cpumem<S, A>(addr_start: u16, addr_end: u16, nes: &Nes<S, A>) {
     (addr_start..addr_end).step_by(16).for_each(|addr| {
         let mut data_row = [0u8; 16];
         (0..16)
-            .map(|offset| buscpu::read(nes, addr + offset).unwrap())
+            .map(|offset| buscpu::peek(nes, addr + offset).unwrap())
             .enumerate()
             .for_each(|(offset, data)| {
                 data_row[offset] = data;
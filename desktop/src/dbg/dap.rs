@@ -0,0 +1,854 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use nes::buscpu;
+use nes::nesaudio::NesAudio;
+use nes::nesscreen::NesScreen;
+use nes::Nes;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::dbg::debugger::Breakpoint;
+use crate::dbg::debugger::Debugger;
+use crate::dbg::debugger::StopReason;
+
+pub const DAP_ADDR: &str = "127.0.0.1:4711";
+
+const THREAD_ID: i64 = 1;
+
+// variablesReference of each scope
+const SCOPE_CPU: i64 = 1;
+const SCOPE_PPU: i64 = 2;
+const SCOPE_APU: i64 = 3;
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/*
+    Debug Adapter Protocol server for editors. Like the GDB stub it is polled from the emulator
+    thread, and it drives the same Debugger as the stdin console, so breakpoints and pauses made
+    from either side show up in both.
+*/
+pub struct DapServer {
+    listener: TcpListener,
+    conn: Option<TcpStream>,
+    buffer: Vec<u8>,
+    seq: i64,
+    // run state last reported to the client
+    running: bool,
+    launched: bool,
+    configured: bool,
+    stop_on_entry: bool,
+    // a launched ROM waits for configurationDone before running
+    resume_on_start: bool,
+    source_root: PathBuf,
+    // breakpoints owned by the client, replaced as a whole on every set*Breakpoints request
+    source_breakpoints: HashMap<String, Vec<Breakpoint>>,
+    function_breakpoints: Vec<Breakpoint>,
+    instruction_breakpoints: Vec<Breakpoint>,
+}
+
+impl DapServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        log::info!("DAP server listening on {}", listener.local_addr()?);
+        Ok(Self {
+            listener,
+            conn: None,
+            buffer: vec![],
+            seq: 1,
+            running: false,
+            launched: false,
+            configured: false,
+            stop_on_entry: false,
+            resume_on_start: false,
+            source_root: PathBuf::new(),
+            source_breakpoints: HashMap::new(),
+            function_breakpoints: vec![],
+            instruction_breakpoints: vec![],
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn poll<S, A>(&mut self, nes: &mut Nes<S, A>, dbg: &mut Debugger) -> Result<()>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        if self.conn.is_none() {
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    log::info!("DAP client connected from {}", peer);
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.conn = Some(stream);
+                    self.buffer.clear();
+                    self.launched = false;
+                    self.configured = false;
+                    self.running = !dbg.paused;
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => Err(err)?,
+            }
+        }
+
+        if !self.receive()? {
+            log::info!("DAP client disconnected");
+            self.detach(dbg);
+            return Ok(());
+        }
+
+        while let Some(msg) = self.next_message()? {
+            let command = msg["command"].as_str().unwrap_or_default().to_string();
+            let result = self.handle(&command, &msg["arguments"], nes, dbg);
+            self.respond(&msg, &command, result)?;
+            match command.as_str() {
+                "disconnect" => {
+                    self.detach(dbg);
+                    return Ok(());
+                }
+                "initialize" => self.event("initialized", Value::Null)?,
+                "configurationDone" | "launch" | "attach" => self.start(dbg)?,
+                "next" | "stepIn" if dbg.paused => self.stopped(dbg)?,
+                _ => {}
+            }
+        }
+
+        // report pauses and resumes, including the ones made from the console
+        if self.configured && self.launched {
+            if self.running && dbg.paused {
+                self.running = false;
+                self.stopped(dbg)?;
+            } else if !self.running && !dbg.paused {
+                self.running = true;
+                self.event(
+                    "continued",
+                    json!({ "threadId": THREAD_ID, "allThreadsContinued": true }),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle<S, A>(
+        &mut self,
+        command: &str,
+        args: &Value,
+        nes: &mut Nes<S, A>,
+        dbg: &mut Debugger,
+    ) -> Result<Value>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        let body = match command {
+            "initialize" => json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsFunctionBreakpoints": true,
+                "supportsInstructionBreakpoints": true,
+                "supportsSetVariable": true,
+                "supportsReadMemoryRequest": true,
+                "supportsWriteMemoryRequest": true,
+                "supportsDisassembleRequest": true,
+                "supportsSteppingGranularity": true,
+                "supportsEvaluateForHovers": true,
+            }),
+            "launch" => {
+                let program = args["program"]
+                    .as_str()
+                    .context("Missing program to launch")?;
+                let rom = fs::read(program).with_context(|| format!("Cannot read {}", program))?;
+                nes.load(&rom)?;
+                nes.reset()?;
+                dbg.call_stack.clear();
+                dbg.symbols = Default::default();
+                dbg.halt(nes)?;
+                dbg.discover_symbols(nes, Path::new(program));
+                if let Some(symbols) = args["symbols"].as_str() {
+                    dbg.load_symbols(nes, Path::new(symbols))?;
+                }
+                self.source_root = Path::new(program)
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+                self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                self.resume_on_start = !self.stop_on_entry;
+                self.launched = true;
+                self.running = false;
+                log::info!("Launched {} from the debug adapter", program);
+                Value::Null
+            }
+            "attach" => {
+                if let Some(root) = args["sourceRoot"].as_str() {
+                    self.source_root = PathBuf::from(root);
+                }
+                self.stop_on_entry = false;
+                self.resume_on_start = false;
+                self.launched = true;
+                Value::Null
+            }
+            "configurationDone" => {
+                self.configured = true;
+                Value::Null
+            }
+            "disconnect" => Value::Null,
+            "setBreakpoints" => {
+                let path = args["source"]["path"]
+                    .as_str()
+                    .context("Missing source path")?
+                    .to_string();
+                let mut added = vec![];
+                let mut replies = vec![];
+                for bp in args["breakpoints"].as_array().into_iter().flatten() {
+                    let line = bp["line"].as_i64().unwrap_or(0) as usize;
+                    let bps = dbg.resolve_line(&path, line);
+                    replies.push(match bps.first() {
+                        Some(first) => json!({
+                            "verified": true,
+                            "line": line,
+                            "instructionReference": reference(address(first)),
+                        }),
+                        None => json!({
+                            "verified": false,
+                            "line": line,
+                            "message": "No code generated for this line",
+                        }),
+                    });
+                    added.extend(bps);
+                }
+                let old = self.source_breakpoints.remove(&path).unwrap_or_default();
+                replace_breakpoints(dbg, &old, &added);
+                self.source_breakpoints.insert(path, added);
+                json!({ "breakpoints": replies })
+            }
+            "setFunctionBreakpoints" => {
+                let mut added = vec![];
+                let mut replies = vec![];
+                for bp in args["breakpoints"].as_array().into_iter().flatten() {
+                    let name = bp["name"].as_str().unwrap_or_default();
+                    replies.push(match dbg.resolve(name) {
+                        Ok(resolved) => {
+                            let reply = json!({
+                                "verified": true,
+                                "instructionReference": reference(address(&resolved)),
+                            });
+                            added.push(resolved);
+                            reply
+                        }
+                        Err(err) => json!({ "verified": false, "message": err.to_string() }),
+                    });
+                }
+                replace_breakpoints(dbg, &self.function_breakpoints, &added);
+                self.function_breakpoints = added;
+                json!({ "breakpoints": replies })
+            }
+            "setInstructionBreakpoints" => {
+                let mut added = vec![];
+                let mut replies = vec![];
+                for bp in args["breakpoints"].as_array().into_iter().flatten() {
+                    let addr = bp["instructionReference"]
+                        .as_str()
+                        .and_then(parse_addr)
+                        .map(|addr| addr.wrapping_add(bp["offset"].as_i64().unwrap_or(0) as u16));
+                    replies.push(match addr {
+                        Some(addr) => {
+                            added.push(Breakpoint::Addr(addr));
+                            json!({ "verified": true, "instructionReference": reference(addr) })
+                        }
+                        None => json!({
+                            "verified": false,
+                            "message": "Invalid instruction reference",
+                        }),
+                    });
+                }
+                replace_breakpoints(dbg, &self.instruction_breakpoints, &added);
+                self.instruction_breakpoints = added;
+                json!({ "breakpoints": replies })
+            }
+            "threads" => json!({ "threads": [{ "id": THREAD_ID, "name": "6502" }] }),
+            "stackTrace" => {
+                let mut addrs = vec![nes.cpu.pc];
+                addrs.extend(dbg.call_stack.iter().rev().map(|frame| frame.call_site));
+                let frames = addrs
+                    .iter()
+                    .enumerate()
+                    .map(|(id, &addr)| self.stack_frame(nes, dbg, id, addr))
+                    .collect::<Vec<Value>>();
+                json!({ "totalFrames": frames.len(), "stackFrames": frames })
+            }
+            "scopes" => {
+                let scope = |name: &str, reference: i64| {
+                    json!({
+                        "name": name,
+                        "variablesReference": reference,
+                        "expensive": false,
+                    })
+                };
+                json!({
+                    "scopes": [
+                        scope("CPU", SCOPE_CPU),
+                        scope("PPU", SCOPE_PPU),
+                        scope("APU", SCOPE_APU),
+                    ]
+                })
+            }
+            "variables" => {
+                let vars = match args["variablesReference"].as_i64() {
+                    Some(SCOPE_CPU) => cpu_variables(nes),
+                    Some(SCOPE_PPU) => ppu_variables(nes),
+                    Some(SCOPE_APU) => apu_variables(nes),
+                    _ => vec![],
+                };
+                let vars = vars
+                    .into_iter()
+                    .map(|(name, value)| {
+                        json!({ "name": name, "value": value, "variablesReference": 0 })
+                    })
+                    .collect::<Vec<Value>>();
+                json!({ "variables": vars })
+            }
+            "setVariable" => {
+                if args["variablesReference"].as_i64() != Some(SCOPE_CPU) {
+                    Err(anyhow!("Only CPU registers can be modified"))?;
+                }
+                let name = args["name"].as_str().unwrap_or_default();
+                let value = args["value"]
+                    .as_str()
+                    .and_then(parse_value)
+                    .context("Invalid value")?;
+                let cpu = &mut nes.cpu;
+                match name {
+                    "A" => cpu.ac = value as u8,
+                    "X" => cpu.x = value as u8,
+                    "Y" => cpu.y = value as u8,
+                    "SP" => cpu.sp = value as u8,
+                    "P" => cpu.status = value as u8,
+                    "PC" => cpu.pc = value,
+                    _ => Err(anyhow!("Unknown register {}", name))?,
+                }
+                let value = cpu_variables(nes)
+                    .into_iter()
+                    .find(|(var, _)| var == name)
+                    .map(|(_, value)| value)
+                    .unwrap_or_default();
+                json!({ "value": value })
+            }
+            "readMemory" => {
+                let addr = memory_reference(args)?;
+                let count = args["count"].as_i64().unwrap_or(0).clamp(0, 0x10000) as usize;
+                let count = count.min(0x10000 - addr as usize);
+                let data = (0..count)
+                    .map(|i| buscpu::peek(nes, addr.wrapping_add(i as u16)))
+                    .collect::<Result<Vec<u8>>>()?;
+                json!({ "address": reference(addr), "data": base64_encode(&data) })
+            }
+            "writeMemory" => {
+                let addr = memory_reference(args)?;
+                let data = args["data"]
+                    .as_str()
+                    .and_then(base64_decode)
+                    .context("Invalid base64 data")?;
+                // only RAM takes a debugger write, stop at the first address that doesn't
+                let mut written = 0;
+                for (i, byte) in data.iter().enumerate() {
+                    if !buscpu::poke(nes, addr.wrapping_add(i as u16), *byte)? {
+                        break;
+                    }
+                    written += 1;
+                }
+                if written < data.len() && !args["allowPartial"].as_bool().unwrap_or(false) {
+                    Err(anyhow!(
+                        "Cannot write memory at {:#06x}",
+                        addr.wrapping_add(written as u16)
+                    ))?;
+                }
+                json!({ "bytesWritten": written })
+            }
+            "disassemble" => {
+                let addr = memory_reference(args)?;
+                // there are no more instructions than bytes in the address space
+                let skip = args["instructionOffset"]
+                    .as_i64()
+                    .unwrap_or(0)
+                    .clamp(-0x10000, 0x10000);
+                let count = args["instructionCount"]
+                    .as_i64()
+                    .unwrap_or(0)
+                    .clamp(0, 0x10000) as usize;
+                let insts = self.disassemble(nes, dbg, addr, skip, count)?;
+                json!({ "instructions": insts })
+            }
+            "evaluate" => {
+                let expr = args["expression"].as_str().unwrap_or_default().trim();
+                match cpu_variables(nes)
+                    .into_iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(expr))
+                {
+                    Some((_, value)) => json!({ "result": value, "variablesReference": 0 }),
+                    None => {
+                        let addr = address(&dbg.resolve(expr)?);
+                        let value = buscpu::peek(nes, addr)?;
+                        json!({
+                            "result": format!("${:02X}", value),
+                            "memoryReference": reference(addr),
+                            "variablesReference": 0,
+                        })
+                    }
+                }
+            }
+            "continue" => {
+                dbg.resume();
+                self.running = true;
+                json!({ "allThreadsContinued": true })
+            }
+            "pause" => {
+                if !dbg.paused {
+                    dbg.pause();
+                }
+                Value::Null
+            }
+            "next" => {
+                ensure_paused(dbg)?;
+                dbg.step_over(nes)?;
+                self.running = !dbg.paused;
+                Value::Null
+            }
+            "stepIn" => {
+                ensure_paused(dbg)?;
+                dbg.step(nes)?;
+                Value::Null
+            }
+            "stepOut" => {
+                ensure_paused(dbg)?;
+                dbg.step_out(nes)?;
+                self.running = true;
+                Value::Null
+            }
+            _ => Err(anyhow!("Unsupported request: {}", command))?,
+        };
+        Ok(body)
+    }
+
+    // Runs once the client is done configuring, whichever of launch/configurationDone came last
+    fn start(&mut self, dbg: &mut Debugger) -> Result<()> {
+        if !self.launched || !self.configured || self.running {
+            return Ok(());
+        }
+        if !dbg.paused {
+            self.running = true;
+        } else if self.resume_on_start {
+            self.resume_on_start = false;
+            dbg.resume();
+            self.running = true;
+        } else if self.stop_on_entry {
+            self.stop_on_entry = false;
+            self.event(
+                "stopped",
+                json!({ "reason": "entry", "threadId": THREAD_ID, "allThreadsStopped": true }),
+            )?;
+        } else {
+            self.stopped(dbg)?;
+        }
+        Ok(())
+    }
+
+    fn stopped(&mut self, dbg: &Debugger) -> Result<()> {
+        let reason = match dbg.stop_reason {
+            StopReason::Pause => "pause",
+            StopReason::Breakpoint => "breakpoint",
+            StopReason::Step => "step",
+        };
+        self.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        )
+    }
+
+    fn detach(&mut self, dbg: &mut Debugger) {
+        self.conn = None;
+        self.running = false;
+        let owned = self
+            .source_breakpoints
+            .drain()
+            .flat_map(|(_, bps)| bps)
+            .chain(self.function_breakpoints.drain(..))
+            .chain(self.instruction_breakpoints.drain(..))
+            .collect::<Vec<Breakpoint>>();
+        replace_breakpoints(dbg, &owned, &[]);
+        if dbg.paused {
+            dbg.resume();
+        }
+    }
+
+    fn stack_frame<S, A>(&self, nes: &Nes<S, A>, dbg: &Debugger, id: usize, addr: u16) -> Value {
+        let prg = dbg.prg_offset(nes, addr);
+        let name = dbg
+            .symbols
+            .nearest(addr, prg)
+            .unwrap_or_else(|| format!("${:04X}", addr));
+        let mut frame = json!({
+            "id": id,
+            "name": name,
+            "instructionPointerReference": reference(addr),
+            "column": 0,
+        });
+        match dbg.symbols.line(addr, prg) {
+            Some(src) => {
+                frame["line"] = src.line.into();
+                frame["source"] = self.source(&src.file);
+            }
+            None => frame["line"] = 0.into(),
+        }
+        frame
+    }
+
+    fn source(&self, file: &str) -> Value {
+        let path = self.source_root.join(file);
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        json!({ "name": name, "path": path.to_string_lossy().to_string() })
+    }
+
+    fn disassemble<S, A>(
+        &self,
+        nes: &Nes<S, A>,
+        dbg: &Debugger,
+        addr: u16,
+        skip: i64,
+        count: usize,
+    ) -> Result<Vec<Value>>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        /*
+            6502 instructions have variable length so there is no exact way to walk backwards.
+            Decode forward from a few bytes earlier and keep what lines up before addr.
+        */
+        let mut before = vec![];
+        if skip < 0 {
+            let back = (skip.unsigned_abs() as usize).min(0x10000);
+            let mut pc = (addr as usize).saturating_sub(back.saturating_mul(3)) as u16;
+            while pc < addr {
+                let inst = dbg.disassemble(nes, pc)?;
+                before.push(pc);
+                pc = pc.wrapping_add(inst.bytes.len() as u16);
+            }
+            before = before.split_off(before.len().saturating_sub(back));
+        }
+        let mut addrs = before;
+        let mut pc = addr;
+        for _ in 0..(skip.max(0) as usize) {
+            pc = pc.wrapping_add(dbg.disassemble(nes, pc)?.bytes.len() as u16);
+        }
+        while addrs.len() < count {
+            addrs.push(pc);
+            pc = pc.wrapping_add(dbg.disassemble(nes, pc)?.bytes.len() as u16);
+        }
+
+        addrs
+            .into_iter()
+            .take(count)
+            .map(|addr| {
+                let inst = dbg.disassemble(nes, addr)?;
+                let bytes = inst
+                    .bytes
+                    .iter()
+                    .map(|byte| format!("{:02X}", byte))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                let mut fields = json!({
+                    "address": reference(addr),
+                    "instructionBytes": bytes,
+                    "instruction": inst.text,
+                });
+                if let Some(label) = dbg.label(nes, addr) {
+                    fields["symbol"] = label.into();
+                }
+                let prg = dbg.prg_offset(nes, addr);
+                if let Some(src) = dbg.symbols.line(addr, prg) {
+                    fields["location"] = self.source(&src.file);
+                    fields["line"] = src.line.into();
+                }
+                Ok(fields)
+            })
+            .collect()
+    }
+
+    fn respond(&mut self, request: &Value, command: &str, body: Result<Value>) -> Result<()> {
+        let request_seq = request["seq"].as_i64().unwrap_or(0);
+        let mut msg = json!({
+            "seq": self.seq,
+            "type": "response",
+            "request_seq": request_seq,
+            "command": command,
+        });
+        match body {
+            Ok(body) => {
+                msg["success"] = true.into();
+                if !body.is_null() {
+                    msg["body"] = body;
+                }
+            }
+            Err(err) => {
+                log::warn!("DAP {} failed: {:?}", command, err);
+                msg["success"] = false.into();
+                msg["message"] = err.to_string().into();
+            }
+        }
+        self.send(msg)
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<()> {
+        let mut msg = json!({ "seq": self.seq, "type": "event", "event": event });
+        if !body.is_null() {
+            msg["body"] = body;
+        }
+        self.send(msg)
+    }
+
+    fn send(&mut self, msg: Value) -> Result<()> {
+        self.seq += 1;
+        let content = msg.to_string();
+        let packet = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
+        if let Some(conn) = self.conn.as_mut() {
+            conn.set_nonblocking(false)?;
+            conn.write_all(packet.as_bytes())?;
+            conn.set_nonblocking(true)?;
+        }
+        Ok(())
+    }
+
+    // Read every pending byte. Returns false once the client hung up.
+    fn receive(&mut self) -> Result<bool> {
+        let conn = match self.conn.as_mut() {
+            Some(conn) => conn,
+            None => return Ok(true),
+        };
+        let mut chunk = [0u8; 4096];
+        loop {
+            match conn.read(&mut chunk) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(true),
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => return Ok(false),
+                Err(err) => Err(err)?,
+            }
+        }
+    }
+
+    // "Content-Length: N\r\n\r\n" followed by N bytes of JSON
+    fn next_message(&mut self) -> Result<Option<Value>> {
+        let header_end = match self.buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let header = String::from_utf8_lossy(&self.buffer[..header_end]).to_string();
+        let start = header_end + 4;
+        let end = header
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length:"))
+            .and_then(|len| len.trim().parse::<usize>().ok())
+            .and_then(|len| start.checked_add(len));
+        let end = match end {
+            Some(end) => end,
+            None => {
+                // drop the bad header so the next message can still be read
+                self.buffer.drain(..start);
+                Err(anyhow!("DAP message without a valid Content-Length"))?
+            }
+        };
+        if self.buffer.len() < end {
+            return Ok(None);
+        }
+        let msg = serde_json::from_slice(&self.buffer[start..end]);
+        self.buffer.drain(..end);
+        Ok(Some(msg?))
+    }
+}
+
+fn ensure_paused(dbg: &Debugger) -> Result<()> {
+    if !dbg.paused {
+        Err(anyhow!("Cannot step while running, pause first"))?;
+    }
+    Ok(())
+}
+
+// Swap the breakpoints a client owns, leaving the ones set from the console alone
+fn replace_breakpoints(dbg: &mut Debugger, old: &[Breakpoint], new: &[Breakpoint]) {
+    for bp in old {
+        if let Some(i) = dbg.breakpoints.iter().position(|other| other == bp) {
+            dbg.breakpoints.remove(i);
+        }
+    }
+    dbg.breakpoints.extend_from_slice(new);
+}
+
+fn address(bp: &Breakpoint) -> u16 {
+    match bp {
+        Breakpoint::Addr(addr) => *addr,
+        Breakpoint::Prg(_, addr) => *addr,
+    }
+}
+
+fn reference(addr: u16) -> String {
+    format!("0x{:04X}", addr)
+}
+
+fn memory_reference(args: &Value) -> Result<u16> {
+    let addr = args["memoryReference"]
+        .as_str()
+        .and_then(parse_addr)
+        .context("Invalid memory reference")?;
+    let offset = args["offset"].as_i64().unwrap_or(0);
+    Ok(addr.wrapping_add(offset as u16))
+}
+
+// "0xC000", "$C000" or "C000"
+fn parse_addr(text: &str) -> Option<u16> {
+    let text = text.trim();
+    let hex = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'))
+        .unwrap_or(text);
+    u16::from_str_radix(hex, 16).ok()
+}
+
+// Values typed in the variables view: "$1F"/"0x1F" are hex, anything else decimal
+fn parse_value(text: &str) -> Option<u16> {
+    let text = text.trim();
+    if text.starts_with('$') || text.starts_with("0x") || text.starts_with("0X") {
+        parse_addr(text)
+    } else {
+        text.parse().ok()
+    }
+}
+
+fn cpu_variables<S, A>(nes: &Nes<S, A>) -> Vec<(String, String)> {
+    let cpu = &nes.cpu;
+    let flags = "NV-BDIZC"
+        .chars()
+        .enumerate()
+        .map(|(i, flag)| {
+            if cpu.status & (0x80 >> i) != 0 {
+                flag
+            } else {
+                '.'
+            }
+        })
+        .collect::<String>();
+    vec![
+        (String::from("A"), format!("${:02X}", cpu.ac)),
+        (String::from("X"), format!("${:02X}", cpu.x)),
+        (String::from("Y"), format!("${:02X}", cpu.y)),
+        (String::from("SP"), format!("${:02X}", cpu.sp)),
+        (String::from("P"), format!("${:02X} {}", cpu.status, flags)),
+        (String::from("PC"), format!("${:04X}", cpu.pc)),
+    ]
+}
+
+fn ppu_variables<S, A>(nes: &Nes<S, A>) -> Vec<(String, String)> {
+    let ppu = &nes.ppu;
+    vec![
+        (
+            String::from("PPUCTRL"),
+            format!("${:02X}", ppu.reg_control.bits()),
+        ),
+        (
+            String::from("PPUMASK"),
+            format!("${:02X}", ppu.reg_mask.bits()),
+        ),
+        (
+            String::from("PPUSTATUS"),
+            format!("${:02X}", ppu.reg_status.bits()),
+        ),
+        (
+            String::from("OAMADDR"),
+            format!("${:02X}", ppu.reg_oam_addr),
+        ),
+        (String::from("PPUADDR"), format!("${:04X}", ppu.reg_addr)),
+        (
+            String::from("Scroll X"),
+            ppu.reg_scroll.scroll_x.to_string(),
+        ),
+        (
+            String::from("Scroll Y"),
+            ppu.reg_scroll.scroll_y.to_string(),
+        ),
+        (String::from("Scanline"), ppu.scan_line.to_string()),
+        (String::from("Cycle"), ppu.scan_cycle.to_string()),
+    ]
+}
+
+fn apu_variables<S, A>(nes: &Nes<S, A>) -> Vec<(String, String)> {
+    let apu = &nes.apu;
+    let mut vars = vec![];
+    for (name, pulse) in [("Pulse 1", &apu.pulse1), ("Pulse 2", &apu.pulse2)] {
+        vars.push((format!("{} enabled", name), pulse.enabled.to_string()));
+        vars.push((
+            format!("{} duty", name),
+            format!("{}%", pulse.duty_cycle * 100.),
+        ));
+        vars.push((format!("{} volume", name), format!("{:.2}", pulse.volume)));
+        vars.push((format!("{} period", name), pulse.period.to_string()));
+    }
+    vars.push((
+        String::from("Triangle enabled"),
+        apu.triangle.enabled.to_string(),
+    ));
+    vars.push((
+        String::from("Triangle period"),
+        apu.triangle.period.to_string(),
+    ));
+    vars
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut data = vec![];
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes().filter(|c| *c != b'=') {
+        let value = BASE64.iter().position(|b| *b == c)? as u32;
+        bits = bits << 6 | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            data.push((bits >> count) as u8);
+        }
+    }
+    Some(data)
+}
@@ -0,0 +1,330 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use nes::buscpu;
+use nes::cartridge;
+use nes::nesaudio::NesAudio;
+use nes::nesscreen::NesScreen;
+use nes::Nes;
+
+use crate::dbg::disasm;
+use crate::dbg::symbols;
+use crate::dbg::symbols::Symbols;
+
+const JSR: u8 = 0x20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    Addr(u16),
+    // PRG ROM offset (and the CPU address it was set at), only hit when that bank is mapped
+    Prg(usize, u16),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StopReason {
+    #[default]
+    Pause,
+    Breakpoint,
+    Step,
+}
+
+pub struct Frame {
+    pub call_site: u16,
+    pub sp: u8,
+    pub interrupt: bool,
+}
+
+#[derive(Default)]
+pub struct Debugger {
+    pub symbols: Symbols,
+    pub breakpoints: Vec<Breakpoint>,
+    pub call_stack: Vec<Frame>,
+    pub paused: bool,
+    pub trace: bool,
+    pub stop_reason: StopReason,
+    break_next: bool,
+    // run until PC reaches the address with the stack at or above the given level
+    run_to: Option<(u16, u8)>,
+    skip_break: bool,
+    last_pc: u16,
+    last_sp: u8,
+}
+
+impl Debugger {
+    pub fn load_symbols<S, A>(&mut self, nes: &Nes<S, A>, path: &Path) -> Result<usize> {
+        self.symbols.load(path, nes.cartridge.prgmem.len())
+    }
+
+    // Load every symbol file found next to the ROM
+    pub fn discover_symbols<S, A>(&mut self, nes: &Nes<S, A>, rom_path: &Path) {
+        for path in symbols::discover(rom_path) {
+            match self.load_symbols(nes, &path) {
+                Ok(count) => log::info!("Loaded {} symbols from {:?}", count, path),
+                Err(err) => log::error!("{:?}", err),
+            }
+        }
+    }
+
+    // PRG ROM offset behind a CPU address with the banks the mapper has selected right now
+    pub fn prg_offset<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        cartridge::prg_offset(nes, addr)
+    }
+
+    pub fn label<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> Option<String> {
+        self.symbols
+            .label(addr, self.prg_offset(nes, addr))
+            .map(String::from)
+    }
+
+    // "$C004 <main+4> (main.s:12)"
+    pub fn describe<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> String {
+        let prg = self.prg_offset(nes, addr);
+        let mut desc = format!("${:04X}", addr);
+        if let Some(name) = self.symbols.nearest(addr, prg) {
+            desc.push_str(&format!(" <{}>", name));
+        }
+        if let Some(line) = self.symbols.line(addr, prg) {
+            desc.push_str(&format!(" ({}:{})", line.file, line.line));
+        }
+        desc
+    }
+
+    // Resolve a label or a hex address typed by the user
+    pub fn resolve(&self, target: &str) -> Result<Breakpoint> {
+        if let Some(sym) = self.symbols.find(target) {
+            return Ok(match sym.prg {
+                Some(prg) => Breakpoint::Prg(prg, sym.addr),
+                None => Breakpoint::Addr(sym.addr),
+            });
+        }
+        let addr = u16::from_str_radix(target.trim_start_matches('$'), 16)
+            .map_err(|_| anyhow!("Unknown label or address: {}", target))?;
+        Ok(Breakpoint::Addr(addr))
+    }
+
+    // Breakpoints for every address generated by a source line
+    pub fn resolve_line(&self, file: &str, line: usize) -> Vec<Breakpoint> {
+        self.symbols
+            .addresses(file, line)
+            .into_iter()
+            .map(|(addr, prg)| match prg {
+                Some(prg) => Breakpoint::Prg(prg, addr),
+                None => Breakpoint::Addr(addr),
+            })
+            .collect()
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.skip_break = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.break_next = true;
+    }
+
+    // Step one instruction, running subroutine calls to completion
+    pub fn step_over<S, A>(&mut self, nes: &mut Nes<S, A>) -> Result<()>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        let (pc, sp) = (nes.cpu.pc, nes.cpu.sp);
+        if buscpu::peek(nes, pc)? == JSR {
+            self.run_to = Some((pc.wrapping_add(3), sp));
+            self.resume();
+            Ok(())
+        } else {
+            self.step(nes)
+        }
+    }
+
+    // Run until the current subroutine or interrupt handler returns
+    pub fn step_out<S, A>(&mut self, nes: &Nes<S, A>) -> Result<()> {
+        let frame = self
+            .call_stack
+            .last()
+            .ok_or_else(|| anyhow!("Not inside a subroutine"))?;
+        self.run_to = Some(if frame.interrupt {
+            // RTI pulls P and then the interrupted PC
+            let ret = peek_word(nes, 0x100 + frame.sp as u16 + 2)?;
+            (ret, frame.sp.wrapping_add(3))
+        } else {
+            (frame.call_site.wrapping_add(3), frame.sp.wrapping_add(2))
+        });
+        self.resume();
+        Ok(())
+    }
+
+    // Stop right away, finishing the instruction currently executing
+    pub fn halt<S, A>(&mut self, nes: &mut Nes<S, A>) -> Result<()>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        while nes.cpu.cycles > 0 {
+            nes.clock()?;
+        }
+        self.paused = true;
+        self.stop_reason = StopReason::Pause;
+        self.run_to = None;
+        Ok(())
+    }
+
+    /*
+        Called before every instruction. Keeps the call stack up to date and returns true
+        when execution must stop before running the instruction at PC.
+    */
+    pub fn on_instruction<S, A>(&mut self, nes: &mut Nes<S, A>) -> Result<bool>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        let pc = nes.cpu.pc;
+        let reason = if self.skip_break {
+            None
+        } else if self.break_next {
+            Some(StopReason::Pause)
+        } else if self.is_breakpoint(nes, pc) {
+            Some(StopReason::Breakpoint)
+        } else {
+            match self.run_to {
+                Some((addr, sp)) if addr == pc && nes.cpu.sp >= sp => Some(StopReason::Step),
+                _ => None,
+            }
+        };
+        if let Some(reason) = reason {
+            self.break_next = false;
+            self.run_to = None;
+            self.paused = true;
+            self.stop_reason = reason;
+            println!("Stopped at {}", self.describe(nes, pc));
+            return Ok(true);
+        }
+        self.skip_break = false;
+        self.track_call_stack(nes)?;
+        if self.trace {
+            println!("{}", self.trace_line(nes)?);
+        }
+        Ok(false)
+    }
+
+    // Run a single instruction while paused
+    pub fn step<S, A>(&mut self, nes: &mut Nes<S, A>) -> Result<()>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        self.track_call_stack(nes)?;
+        let line = self.trace_line(nes)?;
+        nes.clock()?;
+        while nes.cpu.cycles > 0 {
+            nes.clock()?;
+        }
+        self.stop_reason = StopReason::Step;
+        println!("{}", line);
+        Ok(())
+    }
+
+    pub fn trace_line<S, A>(&self, nes: &Nes<S, A>) -> Result<String>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        let pc = nes.cpu.pc;
+        let inst = self.disassemble(nes, pc)?;
+        let bytes = inst
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let mut line = format!(
+            "{:04X}  {:<8}  {:<20} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+            pc, bytes, inst.text, nes.cpu.ac, nes.cpu.x, nes.cpu.y, nes.cpu.status, nes.cpu.sp
+        );
+        let prg = self.prg_offset(nes, pc);
+        if let Some(name) = self.symbols.nearest(pc, prg) {
+            line.push_str(&format!("  <{}>", name));
+        }
+        if let Some(src) = self.symbols.line(pc, prg) {
+            line.push_str(&format!("  {}:{}", src.file, src.line));
+        }
+        Ok(line)
+    }
+
+    pub fn disassemble<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> Result<disasm::Instruction>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        disasm::disassemble(nes, addr, |target| self.label(nes, target))
+    }
+
+    pub fn backtrace<S, A>(&self, nes: &Nes<S, A>) -> Vec<String> {
+        let mut frames = vec![format!("#0 {}", self.describe(nes, nes.cpu.pc))];
+        for (i, frame) in self.call_stack.iter().rev().enumerate() {
+            let kind = if frame.interrupt { " [interrupt]" } else { "" };
+            frames.push(format!(
+                "#{} {}{}",
+                i + 1,
+                self.describe(nes, frame.call_site),
+                kind
+            ));
+        }
+        frames
+    }
+
+    fn is_breakpoint<S, A>(&self, nes: &Nes<S, A>, pc: u16) -> bool {
+        if self.breakpoints.is_empty() {
+            return false;
+        }
+        let prg = self.prg_offset(nes, pc);
+        self.breakpoints.iter().any(|bp| match bp {
+            Breakpoint::Addr(addr) => *addr == pc,
+            Breakpoint::Prg(offset, addr) => match prg {
+                Some(prg) => prg == *offset,
+                None => *addr == pc,
+            },
+        })
+    }
+
+    fn track_call_stack<S, A>(&mut self, nes: &Nes<S, A>) -> Result<()> {
+        let (pc, sp) = (nes.cpu.pc, nes.cpu.sp);
+
+        // frames whose return address has been pulled off the stack are gone
+        self.call_stack.retain(|frame| frame.sp >= sp);
+
+        // three bytes pushed and PC sitting on a vector target means an interrupt was taken
+        if sp == self.last_sp.wrapping_sub(3) {
+            let nmi = peek_word(nes, 0xfffa)?;
+            let irq = peek_word(nes, 0xfffe)?;
+            if pc == nmi || pc == irq {
+                self.call_stack.push(Frame {
+                    call_site: self.last_pc,
+                    sp,
+                    interrupt: true,
+                });
+            }
+        }
+
+        if buscpu::peek(nes, pc)? == JSR {
+            self.call_stack.push(Frame {
+                call_site: pc,
+                sp: sp.wrapping_sub(2),
+                interrupt: false,
+            });
+        }
+
+        self.last_pc = pc;
+        self.last_sp = sp;
+        Ok(())
+    }
+}
+
+fn peek_word<S, A>(nes: &Nes<S, A>, addr: u16) -> Result<u16> {
+    let lo = buscpu::peek(nes, addr)? as u16;
+    let hi = buscpu::peek(nes, addr.wrapping_add(1))? as u16;
+    Ok(hi << 8 | lo)
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use nes::buscpu;
+use nes::cpu::addressing::AddrMode as Mode;
+use nes::cpu::decode;
+use nes::nesaudio::NesAudio;
+use nes::nesscreen::NesScreen;
+use nes::Nes;
+
+pub struct Instruction {
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+// Disassemble the instruction at addr, naming operands with the given label lookup
+pub fn disassemble<S, A>(
+    nes: &Nes<S, A>,
+    addr: u16,
+    label: impl Fn(u16) -> Option<String>,
+) -> Result<Instruction>
+where
+    S: NesScreen,
+    A: NesAudio,
+{
+    let opcode = buscpu::peek(nes, addr)?;
+    let decoded = match decode::decode::<S, A>(opcode) {
+        Ok(decoded) => decoded,
+        Err(_) => {
+            return Ok(Instruction {
+                addr,
+                bytes: vec![opcode],
+                text: format!(".byte ${:02X}", opcode),
+            })
+        }
+    };
+
+    let mut bytes = vec![opcode];
+    for i in 1..decoded.bytes as u16 {
+        bytes.push(buscpu::peek(nes, addr.wrapping_add(i))?);
+    }
+    let byte = bytes.get(1).copied().unwrap_or(0);
+    let word = (bytes.get(2).copied().unwrap_or(0) as u16) << 8 | byte as u16;
+    let name = |target: u16, hex: String| label(target).unwrap_or(hex);
+
+    let operand = match decoded.addr_mode {
+        Mode::Abs => name(word, format!("${:04X}", word)),
+        Mode::Abx => format!("{},X", name(word, format!("${:04X}", word))),
+        Mode::Aby => format!("{},Y", name(word, format!("${:04X}", word))),
+        Mode::Imm => format!("#${:02X}", byte),
+        Mode::Imp => match opcode {
+            0x0a | 0x2a | 0x4a | 0x6a => String::from("A"),
+            _ => String::new(),
+        },
+        Mode::Ind => format!("({})", name(word, format!("${:04X}", word))),
+        Mode::Idx => format!("({},X)", name(byte as u16, format!("${:02X}", byte))),
+        Mode::Idy => format!("({}),Y", name(byte as u16, format!("${:02X}", byte))),
+        Mode::Rel => {
+            let target = addr.wrapping_add(2).wrapping_add(byte as i8 as u16);
+            name(target, format!("${:04X}", target))
+        }
+        Mode::Zpg => name(byte as u16, format!("${:02X}", byte)),
+        Mode::Zpx => format!("{},X", name(byte as u16, format!("${:02X}", byte))),
+        Mode::Zpy => format!("{},Y", name(byte as u16, format!("${:02X}", byte))),
+        Mode::Xxx => match bytes.len() {
+            2 => format!("${:02X}", byte),
+            3 => format!("${:04X}", word),
+            _ => String::new(),
+        },
+    };
+
+    let text = if operand.is_empty() {
+        decoded.instruction_str.to_string()
+    } else {
+        format!("{} {}", decoded.instruction_str, operand)
+    };
+    Ok(Instruction { addr, bytes, text })
+}
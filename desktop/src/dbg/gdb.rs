@@ -0,0 +1,419 @@
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+
+use anyhow::Result;
+use nes::buscpu;
+use nes::nesaudio::NesAudio;
+use nes::nesscreen::NesScreen;
+use nes::Nes;
+
+use crate::dbg::debugger::Breakpoint;
+use crate::dbg::debugger::Debugger;
+
+pub const GDB_ADDR: &str = "127.0.0.1:6502";
+
+const SIGINT: &str = "S02";
+const SIGTRAP: &str = "S05";
+
+// Register order used by g/G/p/P packets: A, X, Y, SP, P (1 byte each), PC (2 bytes LE)
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.nes.6502">
+    <reg name="a" bitsize="8" regnum="0"/>
+    <reg name="x" bitsize="8" regnum="1"/>
+    <reg name="y" bitsize="8" regnum="2"/>
+    <reg name="sp" bitsize="8" regnum="3"/>
+    <reg name="p" bitsize="8" regnum="4"/>
+    <reg name="pc" bitsize="16" regnum="5" type="code_ptr"/>
+  </feature>
+</target>"#;
+
+/*
+    GDB remote serial protocol server. Runs on the emulator thread: poll() accepts a client,
+    reads whatever bytes arrived and answers the complete packets without blocking.
+*/
+pub struct GdbStub {
+    listener: TcpListener,
+    conn: Option<TcpStream>,
+    buffer: Vec<u8>,
+    no_ack: bool,
+    running: bool,
+}
+
+impl GdbStub {
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        log::info!("GDB server listening on {}", listener.local_addr()?);
+        Ok(Self {
+            listener,
+            conn: None,
+            buffer: vec![],
+            no_ack: false,
+            running: false,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn poll<S, A>(&mut self, nes: &mut Nes<S, A>, dbg: &mut Debugger) -> Result<()>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        if self.conn.is_none() {
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    log::info!("GDB client connected from {}", peer);
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.conn = Some(stream);
+                    self.buffer.clear();
+                    self.no_ack = false;
+                    self.running = false;
+                    // the target is expected to be stopped when a debugger attaches
+                    dbg.halt(nes)?;
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => Err(err)?,
+            }
+        }
+
+        if self.running && dbg.paused {
+            self.running = false;
+            self.send(SIGTRAP)?;
+        }
+
+        if !self.receive()? {
+            log::info!("GDB client disconnected");
+            self.detach(dbg);
+            return Ok(());
+        }
+
+        while let Some(packet) = self.next_packet()? {
+            match packet {
+                Packet::Interrupt => {
+                    if self.running {
+                        dbg.halt(nes)?;
+                        self.running = false;
+                        self.send(SIGINT)?;
+                    }
+                }
+                Packet::Data(data) => {
+                    if !self.no_ack {
+                        self.write_raw(b"+")?;
+                    }
+                    if let Some(reply) = self.handle(&data, nes, dbg)? {
+                        self.send(&reply)?;
+                    }
+                    if self.conn.is_none() {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle<S, A>(
+        &mut self,
+        packet: &str,
+        nes: &mut Nes<S, A>,
+        dbg: &mut Debugger,
+    ) -> Result<Option<String>>
+    where
+        S: NesScreen,
+        A: NesAudio,
+    {
+        let (cmd, args) = packet.split_at(packet.len().min(1));
+        let reply = match cmd {
+            "?" => SIGTRAP.to_string(),
+            "g" => hex(&registers(nes)),
+            "G" => match unhex(args) {
+                Some(regs) if regs.len() >= 7 => {
+                    set_registers(nes, &regs);
+                    String::from("OK")
+                }
+                _ => String::from("E01"),
+            },
+            "p" => match usize::from_str_radix(args, 16) {
+                Ok(reg @ 0..=5) => {
+                    let regs = registers(nes);
+                    match reg {
+                        5 => hex(&regs[5..7]),
+                        _ => hex(&regs[reg..reg + 1]),
+                    }
+                }
+                _ => String::from("E01"),
+            },
+            "P" => match parse_register_write(args) {
+                Some((reg, value)) => {
+                    let mut regs = registers(nes);
+                    match reg {
+                        5 => regs[5..7].copy_from_slice(&value[..2.min(value.len())]),
+                        _ => regs[reg] = value[0],
+                    }
+                    set_registers(nes, &regs);
+                    String::from("OK")
+                }
+                None => String::from("E01"),
+            },
+            "m" => match parse_range(args) {
+                Some((addr, len)) => {
+                    let data = (0..len)
+                        .map(|i| buscpu::peek(nes, addr.wrapping_add(i)))
+                        .collect::<Result<Vec<u8>>>()?;
+                    hex(&data)
+                }
+                None => String::from("E01"),
+            },
+            "M" => {
+                let parsed = args
+                    .split_once(':')
+                    .and_then(|(range, data)| Some((parse_range(range)?, unhex(data)?)));
+                match parsed {
+                    Some(((addr, _), data)) => {
+                        // only RAM takes a debugger write, registers and ROM answer an error
+                        let mut written = true;
+                        for (i, byte) in data.into_iter().enumerate() {
+                            let addr = addr.wrapping_add(i as u16);
+                            if !buscpu::poke(nes, addr, byte).unwrap_or(false) {
+                                written = false;
+                                break;
+                            }
+                        }
+                        String::from(if written { "OK" } else { "E01" })
+                    }
+                    None => String::from("E01"),
+                }
+            }
+            "Z" | "z" => match parse_breakpoint(args) {
+                Some(addr) => {
+                    dbg.breakpoints
+                        .retain(|bp| !matches!(bp, Breakpoint::Addr(bp_addr) if *bp_addr == addr));
+                    if cmd == "Z" {
+                        dbg.breakpoints.push(Breakpoint::Addr(addr));
+                    }
+                    String::from("OK")
+                }
+                // watchpoints are not supported
+                None => String::new(),
+            },
+            "s" => {
+                dbg.step(nes)?;
+                SIGTRAP.to_string()
+            }
+            "c" => {
+                self.resume(dbg);
+                return Ok(None);
+            }
+            "v" => match packet {
+                "vCont?" => String::from("vCont;c;s"),
+                _ if packet.starts_with("vCont;s") => {
+                    dbg.step(nes)?;
+                    SIGTRAP.to_string()
+                }
+                _ if packet.starts_with("vCont;c") => {
+                    self.resume(dbg);
+                    return Ok(None);
+                }
+                _ => String::new(),
+            },
+            "D" | "k" => {
+                self.send("OK")?;
+                self.detach(dbg);
+                return Ok(None);
+            }
+            "H" | "T" => String::from("OK"),
+            "q" | "Q" => self.query(packet),
+            _ => String::new(),
+        };
+        Ok(Some(reply))
+    }
+
+    fn query(&mut self, packet: &str) -> String {
+        match packet {
+            _ if packet.starts_with("qSupported") => {
+                String::from("PacketSize=1000;qXfer:features:read+;QStartNoAckMode+")
+            }
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                String::from("OK")
+            }
+            "qAttached" => String::from("1"),
+            "qC" => String::from("QC1"),
+            "qfThreadInfo" => String::from("m1"),
+            "qsThreadInfo" => String::from("l"),
+            _ if packet.starts_with("qXfer:features:read:target.xml:") => {
+                let range = &packet["qXfer:features:read:target.xml:".len()..];
+                let (offset, len) = match range.split_once(',') {
+                    Some((offset, len)) => (
+                        usize::from_str_radix(offset, 16).unwrap_or(0),
+                        usize::from_str_radix(len, 16).unwrap_or(0),
+                    ),
+                    None => (0, 0),
+                };
+                let start = offset.min(TARGET_XML.len());
+                let end = offset.saturating_add(len).min(TARGET_XML.len());
+                let prefix = if end == TARGET_XML.len() { 'l' } else { 'm' };
+                format!("{}{}", prefix, &TARGET_XML[start..end])
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn resume(&mut self, dbg: &mut Debugger) {
+        self.running = true;
+        dbg.resume();
+    }
+
+    fn detach(&mut self, dbg: &mut Debugger) {
+        self.conn = None;
+        self.running = false;
+        if dbg.paused {
+            dbg.resume();
+        }
+    }
+
+    // Read every pending byte. Returns false once the client hung up.
+    fn receive(&mut self) -> Result<bool> {
+        let conn = match self.conn.as_mut() {
+            Some(conn) => conn,
+            None => return Ok(true),
+        };
+        let mut chunk = [0u8; 1024];
+        loop {
+            match conn.read(&mut chunk) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(true),
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => return Ok(false),
+                Err(err) => Err(err)?,
+            }
+        }
+    }
+
+    fn next_packet(&mut self) -> Result<Option<Packet>> {
+        loop {
+            match self.buffer.first() {
+                None => return Ok(None),
+                Some(0x03) => {
+                    self.buffer.remove(0);
+                    return Ok(Some(Packet::Interrupt));
+                }
+                Some(b'$') => break,
+                // acks and noise between packets
+                Some(_) => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+        let end = match self.buffer.iter().position(|&byte| byte == b'#') {
+            Some(end) if self.buffer.len() >= end + 3 => end,
+            _ => return Ok(None),
+        };
+        let data = self.buffer[1..end].to_vec();
+        let checksum = std::str::from_utf8(&self.buffer[end + 1..end + 3])
+            .ok()
+            .and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+        self.buffer.drain(..end + 3);
+        if !self.no_ack && checksum != Some(checksum_of(&data)) {
+            log::warn!("GDB packet with bad checksum, asking for retransmission");
+            self.write_raw(b"-")?;
+            return self.next_packet();
+        }
+        Ok(Some(Packet::Data(
+            String::from_utf8_lossy(&data).to_string(),
+        )))
+    }
+
+    fn send(&mut self, data: &str) -> Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        self.write_raw(packet.as_bytes())
+    }
+
+    fn write_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        if let Some(conn) = self.conn.as_mut() {
+            conn.set_nonblocking(false)?;
+            conn.write_all(bytes)?;
+            conn.set_nonblocking(true)?;
+        }
+        Ok(())
+    }
+}
+
+enum Packet {
+    Interrupt,
+    Data(String),
+}
+
+fn registers<S, A>(nes: &Nes<S, A>) -> [u8; 7] {
+    let cpu = &nes.cpu;
+    let pc = cpu.pc.to_le_bytes();
+    [cpu.ac, cpu.x, cpu.y, cpu.sp, cpu.status, pc[0], pc[1]]
+}
+
+fn set_registers<S, A>(nes: &mut Nes<S, A>, regs: &[u8]) {
+    nes.cpu.ac = regs[0];
+    nes.cpu.x = regs[1];
+    nes.cpu.y = regs[2];
+    nes.cpu.sp = regs[3];
+    nes.cpu.status = regs[4];
+    nes.cpu.pc = u16::from_le_bytes([regs[5], regs[6]]);
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(data: &str) -> Option<Vec<u8>> {
+    if data.len() % 2 == 1 {
+        return None;
+    }
+    (0..data.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(data.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// "addr,len"
+fn parse_range(args: &str) -> Option<(u16, u16)> {
+    let (addr, len) = args.split_once(',')?;
+    let addr = u32::from_str_radix(addr, 16).ok()?;
+    let len = u16::from_str_radix(len, 16).ok()?;
+    Some((addr as u16, len))
+}
+
+// "reg=value"
+fn parse_register_write(args: &str) -> Option<(usize, Vec<u8>)> {
+    let (reg, value) = args.split_once('=')?;
+    let reg = usize::from_str_radix(reg, 16).ok()?;
+    let value = unhex(value)?;
+    match (reg, value.len()) {
+        (0..=4, 1..) | (5, 2..) => Some((reg, value)),
+        _ => None,
+    }
+}
+
+// "type,addr,kind", only software (0) and hardware (1) execution breakpoints
+fn parse_breakpoint(args: &str) -> Option<u16> {
+    let mut parts = args.split(',');
+    match parts.next()? {
+        "0" | "1" => {}
+        _ => return None,
+    }
+    let addr = u32::from_str_radix(parts.next()?, 16).ok()?;
+    Some(addr as u16)
+}
@@ -1,3 +1,8 @@
 pub mod chrscreen;
+pub mod dap;
+pub mod debugger;
+pub mod disasm;
+pub mod gdb;
 pub mod palettescreen;
+pub mod symbols;
 pub mod vramscreen;
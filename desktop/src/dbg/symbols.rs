@@ -0,0 +1,402 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const INES_HEADER_SIZE: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub addr: u16,
+    // PRG ROM offset, only for symbols that live in cartridge ROM
+    pub prg: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Default)]
+pub struct Symbols {
+    symbols: Vec<Symbol>,
+    by_addr: BTreeMap<u16, Vec<usize>>,
+    by_name: HashMap<String, usize>,
+    lines: HashMap<u16, Vec<(Option<usize>, SourceLine)>>,
+}
+
+impl Symbols {
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty() && self.lines.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /*
+        Load a ld65 debug file (.dbg), a FCEUX label file (.nl) or a Mesen label file (.mlb).
+        Returns the number of symbols that were added.
+    */
+    pub fn load(&mut self, path: &Path, prg_len: usize) -> Result<usize> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Cannot read symbol file {:?}", path))?;
+        let before = self.symbols.len();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("dbg") => self.parse_dbg(&text)?,
+            Some("nl") => {
+                let bank = nl_bank(path);
+                self.parse_nl(&text, bank);
+            }
+            Some("mlb") => self.parse_mlb(&text, prg_len),
+            _ => Err(anyhow!("Unknown symbol file format: {:?}", path))?,
+        }
+        Ok(self.symbols.len() - before)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Symbol> {
+        self.by_name.get(name).map(|&i| &self.symbols[i])
+    }
+
+    // Exact label at a CPU address. When the PRG offset is known it picks the right bank.
+    pub fn label(&self, addr: u16, prg: Option<usize>) -> Option<&str> {
+        self.by_addr
+            .get(&addr)
+            .and_then(|ids| pick(&self.symbols, ids, prg))
+            .map(|sym| sym.name.as_str())
+    }
+
+    // Closest label at or before a CPU address, formatted as "label" or "label+offset".
+    pub fn nearest(&self, addr: u16, prg: Option<usize>) -> Option<String> {
+        let (&sym_addr, ids) = self.by_addr.range(..=addr).next_back()?;
+        let offset = addr - sym_addr;
+        if offset > 0xff {
+            return None;
+        }
+        let prg = prg.map(|prg| prg.wrapping_sub(offset as usize));
+        let sym = pick(&self.symbols, ids, prg)?;
+        if offset == 0 {
+            Some(sym.name.clone())
+        } else {
+            Some(format!("{}+{}", sym.name, offset))
+        }
+    }
+
+    pub fn line(&self, addr: u16, prg: Option<usize>) -> Option<&SourceLine> {
+        let lines = self.lines.get(&addr)?;
+        match prg {
+            Some(prg) => lines
+                .iter()
+                .find(|(line_prg, _)| *line_prg == Some(prg))
+                .or_else(|| lines.iter().find(|(line_prg, _)| line_prg.is_none())),
+            None => lines.first(),
+        }
+        .map(|(_, line)| line)
+    }
+
+    /*
+        CPU addresses (and PRG offsets) generated by a source line. The path given by an editor
+        only has to end with the file name recorded by ld65, which is relative to the build.
+    */
+    pub fn addresses(&self, file: &str, line: usize) -> Vec<(u16, Option<usize>)> {
+        let mut addrs = self
+            .lines
+            .iter()
+            .flat_map(|(&addr, lines)| {
+                lines
+                    .iter()
+                    .filter(|(_, src)| src.line == line && Path::new(file).ends_with(&src.file))
+                    .map(move |(prg, _)| (addr, *prg))
+            })
+            .collect::<Vec<(u16, Option<usize>)>>();
+        addrs.sort();
+        addrs
+    }
+
+    fn add(&mut self, name: &str, addr: u16, prg: Option<usize>) {
+        if name.is_empty() {
+            return;
+        }
+        let id = self.symbols.len();
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            addr,
+            prg,
+        });
+        self.by_addr.entry(addr).or_default().push(id);
+        self.by_name.entry(name.to_string()).or_insert(id);
+    }
+
+    fn parse_dbg(&mut self, text: &str) -> Result<()> {
+        struct Seg {
+            start: u16,
+            ooffs: Option<usize>,
+        }
+        struct Span {
+            seg: usize,
+            start: usize,
+        }
+
+        let mut files = HashMap::new();
+        let mut segs = HashMap::new();
+        let mut spans = HashMap::new();
+        let mut syms = vec![];
+        let mut lines = vec![];
+
+        for record in text.lines() {
+            let (kind, fields) = match record.split_once(char::is_whitespace) {
+                Some((kind, fields)) => (kind, dbg_fields(fields.trim())),
+                None => continue,
+            };
+            let num = |key: &str| fields.get(key).and_then(|val| parse_num(val));
+            match kind {
+                "file" => {
+                    if let (Some(id), Some(name)) = (num("id"), fields.get("name")) {
+                        files.insert(id, name.clone());
+                    }
+                }
+                "seg" => {
+                    if let (Some(id), Some(start)) = (num("id"), num("start")) {
+                        // only read-only segments written to the ROM image map to PRG ROM
+                        let ooffs = match fields.get("type").map(|t| t.as_str()) {
+                            Some("ro") => num("ooffs"),
+                            _ => None,
+                        };
+                        segs.insert(
+                            id,
+                            Seg {
+                                start: start as u16,
+                                ooffs,
+                            },
+                        );
+                    }
+                }
+                "span" => {
+                    if let (Some(id), Some(seg), Some(start)) =
+                        (num("id"), num("seg"), num("start"))
+                    {
+                        spans.insert(id, Span { seg, start });
+                    }
+                }
+                // only labels are addresses, equates may be any constant
+                "sym" if fields.get("type").map(|t| t.as_str()) == Some("lab") => {
+                    if let (Some(name), Some(val)) = (fields.get("name"), num("val")) {
+                        syms.push((name.clone(), val as u16, num("seg")));
+                    }
+                }
+                "line" => {
+                    // skip lines generated by macro expansion, prefer the real source
+                    if num("type").unwrap_or(0) != 0 {
+                        continue;
+                    }
+                    if let (Some(file), Some(line), Some(span)) =
+                        (num("file"), num("line"), fields.get("span"))
+                    {
+                        for span in span.split('+').filter_map(parse_num) {
+                            lines.push((file, line, span));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let prg_offset = |seg: &Seg, offset: usize| {
+            seg.ooffs
+                .and_then(|ooffs| ooffs.checked_sub(INES_HEADER_SIZE))
+                .map(|base| base + offset)
+        };
+
+        for (name, val, seg) in syms {
+            let prg = seg
+                .and_then(|seg| segs.get(&seg))
+                .and_then(|seg| prg_offset(seg, val.wrapping_sub(seg.start) as usize));
+            self.add(&name, val, prg);
+        }
+
+        for (file, line, span) in lines {
+            let (span, file) = match (spans.get(&span), files.get(&file)) {
+                (Some(span), Some(file)) => (span, file),
+                _ => continue,
+            };
+            let seg = match segs.get(&span.seg) {
+                Some(seg) => seg,
+                None => continue,
+            };
+            let addr = seg.start.wrapping_add(span.start as u16);
+            let prg = prg_offset(seg, span.start);
+            self.lines.entry(addr).or_default().push((
+                prg,
+                SourceLine {
+                    file: file.clone(),
+                    line,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /*
+        FCEUX name lists: one "$ADDR#label#comment" per line.
+        Bank files ("game.nes.N.nl") hold 16 KB PRG banks, "game.nes.ram.nl" holds RAM labels.
+    */
+    fn parse_nl(&mut self, text: &str, bank: Option<usize>) {
+        for line in text.lines() {
+            let mut parts = line.splitn(3, '#');
+            let (addr, name) = match (parts.next(), parts.next()) {
+                (Some(addr), Some(name)) => (addr, name.trim()),
+                _ => continue,
+            };
+            // "$ADDR/SIZE" declares an array, only the start address is labelled
+            let addr = addr.trim().trim_start_matches('$');
+            let addr = addr.split('/').next().unwrap_or(addr);
+            let addr = match u16::from_str_radix(addr, 16) {
+                Ok(addr) => addr,
+                Err(_) => continue,
+            };
+            let prg = match bank {
+                Some(bank) if addr >= 0x8000 => {
+                    Some(bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1)))
+                }
+                _ => None,
+            };
+            self.add(name, addr, prg);
+        }
+    }
+
+    /*
+        Mesen label files: one "TYPE:ADDR[-END]:label[:comment]" per line, where ADDR is an
+        offset into the memory named by TYPE.
+    */
+    fn parse_mlb(&mut self, text: &str, prg_len: usize) {
+        for line in text.lines() {
+            let mut parts = line.splitn(4, ':');
+            let (kind, addr, name) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(kind), Some(addr), Some(name)) => (kind, addr, name.trim()),
+                _ => continue,
+            };
+            let addr = addr.split('-').next().unwrap_or(addr);
+            let offset = match usize::from_str_radix(addr, 16) {
+                Ok(offset) => offset,
+                Err(_) => continue,
+            };
+            match kind {
+                "P" | "NesPrgRom" => self.add(name, guess_cpu_addr(offset, prg_len), Some(offset)),
+                "R" | "NesInternalRam" | "G" | "NesMemory" if offset <= 0xffff => {
+                    self.add(name, offset as u16, None)
+                }
+                "W" | "S" | "NesWorkRam" | "NesSaveRam" if offset < 0x2000 => {
+                    self.add(name, 0x6000 + offset as u16, None)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Symbol files that sit next to a ROM and share its name
+pub fn discover(rom_path: &Path) -> Vec<PathBuf> {
+    let mut found = vec![];
+    let stem = rom_path.with_extension("");
+    for ext in ["dbg", "mlb"] {
+        let path = stem.with_extension(ext);
+        if path.is_file() {
+            found.push(path);
+        }
+    }
+    if let (Some(dir), Some(name)) = (rom_path.parent(), rom_path.file_name()) {
+        let prefix = format!("{}.", name.to_string_lossy());
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        if let Ok(entries) = fs::read_dir(dir) {
+            let mut nl_files = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    let file_name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    file_name.starts_with(&prefix) && file_name.ends_with(".nl")
+                })
+                .collect::<Vec<PathBuf>>();
+            nl_files.sort();
+            found.extend(nl_files);
+        }
+    }
+    found
+}
+
+// CPU address a PRG ROM offset is usually seen at, assuming the last 16 KB bank is fixed
+fn guess_cpu_addr(offset: usize, prg_len: usize) -> u16 {
+    if prg_len <= 0x8000 {
+        0x8000 | (offset as u16 & 0x7fff)
+    } else if offset >= prg_len - PRG_BANK_SIZE {
+        0xc000 | (offset as u16 & 0x3fff)
+    } else {
+        0x8000 | (offset as u16 & 0x3fff)
+    }
+}
+
+fn pick<'a>(symbols: &'a [Symbol], ids: &[usize], prg: Option<usize>) -> Option<&'a Symbol> {
+    let mut candidates = ids.iter().map(|&i| &symbols[i]);
+    match prg {
+        Some(prg) => {
+            let candidates = candidates.collect::<Vec<&Symbol>>();
+            candidates
+                .iter()
+                .find(|sym| sym.prg == Some(prg))
+                .or_else(|| candidates.iter().find(|sym| sym.prg.is_none()))
+                .copied()
+        }
+        None => candidates.next(),
+    }
+}
+
+// Bank number of a FCEUX bank file ("game.nes.3.nl"), None for RAM files ("game.nes.ram.nl")
+fn nl_bank(path: &Path) -> Option<usize> {
+    let stem = path.file_stem()?.to_string_lossy().to_string();
+    let bank = stem.rsplit('.').next()?;
+    usize::from_str_radix(bank, 16).ok()
+}
+
+fn parse_num(val: &str) -> Option<usize> {
+    match val.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => val.parse().ok(),
+    }
+}
+
+// Split "key=val,key="quoted, val"" into a map
+fn dbg_fields(fields: &str) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    let mut push = |field: &str| {
+        if let Some((key, val)) = field.split_once('=') {
+            map.insert(key.to_string(), val.trim_matches('"').to_string());
+        }
+    };
+    for (i, c) in fields.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                push(&fields[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    push(&fields[start..]);
+    map
+}
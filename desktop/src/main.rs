@@ -1,14 +1,21 @@
 use std::cell::RefCell;
 use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 
+use ::nes::archive;
+use ::nes::mappers::MapperRegistry;
+use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
 use minifb::Scale;
 use minifb::Window;
 use minifb::WindowOptions;
 
+use crate::audio::NesAudio;
 use crate::nes::Nes;
+use crate::screen::NesScreen;
 
 const WIDTH: usize = 256;
 const HEIGHT: usize = 240;
@@ -16,6 +23,21 @@ const HEIGHT: usize = 240;
 fn main() -> Result<()> {
     env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
 
+    if std::env::args().any(|arg| arg == "--list-mappers") {
+        list_mappers();
+        return Ok(());
+    }
+
+    if let Some(out) = arg_value("--wav") {
+        let nsf_path = rom_path()?;
+        let seconds = arg_value("--seconds")
+            .map(|seconds| seconds.parse::<u32>())
+            .transpose()
+            .context("Invalid --seconds value")?;
+        let nsf = fs::read(&nsf_path)?;
+        return wav::render(&nsf, track_arg()?, seconds, Path::new(&out));
+    }
+
     let win_options = WindowOptions {
         scale: Scale::X2,
         ..Default::default()
@@ -32,16 +54,34 @@ fn main() -> Result<()> {
     log::debug!("Debug logs enabled...");
     let mut nes = Nes::new(window)?;
 
-    let nes_rom_path = std::env::args().rev().collect::<Vec<String>>();
-    let nes_rom_path = nes_rom_path
-        .get(0)
-        .context("Cannot get file from CLI arguments")?;
-    let game_rom = fs::read(&nes_rom_path)?;
-    nes.load(&game_rom)?;
-    log::info!("Loaded game {:?}", &nes_rom_path);
+    if cfg!(feature = "dap") && std::env::args().len() == 1 {
+        nes.wait_for_launch();
+    } else {
+        let nes_rom_path = &rom_path()?;
+        if let Some(gamedb_path) = arg_value("--gamedb") {
+            nes.load_gamedb(Path::new(&gamedb_path))?;
+        }
+        let (rom_name, game_rom) = read_rom(Path::new(nes_rom_path))?;
+        if is_disk_image(Path::new(&rom_name)) {
+            let bios_path = fds_bios_path(Path::new(nes_rom_path));
+            let bios = fs::read(&bios_path)
+                .with_context(|| format!("Cannot read FDS BIOS {:?}", bios_path))?;
+            nes.load_disk(Path::new(nes_rom_path), &game_rom, &bios)?;
+        } else {
+            let patches = rom_patches(Path::new(nes_rom_path))?;
+            let patches = patches.iter().map(Vec::as_slice).collect::<Vec<_>>();
+            nes.load_patched(&game_rom, &patches)?;
+            if let Some(track) = track_arg()? {
+                nes.play_track(track)?;
+            }
+        }
+        log::info!("Loaded game {:?}", &nes_rom_path);
+        nes.load_symbols(Path::new(nes_rom_path));
+    }
 
     loop {
         nes.poll_command()?;
+        nes.poll_debug_servers()?;
         nes.poll_key_press()?;
         if let Err(err) = nes.clock() {
             log::error!("Game crahed due to err: {}", err);
@@ -51,8 +91,108 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn is_disk_image(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("fds"))
+}
+
+// Read a ROM, unpacking it from a ZIP, gzip or 7z archive with --entry choosing the file
+fn read_rom(path: &Path) -> Result<(String, Vec<u8>)> {
+    let bytes = fs::read(path).with_context(|| format!("Cannot read ROM {:?}", path))?;
+    let file_name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    if !archive::is_archive(&bytes) {
+        return Ok((file_name(path), bytes));
+    }
+    for name in archive::list(&bytes)? {
+        log::info!("Archive holds {:?}", name);
+    }
+    let rom = archive::extract(&bytes, arg_value("--entry").as_deref())?;
+    // gzip files may not store a name, game.fds.gz then holds game.fds
+    let name = match rom.name.is_empty() {
+        true => file_name(&path.with_extension("")),
+        false => rom.name,
+    };
+    log::info!("Unpacked {:?}", name);
+    Ok((name, rom.data))
+}
+
+// The ROM is always the last argument
+fn rom_path() -> Result<String> {
+    std::env::args()
+        .next_back()
+        .context("Cannot get file from CLI arguments")
+}
+
+// Value following an option like --fds-bios
+fn arg_value(name: &str) -> Option<String> {
+    let args = std::env::args().collect::<Vec<String>>();
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+// NSF track from --track, numbered from 1 on the command line
+fn track_arg() -> Result<Option<u8>> {
+    match arg_value("--track") {
+        Some(track) => match track.parse::<u8>() {
+            Ok(track) if track > 0 => Ok(Some(track - 1)),
+            _ => Err(anyhow!("Invalid --track value {}", track)),
+        },
+        None => Ok(None),
+    }
+}
+
+// Patches named like the ROM, such as game.ips for game.nes
+fn rom_patches(rom_path: &Path) -> Result<Vec<Vec<u8>>> {
+    let mut patches = vec![];
+    for ext in ["ips", "ups", "bps"] {
+        let patch_path = rom_path.with_extension(ext);
+        if patch_path.exists() {
+            log::info!("Applying patch {:?}", patch_path);
+            patches.push(
+                fs::read(&patch_path)
+                    .with_context(|| format!("Cannot read patch {:?}", patch_path))?,
+            );
+        }
+    }
+    Ok(patches)
+}
+
+// BIOS from --fds-bios, or disksys.rom next to the disk image
+fn fds_bios_path(disk_path: &Path) -> PathBuf {
+    arg_value("--fds-bios")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| disk_path.with_file_name("disksys.rom"))
+}
+
+fn list_mappers() {
+    for (number, submapper, name) in MapperRegistry::<NesScreen, NesAudio>::default().list() {
+        match submapper {
+            Some(submapper) => println!("{:>3}.{:<2} {}", number, submapper, name),
+            None => println!("{:>3}    {}", number, name),
+        }
+    }
+}
+
 pub mod audio;
 pub mod commands;
 pub mod dbg;
 pub mod nes;
+pub mod player;
 pub mod screen;
+pub mod wav;
+
+#[cfg(test)]
+mod tests {
+    mod commands;
+    mod dap;
+    mod gdb;
+    mod player;
+    mod symbols;
+    mod wav;
+}
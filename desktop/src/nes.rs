@@ -1,20 +1,33 @@
 use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::mpsc;
 use std::sync::mpsc::Receiver;
 use std::thread;
+use std::time::Duration;
 
 use ::nes::joypad::Button;
+use ::nes::patch;
+use anyhow::Context;
 use anyhow::Result;
 use minifb::Key;
+use minifb::KeyRepeat;
 use minifb::Window;
 
 use crate::audio::NesAudio;
 use crate::commands;
 use crate::dbg::chrscreen::ChrScreen;
+use crate::dbg::dap;
+use crate::dbg::dap::DapServer;
+use crate::dbg::debugger::Debugger;
+use crate::dbg::gdb;
+use crate::dbg::gdb::GdbStub;
 use crate::dbg::palettescreen::PaletteScreen;
 use crate::dbg::vramscreen::Corner;
 use crate::dbg::vramscreen::VramScreen;
+use crate::player::Player;
 use crate::screen::NesScreen;
 
 pub struct Nes {
@@ -25,6 +38,13 @@ pub struct Nes {
     dbg_palette: Option<PaletteScreen>,
     clock: u16,
     command_recv: Receiver<String>,
+    debugger: Debugger,
+    gdb: Option<GdbStub>,
+    dap: Option<DapServer>,
+    // where disk writes are saved, and the untouched image they are diffed against
+    disk_save: Option<(PathBuf, Vec<u8>)>,
+    // set while an NSF tune plays
+    player: Option<Player>,
 }
 
 impl Nes {
@@ -45,6 +65,17 @@ impl Nes {
             (None, None, None)
         };
 
+        let gdb = if cfg!(feature = "gdb") {
+            Some(GdbStub::bind(gdb::GDB_ADDR)?)
+        } else {
+            None
+        };
+        let dap = if cfg!(feature = "dap") {
+            Some(DapServer::bind(dap::DAP_ADDR)?)
+        } else {
+            None
+        };
+
         // Spawn command thread
         let (tx, rx) = mpsc::channel();
         thread::spawn(move || loop {
@@ -61,17 +92,44 @@ impl Nes {
             dbg_palette,
             clock: 0,
             command_recv: rx,
+            debugger: Debugger::default(),
+            gdb,
+            dap,
+            disk_save: None,
+            player: None,
         })
     }
 
     pub fn clock(&mut self) -> Result<()> {
+        if self.debugger.paused {
+            // keep the window responsive while stopped in the debugger
+            self.window.try_borrow_mut()?.update();
+            thread::sleep(Duration::from_millis(10));
+            return Ok(());
+        }
         if cfg!(feature = "step") {
+            if self.debugger.on_instruction(&mut self.nes)? {
+                return Ok(());
+            }
+            if let Some(label) = self.debugger.label(&self.nes, self.nes.cpu.pc) {
+                println!("{label}:");
+            }
             let inst = self.nes.step()?;
             println!("{inst}");
         } else {
+            if self.nes.cpu.cycles == 0 && self.debugger.on_instruction(&mut self.nes)? {
+                return Ok(());
+            }
             self.nes.clock()?;
         }
         self.clock = self.clock.wrapping_add(1);
+        if let Some(player) = self.player.as_mut() {
+            player.clock();
+        }
+        if self.clock == 0 {
+            self.save_disk()?;
+            self.show_player()?;
+        }
         if self.clock == 0 && cfg!(feature = "screens") {
             ::nes::ppu::draw_chr(&mut self.nes, 0, &mut self.dbg_chr.as_mut().unwrap()[0])?;
             ::nes::ppu::draw_chr(&mut self.nes, 1, &mut self.dbg_chr.as_mut().unwrap()[1])?;
@@ -85,15 +143,101 @@ impl Nes {
     }
 
     pub fn load(&mut self, rom_bytes: &[u8]) -> Result<()> {
-        self.nes.load(rom_bytes)?;
+        self.load_patched(rom_bytes, &[])
+    }
+
+    pub fn load_patched(&mut self, rom_bytes: &[u8], patches: &[&[u8]]) -> Result<()> {
+        self.nes.load_patched(rom_bytes, patches)?;
+        self.nes.reset()?;
+        self.player = self.nes.cartridge.nsf.as_ref().map(|nsf| {
+            log::info!("Playing {:?} by {:?}", nsf.title, nsf.artist);
+            log::info!(
+                "Left and right arrows switch between the {} tracks",
+                nsf.tracks
+            );
+            Player::new(nsf)
+        });
+        Ok(())
+    }
+
+    // Add header fixes from a game database file to the built in ones
+    pub fn load_gamedb(&mut self, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Cannot read game database {:?}", path))?;
+        let count = self.nes.gamedb.load(&text)?;
+        log::info!("Loaded {} game database entries from {:?}", count, path);
+        Ok(())
+    }
+
+    // Switch the NSF tune being played to a track numbered from 0
+    pub fn play_track(&mut self, track: u8) -> Result<()> {
+        let player = self
+            .player
+            .as_mut()
+            .context("Tracks can only be selected for NSF files")?;
+        self.nes.play_track(track)?;
+        player.restart(track);
+        Ok(())
+    }
+
+    fn show_player(&mut self) -> Result<()> {
+        if let Some(player) = self.player.as_ref() {
+            self.window.try_borrow_mut()?.set_title(&player.status());
+        }
+        Ok(())
+    }
+
+    // Load an FDS image with the writes saved from earlier sessions
+    pub fn load_disk(&mut self, disk_path: &Path, disk_image: &[u8], bios: &[u8]) -> Result<()> {
+        // game.sav.ips, so it doesn't take the place of a game.ips patch for the image
+        let save_path = disk_path.with_extension("sav.ips");
+        let patched = match fs::read(&save_path) {
+            Ok(ips) => {
+                log::info!("Applying disk save {:?}", save_path);
+                patch::apply_ips(disk_image, &ips)?
+            }
+            Err(_) => disk_image.to_vec(),
+        };
+        self.nes.load_disk(&patched, bios)?;
         self.nes.reset()?;
+        self.disk_save = Some((save_path, disk_image.to_vec()));
         Ok(())
     }
 
+    // Write the disk as a patch over the original image once the game has written to it
+    fn save_disk(&mut self) -> Result<()> {
+        let (save_path, original) = match self.disk_save.as_ref() {
+            Some(disk_save) => disk_save,
+            None => return Ok(()),
+        };
+        let disk = match self.nes.cartridge.disk.as_mut() {
+            Some(disk) if disk.modified => disk,
+            _ => return Ok(()),
+        };
+        let ips = patch::create_ips(original, &disk.image())?;
+        fs::write(save_path, ips)
+            .with_context(|| format!("Cannot write disk save {:?}", save_path))?;
+        disk.modified = false;
+        log::info!("Saved disk to {:?}", save_path);
+        Ok(())
+    }
+
+    pub fn load_symbols(&mut self, rom_path: &Path) {
+        self.debugger.discover_symbols(&self.nes, rom_path);
+    }
+
     pub fn poll_key_press(&mut self) -> Result<()> {
         let window = self.window.try_borrow();
         let nes = &mut self.nes;
-        if let Ok(window) = window {
+        if let (Ok(window), Some(player)) = (&window, self.player.as_mut()) {
+            for (key, forward) in [(Key::Right, true), (Key::Left, false)] {
+                if window.is_key_pressed(key, KeyRepeat::No) {
+                    let track = player.next(forward);
+                    nes.play_track(track)?;
+                    player.restart(track);
+                }
+            }
+        } else if let Ok(window) = window {
             Self::poll_single_key(nes, &window, Key::Up, Button::Up)?;
             Self::poll_single_key(nes, &window, Key::Down, Button::Down)?;
             Self::poll_single_key(nes, &window, Key::Right, Button::Right)?;
@@ -108,9 +252,34 @@ impl Nes {
 
     pub fn poll_command(&mut self) -> Result<()> {
         if let Ok(msg) = self.command_recv.try_recv() {
-            match commands::parse(&msg[..msg.len() - 1]) {
-                Ok(cmd) => commands::exec(cmd, &mut self.nes)?,
-                Err(err) => log::error!("{:?}", err),
+            let result = commands::parse(&msg[..msg.len() - 1])
+                .and_then(|cmd| commands::exec(cmd, &mut self.nes, &mut self.debugger));
+            if let Err(err) = result {
+                log::error!("{:?}", err);
+            }
+        }
+        Ok(())
+    }
+
+    // Stay paused until a debug adapter client launches a ROM
+    pub fn wait_for_launch(&mut self) {
+        log::info!("No ROM given, waiting for a debug adapter client to launch one");
+        self.debugger.paused = true;
+    }
+
+    pub fn poll_debug_servers(&mut self) -> Result<()> {
+        // polling the socket every cycle would slow emulation to a crawl
+        if self.clock & 0x3ff != 0 && !self.debugger.paused {
+            return Ok(());
+        }
+        if let Some(gdb) = self.gdb.as_mut() {
+            if let Err(err) = gdb.poll(&mut self.nes, &mut self.debugger) {
+                log::error!("GDB server: {:?}", err);
+            }
+        }
+        if let Some(dap) = self.dap.as_mut() {
+            if let Err(err) = dap.poll(&mut self.nes, &mut self.debugger) {
+                log::error!("DAP server: {:?}", err);
             }
         }
         Ok(())
@@ -0,0 +1,69 @@
+use ::nes::nsf::Nsf;
+
+const CPU_FREQ: u64 = 1789773;
+
+// What the window shows while an NSF tune plays, in place of a game screen
+pub struct Player {
+    track: u8,
+    tracks: u8,
+    title: String,
+    track_titles: Vec<String>,
+    track_times: Vec<Option<u32>>,
+    cycles: u64,
+}
+
+impl Player {
+    pub fn new(nsf: &Nsf) -> Self {
+        Self {
+            track: nsf.start_track,
+            tracks: nsf.tracks,
+            title: nsf.title.clone(),
+            track_titles: (0..nsf.tracks)
+                .map(|track| nsf.track_title(track).unwrap_or_default().to_string())
+                .collect(),
+            track_times: (0..nsf.tracks).map(|track| nsf.track_time(track)).collect(),
+            cycles: 0,
+        }
+    }
+
+    pub fn clock(&mut self) {
+        self.cycles += 1;
+    }
+
+    pub fn seconds(&self) -> u64 {
+        self.cycles / CPU_FREQ
+    }
+
+    // Track after or before the current one, wrapping around
+    pub fn next(&self, forward: bool) -> u8 {
+        let tracks = self.tracks.max(1);
+        match (forward, self.track) {
+            (true, track) => (track + 1) % tracks,
+            (false, 0) => tracks - 1,
+            (false, track) => track - 1,
+        }
+    }
+
+    pub fn restart(&mut self, track: u8) {
+        self.track = track;
+        self.cycles = 0;
+    }
+
+    // Window title like "Track 2/12 - Title - Song - 01:05 / 02:30"
+    pub fn status(&self) -> String {
+        let mut status = format!("Track {}/{}", self.track + 1, self.tracks);
+        let track_title = self.track_titles.get(self.track as usize);
+        for title in [Some(&self.title), track_title].into_iter().flatten() {
+            if !title.is_empty() {
+                status += &format!(" - {}", title);
+            }
+        }
+        let seconds = self.seconds();
+        status += &format!(" - {:02}:{:02}", seconds / 60, seconds % 60);
+        if let Some(Some(time)) = self.track_times.get(self.track as usize) {
+            let length = time / 1000;
+            status += &format!(" / {:02}:{:02}", length / 60, length % 60);
+        }
+        status
+    }
+}
@@ -0,0 +1,30 @@
+use std::fs;
+
+use anyhow::Result;
+use nes::nesaudio::NoAudio;
+use nes::nesscreen::NoScreen;
+use nes::Nes;
+
+use crate::commands;
+use crate::commands::Command;
+
+const NES_TEST_FILE: &str = "../nes/test-files/nestest.nes";
+
+#[test]
+fn banks_lists_the_mapped_banks() -> Result<()> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&fs::read(NES_TEST_FILE)?)?;
+    nes.reset()?;
+
+    assert!(matches!(commands::parse("banks")?, Command::Banks));
+    assert_eq!(
+        commands::bank_lines(&nes)?,
+        [
+            "Mapper: NROM",
+            "CPU 8000-BFFF: PrgRom bank 0 (offset 0x00000)",
+            "CPU C000-FFFF: PrgRom bank 0 (offset 0x00000)",
+            "PPU 0000-1FFF: ChrRom bank 0 (offset 0x00000)",
+        ]
+    );
+    Ok(())
+}
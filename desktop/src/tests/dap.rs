@@ -0,0 +1,199 @@
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use nes::nesaudio::NoAudio;
+use nes::nesscreen::NoScreen;
+use nes::Nes;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::dbg::dap::DapServer;
+use crate::dbg::debugger::Debugger;
+
+const NES_TEST_FILE: &str = "../nes/test-files/nestest.nes";
+
+struct Client {
+    nes: Nes<NoScreen, NoAudio>,
+    dbg: Debugger,
+    server: DapServer,
+    stream: TcpStream,
+    received: Vec<u8>,
+    seq: i64,
+}
+
+impl Client {
+    fn new() -> Result<Self> {
+        let mut dbg = Debugger::default();
+        dbg.paused = true;
+        let server = DapServer::bind("127.0.0.1:0")?;
+        let stream = TcpStream::connect(server.local_addr()?)?;
+        stream.set_read_timeout(Some(Duration::from_millis(20)))?;
+        Ok(Self {
+            nes: Nes::new(NoScreen, NoAudio),
+            dbg,
+            server,
+            stream,
+            received: vec![],
+            seq: 1,
+        })
+    }
+
+    fn request(&mut self, command: &str, args: Value) -> Result<Value> {
+        let msg =
+            json!({ "seq": self.seq, "type": "request", "command": command, "arguments": args })
+                .to_string();
+        self.seq += 1;
+        self.stream
+            .write_all(format!("Content-Length: {}\r\n\r\n{}", msg.len(), msg).as_bytes())?;
+        let response = self.wait(|msg| msg["type"].as_str() == Some("response"))?;
+        assert_eq!(response["command"].as_str(), Some(command));
+        if response["success"].as_bool() != Some(true) {
+            Err(anyhow!("{} failed: {}", command, response))?;
+        }
+        Ok(response["body"].clone())
+    }
+
+    fn event(&mut self, event: &str) -> Result<Value> {
+        let msg = self.wait(|msg| msg["event"].as_str() == Some(event))?;
+        Ok(msg["body"].clone())
+    }
+
+    // Run the emulator and the server until a matching message arrives
+    fn wait(&mut self, matches: impl Fn(&Value) -> bool) -> Result<Value> {
+        for _ in 0..1000 {
+            while let Some(msg) = self.next_message()? {
+                if matches(&msg) {
+                    return Ok(msg);
+                }
+            }
+            self.server.poll(&mut self.nes, &mut self.dbg)?;
+            if !self.dbg.paused {
+                for _ in 0..1024 {
+                    if self.nes.cpu.cycles == 0 && self.dbg.on_instruction(&mut self.nes)? {
+                        break;
+                    }
+                    self.nes.clock()?;
+                }
+            }
+            let mut chunk = [0u8; 4096];
+            if let Ok(n) = self.stream.read(&mut chunk) {
+                self.received.extend_from_slice(&chunk[..n]);
+            }
+        }
+        Err(anyhow!("No matching message from the DAP server"))
+    }
+
+    fn next_message(&mut self) -> Result<Option<Value>> {
+        let text = String::from_utf8_lossy(&self.received).to_string();
+        let (header, rest) = match text.split_once("\r\n\r\n") {
+            Some(split) => split,
+            None => return Ok(None),
+        };
+        let len = header["Content-Length: ".len()..].parse::<usize>()?;
+        if rest.len() < len {
+            return Ok(None);
+        }
+        let msg = serde_json::from_str(&rest[..len])?;
+        self.received.drain(..header.len() + 4 + len);
+        Ok(Some(msg))
+    }
+}
+
+#[test]
+fn dap_launch_break_and_inspect() -> Result<()> {
+    let mut client = Client::new()?;
+
+    let caps = client.request("initialize", json!({ "adapterID": "nes" }))?;
+    assert_eq!(caps["supportsDisassembleRequest"].as_bool(), Some(true));
+    client.event("initialized")?;
+
+    client.request(
+        "launch",
+        json!({ "program": NES_TEST_FILE, "stopOnEntry": true }),
+    )?;
+    client.request("configurationDone", Value::Null)?;
+    let stopped = client.event("stopped")?;
+    assert_eq!(stopped["reason"].as_str(), Some("entry"));
+
+    // nestest runs unattended from $C000
+    let set = client.request(
+        "setVariable",
+        json!({ "variablesReference": 1, "name": "PC", "value": "$C000" }),
+    )?;
+    assert_eq!(set["value"].as_str(), Some("$C000"));
+
+    let memory = client.request(
+        "readMemory",
+        json!({ "memoryReference": "0xC000", "count": 3 }),
+    )?;
+    assert_eq!(memory["data"].as_str(), Some("TPXF"));
+
+    let disasm = client.request(
+        "disassemble",
+        json!({ "memoryReference": "0xC000", "instructionCount": 2 }),
+    )?;
+    let insts = &disasm["instructions"];
+    assert_eq!(insts.as_array().map(Vec::len), Some(2));
+    assert_eq!(insts[0]["instruction"].as_str(), Some("JMP $C5F5"));
+    assert_eq!(insts[1]["address"].as_str(), Some("0xC003"));
+
+    // counts and offsets past the address space are clamped
+    let disasm = client.request(
+        "disassemble",
+        json!({ "memoryReference": "0xC000", "instructionOffset": -100000, "instructionCount": 1 }),
+    )?;
+    assert!(disasm["instructions"].as_array().is_some());
+
+    // RAM takes writes, ROM doesn't
+    client.request(
+        "writeMemory",
+        json!({ "memoryReference": "0x0010", "data": "vu8=" }),
+    )?;
+    assert_eq!(client.nes.bus_cpu.ram[0x10..0x12], [0xbe, 0xef]);
+    assert!(client
+        .request(
+            "writeMemory",
+            json!({ "memoryReference": "0xC000", "data": "AA==" }),
+        )
+        .is_err());
+
+    let bps = client.request(
+        "setInstructionBreakpoints",
+        json!({ "breakpoints": [{ "instructionReference": "0xC72D" }] }),
+    )?;
+    assert_eq!(bps["breakpoints"][0]["verified"].as_bool(), Some(true));
+    assert_eq!(client.dbg.breakpoints.len(), 1);
+
+    client.request("continue", Value::Null)?;
+    let stopped = client.event("stopped")?;
+    assert_eq!(stopped["reason"].as_str(), Some("breakpoint"));
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }))?;
+    assert_eq!(
+        trace["stackFrames"][0]["instructionPointerReference"].as_str(),
+        Some("0xC72D")
+    );
+
+    let vars = client.request("variables", json!({ "variablesReference": 1 }))?;
+    let pc = vars["variables"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|var| var["name"].as_str() == Some("PC"))
+        .map(|var| var["value"].clone());
+    assert_eq!(pc, Some(Value::from("$C72D")));
+
+    client.request("stepIn", json!({ "threadId": 1 }))?;
+    let stopped = client.event("stopped")?;
+    assert_eq!(stopped["reason"].as_str(), Some("step"));
+    assert_ne!(client.nes.cpu.pc, 0xc72d);
+
+    client.request("disconnect", Value::Null)?;
+    assert!(client.dbg.breakpoints.is_empty());
+    assert!(!client.dbg.paused);
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use nes::nesaudio::NoAudio;
+use nes::nesscreen::NoScreen;
+use nes::Nes;
+
+use crate::dbg::debugger::Debugger;
+use crate::dbg::gdb::GdbStub;
+
+const NES_TEST_FILE: &str = "../nes/test-files/nestest.nes";
+
+struct Target {
+    nes: Nes<NoScreen, NoAudio>,
+    dbg: Debugger,
+    stub: GdbStub,
+    client: TcpStream,
+}
+
+impl Target {
+    fn new() -> Result<Self> {
+        let mut nes = Nes::new(NoScreen, NoAudio);
+        nes.load(&fs::read(NES_TEST_FILE)?)?;
+        nes.reset()?;
+        nes.cpu.pc = 0xc000;
+        let mut dbg = Debugger::default();
+        let mut stub = GdbStub::bind("127.0.0.1:0")?;
+        let client = TcpStream::connect(stub.local_addr()?)?;
+        client.set_read_timeout(Some(Duration::from_millis(20)))?;
+        stub.poll(&mut nes, &mut dbg)?;
+        Ok(Self {
+            nes,
+            dbg,
+            stub,
+            client,
+        })
+    }
+
+    // Send a packet and return the reply, letting the emulator run meanwhile
+    fn request(&mut self, packet: &str) -> Result<String> {
+        self.send(packet)?;
+        self.reply()
+    }
+
+    fn send(&mut self, packet: &str) -> Result<()> {
+        let checksum = packet.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        self.client
+            .write_all(format!("${}#{:02x}", packet, checksum).as_bytes())?;
+        Ok(())
+    }
+
+    fn reply(&mut self) -> Result<String> {
+        let mut received = vec![];
+        for _ in 0..1000 {
+            self.stub.poll(&mut self.nes, &mut self.dbg)?;
+            if !self.dbg.paused {
+                for _ in 0..1024 {
+                    if self.nes.cpu.cycles == 0 && self.dbg.on_instruction(&mut self.nes)? {
+                        break;
+                    }
+                    self.nes.clock()?;
+                }
+            }
+            let mut chunk = [0u8; 256];
+            if let Ok(n) = self.client.read(&mut chunk) {
+                received.extend_from_slice(&chunk[..n]);
+            }
+            let text = String::from_utf8_lossy(&received).to_string();
+            let text = text.trim_start_matches('+');
+            if let (Some(start), Some(end)) = (text.find('$'), text.find('#')) {
+                if text.len() >= end + 3 {
+                    return Ok(text[start + 1..end].to_string());
+                }
+            }
+        }
+        Err(anyhow!("No reply from GDB stub"))
+    }
+}
+
+#[test]
+fn gdb_registers_and_memory() -> Result<()> {
+    let mut target = Target::new()?;
+    assert!(target.dbg.paused);
+
+    assert_eq!(target.request("?")?, "S05");
+    assert_eq!(target.request("p5")?, "00c0");
+
+    assert_eq!(target.request("P0=42")?, "OK");
+    assert_eq!(target.nes.cpu.ac, 0x42);
+    let regs = target.request("g")?;
+    assert_eq!(&regs[..2], "42");
+    assert_eq!(&regs[10..], "00c0");
+
+    // nestest starts with JMP $C5F5
+    assert_eq!(target.request("mc000,3")?, "4cf5c5");
+    assert_eq!(target.request("M0010,2:beef")?, "OK");
+    assert_eq!(target.request("m0010,2")?, "beef");
+    // writes can't reach the PPU registers
+    assert_eq!(target.request("M2000,1:80")?, "E01");
+    assert_eq!(
+        target
+            .request("qXfer:features:read:target.xml:10,ffffffffffffffff")?
+            .chars()
+            .next(),
+        Some('l')
+    );
+    Ok(())
+}
+
+#[test]
+fn gdb_step_breakpoint_and_interrupt() -> Result<()> {
+    let mut target = Target::new()?;
+
+    assert_eq!(target.request("s")?, "S05");
+    assert_eq!(target.nes.cpu.pc, 0xc5f5);
+
+    // run until the JSR target in the first test
+    assert_eq!(target.request("Z0,c72d,1")?, "OK");
+    assert_eq!(target.request("c")?, "S05");
+    assert_eq!(target.nes.cpu.pc, 0xc72d);
+    assert_eq!(target.request("z0,c72d,1")?, "OK");
+    assert!(target.dbg.breakpoints.is_empty());
+
+    // park the CPU in a JMP $0200 loop so it runs until interrupted
+    assert_eq!(target.request("M0200,3:4c0002")?, "OK");
+    assert_eq!(target.request("P5=0002")?, "OK");
+    target.send("c")?;
+    target.stub.poll(&mut target.nes, &mut target.dbg)?;
+    assert!(!target.dbg.paused);
+    target.client.write_all(&[0x03])?;
+    assert_eq!(target.reply()?, "S02");
+    assert!(target.dbg.paused);
+    assert_eq!(target.nes.cpu.cycles, 0);
+
+    assert_eq!(target.request("D")?, "OK");
+    assert!(!target.dbg.paused);
+    Ok(())
+}
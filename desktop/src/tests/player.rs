@@ -0,0 +1,36 @@
+use anyhow::Result;
+use nes::nsf;
+
+use crate::player::Player;
+
+#[test]
+fn player_status_shows_track_and_time() -> Result<()> {
+    let mut file = b"NSFE".to_vec();
+    for (id, data) in [
+        (
+            b"INFO",
+            &[0x00, 0x80, 0x00, 0x80, 0x03, 0x80, 0x00, 0x00, 3, 0][..],
+        ),
+        (b"DATA", &[0x60, 0x60, 0x60, 0x60]),
+        (b"auth", b"Tune\0Artist\0\0\0"),
+        (b"tlbl", b"Intro\0\0Ending\0"),
+        (b"time", &[0x30, 0x75, 0, 0, 0xff, 0xff, 0xff, 0xff]),
+    ] {
+        file.extend((data.len() as u32).to_le_bytes());
+        file.extend(id);
+        file.extend(data);
+    }
+    let nsf = nsf::parse(&file)?;
+
+    let mut player = Player::new(&nsf);
+    assert_eq!(player.status(), "Track 1/3 - Tune - Intro - 00:00 / 00:30");
+    for _ in 0..1789773 * 2 {
+        player.clock();
+    }
+    assert_eq!(player.seconds(), 2);
+
+    assert_eq!(player.next(false), 2);
+    player.restart(player.next(true));
+    assert_eq!(player.status(), "Track 2/3 - Tune - 00:00");
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use nes::nesaudio::NoAudio;
+use nes::nesscreen::NoScreen;
+use nes::Nes;
+
+use crate::dbg::debugger::Debugger;
+use crate::dbg::debugger::Frame;
+use crate::dbg::symbols::Symbols;
+
+const NES_TEST_FILE: &str = "../nes/test-files/nestest.nes";
+
+// ld65 output for a 32 KB NROM image with CODE at $8000 and a BSS variable
+const DBG_FILE: &str = r#"version major=2,minor=0
+file id=0,name="src/main.s",size=120,mtime=0x00000000,mod=0
+seg id=0,name="CODE",start=0x008000,size=0x0010,addrsize=absolute,type=ro,oname="game.nes",ooffs=16
+seg id=1,name="BSS",start=0x000300,size=0x0002,addrsize=absolute,type=rw
+span id=0,seg=0,start=0,size=3
+span id=1,seg=0,start=3,size=2
+line id=0,file=0,line=10,span=0
+line id=1,file=0,line=11,span=1
+line id=2,file=0,line=99,type=2,span=1
+sym id=0,name="reset",addrsize=absolute,scope=0,def=0,ref=1,val=0x8000,seg=0,type=lab
+sym id=1,name="loop",addrsize=absolute,scope=0,def=1,val=0x8003,seg=0,type=lab
+sym id=2,name="score",addrsize=absolute,scope=0,def=2,val=0x300,seg=1,type=lab
+sym id=3,name="SPEED",addrsize=zeropage,scope=0,def=3,val=0x4,type=equ
+"#;
+
+// Write a symbol file fixture to a temporary path and load it
+fn load(symbols: &mut Symbols, name: &str, text: &str, prg_len: usize) -> Result<usize> {
+    let path: PathBuf = std::env::temp_dir().join(name);
+    fs::write(&path, text)?;
+    let count = symbols.load(&path, prg_len);
+    fs::remove_file(&path)?;
+    count
+}
+
+#[test]
+fn dbg_labels_and_lines() -> Result<()> {
+    let mut symbols = Symbols::default();
+    assert_eq!(
+        load(&mut symbols, "nes-symbols-test.dbg", DBG_FILE, 0x8000)?,
+        3
+    );
+
+    // equates are constants, not addresses
+    assert!(symbols.find("SPEED").is_none());
+    let reset = symbols.find("reset").unwrap();
+    assert_eq!((reset.addr, reset.prg), (0x8000, Some(0)));
+    let score = symbols.find("score").unwrap();
+    assert_eq!((score.addr, score.prg), (0x0300, None));
+
+    assert_eq!(symbols.label(0x8003, Some(3)), Some("loop"));
+    assert_eq!(symbols.nearest(0x8004, Some(4)).as_deref(), Some("loop+1"));
+    assert_eq!(symbols.nearest(0x0301, None).as_deref(), Some("score+1"));
+
+    // macro expansion lines lose to the real source line
+    let line = symbols.line(0x8003, Some(3)).unwrap();
+    assert_eq!((line.file.as_str(), line.line), ("src/main.s", 11));
+    assert_eq!(
+        symbols.addresses("/home/dev/game/src/main.s", 10),
+        vec![(0x8000, Some(0))]
+    );
+    assert!(symbols.addresses("other.s", 10).is_empty());
+    Ok(())
+}
+
+#[test]
+fn nl_bank_and_ram_files() -> Result<()> {
+    let mut symbols = Symbols::default();
+    let bank = "$8000#bank1_start#first routine\n$9234#bank1_sub#\nnot a label\n";
+    assert_eq!(
+        load(&mut symbols, "nes-symbols-test.nes.1.nl", bank, 0x10000)?,
+        2
+    );
+    let ram = "$0010/4#ptrs#pointer table\n$0300#lives#\n";
+    assert_eq!(
+        load(&mut symbols, "nes-symbols-test.nes.ram.nl", ram, 0x10000)?,
+        2
+    );
+
+    // bank files are 16 KB banks of PRG ROM
+    assert_eq!(symbols.find("bank1_start").unwrap().prg, Some(0x4000));
+    assert_eq!(symbols.find("bank1_sub").unwrap().prg, Some(0x5234));
+    assert_eq!(symbols.label(0x9234, Some(0x5234)), Some("bank1_sub"));
+    // another bank mapped at the same address has no label there
+    assert_eq!(symbols.label(0x9234, Some(0x1234)), None);
+
+    let ptrs = symbols.find("ptrs").unwrap();
+    assert_eq!((ptrs.addr, ptrs.prg), (0x0010, None));
+    assert_eq!(symbols.label(0x0300, None), Some("lives"));
+    Ok(())
+}
+
+#[test]
+fn mlb_memory_types() -> Result<()> {
+    let mut symbols = Symbols::default();
+    let mlb = "P:0010:early\nP:C004:fixed:in the last bank\nR:0020-0021:temp\n\
+               S:0100:save_slot\nNesWorkRam:0200:work\nG:2002:PPUSTATUS\nX:bad\n";
+    assert_eq!(load(&mut symbols, "nes-symbols-test.mlb", mlb, 0x10000)?, 6);
+
+    // PRG labels are placed at the CPU address their bank is usually mapped at
+    let early = symbols.find("early").unwrap();
+    assert_eq!((early.addr, early.prg), (0x8010, Some(0x0010)));
+    let fixed = symbols.find("fixed").unwrap();
+    assert_eq!((fixed.addr, fixed.prg), (0xc004, Some(0xc004)));
+    assert_eq!(symbols.find("temp").unwrap().addr, 0x0020);
+    assert_eq!(symbols.find("save_slot").unwrap().addr, 0x6100);
+    assert_eq!(symbols.find("work").unwrap().addr, 0x6200);
+    assert_eq!(symbols.find("PPUSTATUS").unwrap().addr, 0x2002);
+    Ok(())
+}
+
+#[test]
+fn unknown_symbol_file_is_rejected() {
+    let mut symbols = Symbols::default();
+    assert!(load(&mut symbols, "nes-symbols-test.sym", "", 0x8000).is_err());
+    assert!(symbols.is_empty());
+}
+
+#[test]
+fn backtrace_names_frames() -> Result<()> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&fs::read(NES_TEST_FILE)?)?;
+    nes.reset()?;
+    nes.cpu.pc = 0xc5f7;
+
+    let mut dbg = Debugger::default();
+    let labels = "$C000#start#\n$C5F5#tests#\n$C728#nmi#\n";
+    load(
+        &mut dbg.symbols,
+        "nes-symbols-test.nes.0.nl",
+        labels,
+        0x4000,
+    )?;
+    dbg.call_stack.push(Frame {
+        call_site: 0xc000,
+        sp: 0xfb,
+        interrupt: false,
+    });
+    dbg.call_stack.push(Frame {
+        call_site: 0xc5f5,
+        sp: 0xf8,
+        interrupt: true,
+    });
+
+    assert_eq!(
+        dbg.backtrace(&nes),
+        vec![
+            "#0 $C5F7 <tests+2>",
+            "#1 $C5F5 <tests> [interrupt]",
+            "#2 $C000 <start>",
+        ]
+    );
+    // operands are named after labels too
+    assert_eq!(dbg.disassemble(&nes, 0xc000)?.text, "JMP tests");
+    Ok(())
+}
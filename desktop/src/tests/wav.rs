@@ -0,0 +1,46 @@
+use std::fs;
+
+use ::nes::apu::expansion::SAMPLE_RATE;
+use anyhow::Result;
+
+use crate::wav;
+
+// NSF with INIT at $8000 turning on pulse 1 at full volume, PLAY at $800a doing nothing
+fn nsf_file() -> Vec<u8> {
+    let mut file = b"NESM\x1a\x01\x01\x01".to_vec();
+    file.extend([0x00, 0x80, 0x00, 0x80, 0x0a, 0x80]);
+    file.resize(0x6e, 0);
+    file.extend(16639u16.to_le_bytes());
+    file.resize(0x80, 0);
+    // LDA #$BF; STA $4000; LDA #$FF; STA $4002; RTS; RTS
+    file.extend([
+        0xa9, 0xbf, 0x8d, 0x00, 0x40, 0xa9, 0xff, 0x8d, 0x02, 0x40, 0x60,
+    ]);
+    file
+}
+
+#[test]
+fn nsf_renders_to_wav() -> Result<()> {
+    let out = std::env::temp_dir().join("nes-desktop-render-test.wav");
+    wav::render(&nsf_file(), None, Some(1), &out)?;
+    let bytes = fs::read(&out)?;
+    fs::remove_file(&out)?;
+
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(&bytes[8..16], b"WAVEfmt ");
+    assert_eq!(u32::from_le_bytes(bytes[24..28].try_into()?), SAMPLE_RATE);
+    let data_size = u32::from_le_bytes(bytes[40..44].try_into()?);
+    assert_eq!(data_size, SAMPLE_RATE * 2);
+    assert_eq!(bytes.len(), 44 + data_size as usize);
+    // the pulse channel is audible
+    assert!(bytes[44..].chunks(2).any(|sample| sample != [0, 0]));
+    Ok(())
+}
+
+#[test]
+fn only_nsf_files_render() {
+    let out = std::env::temp_dir().join("nes-desktop-render-rom.wav");
+    let rom = fs::read("../nes/test-files/nestest.nes").unwrap();
+    assert!(wav::render(&rom, None, Some(1), &out).is_err());
+    assert!(!out.exists());
+}
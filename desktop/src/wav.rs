@@ -0,0 +1,167 @@
+use std::fs;
+use std::path::Path;
+
+use ::nes::apu::expansion::SAMPLE_RATE;
+use ::nes::apu::AudioChannel;
+use ::nes::nesscreen::NoScreen;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+
+// peak level of each APU channel, the expansion chips peak around 0.5
+const PULSE_VOLUME: f32 = 0.125;
+const TRIANGLE_VOLUME: f32 = 0.15;
+
+#[derive(Default)]
+struct Oscillator {
+    enabled: bool,
+    muted: bool,
+    duty_cycle: f32,
+    volume: f32,
+    freq: u16,
+    // position in the current period, from 0 to 1
+    phase: f32,
+}
+
+impl Oscillator {
+    fn advance(&mut self) {
+        self.phase = (self.phase + self.freq as f32 / SAMPLE_RATE as f32).fract();
+    }
+
+    fn pulse(&mut self) -> f32 {
+        self.advance();
+        if self.enabled && self.phase < self.duty_cycle {
+            self.volume * PULSE_VOLUME
+        } else {
+            0.
+        }
+    }
+
+    fn triangle(&mut self) -> f32 {
+        self.advance();
+        if self.enabled && !self.muted {
+            (1. - (2. * self.phase - 1.).abs()) * TRIANGLE_VOLUME
+        } else {
+            0.
+        }
+    }
+}
+
+/*
+    Audio sink for rendering without a sound card. The APU channels are only described
+    by their frequency and volume, so they are synthesized here as plain waves and added
+    to the expansion samples, which keep coming for NSF tunes even without a sound chip.
+*/
+#[derive(Default)]
+pub struct WavAudio {
+    pulses: [Oscillator; 2],
+    triangle: Oscillator,
+    pub samples: Vec<f32>,
+}
+
+impl ::nes::nesaudio::NesAudio for WavAudio {
+    fn enable_channel(&mut self, channel: AudioChannel, enabled: bool) -> Result<()> {
+        match channel {
+            AudioChannel::Pulse1 => self.pulses[0].enabled = enabled,
+            AudioChannel::Pulse2 => self.pulses[1].enabled = enabled,
+            AudioChannel::Triangle => self.triangle.enabled = enabled,
+        }
+        Ok(())
+    }
+
+    fn update_pulse(
+        &mut self,
+        pulse: AudioChannel,
+        duty_cycle: Option<f32>,
+        volume: Option<f32>,
+        freq: Option<u16>,
+    ) -> Result<()> {
+        let pulse = match pulse {
+            AudioChannel::Pulse1 => &mut self.pulses[0],
+            AudioChannel::Pulse2 => &mut self.pulses[1],
+            AudioChannel::Triangle => Err(anyhow!("Invalid argument: triangle pulse"))?,
+        };
+        if let Some(duty_cycle) = duty_cycle {
+            pulse.duty_cycle = duty_cycle;
+        }
+        if let Some(volume) = volume {
+            pulse.volume = volume;
+        }
+        if let Some(freq) = freq {
+            pulse.freq = freq;
+        }
+        Ok(())
+    }
+
+    fn update_triangle(&mut self, freq: Option<u16>, mute: Option<bool>) -> Result<()> {
+        if let Some(freq) = freq {
+            self.triangle.freq = freq;
+        }
+        if let Some(mute) = mute {
+            self.triangle.muted = mute;
+        }
+        Ok(())
+    }
+
+    fn play_samples(&mut self, samples: &[f32]) -> Result<()> {
+        for &sample in samples {
+            let apu = self.pulses[0].pulse() + self.pulses[1].pulse() + self.triangle.triangle();
+            self.samples.push(sample + apu);
+        }
+        Ok(())
+    }
+}
+
+// Play an NSF track without a window and save it as 16 bit mono WAV
+pub fn render(nsf_bytes: &[u8], track: Option<u8>, seconds: Option<u32>, out: &Path) -> Result<()> {
+    let mut nes = ::nes::Nes::new(NoScreen, WavAudio::default());
+    nes.load(nsf_bytes)?;
+    let nsf = nes
+        .cartridge
+        .nsf
+        .as_ref()
+        .context("Only NSF files can be rendered to WAV")?;
+    let track = track.unwrap_or(nsf.start_track);
+    // NSFe files may give the track length
+    let seconds = seconds
+        .or_else(|| nsf.track_time(track).map(|time| time.div_ceil(1000)))
+        .unwrap_or(60);
+    nes.play_track(track)?;
+
+    let length = (seconds * SAMPLE_RATE) as usize;
+    log::info!(
+        "Rendering track {} for {} seconds to {:?}",
+        track + 1,
+        seconds,
+        out
+    );
+    while nes.audio.samples.len() < length {
+        nes.clock()?;
+    }
+    nes.audio.samples.truncate(length);
+    fs::write(out, wav_bytes(&nes.audio.samples))
+        .with_context(|| format!("Cannot write WAV file {:?}", out))?;
+    Ok(())
+}
+
+pub fn wav_bytes(samples: &[f32]) -> Vec<u8> {
+    let data_size = samples.len() as u32 * 2;
+    let mut wav = b"RIFF".to_vec();
+    wav.extend((36 + data_size).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    // PCM, mono, 16 bits
+    wav.extend(16u32.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(SAMPLE_RATE.to_le_bytes());
+    wav.extend((SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend(2u16.to_le_bytes());
+    wav.extend(16u16.to_le_bytes());
+    wav.extend(b"data");
+    wav.extend(data_size.to_le_bytes());
+    for &sample in samples {
+        let sample = (sample.clamp(-1., 1.) * i16::MAX as f32) as i16;
+        wav.extend(sample.to_le_bytes());
+    }
+    wav
+}
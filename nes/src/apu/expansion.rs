@@ -0,0 +1,34 @@
+// Output rate of the expansion audio samples handed to the frontend
+pub const SAMPLE_RATE: u32 = 44100;
+const CPU_FREQ: u32 = 1789773;
+// samples buffered before they are sent, about a frame
+const CHUNK: usize = 735;
+
+/*
+    Cartridge sound chips are emulated sample by sample instead of driving the frontend
+    oscillators like the APU channels. Their level is averaged over the CPU cycles of each
+    output sample.
+*/
+#[derive(Default)]
+pub struct Expansion {
+    sum: f32,
+    count: u32,
+    phase: u32,
+    pub samples: Vec<f32>,
+}
+
+impl Expansion {
+    // Add the level of one CPU cycle, returns true once a chunk of samples is ready
+    pub fn push(&mut self, level: f32) -> bool {
+        self.sum += level;
+        self.count += 1;
+        self.phase += SAMPLE_RATE;
+        if self.phase >= CPU_FREQ {
+            self.phase -= CPU_FREQ;
+            self.samples.push(self.sum / self.count as f32);
+            self.sum = 0.;
+            self.count = 0;
+        }
+        self.samples.len() >= CHUNK
+    }
+}
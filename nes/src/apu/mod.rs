@@ -1,8 +1,10 @@
 use anyhow::anyhow;
 use anyhow::Result;
 
+use self::expansion::Expansion;
 use self::pulse::PulseChannel;
 use self::triangle::TriangleChannel;
+use crate::cartridge;
 use crate::nesaudio::NesAudio;
 use crate::Nes;
 
@@ -14,9 +16,21 @@ pub enum AudioChannel {
 
 #[derive(Default)]
 pub struct Apu {
-    pulse1: PulseChannel,
-    pulse2: PulseChannel,
-    triangle: TriangleChannel,
+    pub pulse1: PulseChannel,
+    pub pulse2: PulseChannel,
+    pub triangle: TriangleChannel,
+    pub expansion: Expansion,
+}
+
+// Collect the cartridge expansion audio for this CPU cycle
+pub fn clock<S, A: NesAudio>(nes: &mut Nes<S, A>) -> Result<()> {
+    if let Some(level) = cartridge::audio_output(nes)? {
+        if nes.apu.expansion.push(level) {
+            nes.audio.play_samples(&nes.apu.expansion.samples)?;
+            nes.apu.expansion.samples.clear();
+        }
+    }
+    Ok(())
 }
 
 pub fn read<S, A>(_nes: &mut Nes<S, A>, _addr: u16) -> Result<u8> {
@@ -127,5 +141,6 @@ pub fn write<S, A: NesAudio>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result
     Ok(())
 }
 
+pub mod expansion;
 pub mod pulse;
 pub mod triangle;
@@ -0,0 +1,660 @@
+use std::io::Cursor;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::gamedb::crc32;
+
+const ZIP_TAG: &[u8; 4] = b"PK\x03\x04";
+const ZIP_END_TAG: &[u8; 4] = b"PK\x05\x06";
+const ZIP_ENTRY_TAG: &[u8; 4] = b"PK\x01\x02";
+const GZIP_TAG: &[u8; 2] = b"\x1f\x8b";
+const SEVENZ_TAG: &[u8; 6] = b"7z\xbc\xaf\x27\x1c";
+
+// Files the emulator can load, recognized by their extension
+pub const ROM_EXTENSIONS: [&str; 6] = ["nes", "fds", "nsf", "nsfe", "unf", "unif"];
+
+pub struct RomFile {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+pub fn is_archive(bytes: &[u8]) -> bool {
+    bytes.starts_with(ZIP_TAG) || bytes.starts_with(GZIP_TAG) || bytes.starts_with(SEVENZ_TAG)
+}
+
+pub fn is_rom_name(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, ext)| {
+        ROM_EXTENSIONS
+            .iter()
+            .any(|rom_ext| ext.eq_ignore_ascii_case(rom_ext))
+    })
+}
+
+// Names of the ROMs in a ZIP, gzip or 7z archive
+pub fn list(bytes: &[u8]) -> Result<Vec<String>> {
+    Ok(roms(bytes)?.into_iter().map(|rom| rom.name).collect())
+}
+
+/*
+    Unpack the ROM of an archive. When the archive holds several ROMs the entry must be
+    chosen by name, and the error lists the ones to choose from. A gzip file always holds
+    a single file, named like the archive when the name was not stored.
+*/
+pub fn extract(bytes: &[u8], entry: Option<&str>) -> Result<RomFile> {
+    let mut roms = roms(bytes)?;
+    let names = || {
+        roms.iter()
+            .map(|rom| rom.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let index = match entry {
+        Some(entry) => roms
+            .iter()
+            .position(|rom| rom.name == entry)
+            .or_else(|| {
+                roms.iter()
+                    .position(|rom| rom.name.eq_ignore_ascii_case(entry))
+            })
+            .ok_or_else(|| anyhow!("No {:?} in the archive, it holds: {}", entry, names()))?,
+        None => match roms.len() {
+            0 => Err(anyhow!("The archive holds no NES, FDS, NSF or UNIF file"))?,
+            1 => 0,
+            _ => Err(anyhow!(
+                "The archive holds several ROMs, choose one of: {}",
+                names()
+            ))?,
+        },
+    };
+    Ok(roms.swap_remove(index))
+}
+
+fn roms(bytes: &[u8]) -> Result<Vec<RomFile>> {
+    if bytes.starts_with(GZIP_TAG) {
+        return Ok(vec![gunzip(bytes)?]);
+    }
+    let files = if bytes.starts_with(ZIP_TAG) {
+        unzip(bytes)?
+    } else if bytes.starts_with(SEVENZ_TAG) {
+        un7z(bytes)?
+    } else {
+        Err(anyhow!("Unknown archive format, expected ZIP, gzip or 7z"))?
+    };
+    Ok(files
+        .into_iter()
+        .filter(|file| is_rom_name(&file.name))
+        .collect())
+}
+
+fn truncated(format: &str) -> anyhow::Error {
+    anyhow!("Invalid {} archive was provided: Truncated data", format)
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> Option<usize> {
+    let data = bytes.get(pos..)?.get(..2)?;
+    Some(u16::from_le_bytes([data[0], data[1]]) as usize)
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> Option<u32> {
+    let data = bytes.get(pos..)?.get(..4)?;
+    Some(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+}
+
+fn inflate(data: &[u8], size: usize, name: &str) -> Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_with_limit(data, size)
+        .map_err(|err| anyhow!("Cannot inflate {:?}: {:?}", name, err.status))
+}
+
+fn check_crc(data: &[u8], crc: u32, name: &str) -> Result<()> {
+    if crc32(data) != crc {
+        Err(anyhow!(
+            "Checksum mismatch for {:?}, the archive is corrupt",
+            name
+        ))?;
+    }
+    Ok(())
+}
+
+fn gunzip(bytes: &[u8]) -> Result<RomFile> {
+    let header = bytes.get(..10).ok_or_else(|| truncated("gzip"))?;
+    if header[2] != 8 {
+        Err(anyhow!("Unsupported gzip compression method {}", header[2]))?;
+    }
+    let flags = header[3];
+    let mut pos = 10;
+    // extra field
+    if flags & 0x04 != 0 {
+        pos += 2 + u16_at(bytes, pos).ok_or_else(|| truncated("gzip"))?;
+    }
+    // zero terminated name and comment
+    let mut name = String::new();
+    for field in [0x08, 0x10] {
+        if flags & field != 0 {
+            let rest = bytes.get(pos..).ok_or_else(|| truncated("gzip"))?;
+            let end = rest
+                .iter()
+                .position(|&byte| byte == 0)
+                .ok_or_else(|| truncated("gzip"))?;
+            if field == 0x08 {
+                name = String::from_utf8_lossy(&rest[..end]).into_owned();
+            }
+            pos += end + 1;
+        }
+    }
+    // header CRC16
+    if flags & 0x02 != 0 {
+        pos += 2;
+    }
+
+    let trailer = bytes
+        .len()
+        .checked_sub(8)
+        .ok_or_else(|| truncated("gzip"))?;
+    let data = bytes.get(pos..trailer).ok_or_else(|| truncated("gzip"))?;
+    let size = u32_at(bytes, trailer + 4).ok_or_else(|| truncated("gzip"))?;
+    let data = inflate(data, size as usize, &name)?;
+    check_crc(&data, u32_at(bytes, trailer).unwrap_or_default(), &name)?;
+    Ok(RomFile { name, data })
+}
+
+// Read the central directory at the end of a ZIP archive
+fn unzip(bytes: &[u8]) -> Result<Vec<RomFile>> {
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&pos| bytes[pos..].starts_with(ZIP_END_TAG))
+        .ok_or_else(|| anyhow!("Invalid ZIP archive was provided: Missing central directory"))?;
+    let count = u16_at(bytes, end + 10).ok_or_else(|| truncated("ZIP"))?;
+    let mut pos = u32_at(bytes, end + 16).ok_or_else(|| truncated("ZIP"))? as usize;
+
+    let mut files = vec![];
+    for _ in 0..count {
+        let entry = bytes
+            .get(pos..)
+            .and_then(|entry| entry.get(..46))
+            .ok_or_else(|| truncated("ZIP"))?;
+        if !entry.starts_with(ZIP_ENTRY_TAG) {
+            Err(anyhow!(
+                "Invalid ZIP archive was provided: Bad central directory"
+            ))?;
+        }
+        let flags = u16_at(entry, 8).unwrap_or_default();
+        let method = u16_at(entry, 10).unwrap_or_default();
+        let crc = u32_at(entry, 16).unwrap_or_default();
+        let packed_size = u32_at(entry, 20).unwrap_or_default() as usize;
+        let size = u32_at(entry, 24).unwrap_or_default() as usize;
+        let name_len = u16_at(entry, 28).unwrap_or_default();
+        let extra_len = u16_at(entry, 30).unwrap_or_default();
+        let comment_len = u16_at(entry, 32).unwrap_or_default();
+        let header = u32_at(entry, 42).unwrap_or_default() as usize;
+        let name_end = pos
+            .checked_add(46 + name_len)
+            .ok_or_else(|| truncated("ZIP"))?;
+        let name = bytes
+            .get(pos + 46..name_end)
+            .ok_or_else(|| truncated("ZIP"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        pos = name_end + extra_len + comment_len;
+
+        // directories and files the emulator cannot load are skipped unread
+        if name.ends_with('/') || !is_rom_name(&name) {
+            continue;
+        }
+        if flags & 0x01 != 0 {
+            Err(anyhow!(
+                "Cannot read {:?}: Encrypted ZIP entries are not supported",
+                name
+            ))?;
+        }
+        // the local header has its own name and extra field lengths
+        let local_name_len =
+            u16_at(bytes, header.saturating_add(26)).ok_or_else(|| truncated("ZIP"))?;
+        let local_extra_len =
+            u16_at(bytes, header.saturating_add(28)).ok_or_else(|| truncated("ZIP"))?;
+        let data_start = header
+            .checked_add(30 + local_name_len + local_extra_len)
+            .ok_or_else(|| truncated("ZIP"))?;
+        let data_end = data_start
+            .checked_add(packed_size)
+            .ok_or_else(|| truncated("ZIP"))?;
+        let data = bytes
+            .get(data_start..data_end)
+            .ok_or_else(|| truncated("ZIP"))?;
+        let data = match method {
+            0 => data.to_vec(),
+            8 => inflate(data, size, &name)?,
+            _ => Err(anyhow!(
+                "Cannot read {:?}: Unsupported ZIP compression method {}",
+                name,
+                method
+            ))?,
+        };
+        check_crc(&data, crc, &name)?;
+        files.push(RomFile { name, data });
+    }
+    Ok(files)
+}
+
+/*
+    7z archives keep their file list in a header at the end, itself usually compressed.
+    Only the LZMA, LZMA2 and copy methods are supported, without filters or encryption,
+    which covers archives made with the default settings.
+*/
+fn un7z(bytes: &[u8]) -> Result<Vec<RomFile>> {
+    let start = bytes.get(..32).ok_or_else(|| truncated("7z"))?;
+    let next_offset = u64::from_le_bytes(start[12..20].try_into()?) as usize;
+    let next_size = u64::from_le_bytes(start[20..28].try_into()?) as usize;
+    let header_start = 32usize
+        .checked_add(next_offset)
+        .ok_or_else(|| truncated("7z"))?;
+    let header = bytes
+        .get(header_start..header_start.saturating_add(next_size))
+        .ok_or_else(|| truncated("7z"))?;
+    check_crc(header, u32_at(start, 28).unwrap_or_default(), "7z header")?;
+
+    // a compressed header is a stream holding the real one
+    let header = match header.first() {
+        Some(0x17) => SevenZReader::new(&header[1..])
+            .streams_info()?
+            .unpack(bytes)?
+            .into_iter()
+            .next()
+            .context("Invalid 7z archive was provided: Empty header")?,
+        _ => header.to_vec(),
+    };
+    let mut reader = SevenZReader::new(&header);
+    reader.expect(0x01)?;
+
+    let mut streams = Streams::default();
+    let mut files = vec![];
+    loop {
+        match reader.byte()? {
+            0x00 => break,
+            // archive properties and additional streams are not needed for files
+            0x02 | 0x03 => Err(anyhow!("Unsupported 7z header property"))?,
+            0x04 => streams = reader.streams_info()?,
+            0x05 => files = reader.files_info()?,
+            id => Err(anyhow!(
+                "Invalid 7z archive was provided: Unknown header {:#x}",
+                id
+            ))?,
+        }
+    }
+
+    // files with data take the unpacked streams in order, the others are empty
+    let mut data = streams.unpack(bytes)?.into_iter();
+    let mut roms = vec![];
+    for (name, has_stream) in files {
+        let file = if has_stream {
+            data.next()
+                .context("Invalid 7z archive was provided: Missing file data")?
+        } else {
+            vec![]
+        };
+        roms.push(RomFile { name, data: file });
+    }
+    Ok(roms)
+}
+
+struct Folder {
+    method: Vec<u8>,
+    properties: Vec<u8>,
+    size: usize,
+    crc: Option<u32>,
+    // sizes of the files packed one after the other in the folder
+    stream_sizes: Vec<usize>,
+    stream_crcs: Vec<Option<u32>>,
+}
+
+#[derive(Default)]
+struct Streams {
+    pack_pos: usize,
+    pack_sizes: Vec<usize>,
+    folders: Vec<Folder>,
+}
+
+impl Streams {
+    // Decompress every folder and split it into its streams
+    fn unpack(&self, bytes: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut streams = vec![];
+        let mut pos = 32usize.saturating_add(self.pack_pos);
+        for (folder, &pack_size) in self.folders.iter().zip(&self.pack_sizes) {
+            let packed = bytes
+                .get(pos..pos.saturating_add(pack_size))
+                .ok_or_else(|| truncated("7z"))?;
+            pos += pack_size;
+            let data = decode_folder(folder, packed)?;
+            if let Some(crc) = folder.crc {
+                check_crc(&data, crc, "7z folder")?;
+            }
+
+            let mut start = 0usize;
+            for (&size, crc) in folder.stream_sizes.iter().zip(&folder.stream_crcs) {
+                let end = start.checked_add(size).ok_or_else(|| truncated("7z"))?;
+                let stream = data.get(start..end).ok_or_else(|| truncated("7z"))?;
+                if let Some(crc) = crc {
+                    check_crc(stream, *crc, "7z file")?;
+                }
+                streams.push(stream.to_vec());
+                start = end;
+            }
+        }
+        Ok(streams)
+    }
+}
+
+fn decode_folder(folder: &Folder, packed: &[u8]) -> Result<Vec<u8>> {
+    // the size comes from the archive, so the data grows as it is decoded
+    let mut data = vec![];
+    match folder.method.as_slice() {
+        [0x00] => data.extend(packed),
+        [0x03, 0x01, 0x01] => {
+            // lzma-rs reads the properties and size from a .lzma style header
+            let mut stream = folder.properties.clone();
+            stream.extend((folder.size as u64).to_le_bytes());
+            stream.extend(packed);
+            lzma_rs::lzma_decompress(&mut Cursor::new(stream), &mut data)
+                .map_err(|err| anyhow!("Cannot decompress 7z LZMA data: {}", err))?;
+        }
+        [0x21] => lzma_rs::lzma2_decompress(&mut Cursor::new(packed), &mut data)
+            .map_err(|err| anyhow!("Cannot decompress 7z LZMA2 data: {}", err))?,
+        method => Err(anyhow!(
+            "Unsupported 7z compression method {:02x?}, only LZMA, LZMA2 and copy are",
+            method
+        ))?,
+    }
+    Ok(data)
+}
+
+struct SevenZReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SevenZReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let data = self
+            .bytes
+            .get(self.pos..self.pos.saturating_add(length))
+            .ok_or_else(|| truncated("7z"))?;
+        self.pos += length;
+        Ok(data)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn expect(&mut self, id: u8) -> Result<()> {
+        match self.byte()? {
+            byte if byte == id => Ok(()),
+            byte => Err(anyhow!(
+                "Invalid 7z archive was provided: Expected {:#x}, got {:#x}",
+                id,
+                byte
+            )),
+        }
+    }
+
+    // Number of items that each take at least a byte, so a corrupt count fails before allocating
+    fn count(&mut self) -> Result<usize> {
+        let count = self.number()?;
+        if count > self.bytes.len() - self.pos {
+            Err(truncated("7z"))?;
+        }
+        Ok(count)
+    }
+
+    // the leading one bits of the first byte count the bytes that follow
+    fn number(&mut self) -> Result<usize> {
+        let first = self.byte()?;
+        let mut value = 0u64;
+        for i in 0..8 {
+            let mask = 0x80 >> i;
+            if first & mask == 0 {
+                value |= ((first & (mask.wrapping_sub(1))) as u64) << (8 * i);
+                return Ok(value as usize);
+            }
+            value |= (self.byte()? as u64) << (8 * i);
+        }
+        Ok(value as usize)
+    }
+
+    fn bits(&mut self, count: usize) -> Result<Vec<bool>> {
+        let bytes = self.bytes(count.div_ceil(8))?;
+        Ok((0..count)
+            .map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0)
+            .collect())
+    }
+
+    // CRCs of some items, those without one marked as absent
+    fn digests(&mut self, count: usize) -> Result<Vec<Option<u32>>> {
+        let defined = match self.byte()? {
+            0 => self.bits(count)?,
+            _ => vec![true; count],
+        };
+        defined
+            .into_iter()
+            .map(|defined| {
+                Ok(match defined {
+                    true => u32_at(self.bytes(4)?, 0),
+                    false => None,
+                })
+            })
+            .collect()
+    }
+
+    fn streams_info(&mut self) -> Result<Streams> {
+        let mut streams = Streams::default();
+        loop {
+            match self.byte()? {
+                0x00 => return Ok(streams),
+                0x06 => self.pack_info(&mut streams)?,
+                0x07 => self.unpack_info(&mut streams)?,
+                0x08 => self.substreams_info(&mut streams)?,
+                id => Err(anyhow!(
+                    "Invalid 7z archive was provided: Unknown streams {:#x}",
+                    id
+                ))?,
+            }
+        }
+    }
+
+    fn pack_info(&mut self, streams: &mut Streams) -> Result<()> {
+        streams.pack_pos = self.number()?;
+        let count = self.count()?;
+        loop {
+            match self.byte()? {
+                0x00 => return Ok(()),
+                0x09 => {
+                    streams.pack_sizes = (0..count).map(|_| self.number()).collect::<Result<_>>()?
+                }
+                0x0a => {
+                    self.digests(count)?;
+                }
+                id => Err(anyhow!(
+                    "Invalid 7z archive was provided: Unknown pack {:#x}",
+                    id
+                ))?,
+            }
+        }
+    }
+
+    fn unpack_info(&mut self, streams: &mut Streams) -> Result<()> {
+        self.expect(0x0b)?;
+        let count = self.number()?;
+        if self.byte()? != 0 {
+            Err(anyhow!("Unsupported 7z external folders"))?;
+        }
+        for _ in 0..count {
+            streams.folders.push(self.folder()?);
+        }
+        self.expect(0x0c)?;
+        for folder in streams.folders.iter_mut() {
+            folder.size = self.number()?;
+            folder.stream_sizes = vec![folder.size];
+            folder.stream_crcs = vec![None];
+        }
+        loop {
+            match self.byte()? {
+                0x00 => return Ok(()),
+                0x0a => {
+                    let crcs = self.digests(count)?;
+                    for (folder, crc) in streams.folders.iter_mut().zip(crcs) {
+                        folder.crc = crc;
+                    }
+                }
+                id => Err(anyhow!(
+                    "Invalid 7z archive was provided: Unknown unpack {:#x}",
+                    id
+                ))?,
+            }
+        }
+    }
+
+    // a folder made of one coder, filters would need several
+    fn folder(&mut self) -> Result<Folder> {
+        if self.number()? != 1 {
+            Err(anyhow!(
+                "Unsupported 7z filters, only single method archives are"
+            ))?;
+        }
+        let flags = self.byte()?;
+        let method = self.bytes((flags & 0x0f) as usize)?.to_vec();
+        if flags & 0x10 != 0 {
+            Err(anyhow!("Unsupported 7z method with several streams"))?;
+        }
+        let properties = match flags & 0x20 {
+            0 => vec![],
+            _ => {
+                let size = self.number()?;
+                self.bytes(size)?.to_vec()
+            }
+        };
+        Ok(Folder {
+            method,
+            properties,
+            size: 0,
+            crc: None,
+            stream_sizes: vec![],
+            stream_crcs: vec![],
+        })
+    }
+
+    fn substreams_info(&mut self, streams: &mut Streams) -> Result<()> {
+        let mut counts = vec![1; streams.folders.len()];
+        let mut id = self.byte()?;
+        if id == 0x0d {
+            for count in counts.iter_mut() {
+                *count = self.number()?;
+            }
+            id = self.byte()?;
+        }
+        // sizes of all but the last stream, which takes what is left of the folder
+        let has_sizes = id == 0x09;
+        for (folder, &count) in streams.folders.iter_mut().zip(&counts) {
+            if count == 0 {
+                folder.stream_sizes = vec![];
+                continue;
+            }
+            let mut sizes = vec![];
+            if has_sizes {
+                for _ in 1..count {
+                    sizes.push(self.number()?);
+                }
+            }
+            let rest = folder
+                .size
+                .checked_sub(sizes.iter().sum())
+                .ok_or_else(|| truncated("7z"))?;
+            sizes.push(rest);
+            folder.stream_sizes = sizes;
+        }
+        if has_sizes {
+            id = self.byte()?;
+        }
+
+        // the CRCs of single stream folders may already be known
+        for folder in streams.folders.iter_mut() {
+            folder.stream_crcs = match (folder.stream_sizes.len(), folder.crc) {
+                (1, Some(crc)) => vec![Some(crc)],
+                (count, _) => vec![None; count],
+            };
+        }
+        loop {
+            match id {
+                0x00 => return Ok(()),
+                0x0a => {
+                    let missing = streams
+                        .folders
+                        .iter()
+                        .flat_map(|folder| &folder.stream_crcs)
+                        .filter(|crc| crc.is_none())
+                        .count();
+                    let mut crcs = self.digests(missing)?.into_iter();
+                    for crc in streams
+                        .folders
+                        .iter_mut()
+                        .flat_map(|folder| folder.stream_crcs.iter_mut())
+                        .filter(|crc| crc.is_none())
+                    {
+                        *crc = crcs.next().flatten();
+                    }
+                }
+                id => Err(anyhow!(
+                    "Invalid 7z archive was provided: Unknown substreams {:#x}",
+                    id
+                ))?,
+            }
+            id = self.byte()?;
+        }
+    }
+
+    // file names and whether each file has data
+    fn files_info(&mut self) -> Result<Vec<(String, bool)>> {
+        let count = self.count()?;
+        let mut names = vec![String::new(); count];
+        let mut has_stream = vec![true; count];
+        loop {
+            let id = self.byte()?;
+            if id == 0x00 {
+                break;
+            }
+            let size = self.number()?;
+            let mut property = SevenZReader::new(self.bytes(size)?);
+            match id {
+                0x0e => {
+                    has_stream = property
+                        .bits(count)?
+                        .into_iter()
+                        .map(|empty| !empty)
+                        .collect()
+                }
+                0x11 => {
+                    if property.byte()? != 0 {
+                        Err(anyhow!("Unsupported 7z external file names"))?;
+                    }
+                    // UTF-16 names, each ending with a zero
+                    let units = property
+                        .bytes(size - 1)?
+                        .chunks(2)
+                        .map(|unit| {
+                            u16::from_le_bytes([unit[0], unit.get(1).copied().unwrap_or(0)])
+                        })
+                        .collect::<Vec<_>>();
+                    for (name, units) in names.iter_mut().zip(units.split(|&unit| unit == 0)) {
+                        *name = String::from_utf16_lossy(units);
+                    }
+                }
+                // times, attributes and such
+                _ => {}
+            }
+        }
+        Ok(names.into_iter().zip(has_stream).collect())
+    }
+}
@@ -3,6 +3,7 @@ use anyhow::Result;
 
 use crate::apu;
 use crate::cartridge;
+use crate::cdl;
 use crate::nesaudio::NesAudio;
 use crate::nesscreen::NesScreen;
 use crate::ppu;
@@ -29,7 +30,11 @@ where
         0x4016 => Ok(nes.joypad.0.read()),
         0x4017 => Ok(0 /*nes.joypad.1.read()*/),
         0x4000..=0x4013 | 0x4015 => apu::read(nes, addr),
-        0x4020..=0xffff => cartridge::prg_read(nes, addr),
+        0x4020..=0xffff => {
+            let data = cartridge::prg_read(nes, addr)?;
+            cdl::log_prg_read(nes, addr);
+            Ok(data)
+        }
         _ => {
             log::warn!("Invalid read on cpu bus at address {:x}", addr);
             Ok(0)
@@ -37,6 +42,29 @@ where
     }
 }
 
+// Read the CPU bus without side effects, for debuggers and memory viewers
+pub fn peek<S, A>(nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+    match addr {
+        0x0000..=0x1fff => Ok(nes.bus_cpu.ram[addr as usize & 0x07ff]),
+        0x2000..=0x3fff => Ok(ppu::peek_ppu_reg(nes, addr & 0x2007)),
+        0x4016 => Ok(nes.joypad.0.peek()),
+        0x4020..=0xffff => cartridge::prg_peek(nes, addr),
+        _ => Ok(0),
+    }
+}
+
+// Write RAM or cartridge PRG RAM without side effects, false where there is none
+pub fn poke<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<bool> {
+    match addr {
+        0x0000..=0x1fff => {
+            nes.bus_cpu.ram[addr as usize & 0x07ff] = data;
+            Ok(true)
+        }
+        0x6000..=0x7fff => cartridge::prg_ram_poke(nes, addr, data),
+        _ => Ok(false),
+    }
+}
+
 pub fn write<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()>
 where
     S: NesScreen,
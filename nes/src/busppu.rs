@@ -2,18 +2,18 @@ use anyhow::anyhow;
 use anyhow::Result;
 
 use crate::cartridge;
-use crate::cartridge::Mirroring;
+use crate::mappers::Nametable;
 use crate::Nes;
 
 pub struct BusPpu {
-    pub vram: [u8; 0x1000], // 4 KB of VRAM
+    pub vram: [u8; 0x800], // 2 KB of console VRAM (CIRAM)
     pub palette: [u8; 0x20],
 }
 
 impl Default for BusPpu {
     fn default() -> Self {
         Self {
-            vram: [0; 0x1000],
+            vram: [0; 0x800],
             palette: [0; 0x20],
         }
     }
@@ -22,37 +22,48 @@ impl Default for BusPpu {
 pub fn read<S, A>(nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
     match addr {
         0x0000..=0x1fff => cartridge::chr_read(nes, addr),
-        0x2000..=0x2fff => {
-            let mapped_addr = mirror_vram_addr(nes, addr);
-            Ok(nes.bus_ppu.vram[mapped_addr as usize])
-        }
-        0x3f10 | 0x3f14 | 0x3f18 | 0x3f1c => {
-            let mut addr_mirror = (addr - 0x10) & 0x3f;
-            if nes.ppu.reg_mask.grayscale() {
-                addr_mirror &= 0x30;
-            }
-            Ok(nes.bus_ppu.palette[addr_mirror as usize])
-        }
-        0x3f00..=0x3fff => {
-            let mut addr_mirror = (addr & 0x3f1f) & 0x3f;
-            if nes.ppu.reg_mask.grayscale() {
-                addr_mirror &= 0x30;
-            }
-            Ok(nes.bus_ppu.palette[addr_mirror as usize])
-        }
+        0x2000..=0x2fff => match cartridge::nametable(nes, addr)? {
+            Nametable::Mapper => cartridge::nametable_read(nes, addr),
+            nametable => Ok(read_vram(nes, nametable, addr)),
+        },
+        0x3f00..=0x3fff => Ok(read_palette(nes, addr)),
+        _ => Err(anyhow!("Invalid read on ppu bus at address {:x}", addr)),
+    }
+}
+
+// Same as read but without any side effect on the cartridge
+pub fn peek<S, A>(nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+    match addr {
+        0x0000..=0x1fff => cartridge::chr_peek(nes, addr),
+        0x2000..=0x2fff => match cartridge::nametable(nes, addr)? {
+            Nametable::Mapper => cartridge::nametable_peek(nes, addr),
+            nametable => Ok(read_vram(nes, nametable, addr)),
+        },
+        0x3f00..=0x3fff => Ok(read_palette(nes, addr)),
         _ => Err(anyhow!("Invalid read on ppu bus at address {:x}", addr)),
     }
 }
 
+pub fn read_palette<S, A>(nes: &Nes<S, A>, addr: u16) -> u8 {
+    let mut addr_mirror = match addr {
+        0x3f10 | 0x3f14 | 0x3f18 | 0x3f1c => (addr - 0x10) & 0x3f,
+        _ => (addr & 0x3f1f) & 0x3f,
+    };
+    if nes.ppu.reg_mask.grayscale() {
+        addr_mirror &= 0x30;
+    }
+    nes.bus_ppu.palette[addr_mirror as usize]
+}
+
 pub fn write<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
     match addr {
         0x0000..=0x1fff => {
             cartridge::chr_write(nes, addr, data)?;
         }
-        0x2000..=0x2fff => {
-            let mapped_addr = mirror_vram_addr(nes, addr);
-            nes.bus_ppu.vram[mapped_addr as usize] = data;
-        }
+        0x2000..=0x2fff => match cartridge::nametable(nes, addr)? {
+            Nametable::Mapper => cartridge::nametable_write(nes, addr, data)?,
+            nametable => write_vram(nes, nametable, addr, data),
+        },
         0x3f10 | 0x3f14 | 0x3f18 | 0x3f1c => {
             let add_mirror = addr - 0x10;
             nes.bus_ppu.palette[(add_mirror - 0x3f00) as usize] = data;
@@ -66,26 +77,26 @@ pub fn write<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
     Ok(())
 }
 
-pub fn mirror_vram_addr<S, A>(nes: &mut Nes<S, A>, addr: u16) -> u16 {
-    let mut mapped_addr = addr & 0x0fff;
-    match &nes.cartridge.mirroring {
-        Mirroring::Horizontal => {
-            if (0x0400..0x0800).contains(&mapped_addr) || mapped_addr >= 0x0c00 {
-                mapped_addr -= 0x0400;
-            }
-        }
-        Mirroring::Vertical => {
-            if (0x0800..0x0c00).contains(&mapped_addr) || mapped_addr >= 0x0c00 {
-                mapped_addr -= 0x0800;
-            }
-        }
-        Mirroring::OneScreenNT0 => {
-            mapped_addr &= 0x3ff;
-        }
-        Mirroring::OneScreenNT1 => {
-            mapped_addr &= 0x3ff;
-            mapped_addr += 0x400;
+fn read_vram<S, A>(nes: &Nes<S, A>, nametable: Nametable, addr: u16) -> u8 {
+    let offset = addr as usize & 0x3ff;
+    match nametable {
+        Nametable::Ciram(page) => nes.bus_ppu.vram[(page & 1) * 0x400 + offset],
+        Nametable::CartVram(page) => {
+            let data = nes.cartridge.vram.get(page * 0x400 + offset);
+            data.copied().unwrap_or(0)
         }
+        Nametable::Mapper => 0,
+    }
+}
+
+fn write_vram<S, A>(nes: &mut Nes<S, A>, nametable: Nametable, addr: u16, data: u8) {
+    let offset = addr as usize & 0x3ff;
+    match nametable {
+        Nametable::Ciram(page) => nes.bus_ppu.vram[(page & 1) * 0x400 + offset] = data,
+        Nametable::CartVram(page) => match nes.cartridge.vram.get_mut(page * 0x400 + offset) {
+            Some(cell) => *cell = data,
+            None => log::warn!("No cartridge VRAM at nametable address {:#x}", addr),
+        },
+        Nametable::Mapper => {}
     }
-    mapped_addr
 }
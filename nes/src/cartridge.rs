@@ -4,12 +4,19 @@ use std::rc::Rc;
 use anyhow::anyhow;
 use anyhow::Result;
 
-use crate::mappers::cnrom::Cnrom;
-use crate::mappers::gxrom::Gxrom;
-use crate::mappers::mmc1::Mmc1;
+use crate::cdl;
+use crate::cheats;
+use crate::disk::Disk;
+use crate::gamedb::GameInfo;
+use crate::mappers::fds::Fds;
 use crate::mappers::nrom::Nrom;
-use crate::mappers::uxrom::Uxrom;
+use crate::mappers::nsf::NsfPlayer;
+use crate::mappers::Bank;
 use crate::mappers::Mapper;
+use crate::mappers::Nametable;
+use crate::nsf;
+use crate::nsf::Nsf;
+use crate::unif;
 use crate::Nes;
 
 const NES_TAG: &[u8; 4] = b"NES\x1a";
@@ -21,14 +28,46 @@ pub struct Cartridge<S, A> {
     pub chr_banks: u8,
     pub mapper: Rc<RefCell<dyn Mapper<S, A>>>,
     pub mirroring: Mirroring,
+    pub mapper_id: u16,
+    // extra nametable RAM on the board, used by four-screen mirroring
+    pub vram: Vec<u8>,
+    // board variant from a NES 2.0 header, 0 when unknown
+    pub submapper: u8,
+    // PRG RAM and battery-backed PRG RAM together, from the header
+    pub prg_ram_size: usize,
+    // PRG RAM kept by a battery
+    pub battery: bool,
+    // Famicom Disk System media, only set for disk images
+    pub disk: Option<Disk>,
+    // tune played instead of a game, only set for NSF files
+    pub nsf: Option<Nsf>,
+    // game database entry that corrected the header, if the ROM was found
+    pub game: Option<GameInfo>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mirroring {
     Horizontal,
     Vertical,
     OneScreenNT0,
     OneScreenNT1,
+    FourScreen,
+}
+
+impl Mirroring {
+    pub fn nametable(&self, slot: usize) -> Nametable {
+        match self {
+            Mirroring::Horizontal => Nametable::Ciram(slot >> 1),
+            Mirroring::Vertical => Nametable::Ciram(slot & 1),
+            Mirroring::OneScreenNT0 => Nametable::Ciram(0),
+            Mirroring::OneScreenNT1 => Nametable::Ciram(1),
+            // CIRAM for the first two nametables, 2 KB of cartridge VRAM for the others
+            Mirroring::FourScreen => match slot {
+                0 | 1 => Nametable::Ciram(slot),
+                _ => Nametable::CartVram(slot - 2),
+            },
+        }
+    }
 }
 
 impl<S, A> Default for Cartridge<S, A> {
@@ -40,6 +79,14 @@ impl<S, A> Default for Cartridge<S, A> {
             chr_banks: 0,
             mapper: Rc::new(RefCell::new(Nrom)),
             mirroring: Mirroring::Horizontal,
+            mapper_id: 0,
+            vram: vec![],
+            submapper: 0,
+            prg_ram_size: 0x2000,
+            battery: false,
+            disk: None,
+            nsf: None,
+            game: None,
         }
     }
 }
@@ -49,13 +96,30 @@ pub fn load_cartridge<S, A>(nes: &mut Nes<S, A>, rom_bytes: &[u8]) -> Result<()>
         Err(anyhow!("Invalid NES ROM was provided: No NES ROM"))?;
     }
 
+    if &rom_bytes[0..4] == unif::UNIF_TAG {
+        return load_unif(nes, rom_bytes);
+    }
+
+    if rom_bytes.starts_with(nsf::NSF_TAG) || rom_bytes.starts_with(nsf::NSFE_TAG) {
+        return load_nsf(nes, rom_bytes);
+    }
+
     if &rom_bytes[0..4] != NES_TAG {
         Err(anyhow!("Invalid NES ROM was provided: Missing NES tag"))?;
     }
 
+    if rom_bytes.len() < 16 {
+        Err(anyhow!("Invalid NES ROM was provided: Truncated header"))?;
+    }
+
     let ines_ver = (rom_bytes[0x7] >> 2) & 0b11;
-    if ines_ver != 0 {
-        Err(anyhow!("NES2.0 format is not supported"))?;
+    let nes2 = match ines_ver {
+        0 => false,
+        2 => true,
+        _ => Err(anyhow!("Unknown iNES header version"))?,
+    };
+    if nes2 && rom_bytes[0x9] != 0 {
+        Err(anyhow!("NES2.0 ROM sizes above 4 MB are not supported"))?;
     }
 
     // read file header
@@ -63,16 +127,21 @@ pub fn load_cartridge<S, A>(nes: &mut Nes<S, A>, rom_bytes: &[u8]) -> Result<()>
     let chr_banks = rom_bytes[0x5];
 
     let trainer_is_present = rom_bytes[0x6] & 0x04 != 0;
+    nes.cartridge.battery = rom_bytes[0x6] & 0x02 != 0;
     let mirroring = rom_bytes[0x6] & 0x01 != 0;
+    let four_screen = rom_bytes[0x6] & 0x08 != 0;
     let prg_size = 0x4000 * prg_banks as usize;
     let mut chr_size = 0x2000 * chr_banks as usize;
 
-    nes.cartridge.mirroring = if !mirroring {
+    nes.cartridge.mirroring = if four_screen {
+        Mirroring::FourScreen
+    } else if !mirroring {
         Mirroring::Horizontal
     } else {
         Mirroring::Vertical
     };
     log::info!("Mirroring: {:?}", nes.cartridge.mirroring);
+    nes.cartridge.vram = if four_screen { vec![0; 0x800] } else { vec![] };
 
     // resize cartridge roms
     nes.cartridge.prg_banks = prg_banks as u8;
@@ -81,28 +150,46 @@ pub fn load_cartridge<S, A>(nes: &mut Nes<S, A>, rom_bytes: &[u8]) -> Result<()>
         nes.cartridge.chrmem.resize(0x2000, 0);
         chr_size = 0x2000;
     }
+    nes.cartridge.prg_ram_size = if nes2 {
+        // shift counts of 64 byte units, 0 meaning none
+        let size = |shift: u8| if shift == 0 { 0 } else { 64 << shift };
+        size(rom_bytes[0xa] & 0x0f) + size(rom_bytes[0xa] >> 4)
+    } else {
+        // 8 KB units, 0 meaning 8 KB for compatibility
+        0x2000 * (rom_bytes[0x8] as usize).max(1)
+    };
     log::info!("PRG banks: {}", prg_banks);
     log::info!("CHR banks: {}", chr_banks);
 
     // choose mapper
-    let mapper_id = (rom_bytes[0x7] & 0xf0) | ((rom_bytes[0x6] & 0xf0) >> 4);
-    nes.cartridge.mapper = match mapper_id {
-        0 => Rc::new(RefCell::new(Nrom)),
-        1 => Rc::new(RefCell::new(Mmc1::new())),
-        2 => Rc::new(RefCell::new(Uxrom::default())),
-        3 => Rc::new(RefCell::new(Cnrom::default())),
-        66 => Rc::new(RefCell::new(Gxrom::default())),
-        _ => Err(anyhow!("Mapper {} not supported yet...", mapper_id))?,
-    };
+    let mut mapper_id = ((rom_bytes[0x7] & 0xf0) | ((rom_bytes[0x6] & 0xf0) >> 4)) as u16;
+    nes.cartridge.submapper = 0;
+    if nes2 {
+        mapper_id |= ((rom_bytes[0x8] & 0x0f) as u16) << 8;
+        nes.cartridge.submapper = rom_bytes[0x8] >> 4;
+    }
+    nes.cartridge.mapper_id = mapper_id;
+    nes.cartridge.disk = None;
+    nes.cartridge.nsf = None;
+
+    // fix up the header from the game database
+    let prg_start = 16 + (trainer_is_present as usize) * 512;
+    let chr_start = prg_start + prg_size;
+    let rom_data = rom_bytes.get(prg_start..(chr_start + 0x2000 * chr_banks as usize));
+    nes.cartridge.game = rom_data.and_then(|data| nes.gamedb.find(data)).cloned();
+    if let Some(game) = nes.cartridge.game.clone() {
+        apply_game_info(nes, &game);
+    }
+
+    nes.cartridge.mapper = nes.mappers.create(&nes.cartridge)?;
     log::info!(
-        "Loaded Mapper {}: {:?}",
-        mapper_id,
+        "Loaded Mapper {}.{}: {:?}",
+        nes.cartridge.mapper_id,
+        nes.cartridge.submapper,
         nes.cartridge.mapper.try_borrow()?.name()
     );
 
     // fill memories
-    let prg_start = 16 + (trainer_is_present as usize) * 512;
-    let chr_start = prg_start + prg_size;
     nes.cartridge.prgmem = rom_bytes[prg_start..(prg_start + prg_size)].to_vec();
     if chr_banks == 0 {
         nes.cartridge.chrmem = rom_bytes[chr_start..].to_vec();
@@ -110,7 +197,203 @@ pub fn load_cartridge<S, A>(nes: &mut Nes<S, A>, rom_bytes: &[u8]) -> Result<()>
     } else {
         nes.cartridge.chrmem = rom_bytes[chr_start..(chr_start + chr_size)].to_vec();
     }
+    let chr_ram_size = nes
+        .cartridge
+        .game
+        .as_ref()
+        .and_then(|game| game.chr_ram_size);
+    if let (0, Some(size)) = (chr_banks, chr_ram_size) {
+        nes.cartridge.chrmem.resize(size, 0x00);
+    }
+    cdl::clear(nes);
+
+    Ok(())
+}
+
+// Override the header fields the database knows better, warning about each change
+fn apply_game_info<S, A>(nes: &mut Nes<S, A>, game: &GameInfo) {
+    let cartridge = &mut nes.cartridge;
+    log::info!("Found {:?} in the game database", game.name);
+    match game.mapper_id {
+        Some(mapper_id) if mapper_id != cartridge.mapper_id => {
+            log::warn!(
+                "Header says mapper {}, game database says {}",
+                cartridge.mapper_id,
+                mapper_id
+            );
+            cartridge.mapper_id = mapper_id;
+        }
+        _ => {}
+    }
+    match game.submapper {
+        Some(submapper) if submapper != cartridge.submapper => {
+            log::warn!(
+                "Header says submapper {}, game database says {}",
+                cartridge.submapper,
+                submapper
+            );
+            cartridge.submapper = submapper;
+        }
+        _ => {}
+    }
+    match game.mirroring {
+        Some(mirroring) if mirroring != cartridge.mirroring => {
+            log::warn!(
+                "Header says {:?} mirroring, game database says {:?}",
+                cartridge.mirroring,
+                mirroring
+            );
+            cartridge.mirroring = mirroring;
+            cartridge.vram = if mirroring == Mirroring::FourScreen {
+                vec![0; 0x800]
+            } else {
+                vec![]
+            };
+        }
+        _ => {}
+    }
+    match game.prg_ram_size {
+        Some(size) if size != cartridge.prg_ram_size => {
+            log::warn!(
+                "Header says {} bytes of PRG RAM, game database says {}",
+                cartridge.prg_ram_size,
+                size
+            );
+            cartridge.prg_ram_size = size;
+        }
+        _ => {}
+    }
+    match game.battery {
+        Some(battery) if battery != cartridge.battery => {
+            log::warn!(
+                "Header says battery {}, game database says {}",
+                cartridge.battery,
+                battery
+            );
+            cartridge.battery = battery;
+        }
+        _ => {}
+    }
+}
+
+// Load a UNIF dump, taking the mapper from its board name
+fn load_unif<S, A>(nes: &mut Nes<S, A>, rom_bytes: &[u8]) -> Result<()> {
+    let unif = unif::parse(rom_bytes)?;
+
+    let four_screen = matches!(unif.mirroring, Some(Mirroring::FourScreen));
+    nes.cartridge.mirroring = unif.mirroring.unwrap_or(Mirroring::Horizontal);
+    log::info!("Mirroring: {:?}", nes.cartridge.mirroring);
+    nes.cartridge.vram = if four_screen { vec![0; 0x800] } else { vec![] };
+
+    nes.cartridge.prg_banks = unif.prgmem.len().div_ceil(0x4000) as u8;
+    nes.cartridge.chr_banks = unif.chrmem.len().div_ceil(0x2000) as u8;
+    nes.cartridge.prg_ram_size = match unif.mapper_id {
+        // the large MMC1 boards, UNIF gives no RAM size
+        1 if unif.board.ends_with("SOROM") => 0x4000,
+        1 if unif.board.ends_with("SXROM") => 0x8000,
+        _ => 0x2000,
+    };
+    nes.cartridge.battery = unif.battery;
+    log::info!("PRG banks: {}", nes.cartridge.prg_banks);
+    log::info!("CHR banks: {}", nes.cartridge.chr_banks);
+
+    nes.cartridge.mapper_id = unif.mapper_id;
+    nes.cartridge.submapper = unif.submapper;
+    nes.cartridge.disk = None;
+    nes.cartridge.nsf = None;
+    nes.cartridge.game = None;
+    nes.cartridge.mapper = nes.mappers.create(&nes.cartridge)?;
+    log::info!(
+        "Loaded UNIF board {} as mapper {}.{}: {:?}",
+        unif.board,
+        unif.mapper_id,
+        unif.submapper,
+        nes.cartridge.mapper.try_borrow()?.name()
+    );
+
+    nes.cartridge.prgmem = unif.prgmem;
+    nes.cartridge.chrmem = unif.chrmem;
+    if nes.cartridge.chrmem.is_empty() {
+        nes.cartridge.chrmem.resize(0x2000, 0);
+    }
+    cdl::clear(nes);
+    Ok(())
+}
+
+// Load a .fds disk image, booting from the 8 KB disk system BIOS
+pub fn load_disk<S, A>(nes: &mut Nes<S, A>, disk_bytes: &[u8], bios: &[u8]) -> Result<()> {
+    if bios.len() != 0x2000 {
+        Err(anyhow!("Invalid FDS BIOS was provided: Expected 8 KB"))?;
+    }
+    let disk = Disk::from_image(disk_bytes)?;
+
+    nes.cartridge.prgmem = bios.to_vec();
+    nes.cartridge.chrmem = vec![0; 0x2000];
+    nes.cartridge.prg_banks = 0;
+    nes.cartridge.chr_banks = 0;
+    nes.cartridge.mirroring = Mirroring::Horizontal;
+    nes.cartridge.vram = vec![];
+    // iNES reserves mapper 20 for the disk system
+    nes.cartridge.mapper_id = 20;
+    nes.cartridge.submapper = 0;
+    nes.cartridge.prg_ram_size = 0x8000;
+    nes.cartridge.battery = false;
+    nes.cartridge.disk = Some(disk);
+    nes.cartridge.nsf = None;
+    nes.cartridge.game = None;
+    nes.cartridge.mapper = Rc::new(RefCell::new(Fds::new()));
+    log::info!("Loaded Famicom Disk System image");
+    cdl::clear(nes);
+    Ok(())
+}
+
+// Load an NSF or NSFe tune, resetting the console starts its first track
+fn load_nsf<S, A>(nes: &mut Nes<S, A>, nsf_bytes: &[u8]) -> Result<()> {
+    let nsf = nsf::parse(nsf_bytes)?;
+    if nsf.chips & nsf::CHIP_VRC7 != 0 {
+        log::warn!("VRC7 audio is not supported, its channels will be silent");
+    }
+
+    // pages count from the 4 KB page of the load address when bankswitching,
+    // otherwise from where the tune is mapped
+    let base = match nsf.banks {
+        Some(_) => nsf.load_addr & 0xf000,
+        None if nsf.chips & nsf::CHIP_FDS != 0 => 0x6000,
+        None => 0x8000,
+    };
+    if nsf.load_addr < base {
+        Err(anyhow!(
+            "Invalid NSF file was provided: Load address {:#x} is below {:#x}",
+            nsf.load_addr,
+            base
+        ))?;
+    }
+    let mut prgmem = vec![0; (nsf.load_addr - base) as usize];
+    prgmem.extend(&nsf.data);
+    prgmem.resize(prgmem.len().div_ceil(0x1000) * 0x1000, 0);
 
+    nes.cartridge.prgmem = prgmem;
+    nes.cartridge.chrmem = vec![0; 0x2000];
+    nes.cartridge.prg_banks = 0;
+    nes.cartridge.chr_banks = 0;
+    nes.cartridge.mirroring = Mirroring::Horizontal;
+    nes.cartridge.vram = vec![];
+    // no iNES mapper number exists for the player
+    nes.cartridge.mapper_id = 0;
+    nes.cartridge.submapper = 0;
+    nes.cartridge.prg_ram_size = 0x2000;
+    nes.cartridge.battery = false;
+    nes.cartridge.disk = None;
+    nes.cartridge.mapper = Rc::new(RefCell::new(NsfPlayer::new(&nsf)));
+    log::info!(
+        "Loaded NSF {:?} by {:?}, {} tracks",
+        nsf.title,
+        nsf.artist,
+        nsf.tracks
+    );
+    nes.cartridge.game = None;
+    nes.cartridge.nsf = Some(nsf);
+    cdl::clear(nes);
     Ok(())
 }
 
@@ -123,7 +406,49 @@ pub fn reset<S, A>(nes: &mut Nes<S, A>) -> Result<()> {
 pub fn prg_read<S, A>(nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
     let mapper = nes.cartridge.mapper.clone();
     let mut mapper_ref = mapper.try_borrow_mut()?;
-    mapper_ref.read_prg(nes, addr)
+    let data = mapper_ref.read_prg(nes, addr)?;
+    Ok(match addr {
+        0x8000..=0xffff => cheats::patch_prg(nes, addr, data),
+        _ => data,
+    })
+}
+
+pub fn prg_peek<S, A>(nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mapper_ref = mapper.try_borrow()?;
+    mapper_ref.peek_prg(nes, addr)
+}
+
+pub fn prg_ram_poke<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<bool> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mut mapper_ref = mapper.try_borrow_mut()?;
+    Ok(mapper_ref.poke_prg_ram(nes, addr, data))
+}
+
+// PRG memory offset a CPU address maps to with the current banking
+pub fn prg_offset<S, A>(nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mapper_ref = mapper.try_borrow().ok()?;
+    mapper_ref.map_prg(nes, addr)
+}
+
+// CHR memory offset a PPU address maps to with the current banking
+pub fn chr_offset<S, A>(nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mapper_ref = mapper.try_borrow().ok()?;
+    mapper_ref.map_chr(nes, addr)
+}
+
+pub fn prg_layout<S, A>(nes: &Nes<S, A>) -> Result<Vec<Bank>> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mapper_ref = mapper.try_borrow()?;
+    Ok(mapper_ref.prg_layout(nes))
+}
+
+pub fn chr_layout<S, A>(nes: &Nes<S, A>) -> Result<Vec<Bank>> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mapper_ref = mapper.try_borrow()?;
+    Ok(mapper_ref.chr_layout(nes))
 }
 
 pub fn prg_write<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
@@ -133,9 +458,59 @@ pub fn prg_write<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
 }
 
 pub fn chr_read<S, A>(nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+    let mapper = nes.cartridge.mapper.clone();
+    let data = mapper.try_borrow_mut()?.read_chr(nes, addr)?;
+    cdl::log_chr_read(nes, addr);
+    Ok(data)
+}
+
+pub fn nametable<S, A>(nes: &Nes<S, A>, addr: u16) -> Result<Nametable> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mapper_ref = mapper.try_borrow()?;
+    Ok(mapper_ref.nametable(nes, (addr as usize >> 10) & 0b11))
+}
+
+pub fn nametable_read<S, A>(nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mut mapper_ref = mapper.try_borrow_mut()?;
+    mapper_ref.read_nametable(nes, addr)
+}
+
+pub fn nametable_peek<S, A>(nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mapper_ref = mapper.try_borrow()?;
+    Ok(mapper_ref.peek_nametable(nes, addr))
+}
+
+pub fn nametable_write<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
     let mapper = nes.cartridge.mapper.clone();
     let mut mapper_ref = mapper.try_borrow_mut()?;
-    mapper_ref.read_chr(nes, addr)
+    mapper_ref.write_nametable(nes, addr, data)
+}
+
+pub fn clock<S, A>(nes: &mut Nes<S, A>) -> Result<()> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mut mapper_ref = mapper.try_borrow_mut()?;
+    mapper_ref.clock(nes)
+}
+
+pub fn scanline<S, A>(nes: &mut Nes<S, A>) -> Result<()> {
+    let mapper = nes.cartridge.mapper.clone();
+    let mut mapper_ref = mapper.try_borrow_mut()?;
+    mapper_ref.scanline(nes)
+}
+
+pub fn irq<S, A>(nes: &Nes<S, A>) -> Result<bool> {
+    Ok(nes.cartridge.mapper.try_borrow()?.irq())
+}
+
+pub fn audio_output<S, A>(nes: &Nes<S, A>) -> Result<Option<f32>> {
+    Ok(nes.cartridge.mapper.try_borrow()?.audio_output())
+}
+
+pub fn chr_peek<S, A>(nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+    let mapped_addr = chr_offset(nes, addr).unwrap_or(0);
+    Ok(nes.cartridge.chrmem.get(mapped_addr).copied().unwrap_or(0))
 }
 
 pub fn chr_write<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
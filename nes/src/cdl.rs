@@ -0,0 +1,180 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::cartridge;
+use crate::Nes;
+
+/*
+    Code/Data Logger. Flags every PRG ROM byte with how the CPU used it and every CHR ROM byte
+    with how the PPU used it. Bits follow the FCEUX .cdl format:
+    PRG: xPdcAADC (C code, D data, AA 8 KB slot it was mapped at, c indirect code, d indirect data)
+    CHR: ------RD (D drawn by the PPU, R read through $2007)
+*/
+pub const CODE: u8 = 0x01;
+pub const DATA: u8 = 0x02;
+pub const INDIRECT_CODE: u8 = 0x10;
+pub const INDIRECT_DATA: u8 = 0x20;
+
+pub const CHR_DRAWN: u8 = 0x01;
+pub const CHR_READ: u8 = 0x02;
+
+pub struct Cdl {
+    pub enabled: bool,
+    pub prg: Vec<u8>,
+    pub chr: Vec<u8>,
+    // flags given to the next PRG and CHR reads
+    prg_access: u8,
+    chr_access: u8,
+}
+
+impl Default for Cdl {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            prg: vec![],
+            chr: vec![],
+            prg_access: DATA,
+            chr_access: CHR_DRAWN,
+        }
+    }
+}
+
+pub struct Coverage {
+    pub code: usize,
+    pub data: usize,
+    pub unused: usize,
+}
+
+// Start logging, keeping what was logged so far for the loaded cartridge
+pub fn start<S, A>(nes: &mut Nes<S, A>) {
+    let (prg_len, chr_len) = rom_sizes(nes);
+    nes.cdl.prg.resize(prg_len, 0);
+    nes.cdl.chr.resize(chr_len, 0);
+    nes.cdl.enabled = true;
+}
+
+pub fn stop<S, A>(nes: &mut Nes<S, A>) {
+    nes.cdl.enabled = false;
+}
+
+// Forget everything logged, a running log restarts sized for the loaded cartridge
+pub fn clear<S, A>(nes: &mut Nes<S, A>) {
+    nes.cdl.prg.clear();
+    nes.cdl.chr.clear();
+    if nes.cdl.enabled {
+        start(nes);
+    }
+}
+
+// FCEUX layout: PRG flags followed by CHR flags (none for CHR RAM)
+pub fn export<S, A>(nes: &Nes<S, A>) -> Vec<u8> {
+    let (prg_len, chr_len) = rom_sizes(nes);
+    let mut out = nes.cdl.prg.clone();
+    out.resize(prg_len, 0);
+    let mut chr = nes.cdl.chr.clone();
+    chr.resize(chr_len, 0);
+    out.extend(chr);
+    out
+}
+
+// Merge a previously saved log into the current one
+pub fn import<S, A>(nes: &mut Nes<S, A>, bytes: &[u8]) -> Result<()> {
+    let (prg_len, chr_len) = rom_sizes(nes);
+    if bytes.len() != prg_len + chr_len {
+        Err(anyhow!(
+            "CDL file has {} bytes but the cartridge needs {}",
+            bytes.len(),
+            prg_len + chr_len
+        ))?;
+    }
+    nes.cdl.prg.resize(prg_len, 0);
+    nes.cdl.chr.resize(chr_len, 0);
+    let (prg, chr) = bytes.split_at(prg_len);
+    nes.cdl.prg.iter_mut().zip(prg).for_each(|(a, b)| *a |= b);
+    nes.cdl.chr.iter_mut().zip(chr).for_each(|(a, b)| *a |= b);
+    Ok(())
+}
+
+pub fn coverage<S, A>(nes: &Nes<S, A>) -> Coverage {
+    let mut coverage = Coverage {
+        code: 0,
+        data: 0,
+        unused: 0,
+    };
+    for flags in nes.cdl.prg.iter() {
+        if flags & CODE != 0 {
+            coverage.code += 1;
+        }
+        if flags & DATA != 0 {
+            coverage.data += 1;
+        }
+        if flags & (CODE | DATA) == 0 {
+            coverage.unused += 1;
+        }
+    }
+    coverage
+}
+
+// Run f with every PRG read flagged as the given access (0 to leave the log untouched)
+pub fn with_prg_access<S, A, T>(
+    nes: &mut Nes<S, A>,
+    access: u8,
+    f: impl FnOnce(&mut Nes<S, A>) -> Result<T>,
+) -> Result<T> {
+    let prev = std::mem::replace(&mut nes.cdl.prg_access, access);
+    let result = f(nes);
+    nes.cdl.prg_access = prev;
+    result
+}
+
+// Run f with every CHR read flagged as the given access (0 to leave the log untouched)
+pub fn with_chr_access<S, A, T>(
+    nes: &mut Nes<S, A>,
+    access: u8,
+    f: impl FnOnce(&mut Nes<S, A>) -> Result<T>,
+) -> Result<T> {
+    let prev = std::mem::replace(&mut nes.cdl.chr_access, access);
+    let result = f(nes);
+    nes.cdl.chr_access = prev;
+    result
+}
+
+pub fn log_prg_read<S, A>(nes: &mut Nes<S, A>, addr: u16) {
+    if nes.cdl.enabled {
+        log_prg(nes, addr, nes.cdl.prg_access);
+    }
+}
+
+pub fn log_chr_read<S, A>(nes: &mut Nes<S, A>, addr: u16) {
+    if !nes.cdl.enabled || nes.cdl.chr_access == 0 {
+        return;
+    }
+    let offset = cartridge::chr_offset(nes, addr);
+    if let Some(flags) = offset.and_then(|offset| nes.cdl.chr.get_mut(offset)) {
+        *flags |= nes.cdl.chr_access;
+    }
+}
+
+pub fn log_prg<S, A>(nes: &mut Nes<S, A>, addr: u16, access: u8) {
+    if !nes.cdl.enabled || access == 0 {
+        return;
+    }
+    let offset = cartridge::prg_offset(nes, addr);
+    if let Some(flags) = offset.and_then(|offset| nes.cdl.prg.get_mut(offset)) {
+        let slot = if addr >= 0x8000 {
+            ((addr >> 13) & 0b11) as u8
+        } else {
+            0
+        };
+        *flags |= access | slot << 2;
+    }
+}
+
+fn rom_sizes<S, A>(nes: &Nes<S, A>) -> (usize, usize) {
+    let chr_len = if nes.cartridge.chr_banks == 0 {
+        0
+    } else {
+        nes.cartridge.chrmem.len()
+    };
+    (nes.cartridge.prgmem.len(), chr_len)
+}
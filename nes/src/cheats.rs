@@ -0,0 +1,305 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::mappers::Memory;
+use crate::Nes;
+
+/*
+    Game Genie codes. Each letter stands for 4 bits, which are shuffled into a ROM address
+    in $8000-$FFFF, the value the CPU reads there instead, and for 8 letter codes a compare
+    value that the ROM must hold for the patch to apply, so it only hits the intended bank.
+*/
+const GENIE_LETTERS: &[u8; 16] = b"APZLGITYEOXUKSVN";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameGenie {
+    pub code: String,
+    pub addr: u16,
+    pub value: u8,
+    pub compare: Option<u8>,
+    pub enabled: bool,
+}
+
+impl GameGenie {
+    pub fn decode(code: &str) -> Result<Self> {
+        let code = code.trim().to_ascii_uppercase();
+        let n = code
+            .bytes()
+            .map(|letter| GENIE_LETTERS.iter().position(|&l| l == letter))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow!("Invalid Game Genie code {}: Unknown letter", code))?
+            .into_iter()
+            .map(|n| n as u16)
+            .collect::<Vec<_>>();
+        if n.len() != 6 && n.len() != 8 {
+            Err(anyhow!(
+                "Invalid Game Genie code {}: Codes have 6 or 8 letters",
+                code
+            ))?;
+        }
+
+        let addr = 0x8000
+            | ((n[3] & 7) << 12)
+            | ((n[5] & 7) << 8)
+            | ((n[4] & 8) << 8)
+            | ((n[2] & 7) << 4)
+            | ((n[1] & 8) << 4)
+            | (n[4] & 7)
+            | (n[3] & 8);
+        let value = ((n[1] & 7) << 4) | ((n[0] & 8) << 4) | (n[0] & 7);
+        let (value, compare) = match n.len() {
+            6 => (value | (n[5] & 8), None),
+            _ => (
+                value | (n[7] & 8),
+                Some(((n[7] & 7) << 4) | ((n[6] & 8) << 4) | (n[6] & 7) | (n[5] & 8)),
+            ),
+        };
+        Ok(Self {
+            code,
+            addr,
+            value: value as u8,
+            compare: compare.map(|compare| compare as u8),
+            enabled: true,
+        })
+    }
+}
+
+// Pro Action Replay style code, keeping a byte of RAM or cartridge PRG RAM at a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RamCheat {
+    pub code: String,
+    pub addr: u16,
+    pub value: u8,
+    pub enabled: bool,
+}
+
+impl RamCheat {
+    // Codes are written AAAA:VV or AAAAVV in hex
+    pub fn decode(code: &str) -> Result<Self> {
+        let code = code.trim().to_ascii_uppercase();
+        let digits = code.replace(':', "");
+        let invalid = || anyhow!("Invalid RAM code {}: Expected AAAA:VV in hex", code);
+        if digits.len() != 6 || !digits.is_ascii() {
+            Err(invalid())?;
+        }
+        let addr = u16::from_str_radix(&digits[..4], 16).map_err(|_| invalid())?;
+        let value = u8::from_str_radix(&digits[4..], 16).map_err(|_| invalid())?;
+        if !matches!(addr, 0x0000..=0x07ff | 0x6000..=0x7fff) {
+            Err(anyhow!(
+                "Invalid RAM code {}: Address is not in RAM ($0000-$07FF) or PRG RAM ($6000-$7FFF)",
+                code
+            ))?;
+        }
+        Ok(Self {
+            code: format!("{:04X}:{:02X}", addr, value),
+            addr,
+            value,
+            enabled: true,
+        })
+    }
+}
+
+// How the values kept by a search compare to the ones seen by the previous step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchFilter {
+    Equal,
+    Changed,
+    Greater,
+    Less,
+    Value(u8),
+}
+
+/*
+    Cheat finder. Starts from every byte of RAM and PRG RAM and narrows the candidates down
+    with each filter, comparing them to the values they had at the previous step.
+*/
+pub struct Search {
+    // address and last seen value of each candidate
+    pub candidates: Vec<(u16, u8)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchType {
+    U8,
+    I8,
+    // 16 bit values are little endian
+    U16,
+    I16,
+    // two decimal digits per byte, the most significant byte first
+    Bcd8,
+    Bcd16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    pub addr: u16,
+    pub kind: WatchType,
+    pub label: String,
+}
+
+#[derive(Default)]
+pub struct Cheats {
+    pub game_genie: Vec<GameGenie>,
+    pub ram: Vec<RamCheat>,
+    pub search: Option<Search>,
+    pub watches: Vec<Watch>,
+}
+
+// Decode and enable a Game Genie code, returning its index
+pub fn add_game_genie<S, A>(nes: &mut Nes<S, A>, code: &str) -> Result<usize> {
+    let genie = GameGenie::decode(code)?;
+    if nes.cheats.game_genie.iter().any(|g| g.code == genie.code) {
+        Err(anyhow!("Game Genie code {} is already added", genie.code))?;
+    }
+    nes.cheats.game_genie.push(genie);
+    Ok(nes.cheats.game_genie.len() - 1)
+}
+
+pub fn remove_game_genie<S, A>(nes: &mut Nes<S, A>, index: usize) -> Result<GameGenie> {
+    if index >= nes.cheats.game_genie.len() {
+        Err(anyhow!("No Game Genie code number {}", index))?;
+    }
+    Ok(nes.cheats.game_genie.remove(index))
+}
+
+pub fn enable_game_genie<S, A>(nes: &mut Nes<S, A>, index: usize, enabled: bool) -> Result<()> {
+    nes.cheats
+        .game_genie
+        .get_mut(index)
+        .ok_or_else(|| anyhow!("No Game Genie code number {}", index))?
+        .enabled = enabled;
+    Ok(())
+}
+
+// What the CPU reads from the cartridge once the enabled codes are applied
+pub(crate) fn patch_prg<S, A>(nes: &Nes<S, A>, addr: u16, data: u8) -> u8 {
+    nes.cheats
+        .game_genie
+        .iter()
+        .find(|g| g.enabled && g.addr == addr && g.compare.unwrap_or(data) == data)
+        .map_or(data, |g| g.value)
+}
+
+// Decode and enable a RAM code, returning its index
+pub fn add_ram_cheat<S, A>(nes: &mut Nes<S, A>, code: &str) -> Result<usize> {
+    let cheat = RamCheat::decode(code)?;
+    if nes.cheats.ram.iter().any(|c| c.code == cheat.code) {
+        Err(anyhow!("RAM code {} is already added", cheat.code))?;
+    }
+    nes.cheats.ram.push(cheat);
+    Ok(nes.cheats.ram.len() - 1)
+}
+
+pub fn remove_ram_cheat<S, A>(nes: &mut Nes<S, A>, index: usize) -> Result<RamCheat> {
+    if index >= nes.cheats.ram.len() {
+        Err(anyhow!("No RAM code number {}", index))?;
+    }
+    Ok(nes.cheats.ram.remove(index))
+}
+
+pub fn enable_ram_cheat<S, A>(nes: &mut Nes<S, A>, index: usize, enabled: bool) -> Result<()> {
+    nes.cheats
+        .ram
+        .get_mut(index)
+        .ok_or_else(|| anyhow!("No RAM code number {}", index))?
+        .enabled = enabled;
+    Ok(())
+}
+
+// Called by the PPU once per frame, as vertical blank starts
+pub(crate) fn frame<S, A>(nes: &mut Nes<S, A>) -> Result<()> {
+    for i in 0..nes.cheats.ram.len() {
+        let cheat = &nes.cheats.ram[i];
+        if cheat.enabled {
+            let (addr, value) = (cheat.addr, cheat.value);
+            buscpu::poke(nes, addr, value)?;
+        }
+    }
+    Ok(())
+}
+
+// Start a new search from all of RAM, and PRG RAM when the cartridge maps some
+pub fn search_start<S, A>(nes: &mut Nes<S, A>) -> Result<usize> {
+    let mut candidates = vec![];
+    for addr in 0x0000..=0x07ff {
+        candidates.push((addr, buscpu::peek(nes, addr)?));
+    }
+    let prg_ram = cartridge_prg_ram(nes)?;
+    for addr in prg_ram {
+        candidates.push((addr, buscpu::peek(nes, addr)?));
+    }
+    let count = candidates.len();
+    nes.cheats.search = Some(Search { candidates });
+    Ok(count)
+}
+
+// Keep the candidates matching the filter, returning how many are left
+pub fn search_filter<S, A>(nes: &mut Nes<S, A>, filter: SearchFilter) -> Result<usize> {
+    let mut search = nes
+        .cheats
+        .search
+        .take()
+        .ok_or_else(|| anyhow!("No search started"))?;
+    let mut candidates = Vec::with_capacity(search.candidates.len());
+    for &(addr, last) in search.candidates.iter() {
+        let value = buscpu::peek(nes, addr)?;
+        let keep = match filter {
+            SearchFilter::Equal => value == last,
+            SearchFilter::Changed => value != last,
+            SearchFilter::Greater => value > last,
+            SearchFilter::Less => value < last,
+            SearchFilter::Value(wanted) => value == wanted,
+        };
+        if keep {
+            candidates.push((addr, value));
+        }
+    }
+    search.candidates = candidates;
+    let count = search.candidates.len();
+    nes.cheats.search = Some(search);
+    Ok(count)
+}
+
+// The $6000-$7FFF addresses backed by PRG RAM, found without writing to them
+fn cartridge_prg_ram<S, A>(nes: &Nes<S, A>) -> Result<Vec<u16>> {
+    let layout = cartridge::prg_layout(nes)?;
+    Ok((0x6000..=0x7fff)
+        .filter(|&addr| {
+            layout
+                .iter()
+                .any(|bank| bank.contains(addr) && bank.memory == Memory::PrgRam)
+        })
+        .collect())
+}
+
+pub fn add_watch<S, A>(nes: &mut Nes<S, A>, addr: u16, kind: WatchType, label: &str) -> usize {
+    nes.cheats.watches.push(Watch {
+        addr,
+        kind,
+        label: label.to_string(),
+    });
+    nes.cheats.watches.len() - 1
+}
+
+pub fn remove_watch<S, A>(nes: &mut Nes<S, A>, index: usize) -> Result<Watch> {
+    if index >= nes.cheats.watches.len() {
+        Err(anyhow!("No watch number {}", index))?;
+    }
+    Ok(nes.cheats.watches.remove(index))
+}
+
+// Current value of a watch, read without side effects
+pub fn watch_value<S, A>(nes: &Nes<S, A>, watch: &Watch) -> Result<i32> {
+    let byte = |offset: u16| buscpu::peek(nes, watch.addr.wrapping_add(offset));
+    let bcd = |byte: u8| (byte >> 4) as i32 * 10 + (byte & 0x0f) as i32;
+    Ok(match watch.kind {
+        WatchType::U8 => byte(0)? as i32,
+        WatchType::I8 => byte(0)? as i8 as i32,
+        WatchType::U16 => u16::from_le_bytes([byte(0)?, byte(1)?]) as i32,
+        WatchType::I16 => i16::from_le_bytes([byte(0)?, byte(1)?]) as i32,
+        WatchType::Bcd8 => bcd(byte(0)?),
+        WatchType::Bcd16 => bcd(byte(0)?) * 100 + bcd(byte(1)?),
+    })
+}
@@ -5,6 +5,47 @@ use crate::nesaudio::NesAudio;
 use crate::nesscreen::NesScreen;
 use crate::Nes;
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddrMode {
+    Abs,
+    Abx,
+    Aby,
+    Imm,
+    #[default]
+    Imp,
+    Ind,
+    Idx,
+    Idy,
+    Rel,
+    Zpg,
+    Zpx,
+    Zpy,
+    Xxx,
+}
+
+// Resolve the operand address of the current instruction
+pub fn run<S, A>(nes: &mut Nes<S, A>, mode: AddrMode) -> Result<()>
+where
+    S: NesScreen,
+    A: NesAudio,
+{
+    match mode {
+        AddrMode::Abs => abs(nes),
+        AddrMode::Abx => abx(nes),
+        AddrMode::Aby => aby(nes),
+        AddrMode::Imm => imm(nes),
+        AddrMode::Imp => imp(nes),
+        AddrMode::Ind => ind(nes),
+        AddrMode::Idx => idx(nes),
+        AddrMode::Idy => idy(nes),
+        AddrMode::Rel => rel(nes),
+        AddrMode::Zpg => zpg(nes),
+        AddrMode::Zpx => zpx(nes),
+        AddrMode::Zpy => zpy(nes),
+        AddrMode::Xxx => xxx(nes),
+    }
+}
+
 pub fn abs<S, A>(nes: &mut Nes<S, A>) -> Result<()>
 where
     S: NesScreen,
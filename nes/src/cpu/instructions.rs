@@ -1,7 +1,7 @@
 use anyhow::Result;
 
 use crate::cpu;
-use crate::cpu::addressing;
+use crate::cpu::addressing::AddrMode;
 use crate::cpu::CpuFlag;
 use crate::nesaudio::NesAudio;
 use crate::nesscreen::NesScreen;
@@ -53,10 +53,10 @@ where
     cpu::set_flag(nes, CpuFlag::Z, tmp & 0x00ff == 0);
     cpu::set_flag(nes, CpuFlag::N, tmp & 0x0080 != 0);
 
-    if nes.cpu.addr_mode == addressing::imp::<S, A> as usize {
+    if nes.cpu.addr_mode == AddrMode::Imp {
         nes.cpu.ac = tmp as u8;
     } else {
-        cpu::write(nes, nes.cpu.addr, tmp as u8)?;
+        cpu::write_modified(nes, tmp as u8)?;
     }
     Ok(())
 }
@@ -295,7 +295,7 @@ where
 {
     cpu::fetch_data(nes)?;
     let tmp = nes.cpu.data.wrapping_sub(1);
-    cpu::write(nes, nes.cpu.addr, tmp)?;
+    cpu::write_modified(nes, tmp)?;
     cpu::set_flag(nes, CpuFlag::Z, tmp == 0);
     cpu::set_flag(nes, CpuFlag::N, tmp & 0x0080 != 0);
     Ok(())
@@ -342,7 +342,7 @@ where
 {
     cpu::fetch_data(nes)?;
     let tmp = nes.cpu.data.wrapping_add(1);
-    cpu::write(nes, nes.cpu.addr, tmp)?;
+    cpu::write_modified(nes, tmp)?;
     cpu::set_flag(nes, CpuFlag::Z, tmp == 0);
     cpu::set_flag(nes, CpuFlag::N, tmp & 0x0080 != 0);
     Ok(())
@@ -451,10 +451,10 @@ where
     cpu::set_flag(nes, CpuFlag::Z, tmp & 0x00ff == 0);
     cpu::set_flag(nes, CpuFlag::N, tmp & 0x0080 != 0);
 
-    if nes.cpu.addr_mode == addressing::imp::<S, A> as usize {
+    if nes.cpu.addr_mode == AddrMode::Imp {
         nes.cpu.ac = tmp as u8;
     } else {
-        cpu::write(nes, nes.cpu.addr, tmp as u8)?;
+        cpu::write_modified(nes, tmp as u8)?;
     }
     Ok(())
 }
@@ -543,10 +543,10 @@ where
     cpu::set_flag(nes, CpuFlag::Z, tmp & 0x00ff == 0);
     cpu::set_flag(nes, CpuFlag::N, tmp & 0x0080 != 0);
 
-    if nes.cpu.addr_mode == addressing::imp::<S, A> as usize {
+    if nes.cpu.addr_mode == AddrMode::Imp {
         nes.cpu.ac = tmp as u8;
     } else {
-        cpu::write(nes, nes.cpu.addr, tmp as u8)?;
+        cpu::write_modified(nes, tmp as u8)?;
     }
     Ok(())
 }
@@ -566,10 +566,10 @@ where
     cpu::set_flag(nes, CpuFlag::Z, tmp & 0x00ff == 0);
     cpu::set_flag(nes, CpuFlag::N, tmp & 0x0080 != 0);
 
-    if nes.cpu.addr_mode == addressing::imp::<S, A> as usize {
+    if nes.cpu.addr_mode == AddrMode::Imp {
         nes.cpu.ac = tmp as u8;
     } else {
-        cpu::write(nes, nes.cpu.addr, tmp as u8)?;
+        cpu::write_modified(nes, tmp as u8)?;
     }
     Ok(())
 }
@@ -763,8 +763,9 @@ where
     A: NesAudio,
 {
     cpu::fetch_data(nes)?;
-    nes.cpu.data = nes.cpu.data.wrapping_sub(1);
-    cpu::write(nes, nes.cpu.addr, nes.cpu.data)?;
+    let tmp = nes.cpu.data.wrapping_sub(1);
+    cpu::write_modified(nes, tmp)?;
+    nes.cpu.data = tmp;
     if nes.cpu.ac >= nes.cpu.data {
         cpu::set_flag(nes, CpuFlag::C, true);
     }
@@ -790,7 +791,7 @@ where
 {
     cpu::fetch_data(nes)?;
     let mut tmp = nes.cpu.data.wrapping_add(1);
-    cpu::write(nes, nes.cpu.addr, tmp)?;
+    cpu::write_modified(nes, tmp)?;
     tmp ^= 0xff;
     let res = nes.cpu.ac as u16 + tmp as u16 + cpu::get_flag(nes, CpuFlag::C) as u16;
     cpu::set_flag(nes, CpuFlag::C, res > 0xff);
@@ -831,10 +832,10 @@ where
 
     cpu::set_flag(nes, CpuFlag::C, tmp & 0xff00 != 0);
 
-    if nes.cpu.addr_mode == addressing::imp::<S, A> as usize {
+    if nes.cpu.addr_mode == AddrMode::Imp {
         nes.cpu.ac = tmp as u8;
     } else {
-        cpu::write(nes, nes.cpu.addr, tmp as u8)?;
+        cpu::write_modified(nes, tmp as u8)?;
     }
 
     nes.cpu.ac &= tmp as u8;
@@ -852,7 +853,7 @@ where
     cpu::fetch_data(nes)?;
     let carry = nes.cpu.data & 0x01;
     let result = (nes.cpu.data >> 1) | (cpu::get_flag(nes, CpuFlag::C) as u8) << 7;
-    cpu::write(nes, nes.cpu.addr, result)?;
+    cpu::write_modified(nes, result)?;
     let add_res = nes.cpu.ac as u16 + result as u16 + carry as u16;
     cpu::set_flag(nes, CpuFlag::C, add_res > 0xff);
     cpu::set_flag(
@@ -886,10 +887,10 @@ where
 
     cpu::set_flag(nes, CpuFlag::C, tmp & 0xff00 != 0);
 
-    if nes.cpu.addr_mode == addressing::imp::<S, A> as usize {
+    if nes.cpu.addr_mode == AddrMode::Imp {
         nes.cpu.ac = tmp as u8;
     } else {
-        cpu::write(nes, nes.cpu.addr, tmp as u8)?;
+        cpu::write_modified(nes, tmp as u8)?;
     }
 
     nes.cpu.ac |= tmp as u8;
@@ -909,10 +910,10 @@ where
 
     let tmp = (nes.cpu.data as u16) >> 1;
 
-    if nes.cpu.addr_mode == addressing::imp::<S, A> as usize {
+    if nes.cpu.addr_mode == AddrMode::Imp {
         nes.cpu.ac = tmp as u8;
     } else {
-        cpu::write(nes, nes.cpu.addr, tmp as u8)?;
+        cpu::write_modified(nes, tmp as u8)?;
     }
 
     nes.cpu.ac ^= tmp as u8;
@@ -3,9 +3,13 @@ use std::fmt::Write as _;
 
 use anyhow::Result;
 
+use self::addressing::AddrMode;
 use self::decode::DecodedOpcode;
+use crate::buscpu::peek;
 use crate::buscpu::read;
 use crate::buscpu::write;
+use crate::cartridge;
+use crate::cdl;
 use crate::nesaudio::NesAudio;
 use crate::nesscreen::NesScreen;
 use crate::Nes;
@@ -23,9 +27,11 @@ pub struct Cpu {
     // helper variables
     pub cycles: u8,
     pub addr: u16,
-    pub addr_mode: usize,
+    pub addr_mode: AddrMode,
     pub data: u8,
     pub is_imp: bool,
+    // set while a read-modify-write instruction writes its result right after the old value
+    pub second_write: bool,
 }
 
 pub enum CpuFlag {
@@ -64,8 +70,13 @@ where
         return Ok(());
     }
 
+    // the cartridge IRQ line is level triggered and only sampled between instructions
+    if cartridge::irq(nes)? && !get_flag(nes, CpuFlag::I) {
+        return irq(nes);
+    }
+
     // fetch
-    let opcode = read(nes, nes.cpu.pc)?;
+    let opcode = fetch_code(nes, nes.cpu.pc)?;
     nes.cpu.pc = nes.cpu.pc.wrapping_add(1);
     // decode
     let DecodedOpcode {
@@ -77,10 +88,14 @@ where
     } = decode::decode(opcode)?;
     nes.cpu.cycles = cycles;
     // execute
-    nes.cpu.addr_mode = addr_mode as usize;
-    (addr_mode)(nes)?;
+    nes.cpu.addr_mode = addr_mode;
+    addressing::run(nes, addr_mode)?;
     (instruction)(nes)?;
 
+    if nes.cdl.enabled && nes.cpu.addr_mode == AddrMode::Ind {
+        cdl::log_prg(nes, nes.cpu.pc, cdl::INDIRECT_CODE);
+    }
+
     Ok(())
 }
 
@@ -177,12 +192,21 @@ where
     Ok(hi << 8 | lo)
 }
 
+// Read instruction bytes, which the code/data logger flags as code
+pub fn fetch_code<S, A>(nes: &mut Nes<S, A>, addr: u16) -> Result<u8>
+where
+    S: NesScreen,
+    A: NesAudio,
+{
+    cdl::with_prg_access(nes, cdl::CODE, |nes| read(nes, addr))
+}
+
 pub fn pc_fetch_byte<S, A>(nes: &mut Nes<S, A>) -> Result<u8>
 where
     S: NesScreen,
     A: NesAudio,
 {
-    let data = read(nes, nes.cpu.pc)?;
+    let data = fetch_code(nes, nes.cpu.pc)?;
     nes.cpu.pc = nes.cpu.pc.wrapping_add(1);
     Ok(data)
 }
@@ -192,7 +216,9 @@ where
     S: NesScreen,
     A: NesAudio,
 {
-    let data = fetch_word(nes, nes.cpu.pc)?;
+    let lo = fetch_code(nes, nes.cpu.pc)? as u16;
+    let hi = fetch_code(nes, nes.cpu.pc.wrapping_add(1))? as u16;
+    let data = hi << 8 | lo;
     nes.cpu.pc = nes.cpu.pc.wrapping_add(2);
     Ok(data)
 }
@@ -203,32 +229,59 @@ where
     A: NesAudio,
 {
     if !nes.cpu.is_imp {
-        nes.cpu.data = read(nes, nes.cpu.addr)?;
+        let access = match nes.cpu.addr_mode {
+            AddrMode::Imm => cdl::CODE,
+            AddrMode::Idx | AddrMode::Idy => cdl::DATA | cdl::INDIRECT_DATA,
+            _ => cdl::DATA,
+        };
+        nes.cpu.data = cdl::with_prg_access(nes, access, |nes| read(nes, nes.cpu.addr))?;
     }
     Ok(())
 }
 
+// Read-modify-write instructions store the unmodified operand back before the result
+pub fn write_modified<S, A>(nes: &mut Nes<S, A>, data: u8) -> Result<()>
+where
+    S: NesScreen,
+    A: NesAudio,
+{
+    write(nes, nes.cpu.addr, nes.cpu.data)?;
+    nes.cpu.second_write = true;
+    let result = write(nes, nes.cpu.addr, data);
+    nes.cpu.second_write = false;
+    result
+}
+
 // For debugging
 pub fn step<S, A>(nes: &mut Nes<S, A>) -> Result<String>
 where
     S: NesScreen,
     A: NesAudio,
 {
-    let inst_pc = nes.cpu.pc;
-    let decoded = decode::decode::<S, A>(read(nes, inst_pc)?)?;
-
-    let (a, x, y, p, sp) = (nes.cpu.ac, nes.cpu.x, nes.cpu.y, nes.cpu.status, nes.cpu.sp);
-
+    let inst = trace(nes)?;
     clock(nes)?;
     while nes.cpu.cycles > 0 {
         clock(nes)?;
     }
+    Ok(inst)
+}
+
+// The next instruction and the registers before it runs, in the nestest log format
+pub fn trace<S, A>(nes: &Nes<S, A>) -> Result<String>
+where
+    S: NesScreen,
+    A: NesAudio,
+{
+    let inst_pc = nes.cpu.pc;
+    let decoded = decode::decode::<S, A>(peek(nes, inst_pc)?)?;
+
+    let (a, x, y, p, sp) = (nes.cpu.ac, nes.cpu.x, nes.cpu.y, nes.cpu.status, nes.cpu.sp);
 
     // Format instruction bytes
     let mut inst_bytes = String::from("");
     let mut bytes = [0u8; 3];
     for i in 0..min(decoded.bytes, 3) as usize {
-        bytes[i] = read(nes, inst_pc.wrapping_add(i as u16))?;
+        bytes[i] = peek(nes, inst_pc.wrapping_add(i as u16))?;
         let _ = write!(&mut inst_bytes, " {:02X}", bytes[i]);
     }
     while inst_bytes.len() < 8 {
@@ -0,0 +1,196 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+const FDS_TAG: &[u8; 4] = b"FDS\x1a";
+const DISK_INFO: &[u8; 15] = b"\x01*NINTENDO-HVC*";
+pub const SIDE_SIZE: usize = 65500;
+// gap before the first block and after every other block, in bytes
+const LEAD_IN_GAP: usize = 28300 / 8;
+const BLOCK_GAP: usize = 976 / 8;
+// raw sides get some slack so games can append files past the dumped blocks
+const RAW_SIDE_SIZE: usize = 0x14000;
+// CPU cycles a swapped disk stays out of the drive, about one second
+const SWAP_DELAY: u32 = 1_789_773;
+
+/*
+    Famicom Disk System media. Images store each side as the bare blocks, while the drive
+    sees a bitstream with a gap before each block, a $80 start mark and a CRC after it,
+    so sides are kept in that raw form and converted back when saving.
+*/
+pub struct Disk {
+    pub sides: Vec<Vec<u8>>,
+    // side in the drive, None when ejected
+    pub inserted: Option<usize>,
+    // set when the drive writes to any side, frontends clear it once saved
+    pub modified: bool,
+    header: Vec<u8>,
+    original: Vec<Vec<u8>>,
+    swap: Option<(usize, u32)>,
+}
+
+impl Disk {
+    pub fn from_image(bytes: &[u8]) -> Result<Self> {
+        let (header, data) = if bytes.starts_with(FDS_TAG) {
+            if bytes.len() < 16 {
+                Err(anyhow!("Invalid FDS image was provided: Truncated header"))?;
+            }
+            (bytes[..16].to_vec(), &bytes[16..])
+        } else {
+            (vec![], bytes)
+        };
+        if data.is_empty() || data.len() % SIDE_SIZE != 0 {
+            Err(anyhow!(
+                "Invalid FDS image was provided: Size is not a multiple of {} bytes",
+                SIDE_SIZE
+            ))?;
+        }
+
+        let original: Vec<Vec<u8>> = data.chunks(SIDE_SIZE).map(|side| side.to_vec()).collect();
+        if original.iter().any(|side| !side.starts_with(DISK_INFO)) {
+            Err(anyhow!(
+                "Invalid FDS image was provided: Missing disk info block"
+            ))?;
+        }
+        log::info!("Disk sides: {}", original.len());
+
+        Ok(Self {
+            sides: original.iter().map(|side| side_to_raw(side)).collect(),
+            inserted: Some(0),
+            modified: false,
+            header,
+            original,
+            swap: None,
+        })
+    }
+
+    // Image in the layout it was loaded from, with the writes made so far
+    pub fn image(&self) -> Vec<u8> {
+        let mut image = self.header.clone();
+        for (raw, original) in self.sides.iter().zip(&self.original) {
+            image.extend(raw_to_side(raw, original));
+        }
+        image
+    }
+
+    pub fn eject(&mut self) {
+        self.inserted = None;
+        self.swap = None;
+    }
+
+    pub fn insert(&mut self, side: usize) -> Result<()> {
+        self.check_side(side)?;
+        self.inserted = Some(side);
+        self.swap = None;
+        Ok(())
+    }
+
+    // Eject and insert another side a moment later, so games notice the change
+    pub fn swap(&mut self, side: usize) -> Result<()> {
+        self.check_side(side)?;
+        self.inserted = None;
+        self.swap = Some((side, SWAP_DELAY));
+        Ok(())
+    }
+
+    pub fn clock(&mut self) {
+        self.swap = match self.swap {
+            Some((side, 0)) => {
+                self.inserted = Some(side);
+                None
+            }
+            Some((side, delay)) => Some((side, delay - 1)),
+            None => None,
+        };
+    }
+
+    fn check_side(&self, side: usize) -> Result<()> {
+        if side >= self.sides.len() {
+            Err(anyhow!(
+                "Disk side {} does not exist, image has {}",
+                side,
+                self.sides.len()
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+// Length of the block starting at pos, remembering the file size the last file header gave
+fn block_length(data: &[u8], pos: usize, file_size: &mut usize) -> Option<usize> {
+    let length = match data.get(pos)? {
+        1 => 56,
+        2 => 2,
+        3 => 16,
+        4 => 1 + *file_size,
+        _ => return None,
+    };
+    if pos + length > data.len() {
+        return None;
+    }
+    if data[pos] == 3 {
+        *file_size = data[pos + 13] as usize | (data[pos + 14] as usize) << 8;
+    }
+    Some(length)
+}
+
+// CRC-16/KERMIT over the start mark and the block
+fn crc(block: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in [0x80].iter().chain(block) {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x8408
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn side_to_raw(side: &[u8]) -> Vec<u8> {
+    let mut raw = vec![0; LEAD_IN_GAP];
+    let mut pos = 0;
+    let mut file_size = 0;
+    while let Some(length) = block_length(side, pos, &mut file_size) {
+        let block = &side[pos..pos + length];
+        raw.push(0x80);
+        raw.extend(block);
+        raw.extend(crc(block).to_le_bytes());
+        raw.extend([0; BLOCK_GAP]);
+        pos += length;
+    }
+    raw.resize(raw.len().max(RAW_SIDE_SIZE), 0);
+    raw
+}
+
+// Blocks are laid back over the original side, keeping whatever followed the last block
+fn raw_to_side(raw: &[u8], original: &[u8]) -> Vec<u8> {
+    let mut side = original.to_vec();
+    let mut side_pos = 0;
+    let mut pos = 0;
+    let mut file_size = 0;
+    loop {
+        while raw.get(pos) == Some(&0) {
+            pos += 1;
+        }
+        if raw.get(pos) != Some(&0x80) {
+            break;
+        }
+        pos += 1;
+        let length = match block_length(raw, pos, &mut file_size) {
+            Some(length) => length,
+            None => break,
+        };
+        if side_pos + length > SIDE_SIZE {
+            log::warn!("Disk side is full, dropping blocks past {:#x}", side_pos);
+            break;
+        }
+        side[side_pos..side_pos + length].copy_from_slice(&raw[pos..pos + length]);
+        side_pos += length;
+        // skip the CRC
+        pos += length + 2;
+    }
+    side
+}
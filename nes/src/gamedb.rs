@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::cartridge::Mirroring;
+
+// Entries compiled into the crate, see the file for the format
+const GAMEDB: &str = include_str!("gamedb.txt");
+
+// What the database knows about a ROM, None where the header is left alone
+#[derive(Debug, Clone, Default)]
+pub struct GameInfo {
+    pub name: String,
+    pub mapper_id: Option<u16>,
+    pub submapper: Option<u8>,
+    pub mirroring: Option<Mirroring>,
+    pub prg_ram_size: Option<usize>,
+    pub chr_ram_size: Option<usize>,
+    pub battery: Option<bool>,
+}
+
+/*
+    Header corrections for ROMs dumped with a wrong iNES header, looked up by the CRC32
+    or SHA-1 of the PRG and CHR data. A SHA-1 match wins over a CRC32 one.
+*/
+pub struct GameDb {
+    by_crc32: HashMap<u32, GameInfo>,
+    by_sha1: HashMap<[u8; 20], GameInfo>,
+}
+
+impl GameDb {
+    pub fn empty() -> Self {
+        Self {
+            by_crc32: HashMap::new(),
+            by_sha1: HashMap::new(),
+        }
+    }
+
+    // Add the entries of a database file, replacing known ones, returns how many were read
+    pub fn load(&mut self, text: &str) -> Result<usize> {
+        let mut count = 0;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            parse_entry(self, line).with_context(|| format!("Game database line {}", i + 1))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    pub fn insert_crc32(&mut self, crc32: u32, info: GameInfo) {
+        self.by_crc32.insert(crc32, info);
+    }
+
+    pub fn insert_sha1(&mut self, sha1: [u8; 20], info: GameInfo) {
+        self.by_sha1.insert(sha1, info);
+    }
+
+    // Look up the PRG and CHR data of a ROM, without its header or trainer
+    pub fn find(&self, rom_data: &[u8]) -> Option<&GameInfo> {
+        if !self.by_sha1.is_empty() {
+            if let Some(info) = self.by_sha1.get(&sha1(rom_data)) {
+                return Some(info);
+            }
+        }
+        self.by_crc32.get(&crc32(rom_data))
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_crc32.len() + self.by_sha1.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for GameDb {
+    fn default() -> Self {
+        let mut db = Self::empty();
+        if let Err(err) = db.load(GAMEDB) {
+            log::error!("Cannot load the built in game database: {:?}", err);
+        }
+        db
+    }
+}
+
+// A line is made of key=value fields separated by semicolons
+fn parse_entry(db: &mut GameDb, line: &str) -> Result<()> {
+    let mut info = GameInfo::default();
+    let mut crc = None;
+    let mut hash = None;
+    for field in line
+        .split(';')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+    {
+        let (key, value) = field
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Field {:?} is not key=value", field))?;
+        let (key, value) = (key.trim(), value.trim());
+        let invalid = || anyhow!("Invalid {} value {:?}", key, value);
+        match key {
+            "crc32" => crc = Some(u32::from_str_radix(value, 16).map_err(|_| invalid())?),
+            "sha1" => hash = Some(parse_sha1(value).ok_or_else(invalid)?),
+            "name" => info.name = value.to_string(),
+            "mapper" => info.mapper_id = Some(value.parse().map_err(|_| invalid())?),
+            "submapper" => info.submapper = Some(value.parse().map_err(|_| invalid())?),
+            "mirroring" => {
+                info.mirroring = Some(match value {
+                    "horizontal" => Mirroring::Horizontal,
+                    "vertical" => Mirroring::Vertical,
+                    "four-screen" => Mirroring::FourScreen,
+                    "one-screen-a" => Mirroring::OneScreenNT0,
+                    "one-screen-b" => Mirroring::OneScreenNT1,
+                    _ => Err(invalid())?,
+                })
+            }
+            "prgram" => info.prg_ram_size = Some(parse_size(value).ok_or_else(invalid)?),
+            "chrram" => info.chr_ram_size = Some(parse_size(value).ok_or_else(invalid)?),
+            "battery" => {
+                info.battery = Some(match value {
+                    "yes" => true,
+                    "no" => false,
+                    _ => Err(invalid())?,
+                })
+            }
+            _ => Err(anyhow!("Unknown field {}", key))?,
+        }
+    }
+
+    match (crc, hash) {
+        (_, Some(hash)) => db.insert_sha1(hash, info),
+        (Some(crc), None) => db.insert_crc32(crc, info),
+        (None, None) => Err(anyhow!("Entry has neither crc32 nor sha1"))?,
+    }
+    Ok(())
+}
+
+fn parse_sha1(value: &str) -> Option<[u8; 20]> {
+    if value.len() != 40 {
+        return None;
+    }
+    let mut hash = [0; 20];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(value.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(hash)
+}
+
+// Bytes, or kilobytes with a K suffix
+fn parse_size(value: &str) -> Option<usize> {
+    match value.strip_suffix(['K', 'k']) {
+        Some(kb) => kb.parse::<usize>().ok().map(|kb| kb * 0x400),
+        None => value.parse().ok(),
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    // pad with a 1 bit, zeros, and the length in bits, to a multiple of 64 bytes
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+
+    let mut hash = [0; 20];
+    for (bytes, word) in hash.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    hash
+}
@@ -54,6 +54,14 @@ impl Joypad {
         response
     }
 
+    // Next bit that read() would return, without shifting
+    pub fn peek(&self) -> u8 {
+        if self.index > 7 {
+            return 1;
+        }
+        (self.status & (1 << self.index)) >> self.index
+    }
+
     pub fn write(&mut self, data: u8) {
         self.strobe = data & 0x01 != 0;
         if self.strobe {
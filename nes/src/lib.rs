@@ -5,8 +5,12 @@ use crate::apu::Apu;
 use crate::buscpu::BusCpu;
 use crate::busppu::BusPpu;
 use crate::cartridge::Cartridge;
+use crate::cdl::Cdl;
+use crate::cheats::Cheats;
 use crate::cpu::Cpu;
+use crate::gamedb::GameDb;
 use crate::joypad::Joypad;
+use crate::mappers::MapperRegistry;
 use crate::nesaudio::NesAudio;
 use crate::nesscreen::NesScreen;
 use crate::ppu::Ppu;
@@ -19,6 +23,12 @@ pub struct Nes<S, A> {
     pub bus_ppu: BusPpu,
     pub cartridge: Cartridge<S, A>,
     pub joypad: (Joypad, Joypad),
+    pub cdl: Cdl,
+    pub cheats: Cheats,
+    // boards load_cartridge can build, frontends may register their own
+    pub mappers: MapperRegistry<S, A>,
+    // header fixes applied by load_cartridge, frontends may add their own
+    pub gamedb: GameDb,
     pub screen: S,
     pub audio: A,
 }
@@ -37,28 +47,39 @@ where
             bus_ppu: BusPpu::default(),
             cartridge: Cartridge::default(),
             joypad: (Joypad::default(), Joypad::default()),
+            cdl: Cdl::default(),
+            cheats: Cheats::default(),
+            mappers: MapperRegistry::default(),
+            gamedb: GameDb::default(),
             screen,
             audio,
         }
     }
 
     pub fn reset(&mut self) -> Result<()> {
+        if let Some(track) = self.cartridge.nsf.as_ref().map(|nsf| nsf.start_track) {
+            return nsf::play_track(self, track);
+        }
         cpu::reset(self)?;
         cartridge::reset(self)
     }
 
     pub fn clock(&mut self) -> Result<()> {
         cpu::clock(self)?;
+        cartridge::clock(self)?;
+        apu::clock(self)?;
         for _ in 0..3 {
             ppu::clock(self)?;
         }
         Ok(())
     }
 
+    // Run a whole instruction, clocking the rest of the console for each of its cycles
     pub fn step(&mut self) -> Result<String> {
-        let inst = cpu::step(self)?;
-        for _ in 0..3 {
-            ppu::clock(self)?;
+        let inst = cpu::trace(self)?;
+        self.clock()?;
+        while self.cpu.cycles > 0 {
+            self.clock()?;
         }
         Ok(inst)
     }
@@ -67,6 +88,24 @@ where
         cartridge::load_cartridge(self, rom_bytes)
     }
 
+    // Load a ROM with IPS, UPS or BPS patches applied in order
+    pub fn load_patched(&mut self, rom_bytes: &[u8], patches: &[&[u8]]) -> Result<()> {
+        let mut rom = rom_bytes.to_vec();
+        for patch in patches {
+            rom = patch::apply(&rom, patch)?;
+        }
+        cartridge::load_cartridge(self, &rom)
+    }
+
+    pub fn load_disk(&mut self, disk_bytes: &[u8], bios: &[u8]) -> Result<()> {
+        cartridge::load_disk(self, disk_bytes, bios)
+    }
+
+    // Restart a loaded NSF tune on another track, numbered from 0
+    pub fn play_track(&mut self, track: u8) -> Result<()> {
+        nsf::play_track(self, track)
+    }
+
     pub fn press_btn(&mut self, key: Button, one: bool) -> Result<()> {
         if one {
             self.joypad.0.press(key);
@@ -87,17 +126,23 @@ where
 }
 
 pub mod apu;
+pub mod archive;
 pub mod buscpu;
 pub mod busppu;
 pub mod cartridge;
+pub mod cdl;
+pub mod cheats;
 pub mod cpu;
+pub mod disk;
+pub mod gamedb;
 pub mod joypad;
 pub mod mappers;
 pub mod nesaudio;
 pub mod nesscreen;
+pub mod nsf;
+pub mod patch;
 pub mod ppu;
+pub mod unif;
 
 #[cfg(test)]
-mod tests {
-    mod cpu;
-}
+mod tests;
@@ -0,0 +1,100 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::cartridge::Mirroring;
+use crate::mappers;
+use crate::Nes;
+
+// Mapper 7
+#[derive(Default)]
+pub struct Axrom {
+    banksel: u8,
+    // AMROM boards AND the written value with the ROM byte at the same address
+    bus_conflicts: bool,
+}
+
+impl Axrom {
+    pub fn new(bus_conflicts: bool) -> Self {
+        Self {
+            banksel: 0,
+            bus_conflicts,
+        }
+    }
+}
+
+impl<S, A> Mapper<S, A> for Axrom {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match self.map_prg(nes, addr) {
+            Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+            None => {
+                log::warn!("Cannot read at PRG address {:#x} for AXROM", addr);
+                Ok(0)
+            }
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x8000..=0xffff => {
+                let banks = (nes.cartridge.prgmem.len() / 0x8000).max(1);
+                let bank = self.banksel as usize % banks;
+                Some(bank * 0x8000 + (addr as usize & 0x7fff))
+            }
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(addr as usize & 0x1fff)
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let offset = self.map_prg(nes, 0x8000).unwrap_or(0);
+        vec![Bank::new(0x8000, 0x8000, Memory::PrgRom, offset)]
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        vec![Bank::new(0x0000, 0x2000, mappers::chr_memory(nes), 0)]
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x8000..=0xffff => {
+                let data = mappers::bus_conflict(self, nes, addr, data, self.bus_conflicts);
+                self.banksel = data & 0x0f;
+                nes.cartridge.mirroring = if data & 0x10 == 0 {
+                    Mirroring::OneScreenNT0
+                } else {
+                    Mirroring::OneScreenNT1
+                };
+            }
+            _ => log::warn!("Cannot write at PRG address {:#x} for AXROM", addr),
+        }
+        Ok(())
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(nes.cartridge.chrmem[addr as usize & 0x1fff])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        nes.cartridge.chrmem[addr as usize & 0x1fff] = data;
+        Ok(())
+    }
+
+    fn reset(&mut self, nes: &mut Nes<S, A>) -> Result<()> {
+        self.banksel = 0;
+        nes.cartridge.mirroring = Mirroring::OneScreenNT0;
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "AxROM"
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::mappers;
+use crate::Nes;
+
+// Mapper 34, BNROM board
+pub struct Bnrom {
+    banksel: u8,
+    bus_conflicts: bool,
+}
+
+impl Bnrom {
+    pub fn new(bus_conflicts: bool) -> Self {
+        Self {
+            banksel: 0,
+            bus_conflicts,
+        }
+    }
+}
+
+impl<S, A> Mapper<S, A> for Bnrom {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match self.map_prg(nes, addr) {
+            Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+            None => {
+                log::warn!("Cannot read at PRG address {:#x} for BNROM", addr);
+                Ok(0)
+            }
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x8000..=0xffff => {
+                let banks = (nes.cartridge.prgmem.len() / 0x8000).max(1);
+                Some(self.banksel as usize % banks * 0x8000 + (addr as usize & 0x7fff))
+            }
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(addr as usize & 0x1fff)
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let offset = self.map_prg(nes, 0x8000).unwrap_or(0);
+        vec![Bank::new(0x8000, 0x8000, Memory::PrgRom, offset)]
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        vec![Bank::new(0x0000, 0x2000, mappers::chr_memory(nes), 0)]
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x8000..=0xffff => {
+                let data = mappers::bus_conflict(self, nes, addr, data, self.bus_conflicts);
+                self.banksel = data;
+            }
+            _ => log::warn!("Cannot write at PRG address {:#x} for BNROM", addr),
+        }
+        Ok(())
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(nes.cartridge.chrmem[addr as usize & 0x1fff])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            nes.cartridge.chrmem[addr as usize & 0x1fff] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for BNROM", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.banksel = 0;
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "BNROM"
+    }
+}
+
+// Mapper 34, NINA-001 board
+pub struct Nina001 {
+    prg_banksel: u8,
+    chr_banksel: [u8; 2],
+    wram: [u8; 0x2000],
+}
+
+impl Nina001 {
+    pub fn new() -> Self {
+        Self {
+            prg_banksel: 0,
+            chr_banksel: [0; 2],
+            wram: [0; 0x2000],
+        }
+    }
+
+    fn chr_offset<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> usize {
+        let banks = (nes.cartridge.chrmem.len() / 0x1000).max(1);
+        let bank = self.chr_banksel[(addr as usize >> 12) & 1] as usize;
+        bank % banks * 0x1000 + (addr as usize & 0x0fff)
+    }
+}
+
+impl Default for Nina001 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A> Mapper<S, A> for Nina001 {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x6000..=0x7fff => Ok(self.wram[(addr & 0x1fff) as usize]),
+            _ => match self.map_prg(nes, addr) {
+                Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+                None => {
+                    log::warn!("Cannot read at PRG address {:#x} for NINA-001", addr);
+                    Ok(0)
+                }
+            },
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x8000..=0xffff => {
+                let banks = (nes.cartridge.prgmem.len() / 0x8000).max(1);
+                Some(self.prg_banksel as usize % banks * 0x8000 + (addr as usize & 0x7fff))
+            }
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(self.chr_offset(nes, addr))
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let offset = self.map_prg(nes, 0x8000).unwrap_or(0);
+        vec![
+            Bank::new(0x6000, 0x2000, Memory::PrgRam, 0),
+            Bank::new(0x8000, 0x8000, Memory::PrgRom, offset),
+        ]
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        [0x0000, 0x1000]
+            .into_iter()
+            .map(|addr| {
+                Bank::new(
+                    addr,
+                    0x1000,
+                    mappers::chr_memory(nes),
+                    self.chr_offset(nes, addr),
+                )
+            })
+            .collect()
+    }
+
+    fn write_prg(&mut self, _nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x6000..=0x7fff => {
+                // the registers sit on top of the RAM, which gets the write too
+                self.wram[(addr & 0x1fff) as usize] = data;
+                match addr {
+                    0x7ffd => self.prg_banksel = data & 0x01,
+                    0x7ffe => self.chr_banksel[0] = data & 0x0f,
+                    0x7fff => self.chr_banksel[1] = data & 0x0f,
+                    _ => {}
+                }
+            }
+            _ => log::warn!("Cannot write at PRG address {:#x} for NINA-001", addr),
+        }
+        Ok(())
+    }
+
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        self.wram[(addr & 0x1fff) as usize] = data;
+        true
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(nes.cartridge.chrmem[self.chr_offset(nes, addr)])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            let mapped_addr = self.chr_offset(nes, addr);
+            nes.cartridge.chrmem[mapped_addr] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for NINA-001", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.prg_banksel = 0;
+        self.chr_banksel = [0; 2];
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "NINA-001"
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::cartridge::Mirroring;
+use crate::mappers;
+use crate::Nes;
+
+// Mapper 71
+pub struct Camerica {
+    banksel: u8,
+    // Fire Hawk's board (submapper 1) has a one-screen mirroring register at $8000-$9FFF
+    fire_hawk: bool,
+}
+
+impl Camerica {
+    pub fn new(fire_hawk: bool) -> Self {
+        Self {
+            banksel: 0,
+            fire_hawk,
+        }
+    }
+}
+
+impl<S, A> Mapper<S, A> for Camerica {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match self.map_prg(nes, addr) {
+            Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+            None => {
+                log::warn!("Cannot read at PRG address {:#x} for Camerica", addr);
+                Ok(0)
+            }
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let banks = (nes.cartridge.prgmem.len() / 0x4000).max(1);
+        let bank = match addr {
+            0x8000..=0xbfff => self.banksel as usize % banks,
+            0xc000..=0xffff => banks - 1,
+            _ => return None,
+        };
+        Some(bank * 0x4000 + (addr as usize & 0x3fff))
+    }
+
+    fn map_chr(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(addr as usize & 0x1fff)
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        [0x8000, 0xc000]
+            .into_iter()
+            .filter_map(|addr| {
+                let offset = self.map_prg(nes, addr)?;
+                Some(Bank::new(addr, 0x4000, Memory::PrgRom, offset))
+            })
+            .collect()
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        vec![Bank::new(0x0000, 0x2000, mappers::chr_memory(nes), 0)]
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            // without a submapper only Fire Hawk writes to $9000-$9FFF
+            0x8000..=0x9fff if self.fire_hawk || addr >= 0x9000 => {
+                nes.cartridge.mirroring = if data & 0x10 == 0 {
+                    Mirroring::OneScreenNT0
+                } else {
+                    Mirroring::OneScreenNT1
+                };
+            }
+            0x8000..=0xbfff => {}
+            0xc000..=0xffff => self.banksel = data,
+            _ => log::warn!("Cannot write at PRG address {:#x} for Camerica", addr),
+        }
+        Ok(())
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(nes.cartridge.chrmem[addr as usize & 0x1fff])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        nes.cartridge.chrmem[addr as usize & 0x1fff] = data;
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.banksel = 0;
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Camerica"
+    }
+}
@@ -1,32 +1,75 @@
 use anyhow::anyhow;
 use anyhow::Result;
 
+use super::Bank;
 use super::Mapper;
+use super::Memory;
+use crate::mappers;
 use crate::Nes;
 
 // Mapper 3
 #[derive(Default)]
 pub struct Cnrom {
     banksel: u8,
+    bus_conflicts: bool,
+}
+
+impl Cnrom {
+    pub fn new(bus_conflicts: bool) -> Self {
+        Self {
+            banksel: 0,
+            bus_conflicts,
+        }
+    }
 }
 
 impl<S, A> Mapper<S, A> for Cnrom {
     fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
-        let mapped_addr = if 0x8000 <= addr {
-            match nes.cartridge.prg_banks {
-                1 => addr & 0x3fff,
-                2 => addr & 0x7fff,
-                _ => 0,
-            }
-        } else {
-            0
-        };
-        Ok(nes.cartridge.prgmem[mapped_addr as usize])
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        let mapped_addr = self.map_prg(nes, addr).unwrap_or(0);
+        Ok(nes.cartridge.prgmem[mapped_addr])
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        if addr < 0x8000 {
+            return None;
+        }
+        match nes.cartridge.prg_banks {
+            1 => Some(addr as usize & 0x3fff),
+            2 => Some(addr as usize & 0x7fff),
+            _ => None,
+        }
     }
 
-    fn write_prg(&mut self, _nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+    fn map_chr(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x0000..=0x1fff => Some(self.banksel as usize * 0x2000 + addr as usize),
+            _ => None,
+        }
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        match nes.cartridge.prg_banks {
+            1 => vec![
+                Bank::new(0x8000, 0x4000, Memory::PrgRom, 0),
+                Bank::new(0xc000, 0x4000, Memory::PrgRom, 0),
+            ],
+            _ => vec![Bank::new(0x8000, 0x8000, Memory::PrgRom, 0)],
+        }
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let offset = self.banksel as usize * 0x2000;
+        vec![Bank::new(0x0000, 0x2000, mappers::chr_memory(nes), offset)]
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
         match addr {
             0x8000..=0xffff => {
+                let data = mappers::bus_conflict(self, nes, addr, data, self.bus_conflicts);
                 self.banksel = (data & 0x03) as u8;
             }
             _ => log::warn!("Cannot write at PRG address {:#x} for CNROM", addr),
@@ -35,12 +78,9 @@ impl<S, A> Mapper<S, A> for Cnrom {
     }
 
     fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
-        match addr {
-            0x0000..=0x1fff => {
-                let mapped_addr = (self.banksel as u16) * 0x2000 + addr;
-                Ok(nes.cartridge.chrmem[mapped_addr as usize])
-            }
-            _ => Err(anyhow!("Cannot read at CHR address {:#x} for CNROM", addr)),
+        match self.map_chr(nes, addr) {
+            Some(mapped_addr) => Ok(nes.cartridge.chrmem[mapped_addr]),
+            None => Err(anyhow!("Cannot read at CHR address {:#x} for CNROM", addr)),
         }
     }
 
@@ -0,0 +1,101 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::mappers;
+use crate::Nes;
+
+// Mapper 11
+pub struct ColorDreams {
+    prg_banksel: u8,
+    chr_banksel: u8,
+    bus_conflicts: bool,
+}
+
+impl ColorDreams {
+    pub fn new(bus_conflicts: bool) -> Self {
+        Self {
+            prg_banksel: 0,
+            chr_banksel: 0,
+            bus_conflicts,
+        }
+    }
+}
+
+impl<S, A> Mapper<S, A> for ColorDreams {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match self.map_prg(nes, addr) {
+            Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+            None => {
+                log::warn!("Cannot read at PRG address {:#x} for Color Dreams", addr);
+                Ok(0)
+            }
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x8000..=0xffff => {
+                let banks = (nes.cartridge.prgmem.len() / 0x8000).max(1);
+                Some(self.prg_banksel as usize % banks * 0x8000 + (addr as usize & 0x7fff))
+            }
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let banks = (nes.cartridge.chrmem.len() / 0x2000).max(1);
+        Some(self.chr_banksel as usize % banks * 0x2000 + (addr as usize & 0x1fff))
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let offset = self.map_prg(nes, 0x8000).unwrap_or(0);
+        vec![Bank::new(0x8000, 0x8000, Memory::PrgRom, offset)]
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let offset = self.map_chr(nes, 0).unwrap_or(0);
+        vec![Bank::new(0x0000, 0x2000, mappers::chr_memory(nes), offset)]
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x8000..=0xffff => {
+                let data = mappers::bus_conflict(self, nes, addr, data, self.bus_conflicts);
+                self.prg_banksel = data & 0x03;
+                self.chr_banksel = data >> 4;
+            }
+            _ => log::warn!("Cannot write at PRG address {:#x} for Color Dreams", addr),
+        }
+        Ok(())
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        let mapped_addr = self.map_chr(nes, addr).unwrap_or(0);
+        Ok(nes.cartridge.chrmem[mapped_addr])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            nes.cartridge.chrmem[addr as usize & 0x1fff] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for Color Dreams", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.prg_banksel = 0;
+        self.chr_banksel = 0;
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Color Dreams"
+    }
+}
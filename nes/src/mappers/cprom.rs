@@ -0,0 +1,105 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::mappers;
+use crate::Nes;
+
+const CHR_RAM_SIZE: usize = 0x4000;
+
+// Mapper 13
+pub struct Cprom {
+    chr_banksel: u8,
+    bus_conflicts: bool,
+}
+
+impl Cprom {
+    pub fn new(bus_conflicts: bool) -> Self {
+        Self {
+            chr_banksel: 0,
+            bus_conflicts,
+        }
+    }
+}
+
+impl<S, A> Mapper<S, A> for Cprom {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match self.map_prg(nes, addr) {
+            Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+            None => {
+                log::warn!("Cannot read at PRG address {:#x} for CPROM", addr);
+                Ok(0)
+            }
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x8000..=0xffff => Some((addr as usize & 0x7fff) % nes.cartridge.prgmem.len()),
+            _ => None,
+        }
+    }
+
+    // First 4 KB page of CHR RAM fixed at $0000, any of the four pages at $1000
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let page = match addr {
+            0x0000..=0x0fff => 0,
+            _ => self.chr_banksel as usize,
+        };
+        Some((page * 0x1000 + (addr as usize & 0x0fff)) % nes.cartridge.chrmem.len())
+    }
+
+    fn prg_layout(&self, _nes: &Nes<S, A>) -> Vec<Bank> {
+        vec![Bank::new(0x8000, 0x8000, Memory::PrgRom, 0)]
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        [0x0000, 0x1000]
+            .into_iter()
+            .filter_map(|addr| {
+                let offset = self.map_chr(nes, addr)?;
+                Some(Bank::new(addr, 0x1000, mappers::chr_memory(nes), offset))
+            })
+            .collect()
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x8000..=0xffff => {
+                let data = mappers::bus_conflict(self, nes, addr, data, self.bus_conflicts);
+                self.chr_banksel = data & 0x03;
+            }
+            _ => log::warn!("Cannot write at PRG address {:#x} for CPROM", addr),
+        }
+        Ok(())
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        let mapped_addr = self.map_chr(nes, addr).unwrap_or(0);
+        Ok(nes.cartridge.chrmem[mapped_addr])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        let mapped_addr = self.map_chr(nes, addr).unwrap_or(0);
+        nes.cartridge.chrmem[mapped_addr] = data;
+        Ok(())
+    }
+
+    fn reset(&mut self, nes: &mut Nes<S, A>) -> Result<()> {
+        // the header only gives 8 KB of CHR RAM unless it is NES 2.0
+        if nes.cartridge.chrmem.len() < CHR_RAM_SIZE {
+            nes.cartridge.chrmem.resize(CHR_RAM_SIZE, 0);
+        }
+        self.chr_banksel = 0;
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "CPROM"
+    }
+}
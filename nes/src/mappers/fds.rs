@@ -0,0 +1,603 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::cartridge::Mirroring;
+use crate::mappers;
+use crate::Nes;
+
+// CPU cycles for the motor to spin up and the head to reach the start of the disk
+const SPIN_UP_DELAY: u32 = 50000;
+// CPU cycles per byte at the drive's 96.4 kHz bit rate
+const BYTE_DELAY: u32 = 150;
+// modulation table entries 0-7: +0, +1, +2, +4, reset, -4, -2, -1
+const MOD_STEPS: [i8; 8] = [0, 1, 2, 4, 0, -4, -2, -1];
+// $4089 master volume 2/2, 2/3, 2/4 and 2/5
+const MASTER_VOLUMES: [f32; 4] = [1., 2. / 3., 2. / 4., 2. / 5.];
+
+// 7-bit two's complement, as kept by the modulation counter
+fn sign_extend7(value: i8) -> i8 {
+    ((value as u8) << 1) as i8 >> 1
+}
+
+struct Envelope {
+    gain: u8,
+    speed: u8,
+    increase: bool,
+    disabled: bool,
+    timer: u32,
+}
+
+impl Envelope {
+    fn new() -> Self {
+        Self {
+            gain: 0,
+            speed: 0,
+            increase: false,
+            disabled: true,
+            timer: 0,
+        }
+    }
+
+    fn write(&mut self, data: u8) {
+        self.disabled = data & 0x80 != 0;
+        self.increase = data & 0x40 != 0;
+        self.speed = data & 0x3f;
+        if self.disabled {
+            self.gain = data & 0x3f;
+        }
+        self.timer = 0;
+    }
+
+    fn clock(&mut self, master_speed: u8) {
+        if self.disabled {
+            return;
+        }
+        self.timer += 1;
+        if self.timer < 8 * (master_speed as u32 + 1) * (self.speed as u32 + 1) {
+            return;
+        }
+        self.timer = 0;
+        if self.increase && self.gain < 32 {
+            self.gain += 1;
+        } else if !self.increase && self.gain > 0 {
+            self.gain -= 1;
+        }
+    }
+}
+
+/*
+    FDS sound, one channel playing a 64 step wavetable through a volume envelope. A
+    modulation unit walks its own 64 step table to move a counter, which scaled by the
+    modulation envelope bends the pitch of the wave.
+*/
+pub(crate) struct FdsAudio {
+    wave: [u8; 64],
+    wave_write: bool,
+    master_volume: u8,
+    wave_freq: u16,
+    wave_halt: bool,
+    env_halt: bool,
+    env_speed: u8,
+    // 6 bits of wave position over 16 bits of fraction
+    wave_accum: u32,
+    volume: Envelope,
+    modulation: Envelope,
+    mod_table: [u8; 64],
+    mod_pos: u8,
+    mod_freq: u16,
+    mod_halt: bool,
+    mod_accum: u32,
+    mod_counter: i8,
+}
+
+impl FdsAudio {
+    pub(crate) fn new() -> Self {
+        Self {
+            wave: [0; 64],
+            wave_write: false,
+            master_volume: 0,
+            wave_freq: 0,
+            wave_halt: true,
+            env_halt: false,
+            env_speed: 0xe8,
+            wave_accum: 0,
+            volume: Envelope::new(),
+            modulation: Envelope::new(),
+            mod_table: [0; 64],
+            mod_pos: 0,
+            mod_freq: 0,
+            mod_halt: true,
+            mod_accum: 0,
+            mod_counter: 0,
+        }
+    }
+
+    pub(crate) fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0x4040..=0x407f => self.wave[(addr & 0x3f) as usize],
+            0x4090 => self.volume.gain,
+            0x4092 => self.modulation.gain,
+            _ => 0,
+        }
+    }
+
+    pub(crate) fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4040..=0x407f if self.wave_write => self.wave[(addr & 0x3f) as usize] = data & 0x3f,
+            0x4080 => self.volume.write(data),
+            0x4082 => self.wave_freq = (self.wave_freq & 0x0f00) | data as u16,
+            0x4083 => {
+                self.wave_freq = (self.wave_freq & 0x00ff) | (data as u16 & 0x0f) << 8;
+                self.wave_halt = data & 0x80 != 0;
+                self.env_halt = data & 0x40 != 0;
+                if self.wave_halt {
+                    self.wave_accum = 0;
+                }
+            }
+            0x4084 => self.modulation.write(data),
+            0x4085 => self.mod_counter = sign_extend7(data as i8),
+            0x4086 => self.mod_freq = (self.mod_freq & 0x0f00) | data as u16,
+            0x4087 => {
+                self.mod_freq = (self.mod_freq & 0x00ff) | (data as u16 & 0x0f) << 8;
+                self.mod_halt = data & 0x80 != 0;
+                if self.mod_halt {
+                    self.mod_accum = 0;
+                }
+            }
+            // each entry fills two steps of the table, only while halted
+            0x4088 if self.mod_halt => {
+                for _ in 0..2 {
+                    self.mod_table[self.mod_pos as usize] = data & 0x07;
+                    self.mod_pos = (self.mod_pos + 1) & 0x3f;
+                }
+            }
+            0x4089 => {
+                self.wave_write = data & 0x80 != 0;
+                self.master_volume = data & 0x03;
+            }
+            0x408a => self.env_speed = data,
+            _ => {}
+        }
+    }
+
+    // Wave frequency bent by the modulation counter, following the 2C33's rounding
+    fn pitch(&self) -> u32 {
+        let freq = self.wave_freq as i32;
+        if self.mod_halt || self.mod_freq == 0 {
+            return freq as u32;
+        }
+        let mut temp = self.mod_counter as i32 * self.modulation.gain as i32;
+        let remainder = temp & 0x0f;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0 {
+            temp += if self.mod_counter < 0 { -1 } else { 2 };
+        }
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+        temp *= freq;
+        let remainder = temp & 0x3f;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+        (freq + temp).max(0) as u32
+    }
+
+    pub(crate) fn clock(&mut self) {
+        if !self.env_halt && !self.wave_halt && self.env_speed != 0 {
+            self.volume.clock(self.env_speed);
+            self.modulation.clock(self.env_speed);
+        }
+
+        if !self.mod_halt {
+            self.mod_accum += self.mod_freq as u32;
+            if self.mod_accum >= 0x10000 {
+                self.mod_accum -= 0x10000;
+                let step = self.mod_table[self.mod_pos as usize];
+                self.mod_pos = (self.mod_pos + 1) & 0x3f;
+                self.mod_counter = match step {
+                    4 => 0,
+                    _ => sign_extend7(self.mod_counter + MOD_STEPS[step as usize]),
+                };
+            }
+        }
+
+        if !self.wave_halt && !self.wave_write {
+            self.wave_accum = (self.wave_accum + self.pitch()) & 0x3f_ffff;
+        }
+    }
+
+    pub(crate) fn output(&self) -> f32 {
+        let level = self.wave[(self.wave_accum >> 16) as usize] as f32;
+        let gain = self.volume.gain.min(32) as f32;
+        level * gain * MASTER_VOLUMES[self.master_volume as usize] / (63. * 32.)
+    }
+}
+
+// Famicom Disk System RAM adapter, the BIOS is the PRG ROM and the disk sits in the cartridge
+pub struct Fds {
+    wram: Vec<u8>,
+    disk_io_enabled: bool,
+    sound_io_enabled: bool,
+    irq_reload: u16,
+    irq_counter: u16,
+    irq_repeat: bool,
+    irq_enabled: bool,
+    timer_irq: bool,
+    // $4025 drive control
+    motor_on: bool,
+    transfer_reset: bool,
+    read_mode: bool,
+    crc_control: bool,
+    disk_ready: bool,
+    disk_irq_enabled: bool,
+    transfer_irq: bool,
+    transfer_complete: bool,
+    read_data: u8,
+    write_data: u8,
+    // drive head position and timing
+    end_of_head: bool,
+    scanning: bool,
+    gap_ended: bool,
+    previous_crc_control: bool,
+    crc: u16,
+    // side the head position belongs to
+    head_side: Option<usize>,
+    position: usize,
+    delay: u32,
+    ext_port: u8,
+    audio: FdsAudio,
+}
+
+impl Fds {
+    pub fn new() -> Self {
+        Self {
+            wram: vec![0; 0x8000],
+            disk_io_enabled: false,
+            sound_io_enabled: false,
+            irq_reload: 0,
+            irq_counter: 0,
+            irq_repeat: false,
+            irq_enabled: false,
+            timer_irq: false,
+            motor_on: false,
+            transfer_reset: false,
+            read_mode: false,
+            crc_control: false,
+            disk_ready: false,
+            disk_irq_enabled: false,
+            transfer_irq: false,
+            transfer_complete: false,
+            read_data: 0,
+            write_data: 0,
+            end_of_head: true,
+            scanning: false,
+            gap_ended: false,
+            previous_crc_control: false,
+            crc: 0,
+            head_side: None,
+            position: 0,
+            delay: 0,
+            ext_port: 0,
+            audio: FdsAudio::new(),
+        }
+    }
+
+    fn peek_register<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> u8 {
+        match addr {
+            0x4030 => {
+                let mut data = 0;
+                if self.timer_irq {
+                    data |= 0x01;
+                }
+                if self.transfer_complete {
+                    data |= 0x02;
+                }
+                if self.end_of_head {
+                    data |= 0x40;
+                }
+                data
+            }
+            0x4031 => self.read_data,
+            0x4032 => {
+                let inserted = nes
+                    .cartridge
+                    .disk
+                    .as_ref()
+                    .is_some_and(|disk| disk.inserted.is_some());
+                let mut data = 0x40;
+                // no disk also reads as write protected
+                if !inserted {
+                    data |= 0x05;
+                }
+                if !inserted || !self.scanning {
+                    data |= 0x02;
+                }
+                data
+            }
+            // battery good, and nothing pulling the expansion port low
+            _ => 0x80 | (self.ext_port & 0x7f),
+        }
+    }
+
+    fn update_crc(&mut self, data: u8) {
+        for bit in 0..8 {
+            let carry = self.crc & 1 != 0;
+            self.crc >>= 1;
+            if carry {
+                self.crc ^= 0x8408;
+            }
+            if data & (1 << bit) != 0 {
+                self.crc ^= 0x8000;
+            }
+        }
+    }
+
+    // Move the head one step, reading or writing a byte once it is over the disk
+    fn clock_drive<S, A>(&mut self, nes: &mut Nes<S, A>) {
+        let disk = match nes.cartridge.disk.as_mut() {
+            Some(disk) => disk,
+            None => return,
+        };
+        disk.clock();
+        let side = match disk.inserted {
+            Some(side) if self.motor_on => side,
+            _ => {
+                self.end_of_head = true;
+                self.scanning = false;
+                return;
+            }
+        };
+        // a side inserted without ejecting first is read from its start
+        if self.head_side != Some(side) {
+            self.head_side = Some(side);
+            self.end_of_head = true;
+        }
+        if self.transfer_reset && !self.scanning {
+            return;
+        }
+        if self.end_of_head {
+            self.delay = SPIN_UP_DELAY;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        let raw = &mut disk.sides[side];
+        if self.position >= raw.len() {
+            self.motor_on = false;
+            return;
+        }
+        self.scanning = true;
+        if self.read_mode {
+            let data = raw[self.position];
+            if !self.previous_crc_control {
+                self.update_crc(data);
+            }
+            let mut irq = self.disk_irq_enabled;
+            if !self.disk_ready {
+                self.gap_ended = false;
+                self.crc = 0;
+            } else if data != 0 && !self.gap_ended {
+                // the start mark ends the gap without an interrupt
+                self.gap_ended = true;
+                irq = false;
+            }
+            if self.gap_ended {
+                self.transfer_complete = true;
+                self.read_data = data;
+                self.transfer_irq |= irq;
+            }
+        } else {
+            let mut data = 0;
+            if !self.crc_control {
+                self.transfer_complete = true;
+                self.transfer_irq |= self.disk_irq_enabled;
+                data = self.write_data;
+            }
+            if !self.disk_ready {
+                data = 0;
+                self.crc = 0;
+            }
+            if !self.crc_control {
+                self.update_crc(data);
+            } else {
+                if !self.previous_crc_control {
+                    self.update_crc(0);
+                    self.update_crc(0);
+                }
+                data = self.crc as u8;
+                self.crc >>= 8;
+            }
+            raw[self.position] = data;
+            disk.modified = true;
+            self.gap_ended = false;
+        }
+        self.previous_crc_control = self.crc_control;
+
+        self.position += 1;
+        if self.position >= raw.len() {
+            self.motor_on = false;
+        } else {
+            self.delay = BYTE_DELAY;
+        }
+    }
+}
+
+impl Default for Fds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A> Mapper<S, A> for Fds {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        let data = self.peek_prg(nes, addr)?;
+        match addr {
+            0x4030 if self.disk_io_enabled => {
+                self.transfer_complete = false;
+                self.timer_irq = false;
+                self.transfer_irq = false;
+            }
+            0x4031 if self.disk_io_enabled => {
+                self.transfer_complete = false;
+                self.transfer_irq = false;
+            }
+            _ => {}
+        }
+        Ok(data)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x4030..=0x4033 if self.disk_io_enabled => Ok(self.peek_register(nes, addr)),
+            0x4040..=0x4097 if self.sound_io_enabled => Ok(self.audio.read(addr)),
+            0x6000..=0xdfff => Ok(self.wram[addr as usize - 0x6000]),
+            _ => match self.map_prg(nes, addr) {
+                Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+                None => {
+                    log::warn!("Cannot read at PRG address {:#x} for FDS", addr);
+                    Ok(0)
+                }
+            },
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0xe000..=0xffff => Some((addr as usize & 0x1fff) % nes.cartridge.prgmem.len()),
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(addr as usize & 0x1fff)
+    }
+
+    fn prg_layout(&self, _nes: &Nes<S, A>) -> Vec<Bank> {
+        vec![
+            Bank::new(0x6000, 0x8000, Memory::PrgRam, 0),
+            Bank::new(0xe000, 0x2000, Memory::PrgRom, 0),
+        ]
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        vec![Bank::new(0x0000, 0x2000, mappers::chr_memory(nes), 0)]
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x4020..=0x4026 if addr != 0x4023 && !self.disk_io_enabled => {}
+            0x4020 => self.irq_reload = (self.irq_reload & 0xff00) | data as u16,
+            0x4021 => self.irq_reload = (self.irq_reload & 0x00ff) | (data as u16) << 8,
+            0x4022 => {
+                self.irq_repeat = data & 0x01 != 0;
+                self.irq_enabled = data & 0x02 != 0;
+                if self.irq_enabled {
+                    self.irq_counter = self.irq_reload;
+                } else {
+                    self.timer_irq = false;
+                }
+            }
+            0x4023 => {
+                self.disk_io_enabled = data & 0x01 != 0;
+                self.sound_io_enabled = data & 0x02 != 0;
+                if !self.disk_io_enabled {
+                    self.irq_enabled = false;
+                    self.timer_irq = false;
+                    self.transfer_irq = false;
+                }
+            }
+            0x4024 => {
+                self.write_data = data;
+                self.transfer_complete = false;
+                self.transfer_irq = false;
+            }
+            0x4025 => {
+                self.motor_on = data & 0x01 != 0;
+                self.transfer_reset = data & 0x02 != 0;
+                self.read_mode = data & 0x04 != 0;
+                nes.cartridge.mirroring = if data & 0x08 != 0 {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                };
+                self.crc_control = data & 0x10 != 0;
+                self.disk_ready = data & 0x40 != 0;
+                self.disk_irq_enabled = data & 0x80 != 0;
+                self.transfer_irq = false;
+            }
+            0x4026 => self.ext_port = data,
+            0x4040..=0x408a if self.sound_io_enabled => self.audio.write(addr, data),
+            0x6000..=0xdfff => self.wram[addr as usize - 0x6000] = data,
+            _ => log::warn!("Cannot write at PRG address {:#x} for FDS", addr),
+        }
+        Ok(())
+    }
+
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        self.wram[addr as usize - 0x6000] = data;
+        true
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(nes.cartridge.chrmem[addr as usize & 0x1fff])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        nes.cartridge.chrmem[addr as usize & 0x1fff] = data;
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.disk_io_enabled = false;
+        self.sound_io_enabled = false;
+        self.irq_enabled = false;
+        self.timer_irq = false;
+        self.transfer_irq = false;
+        self.motor_on = false;
+        self.end_of_head = true;
+        self.scanning = false;
+        self.audio = FdsAudio::new();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "FDS"
+    }
+
+    fn clock(&mut self, nes: &mut Nes<S, A>) -> Result<()> {
+        if self.irq_enabled {
+            if self.irq_counter == 0 {
+                self.timer_irq = true;
+                self.irq_counter = self.irq_reload;
+                if !self.irq_repeat {
+                    self.irq_enabled = false;
+                }
+            } else {
+                self.irq_counter -= 1;
+            }
+        }
+        self.clock_drive(nes);
+        self.audio.clock();
+        Ok(())
+    }
+
+    fn irq(&self) -> bool {
+        self.timer_irq || self.transfer_irq
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        Some(self.audio.output() * 0.5)
+    }
+}
@@ -0,0 +1,360 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::cartridge::Mirroring;
+use crate::mappers;
+use crate::Nes;
+
+// CPU cycles per tick of the 5B tone, noise and envelope generators
+const AUDIO_DIVIDER: u8 = 16;
+
+/*
+    Sunsoft 5B sound, a YM2149F (AY-3-8910 clone) with three square channels sharing a
+    noise generator and an envelope. Levels are logarithmic, 1.5 dB per envelope step,
+    and fixed volumes land on every other envelope step.
+*/
+pub(crate) struct Sunsoft5b {
+    regs: [u8; 16],
+    divider: u8,
+    tone_timers: [u16; 3],
+    tone_outputs: [bool; 3],
+    noise_timer: u8,
+    noise_half: bool,
+    lfsr: u32,
+    env_timer: u16,
+    env_step: u8,
+    // 0 while the envelope ramps up, 31 while it ramps down
+    env_invert: u8,
+    env_holding: bool,
+    levels: [f32; 32],
+}
+
+impl Sunsoft5b {
+    pub(crate) fn new() -> Self {
+        let mut levels = [0.; 32];
+        for (i, level) in levels.iter_mut().enumerate().skip(1) {
+            *level = 10f32.powf(-1.5 * (31 - i) as f32 / 20.);
+        }
+        Self {
+            regs: [0; 16],
+            divider: 0,
+            tone_timers: [0; 3],
+            tone_outputs: [false; 3],
+            noise_timer: 0,
+            noise_half: false,
+            lfsr: 1,
+            env_timer: 0,
+            env_step: 0,
+            env_invert: 0,
+            env_holding: false,
+            levels,
+        }
+    }
+
+    pub(crate) fn write(&mut self, reg: u8, data: u8) {
+        self.regs[reg as usize & 0x0f] = data;
+        if reg == 0x0d {
+            self.env_step = 0;
+            self.env_invert = if data & 0x04 != 0 { 0 } else { 31 };
+            self.env_holding = false;
+            self.env_timer = 0;
+        }
+    }
+
+    fn tone_period(&self, channel: usize) -> u16 {
+        let period =
+            (self.regs[channel * 2 + 1] as u16 & 0x0f) << 8 | self.regs[channel * 2] as u16;
+        period.max(1)
+    }
+
+    fn env_period(&self) -> u16 {
+        (self.regs[0x0c] as u16) << 8 | self.regs[0x0b] as u16
+    }
+
+    pub(crate) fn clock(&mut self) {
+        self.divider += 1;
+        if self.divider < AUDIO_DIVIDER {
+            return;
+        }
+        self.divider = 0;
+
+        for channel in 0..3 {
+            self.tone_timers[channel] += 1;
+            if self.tone_timers[channel] >= self.tone_period(channel) {
+                self.tone_timers[channel] = 0;
+                self.tone_outputs[channel] = !self.tone_outputs[channel];
+            }
+        }
+
+        // the noise runs at half the rate of the tones
+        self.noise_half = !self.noise_half;
+        if self.noise_half {
+            self.noise_timer += 1;
+            if self.noise_timer >= (self.regs[0x06] & 0x1f).max(1) {
+                self.noise_timer = 0;
+                let bit = (self.lfsr ^ (self.lfsr >> 3)) & 1;
+                self.lfsr = (self.lfsr >> 1) | (bit << 16);
+            }
+        }
+
+        self.env_timer += 1;
+        if self.env_timer >= self.env_period().max(1) {
+            self.env_timer = 0;
+            self.clock_envelope();
+        }
+    }
+
+    fn clock_envelope(&mut self) {
+        if self.env_holding {
+            return;
+        }
+        self.env_step += 1;
+        if self.env_step < 32 {
+            return;
+        }
+        let shape = self.regs[0x0d];
+        if shape & 0x08 == 0 {
+            // no continue, drop to 0 and stay there
+            self.env_holding = true;
+            self.env_step = 0;
+            self.env_invert = 0;
+            return;
+        }
+        if shape & 0x02 != 0 {
+            self.env_invert ^= 31;
+        }
+        if shape & 0x01 != 0 {
+            self.env_holding = true;
+            self.env_step = 31;
+        } else {
+            self.env_step = 0;
+        }
+    }
+
+    pub(crate) fn output(&self) -> f32 {
+        let mixer = self.regs[0x07];
+        let noise = self.lfsr & 1 != 0;
+        let mut sum = 0.;
+        for channel in 0..3 {
+            let tone_on = self.tone_outputs[channel] || mixer & (1 << channel) != 0;
+            let noise_on = noise || mixer & (0x08 << channel) != 0;
+            if !(tone_on && noise_on) {
+                continue;
+            }
+            let volume = self.regs[0x08 + channel];
+            let level = if volume & 0x10 != 0 {
+                self.env_step ^ self.env_invert
+            } else if volume & 0x0f == 0 {
+                0
+            } else {
+                (volume & 0x0f) * 2 + 1
+            };
+            sum += self.levels[level as usize];
+        }
+        sum / 3.
+    }
+}
+
+// Mapper 69
+pub struct Fme7 {
+    command: u8,
+    chr_banksel: [u8; 8],
+    prg_banksel: [u8; 4],
+    // $6000 window: bit 7 enables RAM, bit 6 selects RAM over ROM
+    ram_select: bool,
+    ram_enabled: bool,
+    wram: [u8; 0x2000],
+    irq_enabled: bool,
+    irq_counter_enabled: bool,
+    irq_counter: u16,
+    irq_pending: bool,
+    audio_reg: u8,
+    audio: Sunsoft5b,
+}
+
+impl Fme7 {
+    pub fn new() -> Self {
+        Self {
+            command: 0,
+            chr_banksel: [0; 8],
+            prg_banksel: [0; 4],
+            ram_select: false,
+            ram_enabled: false,
+            wram: [0; 0x2000],
+            irq_enabled: false,
+            irq_counter_enabled: false,
+            irq_counter: 0,
+            irq_pending: false,
+            audio_reg: 0,
+            audio: Sunsoft5b::new(),
+        }
+    }
+
+    fn chr_offset<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> usize {
+        let banks = (nes.cartridge.chrmem.len() / 0x400).max(1);
+        let bank = self.chr_banksel[(addr as usize >> 10) & 7] as usize;
+        bank % banks * 0x400 + (addr as usize & 0x3ff)
+    }
+
+    fn write_command<S, A>(&mut self, nes: &mut Nes<S, A>, data: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_banksel[self.command as usize] = data,
+            0x8 => {
+                self.ram_enabled = data & 0x80 != 0;
+                self.ram_select = data & 0x40 != 0;
+                self.prg_banksel[0] = data & 0x3f;
+            }
+            0x9..=0xb => self.prg_banksel[(self.command - 0x8) as usize] = data & 0x3f,
+            0xc => {
+                nes.cartridge.mirroring = match data & 0b11 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::OneScreenNT0,
+                    _ => Mirroring::OneScreenNT1,
+                };
+            }
+            0xd => {
+                self.irq_enabled = data & 0x01 != 0;
+                self.irq_counter_enabled = data & 0x80 != 0;
+                self.irq_pending = false;
+            }
+            0xe => self.irq_counter = (self.irq_counter & 0xff00) | data as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00ff) | (data as u16) << 8,
+        }
+    }
+}
+
+impl Default for Fme7 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A> Mapper<S, A> for Fme7 {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x6000..=0x7fff if self.ram_select && self.ram_enabled => {
+                Ok(self.wram[(addr & 0x1fff) as usize])
+            }
+            0x6000..=0x7fff if self.ram_select => Ok(0),
+            _ => match self.map_prg(nes, addr) {
+                Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+                None => {
+                    log::warn!("Cannot read at PRG address {:#x} for FME-7", addr);
+                    Ok(0)
+                }
+            },
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let banks = (nes.cartridge.prgmem.len() / 0x2000).max(1);
+        let bank = match addr {
+            0x6000..=0x7fff if !self.ram_select => self.prg_banksel[0] as usize,
+            0x8000..=0xdfff => self.prg_banksel[((addr - 0x6000) >> 13) as usize] as usize,
+            0xe000..=0xffff => banks - 1,
+            _ => return None,
+        };
+        Some(bank % banks * 0x2000 + (addr as usize & 0x1fff))
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(self.chr_offset(nes, addr))
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let mut banks = vec![];
+        if self.ram_select {
+            banks.push(Bank::new(0x6000, 0x2000, Memory::PrgRam, 0));
+        }
+        for addr in [0x6000, 0x8000, 0xa000, 0xc000, 0xe000] {
+            if let Some(offset) = self.map_prg(nes, addr) {
+                banks.push(Bank::new(addr, 0x2000, Memory::PrgRom, offset));
+            }
+        }
+        banks
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        (0..8)
+            .map(|i| {
+                let addr = i * 0x400;
+                let offset = self.chr_offset(nes, addr);
+                Bank::new(addr, 0x400, mappers::chr_memory(nes), offset)
+            })
+            .collect()
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x6000..=0x7fff if self.ram_select && self.ram_enabled => {
+                self.wram[(addr & 0x1fff) as usize] = data;
+            }
+            0x8000..=0x9fff => self.command = data & 0x0f,
+            0xa000..=0xbfff => self.write_command(nes, data),
+            0xc000..=0xdfff => self.audio_reg = data & 0x0f,
+            0xe000..=0xffff => self.audio.write(self.audio_reg, data),
+            _ => log::warn!("Cannot write at PRG address {:#x} for FME-7", addr),
+        }
+        Ok(())
+    }
+
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        if self.ram_select {
+            self.wram[(addr & 0x1fff) as usize] = data;
+        }
+        self.ram_select
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(nes.cartridge.chrmem[self.chr_offset(nes, addr)])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            let mapped_addr = self.chr_offset(nes, addr);
+            nes.cartridge.chrmem[mapped_addr] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for FME-7", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.command = 0;
+        self.irq_enabled = false;
+        self.irq_counter_enabled = false;
+        self.irq_pending = false;
+        self.audio = Sunsoft5b::new();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "FME-7"
+    }
+
+    fn clock(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        if self.irq_counter_enabled {
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+            if self.irq_counter == 0xffff && self.irq_enabled {
+                self.irq_pending = true;
+            }
+        }
+        self.audio.clock();
+        Ok(())
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        Some(self.audio.output() * 0.5)
+    }
+}
@@ -1,7 +1,10 @@
 use anyhow::anyhow;
 use anyhow::Result;
 
+use super::Bank;
 use super::Mapper;
+use super::Memory;
+use crate::mappers;
 use crate::Nes;
 
 // Mapper 66
@@ -9,25 +12,61 @@ use crate::Nes;
 pub struct Gxrom {
     prg_banksel: u8,
     chr_banksel: u8,
+    bus_conflicts: bool,
+}
+
+impl Gxrom {
+    pub fn new(bus_conflicts: bool) -> Self {
+        Self {
+            prg_banksel: 0,
+            chr_banksel: 0,
+            bus_conflicts,
+        }
+    }
 }
 
 impl<S, A> Mapper<S, A> for Gxrom {
     fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
-        match addr {
-            0x8000..=0xffff => {
-                let mapped_addr = (self.prg_banksel as usize * 0x8000) + (addr as usize & 0x7fff);
-                Ok(nes.cartridge.prgmem[mapped_addr])
-            }
-            _ => {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match self.map_prg(nes, addr) {
+            Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+            None => {
                 log::warn!("Cannot read at PRG address {:#x} for GXROM", addr);
                 Ok(0)
             }
         }
     }
 
-    fn write_prg(&mut self, _nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+    fn map_prg(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x8000..=0xffff => {
+                Some((self.prg_banksel as usize * 0x8000) + (addr as usize & 0x7fff))
+            }
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some((self.chr_banksel as usize * 0x2000) + (addr as usize & 0x1fff))
+    }
+
+    fn prg_layout(&self, _nes: &Nes<S, A>) -> Vec<Bank> {
+        let offset = self.prg_banksel as usize * 0x8000;
+        vec![Bank::new(0x8000, 0x8000, Memory::PrgRom, offset)]
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let offset = self.chr_banksel as usize * 0x2000;
+        vec![Bank::new(0x0000, 0x2000, mappers::chr_memory(nes), offset)]
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
         match addr {
             0x8000..=0xffff => {
+                let data = mappers::bus_conflict(self, nes, addr, data, self.bus_conflicts);
                 self.prg_banksel = (data & 0x30) >> 4;
                 self.chr_banksel = data & 0x03;
                 Ok(())
@@ -37,7 +76,7 @@ impl<S, A> Mapper<S, A> for Gxrom {
     }
 
     fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
-        let mapped_addr = (self.chr_banksel as usize * 0x2000) + (addr as usize & 0x1fff);
+        let mapped_addr = self.map_chr(nes, addr).unwrap_or(0);
         Ok(nes.cartridge.chrmem[mapped_addr])
     }
 
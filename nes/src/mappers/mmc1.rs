@@ -2,7 +2,10 @@ use anyhow::Result;
 use bitflags::bitflags;
 
 use crate::cartridge::Mirroring;
+use crate::mappers;
+use crate::mappers::Bank;
 use crate::mappers::Mapper;
+use crate::mappers::Memory;
 use crate::Nes;
 
 bitflags! {
@@ -51,56 +54,88 @@ pub struct Mmc1 {
     reg_load: u8,
     load_count: usize,
     reg_control: RegControl,
-    wram: [u8; 0x2000],
+    wram: Vec<u8>,
 
-    prg_bank_sel_16: (u8, u8),
-    prg_bank_sel_32: u8,
-
-    chr_bank_sel_4: (u8, u8),
-    chr_bank_sel_8: u8,
+    chr_bank_sel: (u8, u8),
+    // bits 0-3 select the 16 KB bank, bit 4 disables PRG RAM
+    prg_bank_sel: u8,
 }
 
 impl Mmc1 {
-    pub fn new() -> Self {
+    pub fn new(prg_ram_size: usize) -> Self {
         Self {
             reg_load: 0x00,
             load_count: 0,
             reg_control: RegControl::from_bits_truncate(0x1c),
-            wram: [0; 0x2000],
+            wram: vec![0; prg_ram_size],
+
+            chr_bank_sel: (0x00, 0x00),
+            prg_bank_sel: 0x00,
+        }
+    }
+
+    /*
+        Large boards reuse CHR bank bits when their CHR is 8 KB of RAM: SUROM/SXROM select the
+        256 KB half of a 512 KB PRG ROM with bit 4, SOROM and SXROM select the PRG RAM bank with
+        bit 3 or bits 2-3, and SNROM disables its PRG RAM with bit 4.
+    */
+    fn prg_outer_bank<S, A>(&self, nes: &Nes<S, A>) -> usize {
+        if nes.cartridge.prgmem.len() > 0x40000 {
+            (self.chr_bank_sel.0 & 0x10) as usize
+        } else {
+            0
+        }
+    }
 
-            prg_bank_sel_16: (0x00, 0x00),
-            prg_bank_sel_32: 0x00,
+    fn wram_offset(&self, addr: u16) -> usize {
+        let bank = match self.wram.len() {
+            0x8000 => (self.chr_bank_sel.0 >> 2) & 0b11,
+            0x4000 => (self.chr_bank_sel.0 >> 3) & 0b1,
+            _ => 0,
+        };
+        bank as usize * 0x2000 + (addr as usize & 0x1fff)
+    }
 
-            chr_bank_sel_4: (0x00, 0x00),
-            chr_bank_sel_8: 0x00,
+    fn wram_enabled<S, A>(&self, nes: &Nes<S, A>) -> bool {
+        let snrom_disabled = nes.cartridge.chr_banks == 0
+            && nes.cartridge.prgmem.len() <= 0x40000
+            && self.wram.len() == 0x2000
+            && self.chr_bank_sel.0 & 0x10 != 0;
+        !self.wram.is_empty() && self.prg_bank_sel & 0x10 == 0 && !snrom_disabled
+    }
+
+    fn write_register<S, A>(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) {
+        match addr {
+            0x8000..=0x9fff => {
+                self.reg_control.update(data);
+                nes.cartridge.mirroring = self.reg_control.mirroring();
+                log::info!("Switched mirroring to: {:?}", nes.cartridge.mirroring);
+            }
+            0xa000..=0xbfff => self.chr_bank_sel.0 = data,
+            0xc000..=0xdfff => self.chr_bank_sel.1 = data,
+            _ => self.prg_bank_sel = data,
         }
     }
 }
 
 impl Default for Mmc1 {
     fn default() -> Self {
-        Self::new()
+        Self::new(0x2000)
     }
 }
 
 impl<S, A> Mapper<S, A> for Mmc1 {
     fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
         match addr {
-            0x6000..=0x7fff => Ok(self.wram[(addr & 0x1fff) as usize]),
+            0x6000..=0x7fff if self.wram_enabled(nes) => Ok(self.wram[self.wram_offset(addr)]),
+            // games read disabled or absent WRAM routinely, it is open bus
+            0x6000..=0x7fff => Ok(0),
             0x8000..=0xffff => {
-                let mapped_addr = if self.reg_control.bits & 0b01000 != 0 {
-                    match addr {
-                        0x8000..=0xbfff => {
-                            self.prg_bank_sel_16.0 as usize * 0x4000 + (addr as usize & 0x3fff)
-                        }
-                        0xc000..=0xffff => {
-                            self.prg_bank_sel_16.1 as usize * 0x4000 + (addr as usize & 0x3fff)
-                        }
-                        _ => unreachable!(),
-                    }
-                } else {
-                    self.prg_bank_sel_32 as usize * 0x8000 + (addr as usize & 0x7fff)
-                };
+                let mapped_addr = self.map_prg(nes, addr).unwrap_or(0);
                 Ok(nes.cartridge.prgmem[mapped_addr])
             }
             _ => {
@@ -110,12 +145,93 @@ impl<S, A> Mapper<S, A> for Mmc1 {
         }
     }
 
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        if addr < 0x8000 {
+            return None;
+        }
+        let outer = self.prg_outer_bank(nes);
+        let select = (self.prg_bank_sel & 0x0f) as usize;
+        let bank = match (self.reg_control.prg_bank_mode(), addr) {
+            // 32 KB mode ignores the low bit
+            (0 | 1, 0x8000..=0xbfff) => select & 0x0e,
+            (0 | 1, _) => select | 0x01,
+            (2, 0x8000..=0xbfff) => 0,
+            (2, _) => select,
+            (_, 0x8000..=0xbfff) => select,
+            (_, _) => 0x0f,
+        };
+        let banks = (nes.cartridge.prgmem.len() / 0x4000).max(1);
+        Some((outer | bank) % banks * 0x4000 + (addr as usize & 0x3fff))
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let banks = (nes.cartridge.chrmem.len() / 0x1000).max(1);
+        let bank = if self.reg_control.chr_bank_mode() {
+            match addr {
+                0x0000..=0x0fff => self.chr_bank_sel.0 as usize,
+                _ => self.chr_bank_sel.1 as usize,
+            }
+        } else {
+            // one 8 KB bank, the low bit picks its upper half
+            (self.chr_bank_sel.0 & 0x1e) as usize | (addr as usize >> 12) & 1
+        };
+        Some(bank % banks * 0x1000 + (addr as usize & 0x0fff))
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let mut banks = vec![];
+        if self.wram_enabled(nes) {
+            banks.push(Bank::new(
+                0x6000,
+                0x2000,
+                Memory::PrgRam,
+                self.wram_offset(0x6000),
+            ));
+        }
+        if self.reg_control.prg_bank_mode() < 2 {
+            let offset = self.map_prg(nes, 0x8000).unwrap_or(0);
+            banks.push(Bank::new(0x8000, 0x8000, Memory::PrgRom, offset));
+        } else {
+            for addr in [0x8000, 0xc000] {
+                if let Some(offset) = self.map_prg(nes, addr) {
+                    banks.push(Bank::new(addr, 0x4000, Memory::PrgRom, offset));
+                }
+            }
+        }
+        banks
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let memory = mappers::chr_memory(nes);
+        if self.reg_control.chr_bank_mode() {
+            [0x0000, 0x1000]
+                .into_iter()
+                .filter_map(|addr| {
+                    let offset = self.map_chr(nes, addr)?;
+                    Some(Bank::new(addr, 0x1000, memory, offset))
+                })
+                .collect()
+        } else {
+            let offset = self.map_chr(nes, 0).unwrap_or(0);
+            vec![Bank::new(0x0000, 0x2000, memory, offset)]
+        }
+    }
+
     fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
         match addr {
             0x6000..=0x7fff => {
-                self.wram[(addr & 0x1fff) as usize] = data;
+                if self.wram_enabled(nes) {
+                    let offset = self.wram_offset(addr);
+                    self.wram[offset] = data;
+                }
             }
             0x8000..=0xffff => {
+                // the serial port ignores a write on the cycle right after another one, so
+                // read-modify-write instructions only get their first write through
+                if nes.cpu.second_write {
+                    return Ok(());
+                }
+
                 if data & 0x80 != 0 {
                     self.reg_load = 0x00;
                     self.load_count = 0;
@@ -125,41 +241,7 @@ impl<S, A> Mapper<S, A> for Mmc1 {
                     self.reg_load |= (data & 0x01) << 4;
                     self.load_count += 1;
                     if self.load_count == 5 {
-                        match addr {
-                            0x8000..=0x9fff => {
-                                self.reg_control.update(self.reg_load & 0x1f);
-                                nes.cartridge.mirroring = self.reg_control.mirroring();
-                                log::info!("Switched mirroring to: {:?}", nes.cartridge.mirroring);
-                            }
-                            0xa000..=0xbfff => {
-                                if self.reg_control.chr_bank_mode() {
-                                    self.chr_bank_sel_4.0 = self.reg_load & 0x1f;
-                                } else {
-                                    self.chr_bank_sel_8 = self.reg_load & 0x1e;
-                                }
-                            }
-                            0xc000..=0xdfff => {
-                                if self.reg_control.chr_bank_mode() {
-                                    self.chr_bank_sel_4.1 = self.reg_load & 0x1f;
-                                }
-                            }
-                            0xe000..=0xffff => match self.reg_control.prg_bank_mode() {
-                                0 | 1 => {
-                                    self.prg_bank_sel_32 = (self.reg_load & 0x0e) >> 1;
-                                }
-                                2 => {
-                                    self.prg_bank_sel_16.0 = 0;
-                                    self.prg_bank_sel_16.1 = self.reg_load & 0x0f;
-                                }
-                                3 => {
-                                    self.prg_bank_sel_16.0 = self.reg_load & 0x0f;
-                                    self.prg_bank_sel_16.1 = nes.cartridge.prg_banks - 1;
-                                }
-                                _ => unreachable!(),
-                            },
-                            _ => unreachable!(),
-                        }
-
+                        self.write_register(nes, addr, self.reg_load & 0x1f);
                         self.reg_load = 0x00;
                         self.load_count = 0;
                     }
@@ -170,39 +252,38 @@ impl<S, A> Mapper<S, A> for Mmc1 {
         Ok(())
     }
 
-    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
-        let mapped_addr = if nes.cartridge.chr_banks == 0 {
-            addr as usize
-        } else if self.reg_control.chr_bank_mode() {
-            match addr {
-                0x0000..=0x0fff => {
-                    self.chr_bank_sel_4.0 as usize * 0x1000 + (addr as usize & 0x0fff)
-                }
-                0x1000..=0x1fff => {
-                    self.chr_bank_sel_4.1 as usize * 0x1000 + (addr as usize & 0x0fff)
-                }
-                _ => unreachable!(),
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        let offset = self.wram_offset(addr);
+        match self.wram.get_mut(offset) {
+            Some(byte) => {
+                *byte = data;
+                true
             }
-        } else {
-            self.chr_bank_sel_8 as usize * 0x1000 + (addr as usize & 0x1fff)
-        };
+            None => false,
+        }
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        let mapped_addr = self.map_chr(nes, addr).unwrap_or(0);
         Ok(nes.cartridge.chrmem[mapped_addr])
     }
 
     fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
-        nes.cartridge.chrmem[addr as usize] = data;
+        if nes.cartridge.chr_banks == 0 {
+            let mapped_addr = self.map_chr(nes, addr).unwrap_or(0);
+            nes.cartridge.chrmem[mapped_addr] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for MMC1", addr);
+        }
         Ok(())
     }
 
-    fn reset(&mut self, nes: &mut Nes<S, A>) -> Result<()> {
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
         self.reg_load = 0x00;
         self.load_count = 0;
         self.reg_control.update(0x1c);
-        self.chr_bank_sel_4 = (0x00, 0x00);
-        self.chr_bank_sel_8 = 0x00;
-        self.prg_bank_sel_16.0 = 0x00;
-        self.prg_bank_sel_16.1 = nes.cartridge.prg_banks - 1;
-        self.prg_bank_sel_32 = 0;
+        self.chr_bank_sel = (0x00, 0x00);
+        self.prg_bank_sel = 0x00;
         Ok(())
     }
 
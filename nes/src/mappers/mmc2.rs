@@ -0,0 +1,204 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::cartridge::Mirroring;
+use crate::mappers;
+use crate::Nes;
+
+const FD: usize = 0;
+const FE: usize = 1;
+
+/*
+    CHR banking shared by MMC2 and MMC4. Each 4 KB pattern table has two banks, one for
+    the $FD latch state and one for $FE. Fetching tile $FD or $FE flips the latch of that
+    table, after the fetch itself has been served from the previous bank.
+*/
+pub struct ChrLatch {
+    // [pattern table][latch state]
+    banks: [[u8; 2]; 2],
+    latch: [usize; 2],
+    // MMC4 triggers on any row of the tile for both tables, MMC2 only on the first row of $0FD8/$0FE8
+    full_tile: bool,
+}
+
+impl ChrLatch {
+    pub fn new(full_tile: bool) -> Self {
+        Self {
+            banks: [[0; 2]; 2],
+            latch: [FE; 2],
+            full_tile,
+        }
+    }
+
+    pub fn map(&self, addr: u16, chr_len: usize) -> usize {
+        let table = (addr as usize >> 12) & 1;
+        let banks = (chr_len / 0x1000).max(1);
+        self.banks[table][self.latch[table]] as usize % banks * 0x1000 + (addr as usize & 0x0fff)
+    }
+
+    pub fn update(&mut self, addr: u16) {
+        match addr {
+            0x0fd8 => self.latch[0] = FD,
+            0x0fe8 => self.latch[0] = FE,
+            0x0fd9..=0x0fdf if self.full_tile => self.latch[0] = FD,
+            0x0fe9..=0x0fef if self.full_tile => self.latch[0] = FE,
+            0x1fd8..=0x1fdf => self.latch[1] = FD,
+            0x1fe8..=0x1fef => self.latch[1] = FE,
+            _ => {}
+        }
+    }
+
+    pub fn write_bank(&mut self, table: usize, state: usize, data: u8) {
+        self.banks[table][state] = data & 0x1f;
+    }
+
+    pub fn reset(&mut self) {
+        self.latch = [FE; 2];
+    }
+
+    pub fn layout<S, A>(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        [0x0000, 0x1000]
+            .into_iter()
+            .map(|addr| {
+                Bank::new(
+                    addr,
+                    0x1000,
+                    mappers::chr_memory(nes),
+                    self.map(addr, nes.cartridge.chrmem.len()),
+                )
+            })
+            .collect()
+    }
+}
+
+// $A000-$FFFF registers shared by MMC2 and MMC4, returns false for addresses it doesn't handle
+pub fn write_latch_reg<S, A>(
+    latch: &mut ChrLatch,
+    nes: &mut Nes<S, A>,
+    addr: u16,
+    data: u8,
+) -> bool {
+    match addr {
+        0xb000..=0xbfff => latch.write_bank(0, FD, data),
+        0xc000..=0xcfff => latch.write_bank(0, FE, data),
+        0xd000..=0xdfff => latch.write_bank(1, FD, data),
+        0xe000..=0xefff => latch.write_bank(1, FE, data),
+        0xf000..=0xffff => {
+            nes.cartridge.mirroring = if data & 0x01 == 0 {
+                Mirroring::Vertical
+            } else {
+                Mirroring::Horizontal
+            };
+        }
+        _ => return false,
+    }
+    true
+}
+
+// Mapper 9
+pub struct Mmc2 {
+    prg_banksel: u8,
+    chr: ChrLatch,
+}
+
+impl Mmc2 {
+    pub fn new() -> Self {
+        Self {
+            prg_banksel: 0,
+            chr: ChrLatch::new(false),
+        }
+    }
+}
+
+impl Default for Mmc2 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A> Mapper<S, A> for Mmc2 {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match self.map_prg(nes, addr) {
+            Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+            None => {
+                log::warn!("Cannot read at PRG address {:#x} for MMC2", addr);
+                Ok(0)
+            }
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        // one switchable 8 KB bank followed by the last three 8 KB banks
+        let banks = (nes.cartridge.prgmem.len() / 0x2000).max(1);
+        let last_bank = banks - 1;
+        let bank = match addr {
+            0x8000..=0x9fff => self.prg_banksel as usize % banks,
+            0xa000..=0xbfff => last_bank.saturating_sub(2),
+            0xc000..=0xdfff => last_bank.saturating_sub(1),
+            0xe000..=0xffff => last_bank,
+            _ => return None,
+        };
+        Some(bank * 0x2000 + (addr as usize & 0x1fff))
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(self.chr.map(addr, nes.cartridge.chrmem.len()))
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        [0x8000, 0xa000, 0xc000, 0xe000]
+            .into_iter()
+            .filter_map(|addr| {
+                let offset = self.map_prg(nes, addr)?;
+                Some(Bank::new(addr, 0x2000, Memory::PrgRom, offset))
+            })
+            .collect()
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        self.chr.layout(nes)
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0xa000..=0xafff => self.prg_banksel = data & 0x0f,
+            _ => {
+                if !write_latch_reg(&mut self.chr, nes, addr, data) {
+                    log::warn!("Cannot write at PRG address {:#x} for MMC2", addr);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        let data = nes.cartridge.chrmem[self.chr.map(addr, nes.cartridge.chrmem.len())];
+        self.chr.update(addr);
+        Ok(data)
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            nes.cartridge.chrmem[addr as usize & 0x1fff] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for MMC2", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.prg_banksel = 0;
+        self.chr.reset();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "MMC2"
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::Result;
+
+use super::mmc2;
+use super::mmc2::ChrLatch;
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::Nes;
+
+// Mapper 10
+pub struct Mmc4 {
+    prg_banksel: u8,
+    chr: ChrLatch,
+    wram: [u8; 0x2000],
+}
+
+impl Mmc4 {
+    pub fn new() -> Self {
+        Self {
+            prg_banksel: 0,
+            chr: ChrLatch::new(true),
+            wram: [0; 0x2000],
+        }
+    }
+}
+
+impl Default for Mmc4 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A> Mapper<S, A> for Mmc4 {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x6000..=0x7fff => Ok(self.wram[(addr & 0x1fff) as usize]),
+            _ => match self.map_prg(nes, addr) {
+                Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+                None => {
+                    log::warn!("Cannot read at PRG address {:#x} for MMC4", addr);
+                    Ok(0)
+                }
+            },
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x8000..=0xbfff => {
+                let banks = (nes.cartridge.prgmem.len() / 0x4000).max(1);
+                Some(self.prg_banksel as usize % banks * 0x4000 + (addr as usize & 0x3fff))
+            }
+            0xc000..=0xffff => {
+                let last_bank = (nes.cartridge.prg_banks as usize).saturating_sub(1);
+                Some(last_bank * 0x4000 + (addr as usize & 0x3fff))
+            }
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(self.chr.map(addr, nes.cartridge.chrmem.len()))
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let mut banks = vec![Bank::new(0x6000, 0x2000, Memory::PrgRam, 0)];
+        for addr in [0x8000, 0xc000] {
+            if let Some(offset) = self.map_prg(nes, addr) {
+                banks.push(Bank::new(addr, 0x4000, Memory::PrgRom, offset));
+            }
+        }
+        banks
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        self.chr.layout(nes)
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x6000..=0x7fff => self.wram[(addr & 0x1fff) as usize] = data,
+            0xa000..=0xafff => self.prg_banksel = data & 0x0f,
+            _ => {
+                if !mmc2::write_latch_reg(&mut self.chr, nes, addr, data) {
+                    log::warn!("Cannot write at PRG address {:#x} for MMC4", addr);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        self.wram[(addr & 0x1fff) as usize] = data;
+        true
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        let data = nes.cartridge.chrmem[self.chr.map(addr, nes.cartridge.chrmem.len())];
+        self.chr.update(addr);
+        Ok(data)
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            nes.cartridge.chrmem[addr as usize & 0x1fff] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for MMC4", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.prg_banksel = 0;
+        self.chr.reset();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "MMC4"
+    }
+}
@@ -0,0 +1,626 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use super::Nametable;
+use crate::mappers;
+use crate::ppu::Fetch;
+use crate::Nes;
+
+const PRG_RAM_SIZE: usize = 0x10000;
+// CPU cycles between the 240 Hz envelope and length counter clocks of the pulse channels
+const FRAME_PERIOD: u16 = 7457;
+
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+// Same as the APU pulse channels minus the sweep unit
+#[derive(Default)]
+struct Pulse {
+    enabled: bool,
+    duty: u8,
+    // length counter halt, also loops the envelope
+    halt: bool,
+    constant: bool,
+    // constant volume or envelope period
+    volume: u8,
+    period: u16,
+    timer: u16,
+    step: u8,
+    length: u8,
+    env_start: bool,
+    env_divider: u8,
+    env_decay: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => {
+                self.duty = data >> 6;
+                self.halt = data & 0x20 != 0;
+                self.constant = data & 0x10 != 0;
+                self.volume = data & 0x0f;
+            }
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            3 => {
+                self.period = (self.period & 0x00ff) | ((data & 0b111) as u16) << 8;
+                if self.enabled {
+                    self.length = LENGTH_TABLE[(data >> 3) as usize];
+                }
+                self.step = 0;
+                self.env_start = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.length = 0;
+        }
+    }
+
+    // Clocked every other CPU cycle
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) & 0b111;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_frame(&mut self) {
+        if self.env_start {
+            self.env_start = false;
+            self.env_decay = 15;
+            self.env_divider = self.volume;
+        } else if self.env_divider == 0 {
+            self.env_divider = self.volume;
+            if self.env_decay > 0 {
+                self.env_decay -= 1;
+            } else if self.halt {
+                self.env_decay = 15;
+            }
+        } else {
+            self.env_divider -= 1;
+        }
+
+        if !self.halt && self.length > 0 {
+            self.length -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.length == 0 || DUTY_TABLE[self.duty as usize][self.step as usize] == 0 {
+            0
+        } else if self.constant {
+            self.volume
+        } else {
+            self.env_decay
+        }
+    }
+}
+
+// Pulses and PCM channel at $5000-$5015, also used by the NSF player
+#[derive(Default)]
+pub(crate) struct Mmc5Audio {
+    pulses: [Pulse; 2],
+    pcm_read_mode: bool,
+    pcm_irq_enabled: bool,
+    pcm_irq: bool,
+    pcm: u8,
+    odd_cycle: bool,
+    cycles: u16,
+}
+
+impl Mmc5Audio {
+    pub(crate) fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0x5010 => (self.pcm_irq as u8) << 7 | self.pcm_read_mode as u8,
+            0x5015 => (self.pulses[0].length > 0) as u8 | ((self.pulses[1].length > 0) as u8) << 1,
+            _ => 0,
+        }
+    }
+
+    pub(crate) fn read(&mut self, addr: u16) -> u8 {
+        let data = self.peek(addr);
+        if addr == 0x5010 {
+            self.pcm_irq = false;
+        }
+        data
+    }
+
+    pub(crate) fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x5000..=0x5003 => self.pulses[0].write(addr & 0b11, data),
+            0x5004..=0x5007 => self.pulses[1].write(addr & 0b11, data),
+            0x5010 => {
+                self.pcm_read_mode = data & 0x01 != 0;
+                self.pcm_irq_enabled = data & 0x80 != 0;
+            }
+            0x5011 if !self.pcm_read_mode && data != 0 => self.pcm = data,
+            0x5015 => {
+                self.pulses[0].set_enabled(data & 0x01 != 0);
+                self.pulses[1].set_enabled(data & 0x02 != 0);
+            }
+            _ => {}
+        }
+    }
+
+    // PCM samples are played by reading them from $8000-$BFFF
+    fn read_sample(&mut self, data: u8) {
+        if data == 0 {
+            self.pcm_irq = true;
+        } else {
+            self.pcm = data;
+        }
+    }
+
+    pub(crate) fn clock(&mut self) {
+        self.odd_cycle = !self.odd_cycle;
+        if !self.odd_cycle {
+            self.pulses.iter_mut().for_each(Pulse::clock_timer);
+        }
+        self.cycles += 1;
+        if self.cycles == FRAME_PERIOD {
+            self.cycles = 0;
+            self.pulses.iter_mut().for_each(Pulse::clock_frame);
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.pcm_irq_enabled && self.pcm_irq
+    }
+
+    pub(crate) fn output(&self) -> f32 {
+        let pulses = (self.pulses[0].output() + self.pulses[1].output()) as f32;
+        let pulse_out = if pulses == 0. {
+            0.
+        } else {
+            95.88 / (8128. / pulses + 100.)
+        };
+        pulse_out + self.pcm as f32 / 255. * 0.4
+    }
+}
+
+// Mapper 5
+pub struct Mmc5 {
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: (u8, u8),
+    exram_mode: u8,
+    // 2 bits per nametable: CIRAM page 0, CIRAM page 1, ExRAM or fill mode
+    nametables: u8,
+    fill_tile: u8,
+    fill_attr: u8,
+    prg_ram_bank: u8,
+    // $5114-$5117, bit 7 selects ROM over RAM
+    prg_banks: [u8; 4],
+    // sprite (A) and background (B) CHR banks, with the upper bits from $5130 already applied
+    chr_a: [u16; 8],
+    chr_b: [u16; 4],
+    chr_upper: u8,
+    // set used outside of 8x16 sprite rendering
+    last_chr_b: bool,
+
+    split_mode: u8,
+    split_scroll: u8,
+    split_bank: u8,
+    split_y: u8,
+
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    in_frame: bool,
+    scanline: u8,
+
+    multiplicand: u8,
+    multiplier: u8,
+
+    prg_ram: Vec<u8>,
+    exram: [u8; 0x400],
+
+    // background tile the PPU is fetching, for split screen and extended attributes
+    tile_count: u8,
+    tile_split: bool,
+    tile_ex: u8,
+
+    audio: Mmc5Audio,
+}
+
+impl Mmc5 {
+    pub fn new() -> Self {
+        Self {
+            prg_mode: 3,
+            chr_mode: 0,
+            prg_ram_protect: (0, 0),
+            exram_mode: 0,
+            nametables: 0,
+            fill_tile: 0,
+            fill_attr: 0,
+            prg_ram_bank: 0,
+            prg_banks: [0xff; 4],
+            chr_a: [0; 8],
+            chr_b: [0; 4],
+            chr_upper: 0,
+            last_chr_b: false,
+
+            split_mode: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            split_y: 0,
+
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            in_frame: false,
+            scanline: 0,
+
+            multiplicand: 0xff,
+            multiplier: 0xff,
+
+            prg_ram: vec![0; PRG_RAM_SIZE],
+            exram: [0; 0x400],
+
+            tile_count: 0,
+            tile_split: false,
+            tile_ex: 0,
+
+            audio: Mmc5Audio::default(),
+        }
+    }
+
+    // PRG ROM or RAM offset behind a CPU address
+    fn prg_target<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> Option<(Memory, usize)> {
+        let (reg, size) = match (self.prg_mode, addr) {
+            (_, 0x6000..=0x7fff) => {
+                let offset = (self.prg_ram_bank as usize & 0b111) * 0x2000;
+                return Some((Memory::PrgRam, offset + (addr as usize & 0x1fff)));
+            }
+            (0, 0x8000..=0xffff) => (0x80 | self.prg_banks[3], 0x8000),
+            (1, 0x8000..=0xbfff) => (self.prg_banks[1], 0x4000),
+            (1, 0xc000..=0xffff) => (0x80 | self.prg_banks[3], 0x4000),
+            (2, 0x8000..=0xbfff) => (self.prg_banks[1], 0x4000),
+            (2, 0xc000..=0xdfff) => (self.prg_banks[2], 0x2000),
+            (3, 0x8000..=0x9fff) => (self.prg_banks[0], 0x2000),
+            (3, 0xa000..=0xbfff) => (self.prg_banks[1], 0x2000),
+            (3, 0xc000..=0xdfff) => (self.prg_banks[2], 0x2000),
+            (_, 0xe000..=0xffff) => (0x80 | self.prg_banks[3], 0x2000),
+            _ => return None,
+        };
+        // banks count 8 KB pages, bigger windows ignore the low bits
+        let page = (reg & 0x7f) as usize & !(size / 0x2000 - 1);
+        let offset = page * 0x2000 + (addr as usize & (size - 1));
+        if reg & 0x80 != 0 {
+            Some((Memory::PrgRom, offset % nes.cartridge.prgmem.len()))
+        } else {
+            Some((Memory::PrgRam, offset % PRG_RAM_SIZE))
+        }
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == (0b10, 0b01)
+    }
+
+    fn use_chr_b<S, A>(&self, nes: &Nes<S, A>) -> bool {
+        if nes.ppu.reg_control.spr_height_16() && nes.ppu.fetch != Fetch::Idle {
+            nes.ppu.fetch == Fetch::Background
+        } else {
+            self.last_chr_b
+        }
+    }
+
+    fn chr_bank_offset(&self, addr: u16, chr_b: bool) -> usize {
+        let size = 0x2000 >> self.chr_mode;
+        if !chr_b {
+            let index = (addr as usize / size + 1) * (size / 0x400) - 1;
+            self.chr_a[index] as usize * size + (addr as usize & (size - 1))
+        } else if self.chr_mode == 0 {
+            self.chr_b[3] as usize * 0x2000 + (addr as usize & 0x1fff)
+        } else {
+            // the B set is the same for both pattern tables
+            let addr = addr as usize & 0x0fff;
+            let index = (addr / size + 1) * (size / 0x400) - 1;
+            self.chr_b[index] as usize * size + (addr & (size - 1))
+        }
+    }
+
+    fn split_active(&self, tile: u8) -> bool {
+        let threshold = self.split_mode & 0x1f;
+        if self.split_mode & 0x80 == 0 {
+            false
+        } else if self.split_mode & 0x40 != 0 {
+            tile >= threshold
+        } else {
+            tile < threshold
+        }
+    }
+}
+
+impl Default for Mmc5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A> Mapper<S, A> for Mmc5 {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x5010 => Ok(self.audio.read(addr)),
+            0x5204 => {
+                let data = (self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6;
+                self.irq_pending = false;
+                Ok(data)
+            }
+            0x8000..=0xbfff if self.audio.pcm_read_mode => {
+                let data = self.peek_prg(nes, addr)?;
+                self.audio.read_sample(data);
+                Ok(data)
+            }
+            _ => self.peek_prg(nes, addr),
+        }
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x5010 | 0x5015 => Ok(self.audio.peek(addr)),
+            0x5204 => Ok((self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6),
+            0x5205 => Ok((self.multiplicand as u16 * self.multiplier as u16) as u8),
+            0x5206 => Ok(((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8),
+            0x5c00..=0x5fff if self.exram_mode >= 2 => Ok(self.exram[addr as usize & 0x3ff]),
+            0x6000..=0xffff => match self.prg_target(nes, addr) {
+                Some((Memory::PrgRom, offset)) => Ok(nes.cartridge.prgmem[offset]),
+                Some((_, offset)) => Ok(self.prg_ram[offset]),
+                None => Ok(0),
+            },
+            _ => Ok(0),
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match self.prg_target(nes, addr)? {
+            (Memory::PrgRom, offset) => Some(offset),
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let offset = self.chr_bank_offset(addr, self.use_chr_b(nes));
+        Some(offset % nes.cartridge.chrmem.len())
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let windows: &[(u16, usize)] = match self.prg_mode {
+            0 => &[(0x8000, 0x8000)],
+            1 => &[(0x8000, 0x4000), (0xc000, 0x4000)],
+            2 => &[(0x8000, 0x4000), (0xc000, 0x2000), (0xe000, 0x2000)],
+            _ => &[
+                (0x8000, 0x2000),
+                (0xa000, 0x2000),
+                (0xc000, 0x2000),
+                (0xe000, 0x2000),
+            ],
+        };
+        std::iter::once((0x6000, 0x2000))
+            .chain(windows.iter().copied())
+            .filter_map(|(addr, size)| {
+                let (memory, offset) = self.prg_target(nes, addr)?;
+                Some(Bank::new(addr, size, memory, offset))
+            })
+            .collect()
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let size = 0x2000 >> self.chr_mode;
+        (0..0x2000)
+            .step_by(size)
+            .filter_map(|addr| {
+                let offset = self.map_chr(nes, addr as u16)?;
+                Some(Bank::new(
+                    addr as u16,
+                    size,
+                    mappers::chr_memory(nes),
+                    offset,
+                ))
+            })
+            .collect()
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x5000..=0x5007 | 0x5010 | 0x5011 | 0x5015 => self.audio.write(addr, data),
+            0x5100 => self.prg_mode = data & 0b11,
+            0x5101 => self.chr_mode = data & 0b11,
+            0x5102 => self.prg_ram_protect.0 = data & 0b11,
+            0x5103 => self.prg_ram_protect.1 = data & 0b11,
+            0x5104 => self.exram_mode = data & 0b11,
+            0x5105 => self.nametables = data,
+            0x5106 => self.fill_tile = data,
+            0x5107 => self.fill_attr = data & 0b11,
+            0x5113 => self.prg_ram_bank = data & 0b111,
+            0x5114..=0x5117 => self.prg_banks[addr as usize - 0x5114] = data,
+            0x5120..=0x5127 => {
+                self.chr_a[addr as usize - 0x5120] = data as u16 | (self.chr_upper as u16) << 8;
+                self.last_chr_b = false;
+            }
+            0x5128..=0x512b => {
+                self.chr_b[addr as usize - 0x5128] = data as u16 | (self.chr_upper as u16) << 8;
+                self.last_chr_b = true;
+            }
+            0x5130 => self.chr_upper = data & 0b11,
+            0x5200 => self.split_mode = data,
+            0x5201 => self.split_scroll = data,
+            0x5202 => self.split_bank = data,
+            0x5203 => self.irq_compare = data,
+            0x5204 => self.irq_enabled = data & 0x80 != 0,
+            0x5205 => self.multiplicand = data,
+            0x5206 => self.multiplier = data,
+            0x5c00..=0x5fff => {
+                if self.exram_mode != 3 {
+                    self.exram[addr as usize & 0x3ff] = data;
+                }
+            }
+            0x6000..=0xffff => {
+                if let Some((Memory::PrgRam, offset)) = self.prg_target(nes, addr) {
+                    if self.prg_ram_writable() {
+                        self.prg_ram[offset] = data;
+                    }
+                }
+            }
+            _ => log::warn!("Cannot write at PRG address {:#x} for MMC5", addr),
+        }
+        Ok(())
+    }
+
+    fn poke_prg_ram(&mut self, nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        match self.prg_target(nes, addr) {
+            Some((Memory::PrgRam, offset)) => {
+                self.prg_ram[offset] = data;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        let offset = if nes.ppu.fetch == Fetch::Background && self.tile_split {
+            // split tiles use their own bank and vertical scroll
+            let fine_y = (self.split_y & 0b111) as usize;
+            self.split_bank as usize * 0x1000 + (addr as usize & 0x0ff8) + fine_y
+        } else if nes.ppu.fetch == Fetch::Background && self.exram_mode == 1 {
+            let bank = (self.tile_ex & 0x3f) as usize | (self.chr_upper as usize) << 6;
+            bank * 0x1000 + (addr as usize & 0x0fff)
+        } else {
+            self.chr_bank_offset(addr, self.use_chr_b(nes))
+        };
+        Ok(nes.cartridge.chrmem[offset % nes.cartridge.chrmem.len()])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            let offset = self.chr_bank_offset(addr, self.last_chr_b);
+            let len = nes.cartridge.chrmem.len();
+            nes.cartridge.chrmem[offset % len] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for MMC5", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.prg_mode = 3;
+        self.prg_banks[3] = 0xff;
+        self.irq_enabled = false;
+        self.irq_pending = false;
+        self.in_frame = false;
+        self.audio.pcm_irq = false;
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "MMC5"
+    }
+
+    // every slot goes through the mapper, which has to see each fetch to count tiles
+    fn nametable(&self, _nes: &Nes<S, A>, _slot: usize) -> Nametable {
+        Nametable::Mapper
+    }
+
+    fn read_nametable(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        let offset = addr as usize & 0x3ff;
+        if nes.ppu.fetch == Fetch::Background && self.exram_mode <= 1 {
+            if offset < 0x3c0 {
+                let tile = self.tile_count;
+                self.tile_count = self.tile_count.wrapping_add(1);
+                self.tile_split = self.split_active(tile);
+                if self.tile_split {
+                    let row = self.split_y as usize / 8;
+                    return Ok(self.exram[row * 32 + (tile as usize & 31)]);
+                }
+                if self.exram_mode == 1 {
+                    self.tile_ex = self.exram[offset];
+                }
+            } else if self.tile_split {
+                let row = self.split_y as usize / 8;
+                let col = self.tile_count.wrapping_sub(1) as usize & 31;
+                let attr = self.exram[0x3c0 + row / 4 * 8 + col / 4];
+                let palette = (attr >> ((row & 2) << 1 | (col & 2))) & 0b11;
+                // repeat the palette so it comes out whatever quadrant the PPU picks
+                return Ok(palette * 0x55);
+            } else if self.exram_mode == 1 {
+                return Ok((self.tile_ex >> 6) * 0x55);
+            }
+        }
+        Ok(self.peek_nametable(nes, addr))
+    }
+
+    fn peek_nametable(&self, nes: &Nes<S, A>, addr: u16) -> u8 {
+        let offset = addr as usize & 0x3ff;
+        match (self.nametables >> (((addr >> 10) & 0b11) * 2)) & 0b11 {
+            0 => nes.bus_ppu.vram[offset],
+            1 => nes.bus_ppu.vram[0x400 + offset],
+            2 if self.exram_mode <= 1 => self.exram[offset],
+            2 => 0,
+            _ if offset < 0x3c0 => self.fill_tile,
+            _ => self.fill_attr * 0x55,
+        }
+    }
+
+    fn write_nametable(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        let offset = addr as usize & 0x3ff;
+        match (self.nametables >> (((addr >> 10) & 0b11) * 2)) & 0b11 {
+            0 => nes.bus_ppu.vram[offset] = data,
+            1 => nes.bus_ppu.vram[0x400 + offset] = data,
+            2 if self.exram_mode <= 1 => self.exram[offset] = data,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn clock(&mut self, nes: &mut Nes<S, A>) -> Result<()> {
+        if !(0..240).contains(&nes.ppu.scan_line) || !nes.ppu.reg_mask.render_enabled() {
+            self.in_frame = false;
+        }
+
+        self.audio.clock();
+        Ok(())
+    }
+
+    fn scanline(&mut self, nes: &mut Nes<S, A>) -> Result<()> {
+        let line = nes.ppu.scan_line;
+        if line == 0 || !self.in_frame {
+            self.in_frame = true;
+            self.scanline = 0;
+            self.irq_pending = false;
+        } else {
+            self.scanline = self.scanline.wrapping_add(1);
+            if self.scanline == self.irq_compare {
+                self.irq_pending = true;
+            }
+        }
+        self.tile_count = 0;
+        self.split_y = ((self.split_scroll as u16 + line as u16) % 240) as u8;
+        Ok(())
+    }
+
+    fn irq(&self) -> bool {
+        (self.irq_enabled && self.irq_pending) || self.audio.irq()
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        Some(self.audio.output())
+    }
+}
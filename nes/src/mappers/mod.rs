@@ -1,18 +1,315 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::anyhow;
 use anyhow::Result;
 
+use self::axrom::Axrom;
+use self::bnrom::Bnrom;
+use self::bnrom::Nina001;
+use self::camerica::Camerica;
+use self::cnrom::Cnrom;
+use self::colordreams::ColorDreams;
+use self::cprom::Cprom;
+use self::fme7::Fme7;
+use self::gxrom::Gxrom;
+use self::mmc1::Mmc1;
+use self::mmc2::Mmc2;
+use self::mmc4::Mmc4;
+use self::mmc5::Mmc5;
+use self::n163::N163;
+use self::namco108::Namco108;
+use self::nrom::Nrom;
+use self::uxrom::Uxrom;
+use self::vrc4::Vrc4;
+use self::vrc6::Vrc6;
+use crate::cartridge::Cartridge;
 use crate::Nes;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Memory {
+    PrgRom,
+    PrgRam,
+    ChrRom,
+    ChrRam,
+}
+
+// What a 1 KB nametable slot is backed by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nametable {
+    // 1 KB page of the console's 2 KB CIRAM
+    Ciram(usize),
+    // 1 KB page of extra VRAM on the cartridge, as on four-screen boards
+    CartVram(usize),
+    // Served by the mapper itself (ExRAM, fill mode...)
+    Mapper,
+}
+
+// A window of the CPU or PPU address space and the memory currently mapped into it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bank {
+    pub addr: u16,
+    pub size: usize,
+    pub memory: Memory,
+    pub offset: usize,
+}
+
+impl Bank {
+    pub fn new(addr: u16, size: usize, memory: Memory, offset: usize) -> Self {
+        Self {
+            addr,
+            size,
+            memory,
+            offset,
+        }
+    }
+
+    pub fn contains(&self, addr: u16) -> bool {
+        (self.addr as usize..self.addr as usize + self.size).contains(&(addr as usize))
+    }
+
+    // Index of this bank counting in its own size, as shown by debuggers
+    pub fn number(&self) -> usize {
+        self.offset / self.size
+    }
+}
+
 pub trait Mapper<S, A> {
     fn name(&self) -> &'static str;
     fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8>;
+    // Same as read_prg but must not change any mapper state
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8>;
     fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()>;
+    // Offset into the cartridge PRG memory a CPU address currently maps to, None for RAM/registers
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize>;
+    // Offset into the cartridge CHR memory a PPU address currently maps to
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize>;
+    // Banks currently mapped in the CPU ($6000-$FFFF) and PPU ($0000-$1FFF) address spaces
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank>;
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank>;
     fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8>;
     fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()>;
     fn reset(&mut self, nes: &mut Nes<S, A>) -> Result<()>;
+
+    // Memory behind the 1 KB nametable slot (0-3) at $2000/$2400/$2800/$2C00
+    fn nametable(&self, nes: &Nes<S, A>, slot: usize) -> Nametable {
+        nes.cartridge.mirroring.nametable(slot)
+    }
+    // Access to slots mapped to Nametable::Mapper
+    fn read_nametable(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(self.peek_nametable(nes, addr))
+    }
+    fn peek_nametable(&self, _nes: &Nes<S, A>, _addr: u16) -> u8 {
+        0
+    }
+    fn write_nametable(&mut self, _nes: &mut Nes<S, A>, _addr: u16, _data: u8) -> Result<()> {
+        Ok(())
+    }
+    // Called once every CPU cycle
+    fn clock(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        Ok(())
+    }
+    // Called by the PPU at the start of every visible scanline while rendering is enabled
+    fn scanline(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        Ok(())
+    }
+    // Write PRG RAM at $6000-$7FFF without touching registers or write protection, for cheats
+    // and memory editors, false when no RAM is mapped there
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, _addr: u16, _data: u8) -> bool {
+        false
+    }
+    // Level of the cartridge IRQ line, true while asserted
+    fn irq(&self) -> bool {
+        false
+    }
+    // Current expansion audio level from 0 to 1, None for boards without sound hardware
+    fn audio_output(&self) -> Option<f32> {
+        None
+    }
+}
+
+// CHR is RAM when the header declares no CHR ROM banks
+pub fn chr_memory<S, A>(nes: &Nes<S, A>) -> Memory {
+    if nes.cartridge.chr_banks == 0 {
+        Memory::ChrRam
+    } else {
+        Memory::ChrRom
+    }
+}
+
+// Discrete logic boards that leave the ROM driving the data bus during register writes see the
+// written value ANDed with the ROM byte at that address, boards without them see it unchanged
+pub fn bus_conflict<S, A>(
+    mapper: &impl Mapper<S, A>,
+    nes: &Nes<S, A>,
+    addr: u16,
+    data: u8,
+    bus_conflicts: bool,
+) -> u8 {
+    match mapper.map_prg(nes, addr) {
+        Some(mapped_addr) if bus_conflicts => data & nes.cartridge.prgmem[mapped_addr],
+        _ => data,
+    }
+}
+
+// Builds the mapper of a cartridge whose header has just been parsed
+pub type MapperFactory<S, A> = Box<dyn Fn(&Cartridge<S, A>) -> Rc<RefCell<dyn Mapper<S, A>>>>;
+
+struct MapperEntry<S, A> {
+    number: u16,
+    // None matches any submapper without an entry of its own
+    submapper: Option<u8>,
+    name: &'static str,
+    factory: MapperFactory<S, A>,
+}
+
+// Mapper constructors keyed by iNES mapper and NES 2.0 submapper number
+pub struct MapperRegistry<S, A> {
+    entries: Vec<MapperEntry<S, A>>,
+}
+
+impl<S, A> MapperRegistry<S, A> {
+    pub fn empty() -> Self {
+        Self { entries: vec![] }
+    }
+
+    // Registering a number again replaces the previous constructor
+    pub fn register<F>(
+        &mut self,
+        number: u16,
+        submapper: Option<u8>,
+        name: &'static str,
+        factory: F,
+    ) where
+        F: Fn(&Cartridge<S, A>) -> Rc<RefCell<dyn Mapper<S, A>>> + 'static,
+    {
+        self.entries
+            .retain(|entry| (entry.number, entry.submapper) != (number, submapper));
+        self.entries.push(MapperEntry {
+            number,
+            submapper,
+            name,
+            factory: Box::new(factory),
+        });
+    }
+
+    pub fn create(&self, cartridge: &Cartridge<S, A>) -> Result<Rc<RefCell<dyn Mapper<S, A>>>> {
+        let number = cartridge.mapper_id;
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.number == number && entry.submapper == Some(cartridge.submapper))
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .find(|entry| entry.number == number && entry.submapper.is_none())
+            })
+            .ok_or_else(|| anyhow!("Mapper {} not supported yet...", number))?;
+        Ok((entry.factory)(cartridge))
+    }
+
+    // Mapper number, submapper and board name of every entry, sorted by number
+    pub fn list(&self) -> Vec<(u16, Option<u8>, &'static str)> {
+        let mut list: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| (entry.number, entry.submapper, entry.name))
+            .collect();
+        list.sort();
+        list
+    }
+}
+
+impl<S, A> Default for MapperRegistry<S, A> {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        // UxROM, CNROM, AxROM and BNROM come with and without bus conflicts, submapper 2 marks
+        // the boards that have them
+        let bus_conflicts = |cartridge: &Cartridge<S, A>| cartridge.submapper == 2;
+
+        registry.register(0, None, "NROM", |_| Rc::new(RefCell::new(Nrom)));
+        registry.register(1, None, "MMC1", |cartridge| {
+            Rc::new(RefCell::new(Mmc1::new(cartridge.prg_ram_size)))
+        });
+        registry.register(2, None, "UxROM", move |cartridge| {
+            Rc::new(RefCell::new(Uxrom::new(bus_conflicts(cartridge))))
+        });
+        registry.register(3, None, "CNROM", move |cartridge| {
+            Rc::new(RefCell::new(Cnrom::new(bus_conflicts(cartridge))))
+        });
+        registry.register(5, None, "MMC5", |_| Rc::new(RefCell::new(Mmc5::new())));
+        registry.register(7, None, "AxROM", move |cartridge| {
+            Rc::new(RefCell::new(Axrom::new(bus_conflicts(cartridge))))
+        });
+        registry.register(9, None, "MMC2", |_| Rc::new(RefCell::new(Mmc2::new())));
+        registry.register(10, None, "MMC4", |_| Rc::new(RefCell::new(Mmc4::new())));
+        // Color Dreams, CPROM and GxROM boards all have bus conflicts
+        registry.register(11, None, "Color Dreams", |_| {
+            Rc::new(RefCell::new(ColorDreams::new(true)))
+        });
+        registry.register(13, None, "CPROM", |_| {
+            Rc::new(RefCell::new(Cprom::new(true)))
+        });
+        registry.register(19, None, "N163", |_| Rc::new(RefCell::new(N163::new())));
+        for (number, name) in [
+            (21, "VRC4"),
+            (22, "VRC2"),
+            (23, "VRC2/VRC4"),
+            (25, "VRC2/VRC4"),
+        ] {
+            registry.register(number, None, name, |cartridge| {
+                let vrc = Vrc4::new(cartridge.mapper_id, cartridge.submapper);
+                Rc::new(RefCell::new(vrc))
+            });
+        }
+        registry.register(24, None, "VRC6", |_| {
+            Rc::new(RefCell::new(Vrc6::new(false)))
+        });
+        registry.register(26, None, "VRC6", |_| Rc::new(RefCell::new(Vrc6::new(true))));
+        // both boards share mapper 34, only NINA-001 has CHR ROM banking
+        registry.register(34, None, "BNROM/NINA-001", move |cartridge| {
+            let nina001 = match cartridge.submapper {
+                1 => true,
+                2 => false,
+                _ => cartridge.chr_banks > 1,
+            };
+            if nina001 {
+                Rc::new(RefCell::new(Nina001::new()))
+            } else {
+                Rc::new(RefCell::new(Bnrom::new(bus_conflicts(cartridge))))
+            }
+        });
+        registry.register(66, None, "GxROM", |_| {
+            Rc::new(RefCell::new(Gxrom::new(true)))
+        });
+        registry.register(69, None, "FME-7", |_| Rc::new(RefCell::new(Fme7::new())));
+        registry.register(71, None, "Camerica", |cartridge| {
+            Rc::new(RefCell::new(Camerica::new(cartridge.submapper == 1)))
+        });
+        registry.register(206, None, "Namco 108", |_| {
+            Rc::new(RefCell::new(Namco108::new()))
+        });
+        registry
+    }
 }
 
+pub mod axrom;
+pub mod bnrom;
+pub mod camerica;
 pub mod cnrom;
+pub mod colordreams;
+pub mod cprom;
+pub mod fds;
+pub mod fme7;
 pub mod gxrom;
 pub mod mmc1;
+pub mod mmc2;
+pub mod mmc4;
+pub mod mmc5;
+pub mod n163;
+pub mod namco108;
 pub mod nrom;
+pub mod nsf;
 pub mod uxrom;
+pub mod vrc4;
+pub mod vrc6;
@@ -0,0 +1,333 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use super::Nametable;
+use crate::mappers;
+use crate::Nes;
+
+// CPU cycles spent on each channel update
+const CHANNEL_CYCLES: u8 = 15;
+
+/*
+    Namco 163 wavetable sound. The 128 bytes of sound RAM hold both 4 bit samples and the
+    registers of up to 8 channels at $40-$7F. A single channel is updated every 15 CPU
+    cycles and drives the output alone until the next one, so the channels are
+    time-multiplexed instead of mixed.
+*/
+pub(crate) struct Namco163Audio {
+    ram: [u8; 0x80],
+    addr: u8,
+    auto_increment: bool,
+    // channel being updated, counting down from 7
+    channel: u8,
+    timer: u8,
+    output: u8,
+}
+
+impl Namco163Audio {
+    pub(crate) fn new() -> Self {
+        Self {
+            ram: [0; 0x80],
+            addr: 0,
+            auto_increment: false,
+            channel: 7,
+            timer: 0,
+            output: 0,
+        }
+    }
+
+    pub(crate) fn write_addr(&mut self, data: u8) {
+        self.addr = data & 0x7f;
+        self.auto_increment = data & 0x80 != 0;
+    }
+
+    pub(crate) fn peek(&self) -> u8 {
+        self.ram[self.addr as usize]
+    }
+
+    pub(crate) fn read(&mut self) -> u8 {
+        let data = self.peek();
+        self.increment();
+        data
+    }
+
+    pub(crate) fn write(&mut self, data: u8) {
+        self.ram[self.addr as usize] = data;
+        self.increment();
+    }
+
+    fn increment(&mut self) {
+        if self.auto_increment {
+            self.addr = (self.addr + 1) & 0x7f;
+        }
+    }
+
+    // Channels 7 down to 7 - count + 1 are enabled
+    fn channel_count(&self) -> u8 {
+        ((self.ram[0x7f] >> 4) & 0b111) + 1
+    }
+
+    pub(crate) fn clock(&mut self) {
+        self.timer += 1;
+        if self.timer < CHANNEL_CYCLES {
+            return;
+        }
+        self.timer = 0;
+
+        let base = 0x40 + self.channel as usize * 8;
+        let regs = &mut self.ram[base..base + 8];
+        let freq = (regs[4] as u32 & 0b11) << 16 | (regs[2] as u32) << 8 | regs[0] as u32;
+        let length = (256 - (regs[4] as u32 & 0xfc)) << 16;
+        let mut phase = (regs[5] as u32) << 16 | (regs[3] as u32) << 8 | regs[1] as u32;
+        phase = (phase + freq) % length;
+        regs[5] = (phase >> 16) as u8;
+        regs[3] = (phase >> 8) as u8;
+        regs[1] = phase as u8;
+
+        let sample_addr = (regs[6] as u32 + (phase >> 16)) as u8;
+        let volume = regs[7] & 0x0f;
+        let byte = self.ram[(sample_addr >> 1) as usize];
+        let sample = if sample_addr & 1 == 0 {
+            byte & 0x0f
+        } else {
+            byte >> 4
+        };
+        self.output = sample * volume;
+
+        self.channel = if self.channel + self.channel_count() <= 8 {
+            7
+        } else {
+            self.channel - 1
+        };
+    }
+
+    pub(crate) fn output(&self) -> f32 {
+        self.output as f32 / 225. * 0.5
+    }
+}
+
+// Mapper 19
+pub struct N163 {
+    prg_banksel: [u8; 3],
+    // pattern tables $0000-$1FFF then nametables $2000-$2FFF, 1 KB each
+    chr_banksel: [u8; 12],
+    // pattern table halves that ignore CIRAM selections, $E800 bits 6 and 7
+    no_ciram_low: bool,
+    no_ciram_high: bool,
+    wram: [u8; 0x2000],
+    irq_counter: u16,
+    irq_enabled: bool,
+    irq_pending: bool,
+    sound_disabled: bool,
+    audio: Namco163Audio,
+}
+
+impl N163 {
+    pub fn new() -> Self {
+        Self {
+            prg_banksel: [0; 3],
+            chr_banksel: [0; 12],
+            no_ciram_low: false,
+            no_ciram_high: false,
+            wram: [0; 0x2000],
+            irq_counter: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            sound_disabled: false,
+            audio: Namco163Audio::new(),
+        }
+    }
+
+    // Banks $E0-$FF select a CIRAM page instead of CHR ROM, unless disabled for that area
+    fn ciram_page(&self, slot: usize) -> Option<usize> {
+        let bank = self.chr_banksel[slot];
+        let disabled = match slot {
+            0..=3 => self.no_ciram_low,
+            4..=7 => self.no_ciram_high,
+            _ => false,
+        };
+        (bank >= 0xe0 && !disabled).then_some(bank as usize & 1)
+    }
+
+    fn chr_rom_offset<S, A>(&self, nes: &Nes<S, A>, slot: usize, addr: u16) -> usize {
+        let banks = (nes.cartridge.chrmem.len() / 0x400).max(1);
+        self.chr_banksel[slot] as usize % banks * 0x400 + (addr as usize & 0x3ff)
+    }
+
+    fn peek_chr<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> u8 {
+        let slot = (addr as usize >> 10) & 7;
+        match self.ciram_page(slot) {
+            Some(page) => nes.bus_ppu.vram[page * 0x400 + (addr as usize & 0x3ff)],
+            None => nes.cartridge.chrmem[self.chr_rom_offset(nes, slot, addr)],
+        }
+    }
+}
+
+impl Default for N163 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A> Mapper<S, A> for N163 {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x4800..=0x4fff => Ok(self.audio.read()),
+            _ => self.peek_prg(nes, addr),
+        }
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x4800..=0x4fff => Ok(self.audio.peek()),
+            0x5000..=0x57ff => Ok(self.irq_counter as u8),
+            0x5800..=0x5fff => Ok((self.irq_counter >> 8) as u8 | (self.irq_enabled as u8) << 7),
+            0x6000..=0x7fff => Ok(self.wram[(addr & 0x1fff) as usize]),
+            _ => match self.map_prg(nes, addr) {
+                Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+                None => {
+                    log::warn!("Cannot read at PRG address {:#x} for N163", addr);
+                    Ok(0)
+                }
+            },
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let banks = (nes.cartridge.prgmem.len() / 0x2000).max(1);
+        let bank = match addr {
+            0x8000..=0xdfff => self.prg_banksel[((addr - 0x8000) >> 13) as usize] as usize,
+            0xe000..=0xffff => banks - 1,
+            _ => return None,
+        };
+        Some(bank % banks * 0x2000 + (addr as usize & 0x1fff))
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let slot = (addr as usize >> 10) & 7;
+        match self.ciram_page(slot) {
+            Some(_) => None,
+            None => Some(self.chr_rom_offset(nes, slot, addr)),
+        }
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let mut banks = vec![Bank::new(0x6000, 0x2000, Memory::PrgRam, 0)];
+        for addr in [0x8000, 0xa000, 0xc000, 0xe000] {
+            if let Some(offset) = self.map_prg(nes, addr) {
+                banks.push(Bank::new(addr, 0x2000, Memory::PrgRom, offset));
+            }
+        }
+        banks
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        // CIRAM mapped as pattern memory has no Memory kind and is left out
+        (0..8)
+            .filter_map(|i| {
+                let addr = i * 0x400;
+                let offset = self.map_chr(nes, addr)?;
+                Some(Bank::new(addr, 0x400, mappers::chr_memory(nes), offset))
+            })
+            .collect()
+    }
+
+    fn write_prg(&mut self, _nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x4800..=0x4fff => self.audio.write(data),
+            0x5000..=0x57ff => {
+                self.irq_counter = (self.irq_counter & 0x7f00) | data as u16;
+                self.irq_pending = false;
+            }
+            0x5800..=0x5fff => {
+                self.irq_counter = (self.irq_counter & 0x00ff) | ((data & 0x7f) as u16) << 8;
+                self.irq_enabled = data & 0x80 != 0;
+                self.irq_pending = false;
+            }
+            0x6000..=0x7fff => self.wram[(addr & 0x1fff) as usize] = data,
+            0x8000..=0xdfff => self.chr_banksel[((addr - 0x8000) >> 11) as usize] = data,
+            0xe000..=0xe7ff => {
+                self.prg_banksel[0] = data & 0x3f;
+                self.sound_disabled = data & 0x40 != 0;
+            }
+            0xe800..=0xefff => {
+                self.prg_banksel[1] = data & 0x3f;
+                self.no_ciram_low = data & 0x40 != 0;
+                self.no_ciram_high = data & 0x80 != 0;
+            }
+            0xf000..=0xf7ff => self.prg_banksel[2] = data & 0x3f,
+            0xf800..=0xffff => self.audio.write_addr(data),
+            _ => log::warn!("Cannot write at PRG address {:#x} for N163", addr),
+        }
+        Ok(())
+    }
+
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        self.wram[(addr & 0x1fff) as usize] = data;
+        true
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(self.peek_chr(nes, addr))
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        let slot = (addr as usize >> 10) & 7;
+        match self.ciram_page(slot) {
+            Some(page) => nes.bus_ppu.vram[page * 0x400 + (addr as usize & 0x3ff)] = data,
+            None if nes.cartridge.chr_banks == 0 => {
+                let mapped_addr = self.chr_rom_offset(nes, slot, addr);
+                nes.cartridge.chrmem[mapped_addr] = data;
+            }
+            None => log::warn!("Cannot write at CHR address {:#x} for N163", addr),
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.irq_enabled = false;
+        self.irq_pending = false;
+        self.audio = Namco163Audio::new();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "N163"
+    }
+
+    fn nametable(&self, _nes: &Nes<S, A>, slot: usize) -> Nametable {
+        match self.ciram_page(8 + slot) {
+            Some(page) => Nametable::Ciram(page),
+            None => Nametable::Mapper,
+        }
+    }
+
+    // Nametables mapped to CHR ROM
+    fn peek_nametable(&self, nes: &Nes<S, A>, addr: u16) -> u8 {
+        let slot = 8 + ((addr as usize >> 10) & 0b11);
+        nes.cartridge.chrmem[self.chr_rom_offset(nes, slot, addr)]
+    }
+
+    fn clock(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        if self.irq_enabled && self.irq_counter < 0x7fff {
+            self.irq_counter += 1;
+            if self.irq_counter == 0x7fff {
+                self.irq_pending = true;
+            }
+        }
+        if !self.sound_disabled {
+            self.audio.clock();
+        }
+        Ok(())
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        Some(self.audio.output())
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::mappers;
+use crate::Nes;
+
+// Mapper 206, the MMC3 predecessor without IRQ or mirroring control
+pub struct Namco108 {
+    bank_select: u8,
+    // R0-R1 2 KB CHR banks, R2-R5 1 KB CHR banks, R6-R7 8 KB PRG banks
+    regs: [u8; 8],
+}
+
+impl Namco108 {
+    pub fn new() -> Self {
+        Self {
+            bank_select: 0,
+            regs: [0; 8],
+        }
+    }
+}
+
+impl Default for Namco108 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A> Mapper<S, A> for Namco108 {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match self.map_prg(nes, addr) {
+            Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+            None => {
+                log::warn!("Cannot read at PRG address {:#x} for Namco 108", addr);
+                Ok(0)
+            }
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let banks = (nes.cartridge.prgmem.len() / 0x2000).max(2);
+        let bank = match addr {
+            0x8000..=0x9fff => self.regs[6] as usize,
+            0xa000..=0xbfff => self.regs[7] as usize,
+            0xc000..=0xdfff => banks - 2,
+            0xe000..=0xffff => banks - 1,
+            _ => return None,
+        };
+        Some(bank % banks * 0x2000 + (addr as usize & 0x1fff))
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let bank = match addr {
+            // 2 KB banks ignore the low bit
+            0x0000..=0x07ff => (self.regs[0] & 0x3e) as usize,
+            0x0800..=0x0fff => (self.regs[1] & 0x3e) as usize,
+            _ => self.regs[2 + ((addr as usize >> 10) & 0b11)] as usize,
+        };
+        let offset = match addr {
+            0x0000..=0x0fff => addr as usize & 0x07ff,
+            _ => addr as usize & 0x03ff,
+        };
+        let banks = (nes.cartridge.chrmem.len() / 0x400).max(1);
+        Some((bank % banks) * 0x400 + offset)
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        [0x8000, 0xa000, 0xc000, 0xe000]
+            .into_iter()
+            .filter_map(|addr| {
+                let offset = self.map_prg(nes, addr)?;
+                Some(Bank::new(addr, 0x2000, Memory::PrgRom, offset))
+            })
+            .collect()
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let memory = mappers::chr_memory(nes);
+        let mut banks = vec![];
+        for addr in [0x0000, 0x0800] {
+            if let Some(offset) = self.map_chr(nes, addr) {
+                banks.push(Bank::new(addr, 0x800, memory, offset));
+            }
+        }
+        for addr in [0x1000, 0x1400, 0x1800, 0x1c00] {
+            if let Some(offset) = self.map_chr(nes, addr) {
+                banks.push(Bank::new(addr, 0x400, memory, offset));
+            }
+        }
+        banks
+    }
+
+    fn write_prg(&mut self, _nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x8000..=0x9fff if addr & 1 == 0 => self.bank_select = data & 0b111,
+            0x8000..=0x9fff => {
+                let mask = if self.bank_select >= 6 { 0x0f } else { 0x3f };
+                self.regs[self.bank_select as usize] = data & mask;
+            }
+            _ => log::warn!("Cannot write at PRG address {:#x} for Namco 108", addr),
+        }
+        Ok(())
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        let mapped_addr = self.map_chr(nes, addr).unwrap_or(0);
+        Ok(nes.cartridge.chrmem[mapped_addr])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            let mapped_addr = self.map_chr(nes, addr).unwrap_or(0);
+            nes.cartridge.chrmem[mapped_addr] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for Namco 108", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.bank_select = 0;
+        self.regs = [0; 8];
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Namco 108"
+    }
+}
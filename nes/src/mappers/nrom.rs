@@ -1,6 +1,9 @@
 use anyhow::Result;
 
+use super::Bank;
 use super::Mapper;
+use super::Memory;
+use crate::mappers;
 use crate::Nes;
 
 // Mapper 0
@@ -8,16 +11,41 @@ pub struct Nrom;
 
 impl<S, A> Mapper<S, A> for Nrom {
     fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
-        let mapped_addr = if 0x8000 <= addr {
-            match nes.cartridge.prg_banks {
-                1 => addr & 0x3fff,
-                2 => addr & 0x7fff,
-                _ => 0,
-            }
-        } else {
-            0
-        };
-        Ok(nes.cartridge.prgmem[mapped_addr as usize])
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        let mapped_addr = self.map_prg(nes, addr).unwrap_or(0);
+        Ok(nes.cartridge.prgmem[mapped_addr])
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        if addr < 0x8000 {
+            return None;
+        }
+        match nes.cartridge.prg_banks {
+            1 => Some(addr as usize & 0x3fff),
+            2 => Some(addr as usize & 0x7fff),
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(addr as usize & 0x1fff)
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        match nes.cartridge.prg_banks {
+            1 => vec![
+                Bank::new(0x8000, 0x4000, Memory::PrgRom, 0),
+                Bank::new(0xc000, 0x4000, Memory::PrgRom, 0),
+            ],
+            _ => vec![Bank::new(0x8000, 0x8000, Memory::PrgRom, 0)],
+        }
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        vec![Bank::new(0x0000, 0x2000, mappers::chr_memory(nes), 0)]
     }
 
     fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
@@ -0,0 +1,272 @@
+use anyhow::Result;
+
+use super::fds::FdsAudio;
+use super::fme7::Sunsoft5b;
+use super::mmc5::Mmc5Audio;
+use super::n163::Namco163Audio;
+use super::vrc6::Vrc6Audio;
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::nsf;
+use crate::nsf::Nsf;
+use crate::Nes;
+
+// JMP to itself, where INIT and PLAY return to
+const DRIVER: [u8; 3] = [0x4c, nsf::DRIVER_ADDR as u8, (nsf::DRIVER_ADDR >> 8) as u8];
+
+/*
+    Player for NSF tunes. The program is split in 4 KB pages mapped to $8000-$FFFF by the
+    $5FF8-$5FFF registers, or copied into RAM covering $6000-$FFFF for FDS tunes, where
+    $5FF6-$5FF7 fill $6000-$7FFF too. Once INIT returns the CPU spins at $4100, and PLAY
+    calls are only started from there so a slow PLAY routine is never cut short.
+*/
+pub struct NsfPlayer {
+    chips: u8,
+    // 4 KB banks of $6000-$FFFF, the first two only used by FDS tunes
+    init_banks: [u8; 10],
+    banks: [u8; 10],
+    fds_ram: bool,
+    ram: Vec<u8>,
+    exram: [u8; 0x400],
+    multiplier: (u8, u8),
+    play_addr: u16,
+    play_period: u32,
+    play_timer: u32,
+    vrc6: Vrc6Audio,
+    fds: FdsAudio,
+    mmc5: Mmc5Audio,
+    n163: Namco163Audio,
+    s5b: Sunsoft5b,
+    s5b_reg: u8,
+}
+
+impl NsfPlayer {
+    pub fn new(nsf: &Nsf) -> Self {
+        let fds_ram = nsf.chips & nsf::CHIP_FDS != 0;
+        let mut init_banks = [0; 10];
+        match nsf.banks {
+            Some(banks) => {
+                init_banks[0] = banks[6];
+                init_banks[1] = banks[7];
+                init_banks[2..].copy_from_slice(&banks);
+            }
+            // pages are counted from $6000 for FDS tunes and from $8000 otherwise
+            None => {
+                let first = if fds_ram { 0 } else { 2 };
+                for (page, bank) in init_banks[first..].iter_mut().enumerate() {
+                    *bank = page as u8;
+                }
+            }
+        }
+
+        Self {
+            chips: nsf.chips,
+            init_banks,
+            banks: init_banks,
+            fds_ram,
+            ram: vec![0; if fds_ram { 0xa000 } else { 0x2000 }],
+            exram: [0; 0x400],
+            multiplier: (0, 0),
+            play_addr: nsf.play_addr,
+            play_period: nsf.play_period(),
+            play_timer: 0,
+            vrc6: Vrc6Audio::default(),
+            fds: FdsAudio::new(),
+            mmc5: Mmc5Audio::default(),
+            n163: Namco163Audio::new(),
+            s5b: Sunsoft5b::new(),
+            s5b_reg: 0,
+        }
+    }
+
+    fn has(&self, chip: u8) -> bool {
+        self.chips & chip != 0
+    }
+
+    fn page_offset<S, A>(&self, nes: &Nes<S, A>, slot: usize) -> Option<usize> {
+        let offset = self.banks[slot] as usize * 0x1000;
+        (offset < nes.cartridge.prgmem.len()).then_some(offset)
+    }
+
+    fn select_bank<S, A>(&mut self, nes: &Nes<S, A>, slot: usize, bank: u8) {
+        self.banks[slot] = bank;
+        if self.fds_ram {
+            let offset = self.page_offset(nes, slot);
+            let page = &mut self.ram[slot * 0x1000..(slot + 1) * 0x1000];
+            match offset {
+                Some(offset) => {
+                    page.copy_from_slice(&nes.cartridge.prgmem[offset..offset + 0x1000])
+                }
+                None => page.fill(0),
+            }
+        }
+    }
+}
+
+impl<S, A> Mapper<S, A> for NsfPlayer {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x4800 if self.has(nsf::CHIP_N163) => Ok(self.n163.read()),
+            0x5010 if self.has(nsf::CHIP_MMC5) => Ok(self.mmc5.read(addr)),
+            _ => self.peek_prg(nes, addr),
+        }
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        let product = self.multiplier.0 as u16 * self.multiplier.1 as u16;
+        match addr {
+            0x4040..=0x4097 if self.has(nsf::CHIP_FDS) => Ok(self.fds.read(addr)),
+            0x4100..=0x4102 => Ok(DRIVER[(addr - nsf::DRIVER_ADDR) as usize]),
+            0x4800 if self.has(nsf::CHIP_N163) => Ok(self.n163.peek()),
+            0x5010 | 0x5015 if self.has(nsf::CHIP_MMC5) => Ok(self.mmc5.peek(addr)),
+            0x5205 if self.has(nsf::CHIP_MMC5) => Ok(product as u8),
+            0x5206 if self.has(nsf::CHIP_MMC5) => Ok((product >> 8) as u8),
+            0x5c00..=0x5ff5 if self.has(nsf::CHIP_MMC5) => Ok(self.exram[addr as usize & 0x3ff]),
+            0x6000..=0x7fff => Ok(self.ram[addr as usize - 0x6000]),
+            0x8000..=0xffff if self.fds_ram => Ok(self.ram[addr as usize - 0x6000]),
+            _ => match self.map_prg(nes, addr) {
+                Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+                None => {
+                    log::warn!("Cannot read at PRG address {:#x} for NSF", addr);
+                    Ok(0)
+                }
+            },
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x8000..=0xffff if !self.fds_ram => {
+                let slot = (addr as usize - 0x6000) >> 12;
+                Some(self.page_offset(nes, slot)? + (addr as usize & 0x0fff))
+            }
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(addr as usize & 0x1fff)
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let mut layout = vec![Bank::new(0x6000, self.ram.len(), Memory::PrgRam, 0)];
+        if !self.fds_ram {
+            for slot in 2..10 {
+                let offset = self.page_offset(nes, slot).unwrap_or(0);
+                let addr = 0x6000 + slot as u16 * 0x1000;
+                layout.push(Bank::new(addr, 0x1000, Memory::PrgRom, offset));
+            }
+        }
+        layout
+    }
+
+    fn chr_layout(&self, _nes: &Nes<S, A>) -> Vec<Bank> {
+        vec![Bank::new(0x0000, 0x2000, Memory::ChrRam, 0)]
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        match addr {
+            0x4040..=0x408a if self.has(nsf::CHIP_FDS) => self.fds.write(addr, data),
+            0x4800 if self.has(nsf::CHIP_N163) => self.n163.write(data),
+            0x5000..=0x5015 if self.has(nsf::CHIP_MMC5) => self.mmc5.write(addr, data),
+            0x5205 if self.has(nsf::CHIP_MMC5) => self.multiplier.0 = data,
+            0x5206 if self.has(nsf::CHIP_MMC5) => self.multiplier.1 = data,
+            0x5c00..=0x5ff5 if self.has(nsf::CHIP_MMC5) => self.exram[addr as usize & 0x3ff] = data,
+            0x5ff6..=0x5fff => self.select_bank(nes, addr as usize - 0x5ff6, data),
+            0x6000..=0x7fff => self.ram[addr as usize - 0x6000] = data,
+            0x9000..=0x9003 | 0xa000..=0xa002 | 0xb000..=0xb002 if self.has(nsf::CHIP_VRC6) => {
+                self.vrc6.write(addr, addr & 0x03, data)
+            }
+            0x9010 | 0x9030 if self.has(nsf::CHIP_VRC7) => {}
+            0xc000 if self.has(nsf::CHIP_S5B) => self.s5b_reg = data & 0x0f,
+            0xe000 if self.has(nsf::CHIP_S5B) => self.s5b.write(self.s5b_reg, data),
+            0xf800 if self.has(nsf::CHIP_N163) => self.n163.write_addr(data),
+            0x8000..=0xffff if self.fds_ram => self.ram[addr as usize - 0x6000] = data,
+            _ => log::warn!("Cannot write at PRG address {:#x} for NSF", addr),
+        }
+        Ok(())
+    }
+
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        self.ram[addr as usize - 0x6000] = data;
+        true
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(nes.cartridge.chrmem[addr as usize & 0x1fff])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        nes.cartridge.chrmem[addr as usize & 0x1fff] = data;
+        Ok(())
+    }
+
+    fn reset(&mut self, nes: &mut Nes<S, A>) -> Result<()> {
+        self.ram.fill(0);
+        self.exram = [0; 0x400];
+        for slot in 0..10 {
+            self.select_bank(nes, slot, self.init_banks[slot]);
+        }
+        self.play_timer = self.play_period;
+        self.vrc6 = Vrc6Audio::default();
+        self.fds = FdsAudio::new();
+        self.mmc5 = Mmc5Audio::default();
+        self.n163 = Namco163Audio::new();
+        self.s5b = Sunsoft5b::new();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "NSF"
+    }
+
+    fn clock(&mut self, nes: &mut Nes<S, A>) -> Result<()> {
+        if self.has(nsf::CHIP_VRC6) {
+            self.vrc6.clock();
+        }
+        if self.has(nsf::CHIP_FDS) {
+            self.fds.clock();
+        }
+        if self.has(nsf::CHIP_MMC5) {
+            self.mmc5.clock();
+        }
+        if self.has(nsf::CHIP_N163) {
+            self.n163.clock();
+        }
+        if self.has(nsf::CHIP_S5B) {
+            self.s5b.clock();
+        }
+
+        // the first PLAY comes a period after INIT was called, or as soon as it returns
+        self.play_timer = self.play_timer.saturating_sub(1);
+        let idle =
+            nes.cpu.cycles == 0 && (nsf::DRIVER_ADDR..nsf::DRIVER_ADDR + 3).contains(&nes.cpu.pc);
+        if idle && self.play_timer == 0 {
+            self.play_timer = self.play_period;
+            nsf::call(nes, self.play_addr);
+        }
+        Ok(())
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        // always some output, so frontends keep getting samples for the APU channels
+        let mut level = 0.;
+        if self.has(nsf::CHIP_VRC6) {
+            level += self.vrc6.output();
+        }
+        if self.has(nsf::CHIP_FDS) {
+            level += self.fds.output() * 0.5;
+        }
+        if self.has(nsf::CHIP_MMC5) {
+            level += self.mmc5.output();
+        }
+        if self.has(nsf::CHIP_N163) {
+            level += self.n163.output();
+        }
+        if self.has(nsf::CHIP_S5B) {
+            level += self.s5b.output() * 0.5;
+        }
+        Some(level)
+    }
+}
@@ -1,31 +1,76 @@
 use anyhow::anyhow;
 use anyhow::Result;
 
+use super::Bank;
 use super::Mapper;
+use super::Memory;
+use crate::mappers;
 use crate::Nes;
 
 // Mapper 2
 #[derive(Default)]
 pub struct Uxrom {
     banksel: u8,
+    bus_conflicts: bool,
+}
+
+impl Uxrom {
+    pub fn new(bus_conflicts: bool) -> Self {
+        Self {
+            banksel: 0,
+            bus_conflicts,
+        }
+    }
 }
 
 impl<S, A> Mapper<S, A> for Uxrom {
     fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
-        let mapped_addr = match addr {
-            0x8000..=0xbfff => (self.banksel as u64) * 0x4000 + (addr & 0x3fff) as u64,
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        let mapped_addr = self
+            .map_prg(nes, addr)
+            .ok_or_else(|| anyhow!("Cannot read at PRG address {:#x} for UXROM", addr))?;
+        Ok(nes.cartridge.prgmem[mapped_addr])
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        match addr {
+            0x8000..=0xbfff => Some(self.banksel as usize * 0x4000 + (addr as usize & 0x3fff)),
             0xc000..=0xffff => {
-                let prgbank_size: u16 = nes.cartridge.prg_banks as u16 - 1;
-                (prgbank_size as u64) * 0x4000 + (addr & 0x3fff) as u64
+                let last_bank = (nes.cartridge.prg_banks as usize).saturating_sub(1);
+                Some(last_bank * 0x4000 + (addr as usize & 0x3fff))
             }
-            _ => Err(anyhow!("Cannot read at PRG address {:#x} for UXROM", addr))?,
-        };
-        Ok(nes.cartridge.prgmem[mapped_addr as usize])
+            _ => None,
+        }
+    }
+
+    fn map_chr(&self, _nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(addr as usize & 0x1fff)
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let last_bank = (nes.cartridge.prg_banks as usize).saturating_sub(1);
+        vec![
+            Bank::new(
+                0x8000,
+                0x4000,
+                Memory::PrgRom,
+                self.banksel as usize * 0x4000,
+            ),
+            Bank::new(0xc000, 0x4000, Memory::PrgRom, last_bank * 0x4000),
+        ]
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        vec![Bank::new(0x0000, 0x2000, mappers::chr_memory(nes), 0)]
     }
 
-    fn write_prg(&mut self, _nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
         match addr {
             0x8000..=0xffff => {
+                let data = mappers::bus_conflict(self, nes, addr, data, self.bus_conflicts);
                 self.banksel = data & 0x0f;
             }
             _ => log::warn!("Cannot write at PRG address {:#x} for UXROM", addr),
@@ -0,0 +1,303 @@
+use anyhow::Result;
+
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::cartridge::Mirroring;
+use crate::mappers;
+use crate::Nes;
+
+/*
+    IRQ counter shared by the Konami VRC4, VRC6 and VRC7. In scanline mode a prescaler
+    approximates the 341 PPU dots of a line by counting CPU cycles three dots at a time, in
+    cycle mode the counter ticks every CPU cycle. The IRQ fires when the 8 bit counter
+    overflows, reloading it from the latch.
+*/
+#[derive(Default)]
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enable_after_ack: bool,
+    cycle_mode: bool,
+    pub pending: bool,
+}
+
+impl VrcIrq {
+    pub fn write_latch_low(&mut self, data: u8) {
+        self.latch = (self.latch & 0xf0) | (data & 0x0f);
+    }
+
+    pub fn write_latch_high(&mut self, data: u8) {
+        self.latch = (self.latch & 0x0f) | (data << 4);
+    }
+
+    pub fn write_latch(&mut self, data: u8) {
+        self.latch = data;
+    }
+
+    pub fn write_control(&mut self, data: u8) {
+        self.enable_after_ack = data & 0x01 != 0;
+        self.enabled = data & 0x02 != 0;
+        self.cycle_mode = data & 0x04 != 0;
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = 341;
+        }
+        self.pending = false;
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.pending = false;
+        self.enabled = self.enable_after_ack;
+    }
+
+    // Called once every CPU cycle
+    pub fn clock(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        if self.cycle_mode {
+            self.tick();
+        } else {
+            self.prescaler -= 3;
+            if self.prescaler <= 0 {
+                self.prescaler += 341;
+                self.tick();
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.counter == 0xff {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+/*
+    Boards connect different CPU address lines to the two register select pins of the chip.
+    Each mask lists the address bits wired to one pin, several bits when the board variant
+    is unknown and the usual wirings of the mapper number are merged.
+*/
+struct Wiring {
+    a0: u16,
+    a1: u16,
+}
+
+impl Wiring {
+    fn reg(&self, addr: u16) -> u16 {
+        (addr & self.a0 != 0) as u16 | ((addr & self.a1 != 0) as u16) << 1
+    }
+}
+
+// Mappers 21, 22, 23 and 25
+pub struct Vrc4 {
+    wiring: Wiring,
+    vrc2: bool,
+    // VRC2a ignores the low bit of its CHR bank numbers
+    chr_shift: u8,
+    prg_banksel: [u8; 2],
+    prg_swap: bool,
+    chr_banksel: [u16; 8],
+    wram: [u8; 0x2000],
+    // single bit of RAM the VRC2 exposes at $6000-$7FFF
+    latch: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+    pub fn new(mapper_id: u16, submapper: u8) -> Self {
+        let (a0, a1, vrc2) = match (mapper_id, submapper) {
+            // VRC4a, VRC4c
+            (21, 1) => (0x02, 0x04, false),
+            (21, 2) => (0x40, 0x80, false),
+            (21, _) => (0x42, 0x84, false),
+            // VRC2a
+            (22, _) => (0x02, 0x01, true),
+            // VRC4f, VRC4e, VRC2b
+            (23, 1) => (0x01, 0x02, false),
+            (23, 2) => (0x04, 0x08, false),
+            (23, 3) => (0x01, 0x02, true),
+            (23, _) => (0x05, 0x0a, false),
+            // VRC4b, VRC4d, VRC2c
+            (25, 1) => (0x02, 0x01, false),
+            (25, 2) => (0x08, 0x04, false),
+            (25, 3) => (0x02, 0x01, true),
+            (_, _) => (0x0a, 0x05, false),
+        };
+        Self {
+            wiring: Wiring { a0, a1 },
+            vrc2,
+            chr_shift: (mapper_id == 22) as u8,
+            prg_banksel: [0; 2],
+            prg_swap: false,
+            chr_banksel: [0; 8],
+            wram: [0; 0x2000],
+            latch: 0,
+            irq: VrcIrq::default(),
+        }
+    }
+
+    fn chr_offset<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> usize {
+        let banks = (nes.cartridge.chrmem.len() / 0x400).max(1);
+        let bank = (self.chr_banksel[(addr as usize >> 10) & 7] >> self.chr_shift) as usize;
+        bank % banks * 0x400 + (addr as usize & 0x3ff)
+    }
+
+    fn write_chr_bank(&mut self, reg: u16, data: u8) {
+        let bank = &mut self.chr_banksel[reg as usize >> 1];
+        *bank = if reg & 1 == 0 {
+            (*bank & 0x1f0) | (data & 0x0f) as u16
+        } else {
+            (*bank & 0x0f) | ((data & 0x1f) as u16) << 4
+        };
+    }
+}
+
+impl<S, A> Mapper<S, A> for Vrc4 {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x6000..=0x7fff if self.vrc2 => Ok(0x60 | self.latch),
+            0x6000..=0x7fff => Ok(self.wram[(addr & 0x1fff) as usize]),
+            _ => match self.map_prg(nes, addr) {
+                Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+                None => {
+                    log::warn!("Cannot read at PRG address {:#x} for VRC", addr);
+                    Ok(0)
+                }
+            },
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let banks = (nes.cartridge.prgmem.len() / 0x2000).max(2);
+        let second_last = banks - 2;
+        let bank = match (addr, self.prg_swap) {
+            (0x8000..=0x9fff, false) | (0xc000..=0xdfff, true) => self.prg_banksel[0] as usize,
+            (0x8000..=0x9fff, true) | (0xc000..=0xdfff, false) => second_last,
+            (0xa000..=0xbfff, _) => self.prg_banksel[1] as usize,
+            (0xe000..=0xffff, _) => banks - 1,
+            _ => return None,
+        };
+        Some(bank % banks * 0x2000 + (addr as usize & 0x1fff))
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(self.chr_offset(nes, addr))
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let mut banks = vec![];
+        if !self.vrc2 {
+            banks.push(Bank::new(0x6000, 0x2000, Memory::PrgRam, 0));
+        }
+        for addr in [0x8000, 0xa000, 0xc000, 0xe000] {
+            if let Some(offset) = self.map_prg(nes, addr) {
+                banks.push(Bank::new(addr, 0x2000, Memory::PrgRom, offset));
+            }
+        }
+        banks
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        (0..8)
+            .map(|i| {
+                let addr = i * 0x400;
+                let offset = self.chr_offset(nes, addr);
+                Bank::new(addr, 0x400, mappers::chr_memory(nes), offset)
+            })
+            .collect()
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        let reg = self.wiring.reg(addr);
+        match (addr & 0xf000, reg) {
+            (0x6000 | 0x7000, _) if self.vrc2 => self.latch = data & 0x01,
+            (0x6000 | 0x7000, _) => self.wram[(addr & 0x1fff) as usize] = data,
+            (0x8000, _) => self.prg_banksel[0] = data & 0x1f,
+            (0x9000, _) if self.vrc2 => {
+                nes.cartridge.mirroring = if data & 0x01 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+            }
+            (0x9000, 0) => {
+                nes.cartridge.mirroring = match data & 0b11 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::OneScreenNT0,
+                    _ => Mirroring::OneScreenNT1,
+                };
+            }
+            (0x9000, 2) if !self.vrc2 => self.prg_swap = data & 0x02 != 0,
+            (0xa000, _) => self.prg_banksel[1] = data & 0x1f,
+            (0xb000..=0xe000, _) => {
+                // two registers per 1 KB bank, four per $1000 of address space
+                let reg = ((addr & 0xf000) - 0xb000) >> 10 | reg;
+                self.write_chr_bank(reg, data);
+            }
+            (0xf000, 0) if !self.vrc2 => self.irq.write_latch_low(data),
+            (0xf000, 1) if !self.vrc2 => self.irq.write_latch_high(data),
+            (0xf000, 2) if !self.vrc2 => self.irq.write_control(data),
+            (0xf000, 3) if !self.vrc2 => self.irq.acknowledge(),
+            _ => log::warn!("Cannot write at PRG address {:#x} for VRC", addr),
+        }
+        Ok(())
+    }
+
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        if self.vrc2 {
+            return false;
+        }
+        self.wram[(addr & 0x1fff) as usize] = data;
+        true
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(nes.cartridge.chrmem[self.chr_offset(nes, addr)])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            let mapped_addr = self.chr_offset(nes, addr);
+            nes.cartridge.chrmem[mapped_addr] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for VRC", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.prg_banksel = [0; 2];
+        self.prg_swap = false;
+        self.irq = VrcIrq::default();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        if self.vrc2 {
+            "VRC2"
+        } else {
+            "VRC4"
+        }
+    }
+
+    fn clock(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.irq.clock();
+        Ok(())
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending
+    }
+}
@@ -0,0 +1,328 @@
+use anyhow::Result;
+
+use super::vrc4::VrcIrq;
+use super::Bank;
+use super::Mapper;
+use super::Memory;
+use crate::cartridge::Mirroring;
+use crate::mappers;
+use crate::Nes;
+
+#[derive(Default)]
+struct Pulse {
+    enabled: bool,
+    // ignore the duty cycle and output the volume constantly
+    digitized: bool,
+    duty: u8,
+    volume: u8,
+    period: u16,
+    timer: u16,
+    // counts down from 15, the output is on while it is at or below the duty
+    step: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => {
+                self.digitized = data & 0x80 != 0;
+                self.duty = (data >> 4) & 0b111;
+                self.volume = data & 0x0f;
+            }
+            1 => self.period = (self.period & 0x0f00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00ff) | ((data & 0x0f) as u16) << 8;
+                self.enabled = data & 0x80 != 0;
+                if !self.enabled {
+                    self.step = 15;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step = self.step.wrapping_sub(1) & 0x0f;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.enabled && (self.digitized || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Default)]
+struct Sawtooth {
+    enabled: bool,
+    rate: u8,
+    period: u16,
+    timer: u16,
+    // the accumulator grows on every other of 14 steps, then restarts from 0
+    step: u8,
+    accumulator: u8,
+}
+
+impl Sawtooth {
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => self.rate = data & 0x3f,
+            1 => self.period = (self.period & 0x0f00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00ff) | ((data & 0x0f) as u16) << 8;
+                self.enabled = data & 0x80 != 0;
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.period >> shift;
+        self.step += 1;
+        if self.step == 14 {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step & 1 == 0 {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+// Two pulses and a sawtooth, also used by the NSF player
+#[derive(Default)]
+pub(crate) struct Vrc6Audio {
+    pulses: [Pulse; 2],
+    saw: Sawtooth,
+    halt: bool,
+    // $9003 frequency scaling, periods are shifted right by 4 or 8
+    freq_shift: u8,
+}
+
+impl Vrc6Audio {
+    // Register reg of the $9000, $A000 or $B000 group, with the lines already swapped
+    pub(crate) fn write(&mut self, addr: u16, reg: u16, data: u8) {
+        match (addr & 0xf000, reg) {
+            (0x9000, 3) => {
+                self.halt = data & 0x01 != 0;
+                self.freq_shift = if data & 0x04 != 0 {
+                    8
+                } else if data & 0x02 != 0 {
+                    4
+                } else {
+                    0
+                };
+            }
+            (0x9000, _) => self.pulses[0].write(reg, data),
+            (0xa000, 0..=2) => self.pulses[1].write(reg, data),
+            (0xb000, 0..=2) => self.saw.write(reg, data),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn clock(&mut self) {
+        if !self.halt {
+            for pulse in &mut self.pulses {
+                pulse.clock(self.freq_shift);
+            }
+            self.saw.clock(self.freq_shift);
+        }
+    }
+
+    pub(crate) fn output(&self) -> f32 {
+        // 6 bit linear mix, at full volume about twice as loud as the two APU pulses
+        let level = self.pulses[0].output() + self.pulses[1].output() + self.saw.output();
+        level as f32 / 61. * 0.5
+    }
+}
+
+// Mappers 24 and 26
+pub struct Vrc6 {
+    // mapper 26 boards swap the A0 and A1 lines
+    swap_lines: bool,
+    prg_banksel_16: u8,
+    prg_banksel_8: u8,
+    chr_mode: u8,
+    chr_banksel: [u8; 8],
+    wram: [u8; 0x2000],
+    irq: VrcIrq,
+    audio: Vrc6Audio,
+}
+
+impl Vrc6 {
+    pub fn new(swap_lines: bool) -> Self {
+        Self {
+            swap_lines,
+            prg_banksel_16: 0,
+            prg_banksel_8: 0,
+            chr_mode: 0,
+            chr_banksel: [0; 8],
+            wram: [0; 0x2000],
+            irq: VrcIrq::default(),
+            audio: Vrc6Audio::default(),
+        }
+    }
+
+    fn chr_offset<S, A>(&self, nes: &Nes<S, A>, addr: u16) -> usize {
+        let slot = (addr as usize >> 10) & 7;
+        // 1 KB banks, or 2 KB banks where the low bit comes from the address
+        let (bank, size) = match (self.chr_mode, slot) {
+            (0, _) | (2 | 3, 0..=3) => (self.chr_banksel[slot], 0x400),
+            (1, _) => (self.chr_banksel[slot >> 1], 0x800),
+            _ => (self.chr_banksel[4 + ((slot >> 1) & 1)], 0x800),
+        };
+        let banks = (nes.cartridge.chrmem.len() / size).max(1);
+        bank as usize % banks * size + (addr as usize & (size - 1))
+    }
+}
+
+impl<S, A> Mapper<S, A> for Vrc6 {
+    fn read_prg(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        self.peek_prg(nes, addr)
+    }
+
+    fn peek_prg(&self, nes: &Nes<S, A>, addr: u16) -> Result<u8> {
+        match addr {
+            0x6000..=0x7fff => Ok(self.wram[(addr & 0x1fff) as usize]),
+            _ => match self.map_prg(nes, addr) {
+                Some(mapped_addr) => Ok(nes.cartridge.prgmem[mapped_addr]),
+                None => {
+                    log::warn!("Cannot read at PRG address {:#x} for VRC6", addr);
+                    Ok(0)
+                }
+            },
+        }
+    }
+
+    fn map_prg(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        let banks = (nes.cartridge.prgmem.len() / 0x2000).max(1);
+        let bank = match addr {
+            0x8000..=0xbfff => self.prg_banksel_16 as usize * 2 + ((addr as usize >> 13) & 1),
+            0xc000..=0xdfff => self.prg_banksel_8 as usize,
+            0xe000..=0xffff => banks - 1,
+            _ => return None,
+        };
+        Some(bank % banks * 0x2000 + (addr as usize & 0x1fff))
+    }
+
+    fn map_chr(&self, nes: &Nes<S, A>, addr: u16) -> Option<usize> {
+        Some(self.chr_offset(nes, addr))
+    }
+
+    fn prg_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        let mut banks = vec![Bank::new(0x6000, 0x2000, Memory::PrgRam, 0)];
+        for (addr, size) in [(0x8000, 0x4000), (0xc000, 0x2000), (0xe000, 0x2000)] {
+            if let Some(offset) = self.map_prg(nes, addr) {
+                banks.push(Bank::new(addr, size, Memory::PrgRom, offset));
+            }
+        }
+        banks
+    }
+
+    fn chr_layout(&self, nes: &Nes<S, A>) -> Vec<Bank> {
+        (0..8)
+            .map(|i| {
+                let addr = i * 0x400;
+                let offset = self.chr_offset(nes, addr);
+                Bank::new(addr, 0x400, mappers::chr_memory(nes), offset)
+            })
+            .collect()
+    }
+
+    fn write_prg(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        let reg = if self.swap_lines {
+            (addr & 0x01) << 1 | (addr & 0x02) >> 1
+        } else {
+            addr & 0x03
+        };
+        match (addr & 0xf000, reg) {
+            (0x6000 | 0x7000, _) => self.wram[(addr & 0x1fff) as usize] = data,
+            (0x8000, _) => self.prg_banksel_16 = data & 0x0f,
+            (0x9000, _) | (0xa000 | 0xb000, 0..=2) => self.audio.write(addr, reg, data),
+            (0xb000, _) => {
+                self.chr_mode = data & 0b11;
+                nes.cartridge.mirroring = match (data >> 2) & 0b11 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::OneScreenNT0,
+                    _ => Mirroring::OneScreenNT1,
+                };
+            }
+            (0xc000, _) => self.prg_banksel_8 = data & 0x1f,
+            (0xd000, _) => self.chr_banksel[reg as usize] = data,
+            (0xe000, _) => self.chr_banksel[4 + reg as usize] = data,
+            (0xf000, 0) => self.irq.write_latch(data),
+            (0xf000, 1) => self.irq.write_control(data),
+            (0xf000, 2) => self.irq.acknowledge(),
+            _ => log::warn!("Cannot write at PRG address {:#x} for VRC6", addr),
+        }
+        Ok(())
+    }
+
+    fn poke_prg_ram(&mut self, _nes: &Nes<S, A>, addr: u16, data: u8) -> bool {
+        self.wram[(addr & 0x1fff) as usize] = data;
+        true
+    }
+
+    fn read_chr(&mut self, nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
+        Ok(nes.cartridge.chrmem[self.chr_offset(nes, addr)])
+    }
+
+    fn write_chr(&mut self, nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()> {
+        if nes.cartridge.chr_banks == 0 {
+            let mapped_addr = self.chr_offset(nes, addr);
+            nes.cartridge.chrmem[mapped_addr] = data;
+        } else {
+            log::warn!("Cannot write at CHR address {:#x} for VRC6", addr);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.prg_banksel_16 = 0;
+        self.prg_banksel_8 = 0;
+        self.irq = VrcIrq::default();
+        self.audio = Vrc6Audio::default();
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "VRC6"
+    }
+
+    fn clock(&mut self, _nes: &mut Nes<S, A>) -> Result<()> {
+        self.irq.clock();
+        self.audio.clock();
+        Ok(())
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending
+    }
+
+    fn audio_output(&self) -> Option<f32> {
+        Some(self.audio.output())
+    }
+}
@@ -12,6 +12,8 @@ pub trait NesAudio {
         freq: Option<u16>,
     ) -> Result<()>;
     fn update_triangle(&mut self, freq: Option<u16>, mute: Option<bool>) -> Result<()>;
+    // Mono cartridge expansion audio at apu::expansion::SAMPLE_RATE, to be queued after the previous batch
+    fn play_samples(&mut self, samples: &[f32]) -> Result<()>;
 }
 
 pub struct NoAudio;
@@ -37,4 +39,9 @@ impl NesAudio for NoAudio {
         // Do nothing
         Ok(())
     }
+
+    fn play_samples(&mut self, _samples: &[f32]) -> Result<()> {
+        // Do nothing
+        Ok(())
+    }
 }
@@ -0,0 +1,281 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::cpu::CpuFlag;
+use crate::nesaudio::NesAudio;
+use crate::nesscreen::NesScreen;
+use crate::Nes;
+
+pub const NSF_TAG: &[u8; 5] = b"NESM\x1a";
+pub const NSFE_TAG: &[u8; 4] = b"NSFE";
+const HEADER_SIZE: usize = 0x80;
+// microseconds between PLAY calls when the file does not say
+const NTSC_SPEED: u16 = 16639;
+const PAL_SPEED: u16 = 19997;
+const CPU_FREQ: u64 = 1789773;
+// where the player mapper puts its JMP to itself, run between INIT and PLAY calls
+pub(crate) const DRIVER_ADDR: u16 = 0x4100;
+
+// Expansion sound chips, bits of the header's chip byte
+pub const CHIP_VRC6: u8 = 1 << 0;
+pub const CHIP_VRC7: u8 = 1 << 1;
+pub const CHIP_FDS: u8 = 1 << 2;
+pub const CHIP_MMC5: u8 = 1 << 3;
+pub const CHIP_N163: u8 = 1 << 4;
+pub const CHIP_S5B: u8 = 1 << 5;
+
+// NSF or NSFe tune, tracks are numbered from 0
+pub struct Nsf {
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    pub tracks: u8,
+    pub start_track: u8,
+    pub load_addr: u16,
+    pub init_addr: u16,
+    pub play_addr: u16,
+    pub ntsc_speed: u16,
+    pub pal_speed: u16,
+    // tune made for PAL consoles only
+    pub pal: bool,
+    // initial 4 KB banks for $8000-$FFFF, None when the tune does not bankswitch
+    pub banks: Option<[u8; 8]>,
+    pub chips: u8,
+    pub data: Vec<u8>,
+    // NSFe track names and lengths in milliseconds, empty when not given
+    pub track_titles: Vec<String>,
+    pub track_times: Vec<Option<u32>>,
+}
+
+impl Nsf {
+    // CPU cycles between PLAY calls
+    pub fn play_period(&self) -> u32 {
+        let speed = if self.pal {
+            self.pal_speed
+        } else {
+            self.ntsc_speed
+        };
+        let speed = match speed {
+            0 if self.pal => PAL_SPEED,
+            0 => NTSC_SPEED,
+            speed => speed,
+        };
+        (speed as u64 * CPU_FREQ / 1_000_000) as u32
+    }
+
+    pub fn track_title(&self, track: u8) -> Option<&str> {
+        self.track_titles
+            .get(track as usize)
+            .map(|title| title.as_str())
+            .filter(|title| !title.is_empty())
+    }
+
+    pub fn track_time(&self, track: u8) -> Option<u32> {
+        self.track_times.get(track as usize).copied().flatten()
+    }
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Nsf> {
+    if bytes.starts_with(NSF_TAG) {
+        parse_nsf(bytes)
+    } else if bytes.starts_with(NSFE_TAG) {
+        parse_nsfe(bytes)
+    } else {
+        Err(anyhow!(
+            "Invalid NSF file was provided: Missing NESM or NSFE tag"
+        ))
+    }
+}
+
+fn word(bytes: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([bytes[pos], bytes[pos + 1]])
+}
+
+// Null terminated string, the rest of the field is ignored
+fn string(bytes: &[u8]) -> String {
+    let text = bytes.split(|&c| c == 0).next().unwrap_or_default();
+    String::from_utf8_lossy(text).trim().to_string()
+}
+
+fn banks(bytes: &[u8]) -> Option<[u8; 8]> {
+    let mut banks = [0; 8];
+    banks[..bytes.len().min(8)].copy_from_slice(&bytes[..bytes.len().min(8)]);
+    banks.iter().any(|&bank| bank != 0).then_some(banks)
+}
+
+fn parse_nsf(bytes: &[u8]) -> Result<Nsf> {
+    if bytes.len() <= HEADER_SIZE {
+        Err(anyhow!("Invalid NSF file was provided: Truncated header"))?;
+    }
+    let header = &bytes[..HEADER_SIZE];
+    let mut data = &bytes[HEADER_SIZE..];
+    // NSF2 may append metadata after the program, its length is then given
+    let data_len =
+        header[0x7d] as usize | (header[0x7e] as usize) << 8 | (header[0x7f] as usize) << 16;
+    if header[0x05] >= 2 && data_len != 0 && data_len < data.len() {
+        data = &data[..data_len];
+    }
+
+    Ok(Nsf {
+        title: string(&header[0x0e..0x2e]),
+        artist: string(&header[0x2e..0x4e]),
+        copyright: string(&header[0x4e..0x6e]),
+        tracks: header[0x06],
+        start_track: header[0x07].saturating_sub(1),
+        load_addr: word(header, 0x08),
+        init_addr: word(header, 0x0a),
+        play_addr: word(header, 0x0c),
+        ntsc_speed: word(header, 0x6e),
+        pal_speed: word(header, 0x78),
+        pal: header[0x7a] & 0b11 == 0b01,
+        banks: banks(&header[0x70..0x78]),
+        chips: header[0x7b],
+        data: data.to_vec(),
+        track_titles: vec![],
+        track_times: vec![],
+    })
+}
+
+fn parse_nsfe(bytes: &[u8]) -> Result<Nsf> {
+    let mut nsf = Nsf {
+        title: String::new(),
+        artist: String::new(),
+        copyright: String::new(),
+        tracks: 1,
+        start_track: 0,
+        load_addr: 0,
+        init_addr: 0,
+        play_addr: 0,
+        ntsc_speed: NTSC_SPEED,
+        pal_speed: PAL_SPEED,
+        pal: false,
+        banks: None,
+        chips: 0,
+        data: vec![],
+        track_titles: vec![],
+        track_times: vec![],
+    };
+    let mut info = false;
+
+    let mut pos = NSFE_TAG.len();
+    while pos < bytes.len() {
+        let header = bytes
+            .get(pos..pos + 8)
+            .ok_or_else(|| anyhow!("Invalid NSFe file was provided: Truncated chunk header"))?;
+        let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let id = &header[4..8];
+        let data = bytes
+            .get(pos + 8..pos + 8 + length)
+            .ok_or_else(|| anyhow!("Invalid NSFe file was provided: Truncated chunk"))?;
+        pos += 8 + length;
+
+        match id {
+            b"INFO" => {
+                if data.len() < 9 {
+                    Err(anyhow!(
+                        "Invalid NSFe file was provided: Truncated INFO chunk"
+                    ))?;
+                }
+                nsf.load_addr = word(data, 0);
+                nsf.init_addr = word(data, 2);
+                nsf.play_addr = word(data, 4);
+                nsf.pal = data[6] & 0b11 == 0b01;
+                nsf.chips = data[7];
+                nsf.tracks = data[8];
+                nsf.start_track = data.get(9).copied().unwrap_or(0);
+                info = true;
+            }
+            b"DATA" => nsf.data = data.to_vec(),
+            b"BANK" => nsf.banks = banks(data),
+            b"RATE" => {
+                if data.len() >= 2 {
+                    nsf.ntsc_speed = word(data, 0);
+                }
+                if data.len() >= 4 {
+                    nsf.pal_speed = word(data, 2);
+                }
+            }
+            b"auth" => {
+                let mut fields = data.split(|&c| c == 0).map(string);
+                nsf.title = fields.next().unwrap_or_default();
+                nsf.artist = fields.next().unwrap_or_default();
+                nsf.copyright = fields.next().unwrap_or_default();
+            }
+            b"tlbl" => {
+                nsf.track_titles = data.split(|&c| c == 0).map(string).collect();
+                nsf.track_titles.truncate(nsf.tracks as usize);
+            }
+            b"time" => {
+                nsf.track_times = data
+                    .chunks_exact(4)
+                    .map(|time| i32::from_le_bytes([time[0], time[1], time[2], time[3]]))
+                    .map(|time| u32::try_from(time).ok())
+                    .collect();
+            }
+            b"NEND" => break,
+            // chunks starting with a capital letter must be understood to play the file
+            _ if id[0].is_ascii_uppercase() => Err(anyhow!(
+                "NSFe chunk {} not supported yet",
+                String::from_utf8_lossy(id)
+            ))?,
+            _ => log::debug!("Skipping NSFe chunk {}", String::from_utf8_lossy(id)),
+        }
+    }
+
+    if !info || nsf.data.is_empty() {
+        Err(anyhow!(
+            "Invalid NSFe file was provided: Missing INFO or DATA chunk"
+        ))?;
+    }
+    Ok(nsf)
+}
+
+// Restart the tune on the given track, the player mapper calls PLAY once INIT returns
+pub fn play_track<S, A>(nes: &mut Nes<S, A>, track: u8) -> Result<()>
+where
+    S: NesScreen,
+    A: NesAudio,
+{
+    let (tracks, pal, init_addr) = match &nes.cartridge.nsf {
+        Some(nsf) => (nsf.tracks, nsf.pal, nsf.init_addr),
+        None => Err(anyhow!("Cannot select a track, no NSF file is loaded"))?,
+    };
+    if track >= tracks {
+        Err(anyhow!(
+            "Track {} does not exist, the tune has {}",
+            track + 1,
+            tracks
+        ))?;
+    }
+
+    // banks, RAM and sound chips go back to their initial state
+    cartridge::reset(nes)?;
+    nes.bus_cpu.ram = [0; 0x0800];
+    for addr in 0x4000..=0x4013 {
+        buscpu::write(nes, addr, 0)?;
+    }
+    buscpu::write(nes, 0x4015, 0x00)?;
+    buscpu::write(nes, 0x4015, 0x0f)?;
+
+    nes.cpu.ac = track;
+    nes.cpu.x = pal as u8;
+    nes.cpu.y = 0;
+    nes.cpu.sp = 0xfd;
+    nes.cpu.status = CpuFlag::I as u8 | CpuFlag::U as u8;
+    nes.cpu.cycles = 0;
+    call(nes, init_addr);
+    log::info!("Playing track {}/{}", track + 1, tracks);
+    Ok(())
+}
+
+// Jump to a routine that returns to the idle loop of the player
+pub(crate) fn call<S, A>(nes: &mut Nes<S, A>, addr: u16) {
+    let ret = DRIVER_ADDR - 1;
+    nes.bus_cpu.ram[0x100 + nes.cpu.sp as usize] = (ret >> 8) as u8;
+    nes.cpu.sp = nes.cpu.sp.wrapping_sub(1);
+    nes.bus_cpu.ram[0x100 + nes.cpu.sp as usize] = ret as u8;
+    nes.cpu.sp = nes.cpu.sp.wrapping_sub(1);
+    nes.cpu.pc = addr;
+}
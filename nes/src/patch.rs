@@ -0,0 +1,328 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::gamedb::crc32;
+
+const IPS_TAG: &[u8; 5] = b"PATCH";
+const IPS_EOF: &[u8; 3] = b"EOF";
+// a record starting here would read as the end marker
+const IPS_EOF_OFFSET: usize = 0x454f46;
+const IPS_MAX_OFFSET: usize = 0xffffff;
+const IPS_MAX_RECORD: usize = 0xffff;
+const UPS_TAG: &[u8; 4] = b"UPS1";
+const BPS_TAG: &[u8; 4] = b"BPS1";
+// source, target and patch CRC32 at the end of UPS and BPS patches
+const FOOTER_SIZE: usize = 12;
+// UPS targets are allocated up front, so a size read from the patch is capped well above any ROM
+const UPS_MAX_TARGET: usize = 0x4000000;
+
+// Apply an IPS, UPS or BPS patch, recognized by its tag
+pub fn apply(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    if patch.starts_with(IPS_TAG) {
+        apply_ips(source, patch)
+    } else if patch.starts_with(UPS_TAG) {
+        apply_ups(source, patch)
+    } else if patch.starts_with(BPS_TAG) {
+        apply_bps(source, patch)
+    } else {
+        Err(anyhow!("Unknown patch format, expected IPS, UPS or BPS"))
+    }
+}
+
+pub fn apply_ips(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    if patch.len() < 8 || &patch[0..5] != IPS_TAG {
+        Err(anyhow!("Invalid IPS patch was provided: Missing PATCH tag"))?;
+    }
+    let truncated = || anyhow!("Invalid IPS patch was provided: Truncated record");
+
+    let mut target = source.to_vec();
+    let mut pos = 5;
+    loop {
+        let record = patch.get(pos..pos + 3).ok_or_else(truncated)?;
+        if record == IPS_EOF {
+            pos += 3;
+            break;
+        }
+        let offset = (record[0] as usize) << 16 | (record[1] as usize) << 8 | record[2] as usize;
+        let size = patch.get(pos + 3..pos + 5).ok_or_else(truncated)?;
+        let size = (size[0] as usize) << 8 | size[1] as usize;
+        pos += 5;
+
+        // a zero size marks a run of a single repeated byte
+        let data = if size == 0 {
+            let rle = patch.get(pos..pos + 3).ok_or_else(truncated)?;
+            pos += 3;
+            vec![rle[2]; (rle[0] as usize) << 8 | rle[1] as usize]
+        } else {
+            let data = patch.get(pos..pos + size).ok_or_else(truncated)?;
+            pos += size;
+            data.to_vec()
+        };
+        if target.len() < offset + data.len() {
+            target.resize(offset + data.len(), 0);
+        }
+        target[offset..offset + data.len()].copy_from_slice(&data);
+    }
+
+    // optional extension truncating the output
+    if let Some(size) = patch.get(pos..pos + 3) {
+        target.truncate((size[0] as usize) << 16 | (size[1] as usize) << 8 | size[2] as usize);
+    }
+    Ok(target)
+}
+
+pub fn create_ips(source: &[u8], target: &[u8]) -> Result<Vec<u8>> {
+    if target.len() > IPS_MAX_OFFSET {
+        Err(anyhow!(
+            "Cannot create IPS patch: Target is larger than 16 MB"
+        ))?;
+    }
+    let differs = |i: usize| source.get(i) != Some(&target[i]);
+
+    let mut patch = IPS_TAG.to_vec();
+    let mut i = 0;
+    while i < target.len() {
+        if !differs(i) {
+            i += 1;
+            continue;
+        }
+        let start = if i == IPS_EOF_OFFSET { i - 1 } else { i };
+        let mut end = i;
+        while end < target.len() && end - start < IPS_MAX_RECORD && differs(end) {
+            end += 1;
+        }
+        patch.extend([(start >> 16) as u8, (start >> 8) as u8, start as u8]);
+        patch.extend([((end - start) >> 8) as u8, (end - start) as u8]);
+        patch.extend(&target[start..end]);
+        i = end;
+    }
+    patch.extend(IPS_EOF);
+    if target.len() < source.len() {
+        let size = target.len();
+        patch.extend([(size >> 16) as u8, (size >> 8) as u8, size as u8]);
+    }
+    Ok(patch)
+}
+
+pub fn apply_ups(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let (source_crc, target_crc) = check_footer(source, patch, UPS_TAG, "UPS")?;
+    let mut reader = PatchReader::new(patch, UPS_TAG.len(), "UPS");
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    if target_size > UPS_MAX_TARGET {
+        Err(anyhow!(
+            "UPS patch target of {} bytes is too large",
+            target_size
+        ))?;
+    }
+    if source.len() != source_size {
+        Err(anyhow!(
+            "UPS patch expects a {} byte ROM, got {} bytes",
+            source_size,
+            source.len()
+        ))?;
+    }
+
+    // hunks of bytes XORed with the source, each ending with a zero
+    let mut target = source.to_vec();
+    target.resize(target_size, 0);
+    let mut pos = 0usize;
+    while reader.pos < patch.len() - FOOTER_SIZE {
+        pos = pos
+            .checked_add(reader.number()?)
+            .ok_or_else(|| reader.invalid())?;
+        loop {
+            let byte = reader.byte()?;
+            if let Some(out) = target.get_mut(pos) {
+                *out ^= byte;
+            }
+            pos = pos.checked_add(1).ok_or_else(|| reader.invalid())?;
+            if byte == 0 {
+                break;
+            }
+        }
+    }
+    check_target(&target, source_crc, target_crc, "UPS")?;
+    Ok(target)
+}
+
+pub fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let (source_crc, target_crc) = check_footer(source, patch, BPS_TAG, "BPS")?;
+    let mut reader = PatchReader::new(patch, BPS_TAG.len(), "BPS");
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    if source.len() != source_size {
+        Err(anyhow!(
+            "BPS patch expects a {} byte ROM, got {} bytes",
+            source_size,
+            source.len()
+        ))?;
+    }
+    let metadata_size = reader.number()?;
+    reader.bytes(metadata_size)?;
+
+    // the sizes come from the patch, so the target grows as actions fill it
+    let mut target = vec![];
+    let mut source_pos = 0usize;
+    let mut target_pos = 0usize;
+    while reader.pos < patch.len() - FOOTER_SIZE {
+        let action = reader.number()?;
+        let length = (action >> 2) + 1;
+        if length > target_size - target.len() {
+            Err(reader.invalid())?;
+        }
+        match action & 0x03 {
+            // source bytes at the same offset
+            0 => {
+                let start = target.len();
+                let data = source
+                    .get(start..start + length)
+                    .ok_or_else(|| reader.invalid())?;
+                target.extend(data);
+            }
+            // bytes from the patch
+            1 => target.extend(reader.bytes(length)?),
+            // source bytes from a relative offset
+            2 => {
+                source_pos = reader.relative(source_pos)?;
+                let end = source_pos
+                    .checked_add(length)
+                    .ok_or_else(|| reader.invalid())?;
+                let data = source
+                    .get(source_pos..end)
+                    .ok_or_else(|| reader.invalid())?;
+                target.extend(data);
+                source_pos = end;
+            }
+            // earlier target bytes from a relative offset, which may overlap the output
+            _ => {
+                target_pos = reader.relative(target_pos)?;
+                for _ in 0..length {
+                    let byte = *target.get(target_pos).ok_or_else(|| reader.invalid())?;
+                    target.push(byte);
+                    target_pos += 1;
+                }
+            }
+        }
+    }
+    if target.len() != target_size {
+        Err(anyhow!(
+            "BPS patch produced {} bytes instead of {}",
+            target.len(),
+            target_size
+        ))?;
+    }
+    check_target(&target, source_crc, target_crc, "BPS")?;
+    Ok(target)
+}
+
+// Verify the patch and source checksums, returning the source and target ones
+fn check_footer(source: &[u8], patch: &[u8], tag: &[u8; 4], format: &str) -> Result<(u32, u32)> {
+    if patch.len() < tag.len() + FOOTER_SIZE || !patch.starts_with(tag) {
+        Err(anyhow!(
+            "Invalid {} patch was provided: Missing {} tag",
+            format,
+            String::from_utf8_lossy(tag)
+        ))?;
+    }
+    let footer = patch.len() - FOOTER_SIZE;
+    let crc_at = |pos: usize| {
+        u32::from_le_bytes([patch[pos], patch[pos + 1], patch[pos + 2], patch[pos + 3]])
+    };
+    if crc32(&patch[..footer + 8]) != crc_at(footer + 8) {
+        Err(anyhow!(
+            "{} patch checksum mismatch, the patch is corrupt",
+            format
+        ))?;
+    }
+    let source_crc = crc_at(footer);
+    if crc32(source) != source_crc {
+        Err(anyhow!(
+            "{} patch was made for another ROM, checksum {:08x} instead of {:08x}",
+            format,
+            crc32(source),
+            source_crc
+        ))?;
+    }
+    Ok((source_crc, crc_at(footer + 4)))
+}
+
+fn check_target(target: &[u8], source_crc: u32, target_crc: u32, format: &str) -> Result<()> {
+    let crc = crc32(target);
+    if crc != target_crc {
+        Err(anyhow!(
+            "{} patched ROM checksum {:08x} does not match {:08x}",
+            format,
+            crc,
+            target_crc
+        ))?;
+    }
+    log::info!(
+        "Applied {} patch from ROM {:08x} to {:08x}",
+        format,
+        source_crc,
+        target_crc
+    );
+    Ok(())
+}
+
+// Cursor over the body of a UPS or BPS patch, which stops before the footer
+struct PatchReader<'a> {
+    patch: &'a [u8],
+    pos: usize,
+    format: &'a str,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(patch: &'a [u8], pos: usize, format: &'a str) -> Self {
+        Self { patch, pos, format }
+    }
+
+    fn invalid(&self) -> anyhow::Error {
+        anyhow!("Invalid {} patch was provided: Truncated data", self.format)
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.patch.len() - FOOTER_SIZE;
+        let data = match self.pos.checked_add(length) {
+            Some(stop) if stop <= end => &self.patch[self.pos..stop],
+            _ => Err(self.invalid())?,
+        };
+        self.pos += length;
+        Ok(data)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    // variable length number, 7 bits per byte with the high bit ending it
+    fn number(&mut self) -> Result<usize> {
+        let mut number = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.byte()?;
+            number = (byte as usize & 0x7f)
+                .checked_mul(shift)
+                .and_then(|bits| number.checked_add(bits))
+                .ok_or_else(|| self.invalid())?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift.checked_mul(0x80).ok_or_else(|| self.invalid())?;
+            number = number.checked_add(shift).ok_or_else(|| self.invalid())?;
+        }
+    }
+
+    // offset moved by a signed number, the sign in the low bit
+    fn relative(&mut self, offset: usize) -> Result<usize> {
+        let number = self.number()?;
+        let distance = number >> 1;
+        let offset = if number & 1 != 0 {
+            offset.checked_sub(distance)
+        } else {
+            offset.checked_add(distance)
+        };
+        offset.ok_or_else(|| self.invalid())
+    }
+}
@@ -2,9 +2,13 @@ use anyhow::anyhow;
 use anyhow::Result;
 
 use crate::buscpu;
+use crate::busppu;
+use crate::busppu::peek;
 use crate::busppu::read;
 use crate::busppu::write;
-use crate::cartridge::Mirroring;
+use crate::cartridge;
+use crate::cdl;
+use crate::cheats;
 use crate::cpu;
 use crate::nesaudio::NesAudio;
 use crate::nesscreen::NesScreen;
@@ -28,6 +32,31 @@ pub struct Ppu {
     pub reg_data: u8,
     pub addr_latch: bool,
     pub reg_oam_addr: u8,
+    // what the rendering pipeline is fetching, for mappers that treat them differently
+    pub fetch: Fetch,
+    // vertical scroll is only reloaded from the registers at the start of a frame
+    frame_scroll_y: u8,
+    frame_nt_y: bool,
+    // sprites fetched at the end of the previous scanline
+    line_sprites: Vec<LineSprite>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fetch {
+    #[default]
+    Idle,
+    Background,
+    Sprites,
+}
+
+// A sprite row ready to be drawn on the current scanline
+struct LineSprite {
+    x: u8,
+    attr: u8,
+    // pattern bits, already flipped horizontally if needed
+    lsb: u8,
+    msb: u8,
+    zero: bool,
 }
 
 impl Default for Ppu {
@@ -48,6 +77,11 @@ impl Default for Ppu {
             addr_latch: true,
 
             reg_oam_addr: 0x00,
+            fetch: Fetch::Idle,
+
+            frame_scroll_y: 0,
+            frame_nt_y: false,
+            line_sprites: vec![],
         }
     }
 }
@@ -133,38 +167,30 @@ const OAMDMA: u16 = 0x4014;
     MAIN PPU CLOCK
 */
 
-pub struct ViewPort {
-    pub x1: u8,
-    pub y1: u8,
-    pub x2: u8,
-    pub y2: u8,
-}
-
-impl From<[u8; 4]> for ViewPort {
-    fn from(other: [u8; 4]) -> Self {
-        ViewPort {
-            x1: other[0],
-            y1: other[1],
-            x2: other[2],
-            y2: other[3],
-        }
-    }
-}
-
 pub fn clock<S, A>(nes: &mut Nes<S, A>) -> Result<()>
 where
     S: NesScreen,
     A: NesAudio,
 {
+    if nes.ppu.scan_line >= 0
+        && nes.ppu.scan_line < 240
+        && nes.ppu.scan_cycle == 1
+        && nes.ppu.reg_mask.render_enabled()
+    {
+        cartridge::scanline(nes)?;
+    }
+
+    // Fetch and draw a whole scanline once its visible cycles are over
+    if nes.ppu.scan_line < 240 && nes.ppu.scan_cycle == 257 {
+        render_scanline(nes)?;
+    }
+
     // Enter VBLANK
     if nes.ppu.scan_line == 241 && nes.ppu.scan_cycle == 1 {
         nes.ppu.reg_status.set_vblank(true);
         nes.ppu.reg_status.set_sprite_0_hit(false);
-        //let (back, front) = split_sprites_back_and_front(nes);
-        render_background(nes)?;
-        render_sprites(nes)?;
-        //render_sprites(nes, &front)?;
         nes.screen.vblank()?;
+        cheats::frame(nes)?;
         if nes.ppu.reg_control.is_nmi_enabled() {
             cpu::nmi(nes)?;
         }
@@ -172,15 +198,12 @@ where
 
     nes.ppu.scan_cycle += 1;
     if nes.ppu.scan_cycle >= 341 {
-        if is_sprite_0_hit(&nes.ppu, nes.ppu.scan_cycle) {
-            nes.ppu.reg_status.set_sprite_0_hit(true);
-        }
-
         nes.ppu.scan_cycle = 0;
         nes.ppu.scan_line += 1;
         if nes.ppu.scan_line >= 261 {
             nes.ppu.scan_line = -1;
             nes.ppu.reg_status.set_sprite_0_hit(false);
+            nes.ppu.reg_status.set_sprite_overflow(false);
             nes.ppu.reg_status.set_vblank(false);
         }
     }
@@ -220,7 +243,8 @@ pub fn read_ppu_reg<S, A>(nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
             match maddr {
                 0x0000..=0x2fff => {
                     let output = nes.ppu.reg_data;
-                    nes.ppu.reg_data = read(nes, maddr)?;
+                    nes.ppu.reg_data =
+                        cdl::with_chr_access(nes, cdl::CHR_READ, |nes| read(nes, maddr))?;
                     Ok(output)
                 }
                 0x3f00..=0x3fff => read(nes, maddr),
@@ -231,6 +255,19 @@ pub fn read_ppu_reg<S, A>(nes: &mut Nes<S, A>, addr: u16) -> Result<u8> {
     }
 }
 
+// Register value as the CPU would read it, without clearing flags or moving the read buffer
+pub fn peek_ppu_reg<S, A>(nes: &Nes<S, A>, addr: u16) -> u8 {
+    match addr {
+        PPUSTATUS => nes.ppu.reg_status.get_bits(),
+        OAMDATA => nes.ppu.oam[nes.ppu.reg_oam_addr as usize],
+        PPUDATA => match nes.ppu.reg_addr {
+            0x3f00..=0x3fff => busppu::read_palette(nes, nes.ppu.reg_addr),
+            _ => nes.ppu.reg_data,
+        },
+        _ => 0,
+    }
+}
+
 pub fn write_ppu_reg<S, A>(nes: &mut Nes<S, A>, addr: u16, data: u8) -> Result<()>
 where
     S: NesScreen,
@@ -299,240 +336,215 @@ where
     RENDERING FUNCTIONS
 */
 
-pub fn render_background<S, A>(nes: &mut Nes<S, A>) -> Result<()>
+/*
+    CHR is fetched in the same order as the hardware does it: the background tiles of the
+    scanline from left to right, then the sprites of the next scanline in OAM order (with
+    dummy fetches of tile $FF for empty slots). Mappers that watch the PPU address bus,
+    like the MMC2 latches, depend on this order.
+*/
+fn render_scanline<S, A>(nes: &mut Nes<S, A>) -> Result<()>
 where
     S: NesScreen,
     A: NesAudio,
 {
-    let (nt1, nt2) = match (
-        &nes.cartridge.mirroring,
-        nes.ppu.reg_control.nametable_offset(),
-    ) {
-        (Mirroring::Vertical, 0x000)
-        | (Mirroring::Vertical, 0x800)
-        | (Mirroring::Horizontal, 0x000)
-        | (Mirroring::Horizontal, 0x400) => (0x2000, 0x2400),
-        (Mirroring::Vertical, 0x400)
-        | (Mirroring::Vertical, 0xc00)
-        | (Mirroring::Horizontal, 0x800)
-        | (Mirroring::Horizontal, 0xc00) => (0x2400, 0x2000),
-        _ => Err(anyhow!(
-            "Unsupported Mirroring type: {:?}",
-            &nes.cartridge.mirroring
-        ))?,
-    };
-    let scroll_x = nes.ppu.reg_scroll.scroll_x;
-    let scroll_y = nes.ppu.reg_scroll.scroll_y;
-
-    render_nametable(
-        nes,
-        nt1,
-        ViewPort::from([scroll_x, scroll_y, 255, 240]),
-        -(scroll_x as isize),
-        -(scroll_y as isize),
-    )?;
-    if scroll_x > 0 {
-        render_nametable(
-            nes,
-            nt2,
-            ViewPort::from([0, 0, scroll_x, 240]),
-            256 - scroll_x as isize,
-            0,
-        )?;
-    } else if scroll_y > 0 {
-        render_nametable(
-            nes,
-            nt2,
-            ViewPort::from([0, 0, 255, scroll_y]),
-            0,
-            240 - scroll_y as isize,
-        )?;
+    let line = nes.ppu.scan_line;
+    let rendering = nes.ppu.reg_mask.render_enabled();
+    if line < 0 {
+        nes.ppu.frame_scroll_y = nes.ppu.reg_scroll.scroll_y;
+        nes.ppu.frame_nt_y = nes.ppu.reg_control.contains(RegControl::Ny);
+    } else {
+        let background = if rendering {
+            nes.ppu.fetch = Fetch::Background;
+            fetch_background(nes, line as u8)?
+        } else {
+            [0; 256]
+        };
+        draw_scanline(nes, line as u8, &background)?;
     }
 
+    nes.ppu.line_sprites = if rendering {
+        nes.ppu.fetch = Fetch::Sprites;
+        fetch_sprites(nes, line + 1)?
+    } else {
+        vec![]
+    };
+    nes.ppu.fetch = Fetch::Idle;
     Ok(())
 }
 
-#[allow(dead_code)]
-fn split_sprites_back_and_front<S, A>(nes: &mut Nes<S, A>) -> (Vec<u8>, Vec<u8>)
-where
-    S: NesScreen,
-    A: NesAudio,
-{
-    let mut back = vec![];
-    let mut front = vec![];
-    for i in (0..255).step_by(4).rev() {
-        if nes.ppu.oam[i as usize + 2] >> 5 & 1 != 0 {
-            back.push(i);
+// Palette index (0 when transparent) of every background pixel in the scanline
+fn fetch_background<S, A>(nes: &mut Nes<S, A>, line: u8) -> Result<[u8; 256]> {
+    let scroll_x = nes.ppu.reg_scroll.scroll_x as usize
+        + if nes.ppu.reg_control.contains(RegControl::Nx) {
+            256
         } else {
-            front.push(i);
+            0
+        };
+    let scroll_y = nes.ppu.frame_scroll_y as usize;
+    let mut nt_y = nes.ppu.frame_nt_y as u16;
+    let mut y = scroll_y + line as usize;
+    if scroll_y < 240 {
+        if y >= 240 {
+            y -= 240;
+            nt_y ^= 1;
         }
+    } else {
+        // rows 30 and 31 hold attributes, the hardware reads them as tiles anyway
+        y &= 0xff;
     }
-    (back, front)
-}
+    let tile_row = (y / 8) as u16;
+    let fine_y = (y % 8) as u16;
+    let fine_x = scroll_x % 8;
+    let chr_bank = (nes.ppu.reg_control.get_bg() as u16) * 0x1000;
 
-pub fn render_sprites<S, A>(nes: &mut Nes<S, A>) -> Result<()>
-where
-    S: NesScreen,
-    A: NesAudio,
-{
-    for i in (0..=255).step_by(4) {
-        let tile_id = nes.ppu.oam[i + 1];
-        let tile_x = nes.ppu.oam[i + 3];
-        let tile_y = nes.ppu.oam[i];
-        let tile_attr = nes.ppu.oam[i + 2];
+    let mut pixels = [0; 256];
+    for i in 0..33 {
+        let tile_x = (scroll_x / 8 + i) % 64;
+        let nt_base = 0x2000 | nt_y << 11 | ((tile_x / 32) as u16) << 10;
+        let tile_col = (tile_x % 32) as u16;
+
+        let tile = read(nes, nt_base + tile_row * 32 + tile_col)?;
+        let attr_byte = read(nes, nt_base + 0x3c0 + tile_row / 4 * 8 + tile_col / 4)?;
+        let palette = (attr_byte >> ((tile_row & 2) << 1 | (tile_col & 2))) & 0b11;
+
+        let tile_lsb = read(nes, chr_bank + (tile as u16) * 16 + fine_y)?;
+        let tile_msb = read(nes, chr_bank + (tile as u16) * 16 + fine_y + 8)?;
+        for col in 0..8 {
+            let pixel_x = i * 8 + col;
+            if pixel_x < fine_x || pixel_x - fine_x >= 256 {
+                continue;
+            }
+            let value = (tile_msb >> (7 - col) & 1) << 1 | (tile_lsb >> (7 - col) & 1);
+            if value != 0 {
+                pixels[pixel_x - fine_x] = palette << 2 | value;
+            }
+        }
+    }
+    Ok(pixels)
+}
 
-        let flip_v = tile_attr >> 7 & 1 != 0;
-        let flip_h = tile_attr >> 6 & 1 != 0;
-        let palette_id = tile_attr & 0b11;
-
-        let spr_height_16 = nes.ppu.reg_control.spr_height_16();
-        let spr_height_8_offset = (nes.ppu.reg_control.get_spr() as u16) * 0x1000;
-
-        let mut render_sprite = |tile: u16, tile_y: u8| -> Result<()> {
-            for y in 0..=7 {
-                let mut upper = read(nes, tile + y as u16)?;
-                let mut lower = read(nes, tile + y as u16 + 8)?;
-                for x in (0..=7).rev() {
-                    let value = (1 & lower) << 1 | (1 & upper);
-                    upper >>= 1;
-                    lower >>= 1;
-
-                    if value == 0 {
-                        continue;
-                    }
-
-                    let pal_pixel_id = 0x11 + palette_id * 4 + value - 1;
-                    let mut rgb = PALETTE_TO_RGB[read(nes, 0x3f00 + pal_pixel_id as u16)? as usize];
-                    emphasis(&nes.ppu.reg_mask, &mut rgb);
+// Evaluate OAM for a scanline and fetch the pattern rows of up to 8 sprites on it
+fn fetch_sprites<S, A>(nes: &mut Nes<S, A>, line: i16) -> Result<Vec<LineSprite>> {
+    let spr_height_16 = nes.ppu.reg_control.spr_height_16();
+    let height = if spr_height_16 { 16 } else { 8 };
+    let spr_height_8_offset = (nes.ppu.reg_control.get_spr() as u16) * 0x1000;
+
+    let mut found = vec![];
+    for i in 0..64 {
+        let row = line - nes.ppu.oam[i * 4] as i16;
+        if (0..height).contains(&row) {
+            if found.len() == 8 {
+                nes.ppu.reg_status.set_sprite_overflow(true);
+                break;
+            }
+            found.push((i, row as u16));
+        }
+    }
 
-                    // FIX SPRITES WITH 16x8 HEIGHT
-                    /*
-                    let (pixel_x, pixel_y) = if !spr_height_16 {
-                        match (flip_h, flip_v) {
-                            (false, false) => (tile_x.wrapping_add(x), tile_y.wrapping_add(y)),
-                            (true, false) => (tile_x.wrapping_add(7 - x), tile_y.wrapping_add(y)),
-                            (false, true) => {
-                                (tile_x.wrapping_add(x), tile_y.wrapping_add(7 - y as u8))
-                            }
-                            (true, true) => {
-                                (tile_x.wrapping_add(7 - x), tile_y.wrapping_add(7 - y as u8))
-                            }
-                        }
-                    } else {
-                        match (flip_h, flip_v) {
-                            (false, false) => (tile_x.wrapping_add(x), tile_y.wrapping_add(y)),
-                            (true, false) => (tile_x.wrapping_add(7 - x), tile_y.wrapping_add(y)),
-                            (false, true) => {
-                                (tile_x.wrapping_add(x), tile_y.wrapping_add(7 - y as u8))
-                            }
-                            (true, true) => {
-                                (tile_x.wrapping_add(7 - x), tile_y.wrapping_add(7 - y as u8))
-                            }
-                        }
-                    };
-                    */
-                    let (pixel_x, pixel_y) = match (flip_h, flip_v) {
-                        (false, false) => (tile_x.wrapping_add(x), tile_y.wrapping_add(y)),
-                        (true, false) => (tile_x.wrapping_add(7 - x), tile_y.wrapping_add(y)),
-                        (false, true) => (tile_x.wrapping_add(x), tile_y.wrapping_add(7 - y as u8)),
-                        (true, true) => {
-                            (tile_x.wrapping_add(7 - x), tile_y.wrapping_add(7 - y as u8))
-                        }
-                    };
-
-                    if pixel_y < 240 {
-                        nes.screen.draw_pixel(pixel_x, pixel_y, rgb)?;
-                    }
-                }
+    let mut sprites = vec![];
+    for slot in 0..8 {
+        let (i, row) = match found.get(slot) {
+            Some(&sprite) => sprite,
+            None => {
+                // empty slots still fetch tile $FF
+                let tile = if spr_height_16 {
+                    0x1fe0
+                } else {
+                    spr_height_8_offset + 0xff0
+                };
+                read(nes, tile)?;
+                read(nes, tile + 8)?;
+                continue;
             }
-            Ok(())
         };
+        let tile_id = nes.ppu.oam[i * 4 + 1];
+        let tile_attr = nes.ppu.oam[i * 4 + 2];
+        let flip_v = tile_attr >> 7 & 1 != 0;
+        let flip_h = tile_attr >> 6 & 1 != 0;
 
-        if !spr_height_16 {
-            render_sprite(spr_height_8_offset + tile_id as u16 * 16, tile_y)?;
+        let row = if flip_v { height as u16 - 1 - row } else { row };
+        let tile = if spr_height_16 {
+            let bank = (tile_id as u16 & 1) * 0x1000;
+            let top = tile_id as u16 & 0xfe;
+            bank + (top + row / 8) * 16 + row % 8
         } else {
-            let tile = tile_id as u16 & 0xfe;
-            render_sprite(tile * 16, tile_y)?;
-            render_sprite(
-                (tile + if tile_id & 1 != 0 { 256 } else { 1 }) * 16,
-                tile_y + 1,
-            )?;
+            spr_height_8_offset + tile_id as u16 * 16 + row
+        };
+        let mut lsb = read(nes, tile)?;
+        let mut msb = read(nes, tile + 8)?;
+        if flip_h {
+            lsb = lsb.reverse_bits();
+            msb = msb.reverse_bits();
         }
+        sprites.push(LineSprite {
+            x: nes.ppu.oam[i * 4 + 3],
+            attr: tile_attr,
+            lsb,
+            msb,
+            zero: i == 0,
+        });
     }
-    Ok(())
+    Ok(sprites)
 }
 
-/*
-    UTILITY FUNCTIONS
-*/
-
-pub fn render_nametable<S, A>(
-    nes: &mut Nes<S, A>,
-    nt_base: u16,
-    view_port: ViewPort,
-    scroll_x: isize,
-    scroll_y: isize,
-) -> Result<()>
+// Mix the background with the sprites fetched for this scanline and draw it
+fn draw_scanline<S, A>(nes: &mut Nes<S, A>, line: u8, background: &[u8; 256]) -> Result<()>
 where
     S: NesScreen,
     A: NesAudio,
 {
-    let chr_bank = (nes.ppu.reg_control.get_bg() as u16) * 0x1000;
-    // First nametable
-    for nt_offset in 0x000..=0x3bf {
-        let nt_addr = nt_base + nt_offset;
-        // get tile ID from vram
-        let tile = read(nes, nt_addr)?;
-        let tile_col = nt_offset % 32;
-        let tile_row = nt_offset / 32;
-
-        let attr_table_idx = tile_row / 4 * 8 + tile_col / 4;
-        let attr_byte = read(nes, nt_base + 0x3c0 + attr_table_idx)?;
-
-        let palette_idx = match (tile_col % 4 / 2, tile_row % 4 / 2) {
-            (0, 0) => attr_byte & 0b11,
-            (1, 0) => (attr_byte >> 2) & 0b11,
-            (0, 1) => (attr_byte >> 4) & 0b11,
-            (1, 1) => (attr_byte >> 6) & 0b11,
-            _ => unreachable!(),
+    let mask = &nes.ppu.reg_mask;
+    let bg_enabled = mask.render_bg_enabled();
+    let spr_enabled = mask.render_spr_enabled();
+    let bg_left = mask.render_bg_left();
+    let spr_left = mask.render_spr_left();
+
+    for x in 0..=255u8 {
+        let bg = if bg_enabled && (x >= 8 || bg_left) {
+            background[x as usize]
+        } else {
+            0
         };
-        let palette_start = 4 * palette_idx;
-
-        // Draw tile
-        for row in 0..8 {
-            let mut tile_lsb = read(nes, chr_bank + (tile as u16) * 16 + row)?;
-            let mut tile_msb = read(nes, chr_bank + (tile as u16) * 16 + row + 8)?;
-            for col in 0..8 {
-                let pixel = ((tile_msb & 0x01) << 1) | (tile_lsb & 0x01);
-                tile_lsb >>= 1;
-                tile_msb >>= 1;
-                let palette_idx = match pixel {
-                    0 => read(nes, 0x3f00)?,
-                    1 | 2 | 3 => read(nes, 0x3f00 + palette_start as u16 + pixel as u16)?,
-                    _ => unreachable!(),
-                };
 
-                let pixel_x = (tile_col * 8 + (7 - col)) as u8;
-                let pixel_y = (tile_row * 8 + row) as u8;
-                if pixel_x >= view_port.x1
-                    && pixel_x < view_port.x2
-                    && pixel_y >= view_port.y1
-                    && pixel_y < view_port.y2
-                {
-                    let mut rgb = PALETTE_TO_RGB[palette_idx as usize];
-                    emphasis(&nes.ppu.reg_mask, &mut rgb);
-                    nes.screen.draw_pixel(
-                        (scroll_x + pixel_x as isize) as u8,
-                        (scroll_y + pixel_y as isize) as u8,
-                        rgb,
-                    )?;
+        // lower OAM index wins between overlapping sprites
+        let sprite = nes
+            .ppu
+            .line_sprites
+            .iter()
+            .filter(|_| spr_enabled && (x >= 8 || spr_left))
+            .find_map(|sprite| {
+                let col = x.checked_sub(sprite.x).filter(|col| *col < 8)?;
+                let value = (sprite.msb >> (7 - col) & 1) << 1 | (sprite.lsb >> (7 - col) & 1);
+                (value != 0).then_some((sprite, value))
+            });
+
+        let pal_pixel_id = match sprite {
+            Some((sprite, value)) => {
+                if sprite.zero && bg != 0 && x != 255 {
+                    nes.ppu.reg_status.set_sprite_0_hit(true);
+                }
+                let behind_bg = sprite.attr >> 5 & 1 != 0;
+                if behind_bg && bg != 0 {
+                    bg
+                } else {
+                    0x10 + (sprite.attr & 0b11) * 4 + value
                 }
             }
-        }
+            None => bg,
+        };
+
+        let mut rgb =
+            PALETTE_TO_RGB[busppu::read_palette(nes, 0x3f00 + pal_pixel_id as u16) as usize & 0x3f];
+        emphasis(&nes.ppu.reg_mask, &mut rgb);
+        nes.screen.draw_pixel(x, line, rgb)?;
     }
     Ok(())
 }
 
+/*
+    UTILITY FUNCTIONS
+*/
+
 pub fn emphasis(rmask: &RegMask, rgb: &mut (u8, u8, u8)) {
     if rmask.emphasis_r() {
         rgb.2 = (1.1 * (rgb.2 as f32)) as u8;
@@ -551,16 +563,11 @@ pub fn emphasis(rmask: &RegMask, rgb: &mut (u8, u8, u8)) {
     }
 }
 
-fn is_sprite_0_hit(ppu: &Ppu, cycle: u16) -> bool {
-    let y = ppu.oam[0] as usize;
-    let x = ppu.oam[3] as usize;
-    y == ppu.scan_line as usize && x <= cycle as usize && ppu.reg_mask.render_spr_enabled()
-}
-
 /*
     DEBUG FUNCTIONS
 */
 
+// Debug views peek at memory so they can't trigger mapper latches or show up in the code/data log
 pub fn draw_chr<S, A>(nes: &mut Nes<S, A>, bank: u16, dbg_screen: &mut impl NesScreen) -> Result<()>
 where
     S: NesScreen,
@@ -570,14 +577,14 @@ where
         for tile_y in 0..16 {
             let offset = tile_x * 256 + tile_y * 16;
             for row in 0..8 {
-                let mut tile_lsb = read(nes, bank * 0x1000 + offset + row)?;
-                let mut tile_msb = read(nes, bank * 0x1000 + offset + row + 8)?;
+                let mut tile_lsb = peek(nes, bank * 0x1000 + offset + row)?;
+                let mut tile_msb = peek(nes, bank * 0x1000 + offset + row + 8)?;
                 for col in 0..8 {
                     let pixel = (tile_msb & 0x01) + (tile_lsb & 0x01);
                     tile_lsb >>= 1;
                     tile_msb >>= 1;
 
-                    let mut rgb = PALETTE_TO_RGB[read(nes, 0x3f00 + pixel as u16)? as usize];
+                    let mut rgb = PALETTE_TO_RGB[peek(nes, 0x3f00 + pixel as u16)? as usize];
                     emphasis(&nes.ppu.reg_mask, &mut rgb);
                     dbg_screen.draw_pixel(
                         (tile_y * 8 + (7 - col)) as u8,
@@ -612,12 +619,12 @@ where
     // First nametable
     for i in nt_start..attr_start {
         // get tile ID from vram
-        let tile = read(nes, i)?;
+        let tile = peek(nes, i)?;
         let tile_col = (i - nt_start) % 32;
         let tile_row = (i - nt_start) / 32;
 
         let attr_table_idx = tile_row / 4 * 8 + tile_col / 4;
-        let attr_byte = read(nes, attr_start + attr_table_idx)?;
+        let attr_byte = peek(nes, attr_start + attr_table_idx)?;
 
         let palette_idx = match (tile_col % 4 / 2, tile_row % 4 / 2) {
             (0, 0) => attr_byte & 0b11,
@@ -630,15 +637,15 @@ where
 
         // Draw tile
         for row in 0..8 {
-            let mut tile_lsb = read(nes, chr_bank * 0x1000 + (tile as u16) * 16 + row)?;
-            let mut tile_msb = read(nes, chr_bank * 0x1000 + (tile as u16) * 16 + row + 8)?;
+            let mut tile_lsb = peek(nes, chr_bank * 0x1000 + (tile as u16) * 16 + row)?;
+            let mut tile_msb = peek(nes, chr_bank * 0x1000 + (tile as u16) * 16 + row + 8)?;
             for col in 0..8 {
                 let pixel = ((tile_msb & 0x01) << 1) | (tile_lsb & 0x01);
                 tile_lsb >>= 1;
                 tile_msb >>= 1;
                 let palette_idx = match pixel {
-                    0 => read(nes, 0x3f00)?,
-                    1 | 2 | 3 => read(nes, 0x3f00 + palette_start as u16 + pixel as u16)?,
+                    0 => peek(nes, 0x3f00)?,
+                    1 | 2 | 3 => peek(nes, 0x3f00 + palette_start as u16 + pixel as u16)?,
                     _ => 0,
                 };
                 let mut rgb = PALETTE_TO_RGB[palette_idx as usize];
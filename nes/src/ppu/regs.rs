@@ -97,12 +97,23 @@ impl RegMask {
         self.contains(RegMask::s)
     }
 
-    /*
+    pub fn render_enabled(&self) -> bool {
+        self.render_bg_enabled() || self.render_spr_enabled()
+    }
+
     pub fn render_bg_enabled(&self) -> bool {
-        // TODO: change later
-        return true;//self.get_flag(Flag::b);
+        self.contains(RegMask::b)
+    }
+
+    pub fn render_bg_left(&self) -> bool {
+        self.contains(RegMask::m)
     }
 
+    pub fn render_spr_left(&self) -> bool {
+        self.contains(RegMask::M)
+    }
+
+    /*
     pub fn get_color_emphasis(&self) -> (bool, bool, bool) {
         return (
             self.get_flag(Flag::R),
@@ -153,6 +164,10 @@ impl RegStatus {
     pub fn set_sprite_0_hit(&mut self, val: bool) {
         self.set(RegStatus::S, val);
     }
+
+    pub fn set_sprite_overflow(&mut self, val: bool) {
+        self.set(RegStatus::O, val);
+    }
 }
 
 #[derive(Default)]
@@ -0,0 +1,117 @@
+use std::fs;
+
+use anyhow::Result;
+
+use crate::archive;
+use crate::gamedb;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::Nes;
+
+// roms.zip and roms.7z hold nestest.nes, color_test.nes and readme.txt
+const NES_TEST_FILE: &str = "test-files/nestest.nes";
+const COLOR_TEST_FILE: &str = "test-files/color_test.nes";
+
+#[test]
+fn zip_entries_are_listed_and_extracted() -> Result<()> {
+    let zip = fs::read("test-files/roms.zip")?;
+    assert!(archive::is_archive(&zip));
+    assert_eq!(archive::list(&zip)?, ["nestest.nes", "color_test.nes"]);
+
+    let err = archive::extract(&zip, None).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "The archive holds several ROMs, choose one of: nestest.nes, color_test.nes"
+    );
+    let rom = archive::extract(&zip, Some("COLOR_TEST.NES"))?;
+    assert_eq!(rom.name, "color_test.nes");
+    assert_eq!(rom.data, fs::read(COLOR_TEST_FILE)?);
+    assert!(archive::extract(&zip, Some("readme.txt")).is_err());
+
+    // a local header offset past the end of the archive
+    let mut zip = zip;
+    let entry = zip.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+    zip[entry + 42..entry + 46].copy_from_slice(&u32::MAX.to_le_bytes());
+    let err = archive::extract(&zip, Some("nestest.nes")).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Invalid ZIP archive was provided: Truncated data"
+    );
+    Ok(())
+}
+
+#[test]
+fn sevenz_entries_are_extracted() -> Result<()> {
+    let sevenz = fs::read("test-files/roms.7z")?;
+    assert_eq!(archive::list(&sevenz)?, ["nestest.nes", "color_test.nes"]);
+    let rom = archive::extract(&sevenz, Some("nestest.nes"))?;
+    assert_eq!(rom.data, fs::read(NES_TEST_FILE)?);
+    let rom = archive::extract(&sevenz, Some("color_test.nes"))?;
+    assert_eq!(rom.data, fs::read(COLOR_TEST_FILE)?);
+
+    // a single ROM needs no choice
+    let rom = archive::extract(&fs::read("test-files/nestest.7z")?, None)?;
+    assert_eq!(rom.name, "nestest.nes");
+    assert_eq!(rom.data, fs::read(NES_TEST_FILE)?);
+    Ok(())
+}
+
+// 7z archive made of the signature header and an uncompressed header
+fn sevenz(header: &[u8]) -> Vec<u8> {
+    let mut bytes = b"7z\xbc\xaf\x27\x1c\x00\x04".to_vec();
+    bytes.extend([0; 4]);
+    bytes.extend(0u64.to_le_bytes());
+    bytes.extend((header.len() as u64).to_le_bytes());
+    bytes.extend(gamedb::crc32(header).to_le_bytes());
+    bytes.extend(header);
+    bytes
+}
+
+#[test]
+fn sevenz_sizes_are_not_trusted() -> Result<()> {
+    let huge = [0xff, 0, 0, 0, 0, 0, 0x01, 0, 0];
+    // a file count far larger than the header
+    let mut header = vec![0x01, 0x05];
+    header.extend(huge);
+    header.push(0x00);
+    assert!(archive::extract(&sevenz(&header), None).is_err());
+
+    // a stored folder claiming far more data than it packs
+    let mut header = vec![0x01, 0x04, 0x06, 0x00, 0x01, 0x09, 0x01, 0x00];
+    header.extend([0x07, 0x0b, 0x01, 0x00, 0x01, 0x01, 0x00, 0x0c]);
+    header.extend(huge);
+    header.extend([0x00, 0x00, 0x00]);
+    assert!(archive::extract(&sevenz(&header), None).is_err());
+    Ok(())
+}
+
+#[test]
+fn gzip_is_extracted() -> Result<()> {
+    let rom = archive::extract(&fs::read("test-files/nestest.nes.gz")?, None)?;
+    assert_eq!(rom.name, "nestest.nes");
+    assert_eq!(rom.data, fs::read(NES_TEST_FILE)?);
+
+    // a stored block without a file name
+    let mut gzip = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3];
+    gzip.extend([0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c']);
+    gzip.extend(0x352441c2u32.to_le_bytes());
+    gzip.extend(3u32.to_le_bytes());
+    let file = archive::extract(&gzip, None)?;
+    assert_eq!(file.name, "");
+    assert_eq!(file.data, b"abc");
+
+    gzip[15] = b'x';
+    assert!(archive::extract(&gzip, None).is_err());
+    Ok(())
+}
+
+#[test]
+fn extracted_rom_is_loaded() -> Result<()> {
+    let zip = fs::read("test-files/roms.zip")?;
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    assert!(nes.load(&zip).is_err());
+    nes.load(&archive::extract(&zip, Some("nestest.nes"))?.data)?;
+    assert_eq!(nes.cartridge.prg_banks, 1);
+    assert!(!archive::is_archive(&fs::read(NES_TEST_FILE)?));
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge::Mirroring;
+use crate::tests::ines;
+
+#[test]
+fn axrom_switches_prg_and_nametable() -> Result<()> {
+    let mut nes = ines(7, 8, 0, 0).prg_banks_of(0x8000).nes2(1).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "AxROM");
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::OneScreenNT0));
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 0);
+
+    buscpu::write(&mut nes, 0x8000, 0x13)?;
+    assert_eq!(buscpu::read(&mut nes, 0xfffc)?, 3);
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::OneScreenNT1));
+
+    buscpu::write(&mut nes, 0x8000, 0x02)?;
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 2);
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::OneScreenNT0));
+    Ok(())
+}
+
+#[test]
+fn amrom_has_bus_conflicts() -> Result<()> {
+    let mut nes = ines(7, 8, 0, 0).prg_banks_of(0x8000).nes2(2).nes()?;
+
+    // bank 0 is all zeros, so the written value is lost
+    buscpu::write(&mut nes, 0x8000, 0x03)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 0);
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::tests::ines;
+
+#[test]
+fn bnrom_switches_32k_prg() -> Result<()> {
+    let mut nes = ines(34, 8, 0, 0).prg_banks_of(0x8000).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "BNROM");
+
+    buscpu::write(&mut nes, 0x8000, 2)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 2);
+    assert_eq!(buscpu::read(&mut nes, 0xfffe)?, 2);
+    Ok(())
+}
+
+#[test]
+fn bnrom_with_bus_conflicts() -> Result<()> {
+    let mut rom = ines(34, 8, 0, 0).prg_banks_of(0x8000).nes2(2);
+    // $FF at the end of bank 0 lets the first write through unchanged
+    rom.prg[0x7fff] = 0xff;
+    let mut nes = rom.nes()?;
+    buscpu::write(&mut nes, 0xffff, 3)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+
+    // bank 3 holds 3, so 2 & 3 selects bank 2 and 1 & 2 selects bank 0
+    buscpu::write(&mut nes, 0x8000, 2)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 2);
+    buscpu::write(&mut nes, 0x8000, 1)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 0);
+    Ok(())
+}
+
+#[test]
+fn nina001_switches_prg_and_chr() -> Result<()> {
+    let mut nes = ines(34, 8, 2, 0).prg_banks_of(0x8000).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "NINA-001");
+
+    buscpu::write(&mut nes, 0x7ffd, 1)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 1);
+    buscpu::write(&mut nes, 0x7ffe, 3)?;
+    buscpu::write(&mut nes, 0x7fff, 2)?;
+    assert_eq!(cartridge::chr_offset(&nes, 0x0000), Some(3 * 0x1000));
+    assert_eq!(cartridge::chr_offset(&nes, 0x1010), Some(2 * 0x1000 + 0x10));
+
+    // the registers are backed by PRG RAM
+    assert_eq!(buscpu::read(&mut nes, 0x7ffe)?, 3);
+    buscpu::write(&mut nes, 0x6000, 0x42)?;
+    assert_eq!(buscpu::read(&mut nes, 0x6000)?, 0x42);
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::tests::ines;
+
+#[test]
+fn uxrom_without_bus_conflicts() -> Result<()> {
+    let mut nes = ines(2, 8, 0, 0).nes2(1).nes()?;
+    buscpu::write(&mut nes, 0x8000, 5)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 5);
+    Ok(())
+}
+
+#[test]
+fn uxrom_with_bus_conflicts() -> Result<()> {
+    let mut nes = ines(2, 8, 0, 0).nes2(2).nes()?;
+    // the fixed bank holds 7 everywhere, so 5 & 7 goes through
+    buscpu::write(&mut nes, 0xc000, 5)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 5);
+
+    // bank 5 holds 5, so 6 & 5 selects bank 4
+    buscpu::write(&mut nes, 0x8000, 6)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 4);
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge::Mirroring;
+use crate::tests::ines;
+
+#[test]
+fn camerica_switches_16k_prg() -> Result<()> {
+    let mut nes = ines(71, 8, 0, 0).nes2(0).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "Camerica");
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 7);
+
+    buscpu::write(&mut nes, 0xc000, 3)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 7);
+
+    // $8000-$8FFF is not a register without the Fire Hawk board
+    buscpu::write(&mut nes, 0x8000, 0x10)?;
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::Horizontal));
+    Ok(())
+}
+
+#[test]
+fn camerica_fire_hawk_mirroring() -> Result<()> {
+    let mut nes = ines(71, 8, 0, 0).nes2(1).nes()?;
+    buscpu::write(&mut nes, 0x8000, 0x10)?;
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::OneScreenNT1));
+    buscpu::write(&mut nes, 0x9000, 0x00)?;
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::OneScreenNT0));
+
+    // the bank register is untouched
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 0);
+    Ok(())
+}
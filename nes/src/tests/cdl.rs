@@ -0,0 +1,68 @@
+use std::fs;
+
+use anyhow::Result;
+
+use crate::cdl;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::Nes;
+
+#[test]
+fn cdl_flags_code_and_data() -> Result<()> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&fs::read("test-files/nestest.nes")?)?;
+    nes.reset()?;
+    nes.cpu.pc = 0xc000;
+    cdl::start(&mut nes);
+    for _ in 0..2000 {
+        nes.step()?;
+    }
+
+    // nestest is NROM-128, $C000 maps to PRG offset 0
+    let prg = &nes.cdl.prg;
+    assert_eq!(prg.len(), 0x4000);
+    // JMP $C5F5 is code, its slot bits say it ran from $C000-$DFFF
+    assert_eq!(prg[0x0000], cdl::CODE | 0b10 << 2);
+    assert_eq!(prg[0x0001] & cdl::CODE, cdl::CODE);
+    assert_eq!(prg[0x0002] & cdl::CODE, cdl::CODE);
+    assert_eq!(prg[0x0003] & (cdl::CODE | cdl::DATA), 0);
+
+    let coverage = cdl::coverage(&nes);
+    assert!(coverage.code > 0);
+    assert!(coverage.unused > 0);
+
+    let exported = cdl::export(&nes);
+    assert_eq!(exported.len(), 0x4000 + 0x2000);
+    assert_eq!(&exported[..0x4000], &nes.cdl.prg[..]);
+
+    // a save merged back into a fresh log restores it
+    cdl::clear(&mut nes);
+    assert_eq!(cdl::coverage(&nes).code, 0);
+    cdl::import(&mut nes, &exported)?;
+    assert_eq!(cdl::export(&nes), exported);
+    Ok(())
+}
+
+#[test]
+fn cdl_restarts_when_a_rom_is_loaded() -> Result<()> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&fs::read("test-files/nestest.nes")?)?;
+    nes.reset()?;
+    cdl::start(&mut nes);
+    for _ in 0..100 {
+        nes.step()?;
+    }
+    assert!(cdl::coverage(&nes).code > 0);
+
+    // the log keeps running for the new game without the old game's offsets
+    nes.load(&fs::read("test-files/color_test.nes")?)?;
+    assert!(nes.cdl.enabled);
+    assert_eq!(nes.cdl.prg.len(), 0x8000);
+    assert_eq!(cdl::coverage(&nes).code, 0);
+
+    cdl::stop(&mut nes);
+    nes.load(&fs::read("test-files/nestest.nes")?)?;
+    assert!(nes.cdl.prg.is_empty());
+    assert_eq!(cdl::export(&nes).len(), 0x4000 + 0x2000);
+    Ok(())
+}
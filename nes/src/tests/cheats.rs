@@ -0,0 +1,196 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cheats;
+use crate::cheats::GameGenie;
+use crate::cheats::RamCheat;
+use crate::cheats::SearchFilter;
+use crate::cheats::WatchType;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::tests::ines;
+use crate::Nes;
+
+// NROM with 16 KB of PRG, mirrored at $8000 and $C000
+fn nes_with_prg(offset: usize, data: u8) -> Result<Nes<NoScreen, NoAudio>> {
+    let mut rom = ines(0, 1, 0, 0);
+    rom.prg.fill(0xea);
+    rom.prg[offset] = data;
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&rom.bytes())?;
+    Ok(nes)
+}
+
+// MMC1 with 32 KB of PRG, CHR RAM and 8 KB of PRG RAM
+fn nes_with_prg_ram() -> Result<Nes<NoScreen, NoAudio>> {
+    let mut rom = ines(1, 2, 0, 0);
+    rom.prg.fill(0xea);
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&rom.bytes())?;
+    Ok(nes)
+}
+
+fn run_to_vblank(nes: &mut Nes<NoScreen, NoAudio>) -> Result<()> {
+    while !(nes.ppu.scan_line == 241 && nes.ppu.scan_cycle == 2) {
+        nes.clock()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn game_genie_codes_are_decoded() -> Result<()> {
+    // infinite lives in Super Mario Bros.
+    let genie = GameGenie::decode("sxiopo")?;
+    assert_eq!(genie.code, "SXIOPO");
+    assert_eq!(
+        (genie.addr, genie.value, genie.compare),
+        (0x91d9, 0xad, None)
+    );
+
+    let genie = GameGenie::decode("SXIOPOVK")?;
+    assert_eq!(
+        (genie.addr, genie.value, genie.compare),
+        (0x91d9, 0xad, Some(0xce))
+    );
+
+    assert!(GameGenie::decode("SXIOP").is_err());
+    assert!(GameGenie::decode("SXIOPB").is_err());
+    Ok(())
+}
+
+#[test]
+fn game_genie_patches_prg_reads() -> Result<()> {
+    let mut nes = nes_with_prg(0x11d9, 0x12)?;
+    assert_eq!(cheats::add_game_genie(&mut nes, "SXIOPO")?, 0);
+    assert!(cheats::add_game_genie(&mut nes, "sxiopo").is_err());
+    assert_eq!(buscpu::read(&mut nes, 0x91d9)?, 0xad);
+    // the mirror at $D1D9 is another address
+    assert_eq!(buscpu::read(&mut nes, 0xd1d9)?, 0x12);
+
+    cheats::enable_game_genie(&mut nes, 0, false)?;
+    assert_eq!(buscpu::read(&mut nes, 0x91d9)?, 0x12);
+    cheats::enable_game_genie(&mut nes, 0, true)?;
+    assert_eq!(buscpu::read(&mut nes, 0x91d9)?, 0xad);
+
+    assert_eq!(cheats::remove_game_genie(&mut nes, 0)?.code, "SXIOPO");
+    assert_eq!(buscpu::read(&mut nes, 0x91d9)?, 0x12);
+    assert!(cheats::remove_game_genie(&mut nes, 0).is_err());
+    Ok(())
+}
+
+#[test]
+fn game_genie_compare_value_gates_patch() -> Result<()> {
+    let mut nes = nes_with_prg(0x11d9, 0xce)?;
+    cheats::add_game_genie(&mut nes, "SXIOPOVK")?;
+    assert_eq!(buscpu::read(&mut nes, 0x91d9)?, 0xad);
+
+    let mut nes = nes_with_prg(0x11d9, 0xcf)?;
+    cheats::add_game_genie(&mut nes, "SXIOPOVK")?;
+    assert_eq!(buscpu::read(&mut nes, 0x91d9)?, 0xcf);
+    Ok(())
+}
+
+#[test]
+fn ram_codes_are_decoded() -> Result<()> {
+    let cheat = RamCheat::decode("075a:09")?;
+    assert_eq!(cheat.code, "075A:09");
+    assert_eq!((cheat.addr, cheat.value), (0x075a, 0x09));
+    assert_eq!(RamCheat::decode("6123FF")?.addr, 0x6123);
+
+    assert!(RamCheat::decode("075A:9").is_err());
+    assert!(RamCheat::decode("07G5:09").is_err());
+    // six bytes but not six digits
+    assert!(RamCheat::decode("12€4").is_err());
+    // PPU registers are not RAM
+    assert!(RamCheat::decode("2000:80").is_err());
+    Ok(())
+}
+
+#[test]
+fn ram_codes_freeze_each_frame() -> Result<()> {
+    let mut nes = nes_with_prg(0, 0xea)?;
+    cheats::add_ram_cheat(&mut nes, "075A:09")?;
+    assert!(cheats::add_ram_cheat(&mut nes, "075a09").is_err());
+    nes.bus_cpu.ram[0x075a] = 2;
+    run_to_vblank(&mut nes)?;
+    assert_eq!(nes.bus_cpu.ram[0x075a], 9);
+
+    cheats::enable_ram_cheat(&mut nes, 0, false)?;
+    nes.bus_cpu.ram[0x075a] = 2;
+    nes.clock()?;
+    run_to_vblank(&mut nes)?;
+    assert_eq!(nes.bus_cpu.ram[0x075a], 2);
+
+    assert_eq!(cheats::remove_ram_cheat(&mut nes, 0)?.code, "075A:09");
+    assert!(cheats::enable_ram_cheat(&mut nes, 0, true).is_err());
+    Ok(())
+}
+
+#[test]
+fn ram_codes_freeze_prg_ram() -> Result<()> {
+    let mut nes = nes_with_prg_ram()?;
+    cheats::add_ram_cheat(&mut nes, "6010:42")?;
+    run_to_vblank(&mut nes)?;
+    assert_eq!(buscpu::peek(&nes, 0x6010)?, 0x42);
+
+    // no PRG RAM on NROM
+    let mut nes = nes_with_prg(0, 0xea)?;
+    assert!(buscpu::poke(&mut nes, 0x0800, 7)?);
+    assert_eq!(nes.bus_cpu.ram[0], 7);
+    assert!(!buscpu::poke(&mut nes, 0x6010, 0x42)?);
+    assert!(!buscpu::poke(&mut nes, 0x8000, 0x42)?);
+    assert_eq!(buscpu::peek(&nes, 0x8000)?, 0xea);
+    Ok(())
+}
+
+#[test]
+fn search_narrows_candidates() -> Result<()> {
+    let mut nes = nes_with_prg(0, 0xea)?;
+    assert!(cheats::search_filter(&mut nes, SearchFilter::Equal).is_err());
+    assert_eq!(cheats::search_start(&mut nes)?, 0x800);
+
+    nes.bus_cpu.ram[0x10] = 5;
+    nes.bus_cpu.ram[0x20] = 3;
+    assert_eq!(cheats::search_filter(&mut nes, SearchFilter::Changed)?, 2);
+    nes.bus_cpu.ram[0x10] = 4;
+    nes.bus_cpu.ram[0x20] = 6;
+    assert_eq!(cheats::search_filter(&mut nes, SearchFilter::Less)?, 1);
+    assert_eq!(cheats::search_filter(&mut nes, SearchFilter::Equal)?, 1);
+    assert_eq!(
+        nes.cheats.search.as_ref().unwrap().candidates,
+        vec![(0x10, 4)]
+    );
+    assert_eq!(cheats::search_filter(&mut nes, SearchFilter::Greater)?, 0);
+
+    cheats::search_start(&mut nes)?;
+    assert_eq!(cheats::search_filter(&mut nes, SearchFilter::Value(6))?, 1);
+
+    // PRG RAM is searched too
+    let mut nes = nes_with_prg_ram()?;
+    assert_eq!(cheats::search_start(&mut nes)?, 0x800 + 0x2000);
+    Ok(())
+}
+
+#[test]
+fn watches_read_typed_values() -> Result<()> {
+    let mut nes = nes_with_prg(0, 0xea)?;
+    nes.bus_cpu.ram[0x30] = 0x98;
+    nes.bus_cpu.ram[0x31] = 0x76;
+    let mut value = |kind| {
+        let index = cheats::add_watch(&mut nes, 0x30, kind, "score");
+        cheats::watch_value(&nes, &nes.cheats.watches[index])
+    };
+    assert_eq!(value(WatchType::U8)?, 0x98);
+    assert_eq!(value(WatchType::I8)?, -0x68);
+    assert_eq!(value(WatchType::U16)?, 0x7698);
+    assert_eq!(value(WatchType::I16)?, 0x7698);
+    assert_eq!(value(WatchType::Bcd8)?, 98);
+    assert_eq!(value(WatchType::Bcd16)?, 9876);
+
+    nes.bus_cpu.ram[0x31] = 0xff;
+    let watch = cheats::remove_watch(&mut nes, 3)?;
+    assert_eq!(watch.label, "score");
+    assert_eq!(cheats::watch_value(&nes, &watch)?, -0x68);
+    assert_eq!(nes.cheats.watches.len(), 5);
+    Ok(())
+}
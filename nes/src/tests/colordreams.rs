@@ -0,0 +1,33 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::tests::ines;
+
+#[test]
+fn colordreams_switches_prg_and_chr() -> Result<()> {
+    let mut rom = ines(11, 8, 16, 0).prg_banks_of(0x8000);
+    // the board has bus conflicts, $FF at the end of bank 0 lets the write through
+    rom.prg[0x7fff] = 0xff;
+    let mut nes = rom.nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "Color Dreams");
+
+    buscpu::write(&mut nes, 0xffff, 0xa2)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 2);
+    assert_eq!(buscpu::read(&mut nes, 0xfffa)?, 2);
+    assert_eq!(
+        cartridge::chr_offset(&nes, 0x0123),
+        Some(10 * 0x2000 + 0x123)
+    );
+    Ok(())
+}
+
+#[test]
+fn colordreams_has_bus_conflicts() -> Result<()> {
+    let mut nes = ines(11, 8, 16, 0).prg_banks_of(0x8000).nes()?;
+    // bank 0 is all zeros, so the written value is lost
+    buscpu::write(&mut nes, 0x8000, 0x12)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 0);
+    assert_eq!(cartridge::chr_offset(&nes, 0x0000), Some(0));
+    Ok(())
+}
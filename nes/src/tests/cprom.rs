@@ -0,0 +1,27 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::busppu;
+use crate::tests::ines;
+
+#[test]
+fn cprom_switches_upper_chr_ram_page() -> Result<()> {
+    let mut rom = ines(13, 2, 0, 0);
+    // the board has bus conflicts, $FF at $8000 lets the writes through
+    rom.prg[0] = 0xff;
+    let mut nes = rom.nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "CPROM");
+    assert_eq!(nes.cartridge.chrmem.len(), 0x4000);
+
+    for page in 0..4 {
+        buscpu::write(&mut nes, 0x8000, page)?;
+        busppu::write(&mut nes, 0x1000, 0x10 + page)?;
+    }
+    // $0000 always shows page 0
+    assert_eq!(busppu::read(&mut nes, 0x0000)?, 0x10);
+    for page in 0..4 {
+        buscpu::write(&mut nes, 0x8000, page)?;
+        assert_eq!(busppu::read(&mut nes, 0x1000)?, 0x10 + page);
+    }
+    Ok(())
+}
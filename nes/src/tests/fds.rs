@@ -0,0 +1,191 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::disk::Disk;
+use crate::disk::SIDE_SIZE;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::patch;
+use crate::Nes;
+
+// One side with the disk info, file count, and a single 4 byte file
+fn disk_side() -> Vec<u8> {
+    let mut side = b"\x01*NINTENDO-HVC*".to_vec();
+    side.resize(56, 0);
+    side.extend([0x02, 0x01]);
+    let mut header = vec![0x03, 0x00, 0x00];
+    header.extend(b"FILE    ");
+    header.extend([0x00, 0x60, 0x04, 0x00, 0x00]);
+    side.extend(header);
+    side.extend([0x04, 0xde, 0xad, 0xbe, 0xef]);
+    side.resize(SIDE_SIZE, 0);
+    side
+}
+
+fn disk_image(sides: u8) -> Vec<u8> {
+    let mut image = b"FDS\x1a".to_vec();
+    image.push(sides);
+    image.resize(16, 0);
+    for _ in 0..sides {
+        image.extend(disk_side());
+    }
+    image
+}
+
+fn fds_nes() -> Result<Nes<NoScreen, NoAudio>> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load_disk(&disk_image(2), &[0; 0x2000])?;
+    nes.reset()?;
+    Ok(nes)
+}
+
+#[test]
+fn disk_image_round_trips() -> Result<()> {
+    let image = disk_image(2);
+    let disk = Disk::from_image(&image)?;
+    assert_eq!(disk.sides.len(), 2);
+    assert_eq!(disk.image(), image);
+
+    // headerless images are accepted too
+    let disk = Disk::from_image(&image[16..])?;
+    assert_eq!(disk.image(), &image[16..]);
+    assert!(Disk::from_image(&image[..1000]).is_err());
+    Ok(())
+}
+
+#[test]
+fn disk_writes_are_saved_as_a_patch() -> Result<()> {
+    let image = disk_image(1);
+    let mut disk = Disk::from_image(&image)?;
+    // change the last byte of the file
+    let pos = disk.sides[0]
+        .iter()
+        .rposition(|&data| data == 0xef)
+        .unwrap();
+    disk.sides[0][pos] = 0x42;
+
+    let saved = disk.image();
+    assert_eq!(saved[16 + 56 + 2 + 16 + 4], 0x42);
+    let ips = patch::create_ips(&image, &saved)?;
+    assert_eq!(patch::apply_ips(&image, &ips)?, saved);
+    Ok(())
+}
+
+#[test]
+fn fds_drive_reads_blocks_after_gap() -> Result<()> {
+    let mut nes = fds_nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "FDS");
+    buscpu::write(&mut nes, 0x4023, 0x01)?;
+    assert_eq!(buscpu::read(&mut nes, 0x4032)? & 0x01, 0);
+
+    // motor on, read mode, disk ready
+    buscpu::write(&mut nes, 0x4025, 0x65)?;
+    let mut bytes = vec![];
+    for _ in 0..1_000_000 {
+        cartridge::clock(&mut nes)?;
+        if buscpu::peek(&nes, 0x4030)? & 0x02 != 0 {
+            bytes.push(buscpu::read(&mut nes, 0x4031)?);
+            if bytes.len() == 16 {
+                break;
+            }
+        }
+    }
+    assert_eq!(bytes[..2], [0x80, 0x01]);
+    assert_eq!(&bytes[2..16], b"*NINTENDO-HVC*");
+    Ok(())
+}
+
+#[test]
+fn fds_drive_restarts_on_a_new_side() -> Result<()> {
+    let mut nes = fds_nes()?;
+    buscpu::write(&mut nes, 0x4023, 0x01)?;
+    buscpu::write(&mut nes, 0x4025, 0x65)?;
+    for _ in 0..1_000_000 {
+        cartridge::clock(&mut nes)?;
+    }
+
+    // the head is past the end of a shorter side put in without ejecting the first
+    let disk = nes.cartridge.disk.as_mut().unwrap();
+    disk.sides[1].truncate(3600);
+    disk.insert(1)?;
+    buscpu::read(&mut nes, 0x4031)?;
+    let mut bytes = vec![];
+    for _ in 0..1_000_000 {
+        cartridge::clock(&mut nes)?;
+        if buscpu::peek(&nes, 0x4030)? & 0x02 != 0 {
+            bytes.push(buscpu::read(&mut nes, 0x4031)?);
+        }
+    }
+    assert_eq!(bytes[..2], [0x80, 0x01]);
+    // and the motor stops at the end of the side
+    assert_eq!(buscpu::read(&mut nes, 0x4032)? & 0x02, 0x02);
+    Ok(())
+}
+
+#[test]
+fn fds_timer_irq() -> Result<()> {
+    let mut nes = fds_nes()?;
+    buscpu::write(&mut nes, 0x4023, 0x01)?;
+    buscpu::write(&mut nes, 0x4020, 10)?;
+    buscpu::write(&mut nes, 0x4021, 0)?;
+    buscpu::write(&mut nes, 0x4022, 0x02)?;
+    for _ in 0..10 {
+        cartridge::clock(&mut nes)?;
+    }
+    assert!(!cartridge::irq(&nes)?);
+    cartridge::clock(&mut nes)?;
+    assert!(cartridge::irq(&nes)?);
+
+    // reading the status acknowledges, and without repeat the timer stops
+    assert_eq!(buscpu::read(&mut nes, 0x4030)? & 0x01, 0x01);
+    assert!(!cartridge::irq(&nes)?);
+    for _ in 0..20 {
+        cartridge::clock(&mut nes)?;
+    }
+    assert!(!cartridge::irq(&nes)?);
+    Ok(())
+}
+
+#[test]
+fn fds_disk_swap_ejects_first() -> Result<()> {
+    let mut nes = fds_nes()?;
+    buscpu::write(&mut nes, 0x4023, 0x01)?;
+    nes.cartridge.disk.as_mut().unwrap().swap(1)?;
+    assert_eq!(buscpu::read(&mut nes, 0x4032)? & 0x01, 0x01);
+
+    for _ in 0..2_000_000 {
+        cartridge::clock(&mut nes)?;
+    }
+    assert_eq!(nes.cartridge.disk.as_ref().unwrap().inserted, Some(1));
+    assert_eq!(buscpu::read(&mut nes, 0x4032)? & 0x01, 0);
+    assert!(nes.cartridge.disk.as_mut().unwrap().swap(2).is_err());
+    Ok(())
+}
+
+#[test]
+fn fds_wavetable_audio() -> Result<()> {
+    let mut nes = fds_nes()?;
+    buscpu::write(&mut nes, 0x4023, 0x02)?;
+    buscpu::write(&mut nes, 0x4089, 0x80)?;
+    for i in 0..64 {
+        buscpu::write(&mut nes, 0x4040 + i, 0x3f)?;
+    }
+    buscpu::write(&mut nes, 0x4089, 0x00)?;
+    // envelope off with a gain of 32
+    buscpu::write(&mut nes, 0x4080, 0xa0)?;
+    assert_eq!(buscpu::read(&mut nes, 0x4090)?, 32);
+    assert_eq!(cartridge::audio_output(&nes)?, Some(0.5));
+
+    buscpu::write(&mut nes, 0x4082, 0x00)?;
+    buscpu::write(&mut nes, 0x4083, 0x01)?;
+    for _ in 0..100 {
+        cartridge::clock(&mut nes)?;
+    }
+    assert_eq!(cartridge::audio_output(&nes)?, Some(0.5));
+
+    // master volume 2/5
+    buscpu::write(&mut nes, 0x4089, 0x03)?;
+    assert_eq!(cartridge::audio_output(&nes)?, Some(0.2));
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::tests::ines;
+use crate::Nes;
+
+fn command(nes: &mut Nes<NoScreen, NoAudio>, command: u8, data: u8) -> Result<()> {
+    buscpu::write(nes, 0x8000, command)?;
+    buscpu::write(nes, 0xa000, data)
+}
+
+#[test]
+fn fme7_prg_rom_and_ram_at_6000() -> Result<()> {
+    let mut nes = ines(69, 8, 1, 0).prg_banks_of(0x2000).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "FME-7");
+
+    command(&mut nes, 0x9, 3)?;
+    command(&mut nes, 0xb, 7)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 7);
+    assert_eq!(buscpu::read(&mut nes, 0xe000)?, 15);
+
+    // ROM bank at $6000, then enabled RAM
+    command(&mut nes, 0x8, 0x05)?;
+    assert_eq!(buscpu::read(&mut nes, 0x6000)?, 5);
+    command(&mut nes, 0x8, 0xc0)?;
+    buscpu::write(&mut nes, 0x6010, 0x42)?;
+    assert_eq!(buscpu::read(&mut nes, 0x6010)?, 0x42);
+    Ok(())
+}
+
+#[test]
+fn fme7_cycle_irq() -> Result<()> {
+    let mut nes = ines(69, 8, 1, 0).prg_banks_of(0x2000).nes()?;
+    command(&mut nes, 0xe, 3)?;
+    command(&mut nes, 0xf, 0)?;
+    command(&mut nes, 0xd, 0x81)?;
+
+    for _ in 0..3 {
+        cartridge::clock(&mut nes)?;
+    }
+    assert!(!cartridge::irq(&nes)?);
+    cartridge::clock(&mut nes)?;
+    assert!(cartridge::irq(&nes)?);
+
+    // writing the control register acknowledges
+    command(&mut nes, 0xd, 0x81)?;
+    assert!(!cartridge::irq(&nes)?);
+    Ok(())
+}
+
+#[test]
+fn fme7_irq_counts_while_stepping() -> Result<()> {
+    let mut rom = ines(69, 8, 1, 0);
+    rom.prg.fill(0xea);
+    let mut nes = rom.nes()?;
+    command(&mut nes, 0xe, 9)?;
+    command(&mut nes, 0xf, 0)?;
+    command(&mut nes, 0xd, 0x81)?;
+
+    // the mapper is clocked for every cycle of the instructions, not once per step
+    nes.step()?;
+    assert!(!cartridge::irq(&nes)?);
+    for _ in 0..4 {
+        nes.step()?;
+    }
+    assert!(cartridge::irq(&nes)?);
+    Ok(())
+}
+
+#[test]
+fn sunsoft_5b_square_output() -> Result<()> {
+    let mut nes = ines(69, 8, 1, 0).prg_banks_of(0x2000).nes()?;
+    let mut audio = |reg: u8, data: u8| -> Result<()> {
+        buscpu::write(&mut nes, 0xc000, reg)?;
+        buscpu::write(&mut nes, 0xe000, data)
+    };
+    // channel A tone only, full volume
+    audio(0x00, 0x01)?;
+    audio(0x07, 0x3e)?;
+    audio(0x08, 0x0f)?;
+
+    let mut levels = vec![];
+    for _ in 0..64 {
+        cartridge::clock(&mut nes)?;
+        levels.push(cartridge::audio_output(&nes)?.unwrap());
+    }
+    assert!(levels.contains(&0.));
+    assert!(levels.iter().any(|&level| level > 0.1));
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+
+use crate::cartridge::Mirroring;
+use crate::gamedb;
+use crate::gamedb::GameDb;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::tests::ines;
+use crate::Nes;
+
+// NROM with horizontal mirroring and no CHR ROM, as a bad dump of a UxROM game would say
+fn rom() -> Vec<u8> {
+    ines(0, 2, 0, 0).prg_banks_of(0x100).bytes()
+}
+
+#[test]
+fn hashes_match_reference_values() {
+    assert_eq!(gamedb::crc32(b"123456789"), 0xcbf43926);
+    let hex = |hash: [u8; 20]| {
+        hash.iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    };
+    assert_eq!(
+        hex(gamedb::sha1(b"abc")),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    // two blocks once padded
+    assert_eq!(
+        hex(gamedb::sha1(&[b'a'; 56])),
+        "c2db330f6083854c99d4b5bfb6e8f29f201be699"
+    );
+}
+
+#[test]
+fn database_lines_are_parsed() -> Result<()> {
+    assert!(!GameDb::default().is_empty());
+
+    let mut db = GameDb::empty();
+    let count =
+        db.load("# comment\n\ncrc32=0000abcd; name=Test; mapper=4; prgram=8K; chrram=16K\n")?;
+    assert_eq!(count, 1);
+    assert_eq!(db.len(), 1);
+
+    let err = db.load("crc32=1\nname=No hash\n").unwrap_err();
+    assert_eq!(err.to_string(), "Game database line 2");
+    assert!(db.load("crc32=1; mirroring=diagonal").is_err());
+    assert!(db.load("crc32=1; colour=red").is_err());
+    assert!(db.load("sha1=1234").is_err());
+    Ok(())
+}
+
+#[test]
+fn database_entry_overrides_header() -> Result<()> {
+    let rom = rom();
+    let crc = gamedb::crc32(&rom[16..]);
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.gamedb.load(&format!(
+        "crc32={:08X}; name=Test; mapper=2; mirroring=vertical; battery=yes; chrram=16K",
+        crc
+    ))?;
+    nes.load(&rom)?;
+    assert_eq!(nes.cartridge.mapper_id, 2);
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "UxROM");
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::Vertical));
+    assert!(nes.cartridge.battery);
+    assert_eq!(nes.cartridge.chrmem.len(), 0x4000);
+    let game = nes.cartridge.game.as_ref().unwrap();
+    assert_eq!(game.name, "Test");
+
+    // a SHA-1 entry wins over the CRC32 one
+    nes.gamedb.load(&format!(
+        "sha1={}; mapper=0",
+        gamedb::sha1(&rom[16..])
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    ))?;
+    nes.load(&rom)?;
+    assert_eq!(nes.cartridge.mapper_id, 0);
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::Horizontal));
+
+    // unknown ROMs keep their header
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&rom)?;
+    assert!(nes.cartridge.game.is_none());
+    assert_eq!(nes.cartridge.mapper_id, 0);
+    Ok(())
+}
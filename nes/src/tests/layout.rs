@@ -0,0 +1,157 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::mappers::Bank;
+use crate::mappers::Memory;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::tests::ines;
+use crate::Nes;
+
+#[test]
+fn nrom_layout() -> Result<()> {
+    let nes = ines(0, 1, 1, 0).nes()?;
+    assert_eq!(
+        cartridge::prg_layout(&nes)?,
+        [
+            Bank::new(0x8000, 0x4000, Memory::PrgRom, 0),
+            Bank::new(0xc000, 0x4000, Memory::PrgRom, 0),
+        ]
+    );
+    assert_eq!(
+        cartridge::chr_layout(&nes)?,
+        [Bank::new(0x0000, 0x2000, Memory::ChrRom, 0)]
+    );
+    // the single bank is mirrored
+    assert_eq!(cartridge::prg_offset(&nes, 0xc123), Some(0x0123));
+
+    let nes = ines(0, 2, 0, 0).nes()?;
+    assert_eq!(
+        cartridge::prg_layout(&nes)?,
+        [Bank::new(0x8000, 0x8000, Memory::PrgRom, 0)]
+    );
+    assert_eq!(
+        cartridge::chr_layout(&nes)?,
+        [Bank::new(0x0000, 0x2000, Memory::ChrRam, 0)]
+    );
+    assert_eq!(cartridge::prg_offset(&nes, 0xc123), Some(0x4123));
+    assert_eq!(cartridge::prg_offset(&nes, 0x6000), None);
+    Ok(())
+}
+
+// Shift a value into an MMC1 register, one CPU cycle apart
+fn mmc1_write(nes: &mut Nes<NoScreen, NoAudio>, addr: u16, data: u8) -> Result<()> {
+    for bit in 0..5 {
+        buscpu::write(nes, addr, (data >> bit) & 1)?;
+        cartridge::clock(nes)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn mmc1_layout() -> Result<()> {
+    let mut nes = ines(1, 8, 2, 0).chr_banks_of(0x1000).nes()?;
+    // 16 KB PRG and 4 KB CHR banks
+    mmc1_write(&mut nes, 0x8000, 0x1c)?;
+    mmc1_write(&mut nes, 0xe000, 0x02)?;
+    mmc1_write(&mut nes, 0xa000, 0x01)?;
+    mmc1_write(&mut nes, 0xc000, 0x03)?;
+    assert_eq!(
+        cartridge::prg_layout(&nes)?,
+        [
+            Bank::new(0x6000, 0x2000, Memory::PrgRam, 0),
+            Bank::new(0x8000, 0x4000, Memory::PrgRom, 2 * 0x4000),
+            Bank::new(0xc000, 0x4000, Memory::PrgRom, 7 * 0x4000),
+        ]
+    );
+    assert_eq!(
+        cartridge::chr_layout(&nes)?,
+        [
+            Bank::new(0x0000, 0x1000, Memory::ChrRom, 0x1000),
+            Bank::new(0x1000, 0x1000, Memory::ChrRom, 3 * 0x1000),
+        ]
+    );
+
+    // 32 KB PRG and 8 KB CHR banks, the low bank bit is ignored
+    mmc1_write(&mut nes, 0x8000, 0x00)?;
+    mmc1_write(&mut nes, 0xe000, 0x03)?;
+    assert_eq!(
+        cartridge::prg_layout(&nes)?[1..],
+        [Bank::new(0x8000, 0x8000, Memory::PrgRom, 2 * 0x4000)]
+    );
+    assert_eq!(
+        cartridge::chr_layout(&nes)?,
+        [Bank::new(0x0000, 0x2000, Memory::ChrRom, 0)]
+    );
+    assert_eq!(cartridge::prg_offset(&nes, 0xc000), Some(3 * 0x4000));
+    Ok(())
+}
+
+#[test]
+fn uxrom_layout() -> Result<()> {
+    let mut nes = ines(2, 8, 0, 0).nes()?;
+    buscpu::write(&mut nes, 0x8000, 0x05)?;
+    assert_eq!(
+        cartridge::prg_layout(&nes)?,
+        [
+            Bank::new(0x8000, 0x4000, Memory::PrgRom, 5 * 0x4000),
+            Bank::new(0xc000, 0x4000, Memory::PrgRom, 7 * 0x4000),
+        ]
+    );
+    assert_eq!(cartridge::prg_offset(&nes, 0x8001), Some(5 * 0x4000 + 1));
+    assert_eq!(cartridge::prg_offset(&nes, 0xffff), Some(8 * 0x4000 - 1));
+    Ok(())
+}
+
+#[test]
+fn uxrom_layout_without_prg() -> Result<()> {
+    // a bad header, the layout can still be asked for
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&ines(2, 0, 0, 0).bytes())?;
+    assert_eq!(
+        cartridge::prg_layout(&nes)?[1],
+        Bank::new(0xc000, 0x4000, Memory::PrgRom, 0)
+    );
+    assert_eq!(cartridge::prg_offset(&nes, 0xc000), Some(0));
+    Ok(())
+}
+
+#[test]
+fn cnrom_layout() -> Result<()> {
+    let mut nes = ines(3, 2, 4, 0).nes()?;
+    buscpu::write(&mut nes, 0x8000, 0x02)?;
+    assert_eq!(
+        cartridge::prg_layout(&nes)?,
+        [Bank::new(0x8000, 0x8000, Memory::PrgRom, 0)]
+    );
+    assert_eq!(
+        cartridge::chr_layout(&nes)?,
+        [Bank::new(0x0000, 0x2000, Memory::ChrRom, 2 * 0x2000)]
+    );
+    assert_eq!(
+        cartridge::chr_offset(&nes, 0x1234),
+        Some(2 * 0x2000 + 0x1234)
+    );
+    Ok(())
+}
+
+#[test]
+fn gxrom_layout() -> Result<()> {
+    let mut rom = ines(66, 8, 4, 0).prg_banks_of(0x8000);
+    // GxROM has bus conflicts, $FF at the end of bank 0 lets the write through
+    rom.prg[0x7fff] = 0xff;
+    let mut nes = rom.nes()?;
+    buscpu::write(&mut nes, 0xffff, 0x31)?;
+    assert_eq!(
+        cartridge::prg_layout(&nes)?,
+        [Bank::new(0x8000, 0x8000, Memory::PrgRom, 3 * 0x8000)]
+    );
+    assert_eq!(
+        cartridge::chr_layout(&nes)?,
+        [Bank::new(0x0000, 0x2000, Memory::ChrRom, 0x2000)]
+    );
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+    assert_eq!(cartridge::chr_offset(&nes, 0x0010), Some(0x2010));
+    Ok(())
+}
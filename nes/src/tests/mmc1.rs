@@ -0,0 +1,112 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::busppu;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::tests::ines;
+use crate::Nes;
+
+// NES 2.0 MMC1 image with 16 KB PRG banks and 4 KB CHR banks filled with their bank number
+fn mmc1_nes(prg_banks: u8, chr_banks: u8, prg_ram_shift: u8) -> Result<Nes<NoScreen, NoAudio>> {
+    let mut rom = ines(1, prg_banks, chr_banks, 0)
+        .chr_banks_of(0x1000)
+        .nes2(0);
+    rom.header[0xa] = prg_ram_shift;
+    rom.nes()
+}
+
+// Shift a value into an MMC1 register
+fn write_reg(nes: &mut Nes<NoScreen, NoAudio>, addr: u16, data: u8) -> Result<()> {
+    for bit in 0..5 {
+        buscpu::write(nes, addr, (data >> bit) & 1)?;
+    }
+    Ok(())
+}
+
+// Copy a program to RAM at $0000 and step through its instructions
+fn run_from_ram(nes: &mut Nes<NoScreen, NoAudio>, program: &[u8], steps: usize) -> Result<()> {
+    for (addr, &data) in program.iter().enumerate() {
+        buscpu::write(nes, addr as u16, data)?;
+    }
+    nes.cpu.pc = 0x0000;
+    // start on an instruction rather than the rest of the reset sequence
+    nes.cpu.cycles = 0;
+    for _ in 0..steps {
+        nes.step()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn mmc1_8k_chr_mode() -> Result<()> {
+    let mut nes = mmc1_nes(2, 4, 7)?;
+    write_reg(&mut nes, 0x8000, 0x0c)?;
+    write_reg(&mut nes, 0xa000, 0x03)?;
+    assert_eq!(busppu::read(&mut nes, 0x0000)?, 2);
+    assert_eq!(busppu::read(&mut nes, 0x1000)?, 3);
+
+    // 4 KB mode
+    write_reg(&mut nes, 0x8000, 0x1c)?;
+    write_reg(&mut nes, 0xc000, 0x05)?;
+    assert_eq!(busppu::read(&mut nes, 0x0000)?, 3);
+    assert_eq!(busppu::read(&mut nes, 0x1000)?, 5);
+    Ok(())
+}
+
+#[test]
+fn surom_selects_prg_half_with_chr_bit_4() -> Result<()> {
+    let mut nes = mmc1_nes(32, 0, 7)?;
+    write_reg(&mut nes, 0xe000, 0x02)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 2);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 15);
+
+    write_reg(&mut nes, 0xa000, 0x10)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 18);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 31);
+    Ok(())
+}
+
+#[test]
+fn sxrom_banks_32k_prg_ram() -> Result<()> {
+    let mut nes = mmc1_nes(16, 0, 9)?;
+    buscpu::write(&mut nes, 0x6000, 0x11)?;
+    write_reg(&mut nes, 0xa000, 0x04)?;
+    assert_eq!(buscpu::read(&mut nes, 0x6000)?, 0);
+    buscpu::write(&mut nes, 0x6000, 0x22)?;
+
+    write_reg(&mut nes, 0xa000, 0x00)?;
+    assert_eq!(buscpu::read(&mut nes, 0x6000)?, 0x11);
+
+    // PRG RAM disable bit
+    write_reg(&mut nes, 0xe000, 0x10)?;
+    buscpu::write(&mut nes, 0x6000, 0x33)?;
+    write_reg(&mut nes, 0xe000, 0x00)?;
+    assert_eq!(buscpu::read(&mut nes, 0x6000)?, 0x11);
+    Ok(())
+}
+
+#[test]
+fn mmc1_ignores_consecutive_writes() -> Result<()> {
+    let mut nes = mmc1_nes(8, 0, 7)?;
+    // INC $E000 writes the fixed bank's 7 then 8, only the 7 shifts in a 1
+    run_from_ram(&mut nes, &[0xee, 0x00, 0xe0], 1)?;
+    for bit in 1..5 {
+        buscpu::write(&mut nes, 0xe000, (0x03 >> bit) & 1)?;
+    }
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+    Ok(())
+}
+
+#[test]
+fn mmc1_takes_separate_writes_while_stepping() -> Result<()> {
+    let mut nes = mmc1_nes(8, 0, 7)?;
+    let mut program = vec![];
+    for bit in 0..5 {
+        // LDA #bit, STA $E000
+        program.extend([0xa9, (0x02 >> bit) & 1, 0x8d, 0x00, 0xe0]);
+    }
+    run_from_ram(&mut nes, &program, 10)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 2);
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::busppu;
+use crate::cartridge;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::tests::ines;
+use crate::Nes;
+
+#[test]
+fn mmc2_latches_switch_chr_on_fd_fe_fetches() -> Result<()> {
+    let mut nes = ines(9, 8, 16, 0).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "MMC2");
+
+    // $0000 FD/FE banks 4/5, $1000 FD/FE banks 6/7
+    buscpu::write(&mut nes, 0xb000, 4)?;
+    buscpu::write(&mut nes, 0xc000, 5)?;
+    buscpu::write(&mut nes, 0xd000, 6)?;
+    buscpu::write(&mut nes, 0xe000, 7)?;
+    assert_eq!(cartridge::chr_offset(&nes, 0x0000), Some(5 * 0x1000));
+    assert_eq!(cartridge::chr_offset(&nes, 0x1000), Some(7 * 0x1000));
+
+    // the fetch that trips the latch still comes from the old bank
+    busppu::read(&mut nes, 0x0fd8)?;
+    assert_eq!(cartridge::chr_offset(&nes, 0x0000), Some(4 * 0x1000));
+    // MMC2 only watches the first row of tile $FD/$FE in the low table
+    busppu::read(&mut nes, 0x0fe9)?;
+    assert_eq!(cartridge::chr_offset(&nes, 0x0000), Some(4 * 0x1000));
+    busppu::read(&mut nes, 0x0fe8)?;
+    assert_eq!(cartridge::chr_offset(&nes, 0x0000), Some(5 * 0x1000));
+
+    busppu::read(&mut nes, 0x1fdd)?;
+    assert_eq!(cartridge::chr_offset(&nes, 0x1000), Some(6 * 0x1000));
+
+    // peeking leaves the latches alone
+    busppu::peek(&nes, 0x1fe8)?;
+    assert_eq!(cartridge::chr_offset(&nes, 0x1000), Some(6 * 0x1000));
+    Ok(())
+}
+
+#[test]
+fn mmc2_maps_small_prg() -> Result<()> {
+    // 16 KB of PRG is less than the three fixed 8 KB banks
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&ines(9, 1, 1, 0).prg_banks_of(0x2000).bytes())?;
+    assert_eq!(cartridge::prg_offset(&nes, 0xa000), Some(0));
+    assert_eq!(cartridge::prg_offset(&nes, 0xe000), Some(0x2000));
+    Ok(())
+}
+
+#[test]
+fn mmc2_wraps_banks_to_small_roms() -> Result<()> {
+    // 32 KB PRG and 16 KB CHR, fewer banks than the registers can select
+    let mut nes = ines(9, 2, 2, 0)
+        .prg_banks_of(0x2000)
+        .chr_banks_of(0x1000)
+        .nes()?;
+    buscpu::write(&mut nes, 0xa000, 0x0f)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+    buscpu::write(&mut nes, 0xb000, 0x1f)?;
+    buscpu::write(&mut nes, 0xc000, 0x1e)?;
+    assert_eq!(busppu::read(&mut nes, 0x0000)?, 2);
+    busppu::read(&mut nes, 0x0fd8)?;
+    assert_eq!(busppu::read(&mut nes, 0x0000)?, 3);
+    Ok(())
+}
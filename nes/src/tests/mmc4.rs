@@ -0,0 +1,83 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::busppu;
+use crate::cartridge;
+use crate::mappers::Bank;
+use crate::mappers::Memory;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::tests::ines;
+use crate::Nes;
+
+#[test]
+fn mmc4_switches_16k_prg() -> Result<()> {
+    let mut nes = ines(10, 8, 16, 0).chr_banks_of(0x1000).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "MMC4");
+    buscpu::write(&mut nes, 0xa000, 3)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 7);
+    assert_eq!(
+        cartridge::prg_layout(&nes)?[1..],
+        [
+            Bank::new(0x8000, 0x4000, Memory::PrgRom, 3 * 0x4000),
+            Bank::new(0xc000, 0x4000, Memory::PrgRom, 7 * 0x4000),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn mmc4_latches_switch_chr_on_whole_fd_fe_tiles() -> Result<()> {
+    let mut nes = ines(10, 8, 16, 0).chr_banks_of(0x1000).nes()?;
+    // $0000 FD/FE banks 4/5, $1000 FD/FE banks 6/7
+    buscpu::write(&mut nes, 0xb000, 4)?;
+    buscpu::write(&mut nes, 0xc000, 5)?;
+    buscpu::write(&mut nes, 0xd000, 6)?;
+    buscpu::write(&mut nes, 0xe000, 7)?;
+    assert_eq!(busppu::read(&mut nes, 0x0000)?, 5);
+    assert_eq!(busppu::read(&mut nes, 0x1000)?, 7);
+
+    // unlike MMC2, any row of tile $FD/$FE in the low table trips the latch
+    busppu::read(&mut nes, 0x0fdf)?;
+    assert_eq!(busppu::read(&mut nes, 0x0000)?, 4);
+    busppu::read(&mut nes, 0x0fe9)?;
+    assert_eq!(busppu::read(&mut nes, 0x0000)?, 5);
+
+    busppu::read(&mut nes, 0x1fda)?;
+    assert_eq!(busppu::read(&mut nes, 0x1000)?, 6);
+    assert_eq!(
+        cartridge::chr_layout(&nes)?,
+        [
+            Bank::new(0x0000, 0x1000, Memory::ChrRom, 5 * 0x1000),
+            Bank::new(0x1000, 0x1000, Memory::ChrRom, 6 * 0x1000),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn mmc4_maps_empty_prg() -> Result<()> {
+    // a bad header, the mapping can still be asked for
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&ines(10, 0, 1, 0).bytes())?;
+    assert_eq!(cartridge::prg_offset(&nes, 0xc000), Some(0));
+    Ok(())
+}
+
+#[test]
+fn mmc4_wraps_banks_to_small_roms() -> Result<()> {
+    // 32 KB PRG and 16 KB CHR, fewer banks than the registers can select
+    let mut nes = ines(10, 2, 2, 0)
+        .prg_banks_of(0x4000)
+        .chr_banks_of(0x1000)
+        .nes()?;
+    buscpu::write(&mut nes, 0xa000, 0x0f)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 1);
+    buscpu::write(&mut nes, 0xd000, 0x1d)?;
+    buscpu::write(&mut nes, 0xe000, 0x1f)?;
+    assert_eq!(busppu::read(&mut nes, 0x1000)?, 3);
+    busppu::read(&mut nes, 0x1fd8)?;
+    assert_eq!(busppu::read(&mut nes, 0x1000)?, 1);
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::busppu;
+use crate::cartridge;
+use crate::tests::ines;
+
+#[test]
+fn mmc5_prg_banking_and_ram() -> Result<()> {
+    let mut nes = ines(5, 8, 0, 0).prg_banks_of(0x2000).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "MMC5");
+
+    // powers up in mode 3 with the last page at $E000
+    assert_eq!(buscpu::read(&mut nes, 0xe000)?, 15);
+    buscpu::write(&mut nes, 0x5114, 0x82)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 2);
+
+    // RAM mapped into the ROM area only takes writes once unlocked
+    buscpu::write(&mut nes, 0x5115, 0x01)?;
+    buscpu::write(&mut nes, 0xa000, 0x55)?;
+    assert_eq!(buscpu::read(&mut nes, 0xa000)?, 0x00);
+    buscpu::write(&mut nes, 0x5102, 0x02)?;
+    buscpu::write(&mut nes, 0x5103, 0x01)?;
+    buscpu::write(&mut nes, 0xa000, 0x55)?;
+    assert_eq!(buscpu::read(&mut nes, 0xa000)?, 0x55);
+    buscpu::write(&mut nes, 0x5113, 0x01)?;
+    assert_eq!(buscpu::read(&mut nes, 0x6000)?, 0x55);
+
+    // 32 KB mode ignores the low two bits of the bank
+    buscpu::write(&mut nes, 0x5100, 0x00)?;
+    buscpu::write(&mut nes, 0x5117, 0x05)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 4);
+    assert_eq!(buscpu::read(&mut nes, 0xffff)?, 7);
+    assert_eq!(cartridge::prg_offset(&nes, 0x8000), Some(4 * 0x2000));
+
+    buscpu::write(&mut nes, 0x5205, 12)?;
+    buscpu::write(&mut nes, 0x5206, 34)?;
+    assert_eq!(buscpu::read(&mut nes, 0x5205)?, (408 & 0xff) as u8);
+    assert_eq!(buscpu::read(&mut nes, 0x5206)?, (408 >> 8) as u8);
+    Ok(())
+}
+
+#[test]
+fn mmc5_nametables_go_through_the_mapper() -> Result<()> {
+    let mut nes = ines(5, 8, 0, 0).prg_banks_of(0x2000).nes()?;
+
+    // $2000 fill mode, $2400 ExRAM, $2800 CIRAM page 1
+    buscpu::write(&mut nes, 0x5105, 0b01_10_11)?;
+    buscpu::write(&mut nes, 0x5106, 0x42)?;
+    buscpu::write(&mut nes, 0x5107, 0x02)?;
+    assert_eq!(busppu::read(&mut nes, 0x2010)?, 0x42);
+    assert_eq!(busppu::read(&mut nes, 0x23c1)?, 0xaa);
+
+    buscpu::write(&mut nes, 0x5c05, 0x77)?;
+    assert_eq!(busppu::read(&mut nes, 0x2405)?, 0x77);
+
+    busppu::write(&mut nes, 0x2803, 0x99)?;
+    assert_eq!(nes.bus_ppu.vram[0x403], 0x99);
+    Ok(())
+}
+
+#[test]
+fn mmc5_scanline_irq() -> Result<()> {
+    let mut nes = ines(5, 8, 0, 0).prg_banks_of(0x2000).nes()?;
+    buscpu::write(&mut nes, 0x5203, 10)?;
+    buscpu::write(&mut nes, 0x5204, 0x80)?;
+    buscpu::write(&mut nes, 0x2001, 0x18)?;
+
+    while !cartridge::irq(&nes)? {
+        nes.clock()?;
+    }
+    assert_eq!(nes.ppu.scan_line, 10);
+    assert_eq!(buscpu::read(&mut nes, 0x5204)? & 0xc0, 0xc0);
+    assert!(!cartridge::irq(&nes)?);
+    Ok(())
+}
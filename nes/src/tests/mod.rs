@@ -0,0 +1,102 @@
+use anyhow::Result;
+
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::Nes;
+
+mod archive;
+mod axrom;
+mod bnrom;
+mod bus_conflicts;
+mod camerica;
+mod cdl;
+mod cheats;
+mod colordreams;
+mod cprom;
+mod cpu;
+mod fds;
+mod fme7;
+mod gamedb;
+mod layout;
+mod mmc1;
+mod mmc2;
+mod mmc4;
+mod mmc5;
+mod n163;
+mod namco108;
+mod nametables;
+mod nsf;
+mod patch;
+mod ppu;
+mod registry;
+mod unif;
+mod vrc4;
+mod vrc6;
+
+const NES_TAG: &[u8; 4] = b"NES\x1a";
+
+// iNES image for a test, the fields can be edited before building it
+pub struct Rom {
+    pub header: [u8; 16],
+    pub prg: Vec<u8>,
+    pub chr: Vec<u8>,
+}
+
+/*
+    iNES image with 16 KB PRG banks and 8 KB CHR banks each filled with their own bank number,
+    CHR RAM when chr_banks is 0. flags is the low nibble of header byte 6 (mirroring, battery,
+    four-screen).
+*/
+pub fn ines(mapper: u8, prg_banks: u8, chr_banks: u8, flags: u8) -> Rom {
+    let mut header = [0; 16];
+    header[0..4].copy_from_slice(NES_TAG);
+    header[4] = prg_banks;
+    header[5] = chr_banks;
+    header[6] = mapper << 4 | flags & 0x0f;
+    header[7] = mapper & 0xf0;
+    Rom {
+        header,
+        prg: numbered(prg_banks as usize * 0x4000, 0x4000),
+        chr: numbered(chr_banks as usize * 0x2000, 0x2000),
+    }
+}
+
+impl Rom {
+    // Renumber PRG ROM in banks of the given size
+    pub fn prg_banks_of(mut self, size: usize) -> Self {
+        self.prg = numbered(self.prg.len(), size);
+        self
+    }
+
+    // Renumber CHR ROM in banks of the given size
+    pub fn chr_banks_of(mut self, size: usize) -> Self {
+        self.chr = numbered(self.chr.len(), size);
+        self
+    }
+
+    // Switch to a NES 2.0 header with the given submapper
+    pub fn nes2(mut self, submapper: u8) -> Self {
+        self.header[7] |= 0x08;
+        self.header[8] = submapper << 4;
+        self
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut rom = self.header.to_vec();
+        rom.extend(&self.prg);
+        rom.extend(&self.chr);
+        rom
+    }
+
+    // Load into a new console and reset it
+    pub fn nes(&self) -> Result<Nes<NoScreen, NoAudio>> {
+        let mut nes = Nes::new(NoScreen, NoAudio);
+        nes.load(&self.bytes())?;
+        nes.reset()?;
+        Ok(nes)
+    }
+}
+
+fn numbered(len: usize, bank_size: usize) -> Vec<u8> {
+    (0..len).map(|i| (i / bank_size) as u8).collect()
+}
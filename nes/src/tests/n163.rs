@@ -0,0 +1,82 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::busppu;
+use crate::cartridge;
+use crate::tests::ines;
+
+#[test]
+fn n163_nametables_from_chr_rom_and_ciram() -> Result<()> {
+    let mut nes = ines(19, 8, 2, 0)
+        .prg_banks_of(0x2000)
+        .chr_banks_of(0x400)
+        .nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "N163");
+
+    buscpu::write(&mut nes, 0xe000, 2)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 2);
+    assert_eq!(buscpu::read(&mut nes, 0xe000)?, 15);
+
+    // $2000 from CHR ROM bank 5, $2400 from CIRAM page 1
+    buscpu::write(&mut nes, 0xc000, 5)?;
+    buscpu::write(&mut nes, 0xc800, 0xe1)?;
+    assert_eq!(busppu::read(&mut nes, 0x2010)?, 5);
+    busppu::write(&mut nes, 0x2410, 0x42)?;
+    assert_eq!(nes.bus_ppu.vram[0x410], 0x42);
+
+    // pattern table slot mapped to CIRAM unless disabled through $E800
+    buscpu::write(&mut nes, 0x8000, 0xe1)?;
+    assert_eq!(busppu::read(&mut nes, 0x0010)?, 0x42);
+    buscpu::write(&mut nes, 0xe800, 0x40)?;
+    assert_eq!(busppu::read(&mut nes, 0x0010)?, 0xe1 % 16);
+    Ok(())
+}
+
+#[test]
+fn n163_irq_counts_up_to_7fff() -> Result<()> {
+    let mut nes = ines(19, 8, 2, 0)
+        .prg_banks_of(0x2000)
+        .chr_banks_of(0x400)
+        .nes()?;
+    buscpu::write(&mut nes, 0x5000, 0xfd)?;
+    buscpu::write(&mut nes, 0x5800, 0xff)?;
+
+    cartridge::clock(&mut nes)?;
+    assert!(!cartridge::irq(&nes)?);
+    cartridge::clock(&mut nes)?;
+    assert!(cartridge::irq(&nes)?);
+    assert_eq!(buscpu::read(&mut nes, 0x5000)?, 0xff);
+
+    buscpu::write(&mut nes, 0x5800, 0x00)?;
+    assert!(!cartridge::irq(&nes)?);
+    Ok(())
+}
+
+#[test]
+fn n163_sound_ram_and_channel_output() -> Result<()> {
+    let mut nes = ines(19, 8, 2, 0)
+        .prg_banks_of(0x2000)
+        .chr_banks_of(0x400)
+        .nes()?;
+    // auto-incremented writes from $00: a wave of 15s
+    buscpu::write(&mut nes, 0xf800, 0x80)?;
+    for _ in 0..4 {
+        buscpu::write(&mut nes, 0x4800, 0xff)?;
+    }
+    // channel 8 registers at $78: 8 sample wave at $00, full volume, one channel
+    buscpu::write(&mut nes, 0xf800, 0xfc)?;
+    buscpu::write(&mut nes, 0x4800, 0xf8)?;
+    buscpu::write(&mut nes, 0x4800, 0x00)?;
+    buscpu::write(&mut nes, 0x4800, 0x00)?;
+    buscpu::write(&mut nes, 0x4800, 0x0f)?;
+
+    buscpu::write(&mut nes, 0xf800, 0x81)?;
+    assert_eq!(buscpu::read(&mut nes, 0x4800)?, 0xff);
+    assert_eq!(buscpu::read(&mut nes, 0x4800)?, 0xff);
+
+    for _ in 0..15 {
+        cartridge::clock(&mut nes)?;
+    }
+    assert_eq!(cartridge::audio_output(&nes)?, Some(0.5));
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::tests::ines;
+
+#[test]
+fn namco108_switches_prg_banks() -> Result<()> {
+    let mut nes = ines(206, 8, 8, 0).prg_banks_of(0x2000).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "Namco 108");
+
+    buscpu::write(&mut nes, 0x8000, 6)?;
+    buscpu::write(&mut nes, 0x8001, 3)?;
+    buscpu::write(&mut nes, 0x8000, 7)?;
+    buscpu::write(&mut nes, 0x8001, 9)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+    assert_eq!(buscpu::read(&mut nes, 0xa000)?, 9);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 14);
+    assert_eq!(buscpu::read(&mut nes, 0xe000)?, 15);
+
+    // only $8000-$9FFF is decoded
+    buscpu::write(&mut nes, 0xa001, 5)?;
+    assert_eq!(buscpu::read(&mut nes, 0xa000)?, 9);
+    Ok(())
+}
+
+#[test]
+fn namco108_switches_chr_banks() -> Result<()> {
+    let mut nes = ines(206, 8, 8, 0).prg_banks_of(0x2000).nes()?;
+
+    // 2 KB banks drop the low bit
+    buscpu::write(&mut nes, 0x8000, 0)?;
+    buscpu::write(&mut nes, 0x8001, 5)?;
+    buscpu::write(&mut nes, 0x8000, 5)?;
+    buscpu::write(&mut nes, 0x8001, 33)?;
+    assert_eq!(cartridge::chr_offset(&nes, 0x0000), Some(4 * 0x400));
+    assert_eq!(cartridge::chr_offset(&nes, 0x0401), Some(5 * 0x400 + 1));
+    assert_eq!(cartridge::chr_offset(&nes, 0x1c00), Some(33 * 0x400));
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+
+use crate::busppu;
+use crate::cartridge::Mirroring;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::tests::ines;
+use crate::Nes;
+
+// 32 KB NROM image with CHR RAM and the given header byte 6 flags
+fn nrom_rom(flags6: u8) -> Vec<u8> {
+    ines(0, 2, 0, flags6).bytes()
+}
+
+fn fill_nametables(nes: &mut Nes<NoScreen, NoAudio>) -> Result<()> {
+    for (slot, addr) in [0x2000, 0x2400, 0x2800, 0x2c00].into_iter().enumerate() {
+        busppu::write(nes, addr + 5, slot as u8 + 1)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn vertical_mirroring_shares_two_pages() -> Result<()> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&nrom_rom(0x01))?;
+    fill_nametables(&mut nes)?;
+
+    assert_eq!(busppu::read(&mut nes, 0x2005)?, 3);
+    assert_eq!(busppu::read(&mut nes, 0x2405)?, 4);
+    assert_eq!(nes.bus_ppu.vram[0x005], 3);
+    assert_eq!(nes.bus_ppu.vram[0x405], 4);
+    Ok(())
+}
+
+#[test]
+fn four_screen_uses_cartridge_vram() -> Result<()> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    // four-screen bit wins over the mirroring bit
+    nes.load(&nrom_rom(0x09))?;
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::FourScreen));
+    fill_nametables(&mut nes)?;
+
+    for (slot, addr) in [0x2000, 0x2400, 0x2800, 0x2c00].into_iter().enumerate() {
+        assert_eq!(busppu::read(&mut nes, addr + 5)?, slot as u8 + 1);
+    }
+    assert_eq!(nes.bus_ppu.vram[0x405], 2);
+    assert_eq!(nes.cartridge.vram[0x405], 4);
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::nsf;
+use crate::Nes;
+
+// INIT at $8000 stores the track in $00, PLAY at $8003 counts its calls in $01
+const PROGRAM: [u8; 6] = [0x85, 0x00, 0x60, 0xe6, 0x01, 0x60];
+
+fn nsf_file(chips: u8, banks: [u8; 8], data: &[u8]) -> Vec<u8> {
+    let mut file = b"NESM\x1a\x01".to_vec();
+    // 5 tracks starting with the second
+    file.extend([5, 2]);
+    file.extend([0x00, 0x80, 0x00, 0x80, 0x03, 0x80]);
+    let mut title = b"Test Tune".to_vec();
+    title.resize(32, 0);
+    file.extend(title);
+    file.extend([0; 64]);
+    // 60 Hz
+    file.extend(16639u16.to_le_bytes());
+    file.extend(banks);
+    file.extend([0x20, 0x4e, 0x00, chips, 0, 0, 0, 0]);
+    file.extend(data);
+    file
+}
+
+fn run_frames(nes: &mut Nes<NoScreen, NoAudio>, frames: u32) -> Result<()> {
+    for _ in 0..frames * 29781 {
+        nes.clock()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn nsf_header_is_parsed() -> Result<()> {
+    let nsf = nsf::parse(&nsf_file(nsf::CHIP_VRC6, [0; 8], &PROGRAM))?;
+    assert_eq!(nsf.title, "Test Tune");
+    assert_eq!(nsf.tracks, 5);
+    assert_eq!(nsf.start_track, 1);
+    assert_eq!(nsf.play_addr, 0x8003);
+    assert_eq!(nsf.chips, nsf::CHIP_VRC6);
+    assert!(nsf.banks.is_none());
+    assert_eq!(nsf.play_period(), 29780);
+    assert_eq!(nsf.data, PROGRAM);
+    Ok(())
+}
+
+#[test]
+fn nsfe_chunks_are_parsed() -> Result<()> {
+    let chunk = |id: &[u8; 4], data: &[u8]| {
+        let mut chunk = (data.len() as u32).to_le_bytes().to_vec();
+        chunk.extend(id);
+        chunk.extend(data);
+        chunk
+    };
+    let mut file = b"NSFE".to_vec();
+    file.extend(chunk(
+        b"INFO",
+        &[0x00, 0x80, 0x00, 0x80, 0x03, 0x80, 0x00, 0x00, 2, 1],
+    ));
+    file.extend(chunk(b"DATA", &PROGRAM));
+    file.extend(chunk(b"auth", b"Title\0Artist\0Copyright\0Ripper\0"));
+    file.extend(chunk(b"tlbl", b"Intro\0Theme\0"));
+    file.extend(chunk(b"time", &[0x10, 0x27, 0, 0, 0xff, 0xff, 0xff, 0xff]));
+    file.extend(chunk(b"NEND", &[]));
+
+    let nsf = nsf::parse(&file)?;
+    assert_eq!(nsf.tracks, 2);
+    assert_eq!(nsf.start_track, 1);
+    assert_eq!(nsf.artist, "Artist");
+    assert_eq!(nsf.track_title(1), Some("Theme"));
+    assert_eq!(nsf.track_time(0), Some(10000));
+    assert_eq!(nsf.track_time(1), None);
+    assert_eq!(nsf.data, PROGRAM);
+
+    // unknown chunks that must be understood are refused
+    let mut file = b"NSFE".to_vec();
+    file.extend(chunk(b"XTRA", &[]));
+    assert!(nsf::parse(&file).is_err());
+    Ok(())
+}
+
+#[test]
+fn nsf_init_and_play_are_called() -> Result<()> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&nsf_file(0, [0; 8], &PROGRAM))?;
+    nes.reset()?;
+    run_frames(&mut nes, 3)?;
+    assert_eq!(nes.bus_cpu.ram[0x00], 1);
+    assert!((2..=3).contains(&nes.bus_cpu.ram[0x01]));
+
+    nes.play_track(4)?;
+    assert_eq!(nes.bus_cpu.ram[0x01], 0);
+    run_frames(&mut nes, 1)?;
+    assert_eq!(nes.bus_cpu.ram[0x00], 4);
+    assert!(nes.play_track(5).is_err());
+    Ok(())
+}
+
+#[test]
+fn nsf_banks_are_switched() -> Result<()> {
+    let mut data = vec![0; 0x3000];
+    data[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+    data[0x1000] = 0x11;
+    data[0x2000] = 0x22;
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&nsf_file(0, [0, 1, 2, 0, 0, 0, 0, 0], &data))?;
+    nes.reset()?;
+    assert_eq!(buscpu::peek(&nes, 0x9000)?, 0x11);
+    assert_eq!(buscpu::peek(&nes, 0xa000)?, 0x22);
+
+    buscpu::write(&mut nes, 0x5ff9, 2)?;
+    assert_eq!(buscpu::peek(&nes, 0x9000)?, 0x22);
+    // a new track starts from the initial banks
+    nes.play_track(0)?;
+    assert_eq!(buscpu::peek(&nes, 0x9000)?, 0x11);
+    Ok(())
+}
+
+#[test]
+fn nsf_expansion_audio_follows_chip_flags() -> Result<()> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&nsf_file(0, [0; 8], &PROGRAM))?;
+    nes.reset()?;
+    // VRC6 pulse at full volume, ignored without the chip flag
+    buscpu::write(&mut nes, 0x9000, 0x8f)?;
+    buscpu::write(&mut nes, 0x9002, 0x80)?;
+    assert_eq!(cartridge::audio_output(&nes)?, Some(0.));
+
+    nes.load(&nsf_file(nsf::CHIP_VRC6, [0; 8], &PROGRAM))?;
+    nes.reset()?;
+    buscpu::write(&mut nes, 0x9000, 0x8f)?;
+    buscpu::write(&mut nes, 0x9002, 0x80)?;
+    assert!(cartridge::audio_output(&nes)?.is_some_and(|level| level > 0.));
+    Ok(())
+}
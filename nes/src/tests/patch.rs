@@ -0,0 +1,189 @@
+use anyhow::Result;
+
+use crate::cartridge::Mirroring;
+use crate::gamedb;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::patch;
+use crate::tests::ines;
+use crate::Nes;
+
+#[test]
+fn ips_round_trip() -> Result<()> {
+    let source: Vec<u8> = (0..=255).collect();
+    let mut target = source.clone();
+    target[3] = 0xaa;
+    target[100..110].fill(0);
+    target.extend([1, 2, 3]);
+
+    let ips = patch::create_ips(&source, &target)?;
+    assert_eq!(&ips[..5], b"PATCH");
+    assert_eq!(&ips[ips.len() - 3..], b"EOF");
+    assert_eq!(patch::apply_ips(&source, &ips)?, target);
+    Ok(())
+}
+
+#[test]
+fn ips_truncates_shorter_targets() -> Result<()> {
+    let source = vec![7; 64];
+    let target = vec![7; 32];
+    let ips = patch::create_ips(&source, &target)?;
+    assert_eq!(patch::apply_ips(&source, &ips)?, target);
+    Ok(())
+}
+
+#[test]
+fn ips_avoids_eof_offset() -> Result<()> {
+    let source = vec![0; 0x454f50];
+    let mut target = source.clone();
+    target[0x454f46] = 1;
+    let ips = patch::create_ips(&source, &target)?;
+    assert_eq!(&ips[5..8], [0x45, 0x4f, 0x45]);
+    assert_eq!(patch::apply_ips(&source, &ips)?, target);
+    Ok(())
+}
+
+#[test]
+fn ips_rle_records() -> Result<()> {
+    let mut ips = b"PATCH".to_vec();
+    ips.extend([0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x04, 0xff]);
+    ips.extend(b"EOF");
+    assert_eq!(
+        patch::apply_ips(&[0; 8], &ips)?,
+        [0, 0, 0xff, 0xff, 0xff, 0xff, 0, 0]
+    );
+    assert!(patch::apply_ips(&[0; 8], &ips[..10]).is_err());
+    Ok(())
+}
+
+fn number(mut value: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    loop {
+        let low = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(low | 0x80);
+            return bytes;
+        }
+        bytes.push(low);
+        value -= 1;
+    }
+}
+
+fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+    patch.extend(gamedb::crc32(source).to_le_bytes());
+    patch.extend(gamedb::crc32(target).to_le_bytes());
+    patch.extend(gamedb::crc32(&patch).to_le_bytes());
+    patch
+}
+
+#[test]
+fn ups_xors_hunks() -> Result<()> {
+    let source: Vec<u8> = (0..32).collect();
+    let mut target = source.clone();
+    target[2] = 0xff;
+    target[3] = 0xfe;
+    target.extend([9, 9]);
+
+    let mut ups = b"UPS1".to_vec();
+    ups.extend(number(source.len()));
+    ups.extend(number(target.len()));
+    ups.extend(number(2));
+    ups.extend([2 ^ 0xff, 3 ^ 0xfe, 0]);
+    // the terminating zero counts as an unchanged byte
+    ups.extend(number(27));
+    ups.extend([9, 9, 0]);
+    let ups = with_footer(ups, &source, &target);
+    assert_eq!(patch::apply(&source, &ups)?, target);
+
+    // the wrong ROM is refused
+    assert!(patch::apply_ups(&[0; 32], &ups).is_err());
+    // as is a corrupt patch
+    let mut corrupt = ups.clone();
+    corrupt[8] ^= 1;
+    assert!(patch::apply_ups(&source, &corrupt).is_err());
+
+    // hunks can't run past the end of the address space, nor targets grow without bound
+    let mut ups = b"UPS1".to_vec();
+    ups.extend(number(source.len()));
+    ups.extend(number(source.len()));
+    ups.extend(number(usize::MAX));
+    ups.extend([1, 0]);
+    let ups = with_footer(ups, &source, &source);
+    assert!(patch::apply_ups(&source, &ups).is_err());
+    let mut ups = b"UPS1".to_vec();
+    ups.extend(number(source.len()));
+    ups.extend(number(1 << 40));
+    let ups = with_footer(ups, &source, &source);
+    assert!(patch::apply_ups(&source, &ups).is_err());
+    Ok(())
+}
+
+#[test]
+fn bps_actions() -> Result<()> {
+    let source = b"abcdefgh".to_vec();
+    let target = b"abcXYXYXYcd".to_vec();
+
+    let mut bps = b"BPS1".to_vec();
+    bps.extend(number(source.len()));
+    bps.extend(number(target.len()));
+    bps.extend(number(4));
+    bps.extend(b"meta");
+    // source read "abc"
+    bps.extend(number((3 - 1) << 2));
+    // target read "XY"
+    bps.extend(number(((2 - 1) << 2) | 1));
+    bps.extend(b"XY");
+    // target copy of 4 overlapping bytes from offset 3
+    bps.extend(number(((4 - 1) << 2) | 3));
+    bps.extend(number(3 << 1));
+    // source copy "cd" from offset 2
+    bps.extend(number(((2 - 1) << 2) | 2));
+    bps.extend(number(2 << 1));
+    let bps = with_footer(bps, &source, &target);
+    assert_eq!(patch::apply(&source, &bps)?, target);
+
+    assert!(patch::apply_bps(b"abcdefgX", &bps).is_err());
+
+    // a huge target size is not allocated up front, and actions cannot run past it
+    let mut bps = b"BPS1".to_vec();
+    bps.extend(number(source.len()));
+    bps.extend(number(1 << 48));
+    bps.extend(number(0));
+    bps.extend(number(((2 - 1) << 2) | 1));
+    bps.extend(b"XY");
+    let bps = with_footer(bps, &source, &target);
+    assert!(patch::apply(&source, &bps).is_err());
+    let mut bps = b"BPS1".to_vec();
+    bps.extend(number(source.len()));
+    bps.extend(number(2));
+    bps.extend(number(0));
+    bps.extend(number(((2 - 1) << 2) | 1));
+    bps.extend(b"XY");
+    // target copy of far more bytes than the target holds
+    bps.extend(number((1 << 40) | 3));
+    bps.extend(number(0));
+    let bps = with_footer(bps, &source, &target);
+    assert!(patch::apply(&source, &bps).is_err());
+    assert!(patch::apply(&source, b"NOTAPATCH").is_err());
+    Ok(())
+}
+
+#[test]
+fn patches_are_applied_before_loading() -> Result<()> {
+    let mut rom = ines(0, 1, 0, 0);
+    rom.prg.fill(0xea);
+    let rom = rom.bytes();
+    // the patch sets the vertical mirroring bit
+    let mut ips = b"PATCH".to_vec();
+    ips.extend([0x00, 0x00, 0x06, 0x00, 0x01, 0x01]);
+    ips.extend([0x00, 0x00, 0x10, 0x00, 0x01, 0x42]);
+    ips.extend(b"EOF");
+
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load_patched(&rom, &[&ips])?;
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::Vertical));
+    assert_eq!(nes.cartridge.prgmem[0], 0x42);
+    assert!(nes.load_patched(&rom, &[b"junk"]).is_err());
+    Ok(())
+}
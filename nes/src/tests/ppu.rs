@@ -0,0 +1,84 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::ppu;
+use crate::tests::ines;
+use crate::Nes;
+
+const SPRITE_0_HIT: u8 = 0x40;
+
+// Clock the PPU until it is about to run the given cycle of a scanline
+fn run_to(nes: &mut Nes<NoScreen, NoAudio>, line: i16, cycle: u16) -> Result<()> {
+    while (nes.ppu.scan_line, nes.ppu.scan_cycle) != (line, cycle) {
+        ppu::clock(nes)?;
+    }
+    Ok(())
+}
+
+fn sprite_0_hit(nes: &Nes<NoScreen, NoAudio>) -> bool {
+    ppu::peek_ppu_reg(nes, 0x2002) & SPRITE_0_HIT != 0
+}
+
+// Opaque background everywhere and sprite 0 at the given position, both using tile 1
+fn sprite_0_nes(x: u8, y: u8) -> Result<Nes<NoScreen, NoAudio>> {
+    let mut nes = ines(0, 1, 0, 0).nes()?;
+    buscpu::write(&mut nes, 0x2006, 0x00)?;
+    buscpu::write(&mut nes, 0x2006, 0x10)?;
+    for _ in 0..8 {
+        buscpu::write(&mut nes, 0x2007, 0xff)?;
+    }
+    buscpu::write(&mut nes, 0x2006, 0x20)?;
+    buscpu::write(&mut nes, 0x2006, 0x00)?;
+    for _ in 0..0x3c0 {
+        buscpu::write(&mut nes, 0x2007, 0x01)?;
+    }
+    buscpu::write(&mut nes, 0x2003, 0x00)?;
+    for data in [y, 0x01, 0x00, x] {
+        buscpu::write(&mut nes, 0x2004, data)?;
+    }
+    buscpu::write(&mut nes, 0x2005, 0x00)?;
+    buscpu::write(&mut nes, 0x2005, 0x00)?;
+    // background and sprites, including the left column
+    buscpu::write(&mut nes, 0x2001, 0x1e)?;
+    run_to(&mut nes, -1, 0)?;
+    Ok(nes)
+}
+
+#[test]
+fn sprite_0_hit_is_set_on_the_overlapping_scanline() -> Result<()> {
+    let mut nes = sprite_0_nes(100, 30)?;
+    run_to(&mut nes, 29, 340)?;
+    assert!(!sprite_0_hit(&nes));
+    // the whole scanline is drawn once its visible cycles are over
+    run_to(&mut nes, 30, 257)?;
+    assert!(!sprite_0_hit(&nes));
+    ppu::clock(&mut nes)?;
+    assert!(sprite_0_hit(&nes));
+
+    // the flag stays set for the rest of the picture and is gone by the next frame
+    run_to(&mut nes, 239, 340)?;
+    assert!(sprite_0_hit(&nes));
+    run_to(&mut nes, -1, 0)?;
+    assert!(!sprite_0_hit(&nes));
+    Ok(())
+}
+
+#[test]
+fn sprite_0_hit_needs_visible_pixels() -> Result<()> {
+    // the rightmost column never hits
+    let mut nes = sprite_0_nes(255, 30)?;
+    run_to(&mut nes, 239, 340)?;
+    assert!(!sprite_0_hit(&nes));
+
+    // nor does the left column when it is clipped
+    let mut nes = sprite_0_nes(0, 30)?;
+    buscpu::write(&mut nes, 0x2001, 0x18)?;
+    run_to(&mut nes, 239, 340)?;
+    assert!(!sprite_0_hit(&nes));
+    buscpu::write(&mut nes, 0x2001, 0x1e)?;
+    run_to(&mut nes, 30, 258)?;
+    assert!(sprite_0_hit(&nes));
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::mappers::nrom::Nrom;
+use crate::mappers::MapperRegistry;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::tests::ines;
+use crate::Nes;
+
+fn rom(mapper: u8) -> Vec<u8> {
+    let mut rom = ines(mapper, 2, 0, 0);
+    rom.prg.fill(0xea);
+    rom.bytes()
+}
+
+#[test]
+fn unknown_mapper_is_rejected() {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    let err = nes.load(&rom(99)).unwrap_err();
+    assert_eq!(err.to_string(), "Mapper 99 not supported yet...");
+}
+
+#[test]
+fn registered_mapper_is_loaded() -> Result<()> {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.mappers
+        .register(99, None, "Custom NROM", |_| Rc::new(RefCell::new(Nrom)));
+    nes.load(&rom(99))?;
+    assert_eq!(nes.cartridge.mapper_id, 99);
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 0xea);
+    Ok(())
+}
+
+#[test]
+fn registry_lists_builtin_mappers() {
+    let list = MapperRegistry::<NoScreen, NoAudio>::default().list();
+    assert_eq!(list[0], (0, None, "NROM"));
+    assert!(list.contains(&(69, None, "FME-7")));
+    assert!(list.windows(2).all(|pair| pair[0] <= pair[1]));
+}
@@ -0,0 +1,107 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge::Mirroring;
+use crate::nesaudio::NoAudio;
+use crate::nesscreen::NoScreen;
+use crate::unif;
+use crate::Nes;
+
+fn unif_rom(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut rom = b"UNIF".to_vec();
+    rom.extend(7u32.to_le_bytes());
+    rom.resize(32, 0);
+    for (id, data) in chunks {
+        rom.extend(*id);
+        rom.extend((data.len() as u32).to_le_bytes());
+        rom.extend(data);
+    }
+    rom
+}
+
+// 128 KB of PRG split in 16 KB banks filled with their own number
+fn unrom_prg() -> Vec<u8> {
+    (0..8).flat_map(|bank| vec![bank; 0x4000]).collect()
+}
+
+#[test]
+fn unif_unrom_loads_and_switches_banks() -> Result<()> {
+    let rom = unif_rom(&[
+        (b"NAME", b"Test\0".to_vec()),
+        (b"MAPR", b"NES-UNROM\0".to_vec()),
+        (b"PRG0", unrom_prg()),
+        (b"MIRR", vec![1]),
+        (b"BATR", vec![1]),
+    ]);
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&rom)?;
+    nes.reset()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "UxROM");
+    assert_eq!(nes.cartridge.mapper_id, 2);
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::Vertical));
+    assert!(nes.cartridge.battery);
+    // no CHR chunks means CHR RAM
+    assert_eq!(nes.cartridge.chr_banks, 0);
+    assert_eq!(nes.cartridge.chrmem.len(), 0x2000);
+
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 7);
+    buscpu::write(&mut nes, 0x8000, 3)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+    Ok(())
+}
+
+#[test]
+fn unif_joins_chunks_in_numbered_order() -> Result<()> {
+    let rom = unif_rom(&[
+        (b"PRG1", vec![2; 0x4000]),
+        (b"CHR0", vec![3; 0x2000]),
+        (b"PRG0", vec![1; 0x4000]),
+        (b"MAPR", b"NES-NROM-256\0".to_vec()),
+    ]);
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    nes.load(&rom)?;
+    nes.reset()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "NROM");
+    assert_eq!(nes.cartridge.prg_banks, 2);
+    assert_eq!(nes.cartridge.chr_banks, 1);
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 1);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 2);
+    assert!(matches!(nes.cartridge.mirroring, Mirroring::Horizontal));
+    Ok(())
+}
+
+#[test]
+fn unif_board_names() {
+    assert_eq!(unif::board_mapper("NES-SLROM"), Some((1, 0)));
+    assert_eq!(unif::board_mapper("HVC-CNROM"), Some((3, 0)));
+    assert_eq!(unif::board_mapper("NES-AMROM"), Some((7, 2)));
+    assert_eq!(unif::board_mapper("AVE-NINA-01"), Some((34, 1)));
+    assert_eq!(unif::board_mapper("CAMERICA-BF9097"), Some((71, 1)));
+    assert_eq!(unif::board_mapper("UNL-DEROM"), Some((206, 0)));
+    assert_eq!(unif::board_mapper("NES-AOROM"), Some((7, 1)));
+    assert_eq!(unif::board_mapper("BMC-GNROM"), Some((66, 0)));
+    assert_eq!(unif::board_mapper("TENGEN-800008"), Some((3, 0)));
+    // MMC3 isn't supported, its boards are left to the unsupported board error
+    assert_eq!(unif::board_mapper("NES-TLROM"), None);
+    assert_eq!(unif::board_mapper("UNL-NOT-A-BOARD"), None);
+}
+
+#[test]
+fn unif_rejects_bad_dumps() {
+    let mut nes = Nes::new(NoScreen, NoAudio);
+    let unknown = unif_rom(&[
+        (b"MAPR", b"UNL-NOT-A-BOARD\0".to_vec()),
+        (b"PRG0", vec![0; 0x4000]),
+    ]);
+    assert!(nes.load(&unknown).is_err());
+
+    let no_board = unif_rom(&[(b"PRG0", vec![0; 0x4000])]);
+    assert!(nes.load(&no_board).is_err());
+
+    let mut truncated = unif_rom(&[
+        (b"MAPR", b"NES-NROM\0".to_vec()),
+        (b"PRG0", vec![0; 0x4000]),
+    ]);
+    truncated.truncate(truncated.len() - 1);
+    assert!(nes.load(&truncated).is_err());
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::tests::ines;
+
+#[test]
+fn vrc4e_prg_swap_mode() -> Result<()> {
+    // VRC4e selects registers with A2 and A3
+    let mut nes = ines(23, 4, 1, 0).prg_banks_of(0x2000).nes2(2).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "VRC4");
+
+    buscpu::write(&mut nes, 0x8000, 3)?;
+    buscpu::write(&mut nes, 0xa000, 5)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 3);
+    assert_eq!(buscpu::read(&mut nes, 0xa000)?, 5);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 6);
+    assert_eq!(buscpu::read(&mut nes, 0xe000)?, 7);
+
+    // $9002 on the chip, wired to $9008
+    buscpu::write(&mut nes, 0x9008, 0x02)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 6);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 3);
+    Ok(())
+}
+
+#[test]
+fn vrc4_cycle_irq() -> Result<()> {
+    let mut nes = ines(23, 4, 1, 0).prg_banks_of(0x2000).nes2(2).nes()?;
+    buscpu::write(&mut nes, 0xf000, 0x0a)?;
+    buscpu::write(&mut nes, 0xf004, 0x0f)?;
+    // enabled, cycle mode
+    buscpu::write(&mut nes, 0xf008, 0x06)?;
+
+    for _ in 0..5 {
+        cartridge::clock(&mut nes)?;
+    }
+    assert!(!cartridge::irq(&nes)?);
+    cartridge::clock(&mut nes)?;
+    assert!(cartridge::irq(&nes)?);
+
+    buscpu::write(&mut nes, 0xf00c, 0)?;
+    assert!(!cartridge::irq(&nes)?);
+    Ok(())
+}
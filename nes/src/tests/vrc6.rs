@@ -0,0 +1,42 @@
+use anyhow::Result;
+
+use crate::buscpu;
+use crate::cartridge;
+use crate::tests::ines;
+
+#[test]
+fn vrc6_prg_banking() -> Result<()> {
+    let mut nes = ines(26, 8, 1, 0).prg_banks_of(0x2000).nes()?;
+    assert_eq!(nes.cartridge.mapper.try_borrow()?.name(), "VRC6");
+
+    buscpu::write(&mut nes, 0x8000, 2)?;
+    buscpu::write(&mut nes, 0xc000, 9)?;
+    assert_eq!(buscpu::read(&mut nes, 0x8000)?, 4);
+    assert_eq!(buscpu::read(&mut nes, 0xa000)?, 5);
+    assert_eq!(buscpu::read(&mut nes, 0xc000)?, 9);
+    assert_eq!(buscpu::read(&mut nes, 0xe000)?, 15);
+    Ok(())
+}
+
+#[test]
+fn vrc6_pulse_and_sawtooth_audio() -> Result<()> {
+    let mut nes = ines(26, 8, 1, 0).prg_banks_of(0x2000).nes()?;
+    assert_eq!(cartridge::audio_output(&nes)?, Some(0.));
+
+    // digitized pulse 1 at full volume
+    buscpu::write(&mut nes, 0x9000, 0x8f)?;
+    // mapper 26 swaps A0/A1, so $9002 is written at $9001
+    buscpu::write(&mut nes, 0x9001, 0x80)?;
+    cartridge::clock(&mut nes)?;
+    let pulse = cartridge::audio_output(&nes)?.unwrap();
+    assert!(pulse > 0.);
+
+    // fastest sawtooth, the accumulator grows every other step
+    buscpu::write(&mut nes, 0xb000, 0x3f)?;
+    buscpu::write(&mut nes, 0xb001, 0x80)?;
+    for _ in 0..4 {
+        cartridge::clock(&mut nes)?;
+    }
+    assert!(cartridge::audio_output(&nes)?.unwrap() > pulse);
+    Ok(())
+}
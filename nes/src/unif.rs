@@ -0,0 +1,197 @@
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::cartridge::Mirroring;
+
+pub const UNIF_TAG: &[u8; 4] = b"UNIF";
+const HEADER_SIZE: usize = 32;
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+// Board names without their NES-/HVC-/UNL- style prefix, and the iNES mapper and submapper
+const BOARDS: &[(&str, u16, u8)] = &[
+    ("NROM", 0, 0),
+    ("NROM-128", 0, 0),
+    ("NROM-256", 0, 0),
+    ("RROM", 0, 0),
+    ("RROM-128", 0, 0),
+    ("SAROM", 1, 0),
+    ("SBROM", 1, 0),
+    ("SCROM", 1, 0),
+    ("SEROM", 1, 0),
+    ("SFROM", 1, 0),
+    ("SGROM", 1, 0),
+    ("SHROM", 1, 0),
+    ("SJROM", 1, 0),
+    ("SKROM", 1, 0),
+    ("SLROM", 1, 0),
+    ("SL1ROM", 1, 0),
+    ("SNROM", 1, 0),
+    ("SOROM", 1, 0),
+    ("SUROM", 1, 0),
+    ("SXROM", 1, 0),
+    ("UNROM", 2, 0),
+    ("UOROM", 2, 0),
+    ("CNROM", 3, 0),
+    ("EKROM", 5, 0),
+    ("ELROM", 5, 0),
+    ("ETROM", 5, 0),
+    ("EWROM", 5, 0),
+    ("AMROM", 7, 2),
+    ("ANROM", 7, 1),
+    ("AN1ROM", 7, 1),
+    ("AOROM", 7, 1),
+    ("PNROM", 9, 0),
+    ("PEEOROM", 9, 0),
+    ("FJROM", 10, 0),
+    ("FKROM", 10, 0),
+    ("CPROM", 13, 0),
+    ("BNROM", 34, 2),
+    ("NINA-001", 34, 1),
+    ("AVE-NINA-01", 34, 1),
+    ("GNROM", 66, 0),
+    ("MHROM", 66, 0),
+    ("BTR", 69, 0),
+    ("JLROM", 69, 0),
+    ("JSROM", 69, 0),
+    ("CAMERICA-BF9093", 71, 0),
+    ("CAMERICA-BF9097", 71, 1),
+    ("DEROM", 206, 0),
+    ("DE1ROM", 206, 0),
+    ("DRROM", 206, 0),
+    // unlicensed and publisher boards go by their full name
+    ("AVE-NINA-02", 34, 1),
+    ("CAMERICA-ALGN", 71, 0),
+    ("COLORDREAMS-74*377", 11, 0),
+    ("NAMCOT-163", 19, 0),
+    ("NAMCOT-3401", 206, 0),
+    ("NAMCOT-3405", 206, 0),
+    ("NAMCOT-3406", 206, 0),
+    ("NAMCOT-3407", 206, 0),
+    ("NAMCOT-3413", 206, 0),
+    ("NAMCOT-3414", 206, 0),
+    ("NAMCOT-3415", 206, 0),
+    ("NAMCOT-3416", 206, 0),
+    ("NAMCOT-3417", 206, 0),
+    ("NAMCOT-3451", 206, 0),
+    ("SUNSOFT-5B", 69, 0),
+    ("SUNSOFT-FME-7", 69, 0),
+    ("TENGEN-800002", 206, 0),
+    ("TENGEN-800004", 206, 0),
+    ("TENGEN-800008", 3, 0),
+    ("TENGEN-800030", 206, 0),
+];
+
+// UNIF dump split into the pieces the cartridge needs
+pub struct Unif {
+    pub board: String,
+    pub mapper_id: u16,
+    pub submapper: u8,
+    pub prgmem: Vec<u8>,
+    pub chrmem: Vec<u8>,
+    // None when the mapper controls mirroring
+    pub mirroring: Option<Mirroring>,
+    pub battery: bool,
+}
+
+pub fn parse(rom_bytes: &[u8]) -> Result<Unif> {
+    if rom_bytes.len() < HEADER_SIZE || &rom_bytes[0..4] != UNIF_TAG {
+        Err(anyhow!("Invalid UNIF ROM was provided: Missing UNIF tag"))?;
+    }
+
+    let mut board = None;
+    let mut prg: [Option<&[u8]>; 16] = [None; 16];
+    let mut chr: [Option<&[u8]>; 16] = [None; 16];
+    let mut mirroring = None;
+    let mut battery = false;
+
+    let mut pos = HEADER_SIZE;
+    while pos < rom_bytes.len() {
+        let header = rom_bytes
+            .get(pos..pos + 8)
+            .ok_or_else(|| anyhow!("Invalid UNIF ROM was provided: Truncated chunk header"))?;
+        let id = &header[0..4];
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let data = rom_bytes
+            .get(pos + 8..pos + 8 + length)
+            .ok_or_else(|| anyhow!("Invalid UNIF ROM was provided: Truncated chunk"))?;
+        pos += 8 + length;
+
+        match id {
+            b"MAPR" => {
+                let name = data.split(|&c| c == 0).next().unwrap_or_default();
+                board = Some(String::from_utf8_lossy(name).trim().to_string());
+            }
+            b"MIRR" => {
+                mirroring = match data.first() {
+                    Some(0) => Some(Mirroring::Horizontal),
+                    Some(1) => Some(Mirroring::Vertical),
+                    Some(2) => Some(Mirroring::OneScreenNT0),
+                    Some(3) => Some(Mirroring::OneScreenNT1),
+                    Some(4) => Some(Mirroring::FourScreen),
+                    _ => None,
+                };
+            }
+            b"BATR" => battery = data.first().is_some_and(|&data| data != 0),
+            _ if id.starts_with(b"PRG") || id.starts_with(b"CHR") => {
+                let index = match HEX_DIGITS.iter().position(|&digit| digit == id[3]) {
+                    Some(index) => index,
+                    None => {
+                        log::warn!("Skipping UNIF chunk {}", String::from_utf8_lossy(id));
+                        continue;
+                    }
+                };
+                if id.starts_with(b"PRG") {
+                    prg[index] = Some(data);
+                } else {
+                    chr[index] = Some(data);
+                }
+            }
+            // names, dumper info, CRCs and the like
+            _ => log::debug!("Skipping UNIF chunk {}", String::from_utf8_lossy(id)),
+        }
+    }
+
+    let board =
+        board.ok_or_else(|| anyhow!("Invalid UNIF ROM was provided: Missing MAPR chunk"))?;
+    let (mapper_id, submapper) =
+        board_mapper(&board).ok_or_else(|| anyhow!("UNIF board {} not supported yet", board))?;
+    // chunks are numbered, so they are joined in that order whatever order they came in
+    let prgmem: Vec<u8> = prg
+        .iter()
+        .flatten()
+        .flat_map(|data| data.iter())
+        .copied()
+        .collect();
+    let chrmem: Vec<u8> = chr
+        .iter()
+        .flatten()
+        .flat_map(|data| data.iter())
+        .copied()
+        .collect();
+    if prgmem.is_empty() {
+        Err(anyhow!("Invalid UNIF ROM was provided: Missing PRG chunks"))?;
+    }
+
+    Ok(Unif {
+        board,
+        mapper_id,
+        submapper,
+        prgmem,
+        chrmem,
+        mirroring,
+        battery,
+    })
+}
+
+pub fn board_mapper(board: &str) -> Option<(u16, u8)> {
+    let board = board.to_ascii_uppercase();
+    // drop prefixes like NES-, HVC-, UNL- or BMC-, the rest names the board type
+    let name = match board.split_once('-') {
+        Some((prefix, name)) if prefix.len() == 3 => name,
+        _ => &board,
+    };
+    BOARDS
+        .iter()
+        .find(|(known, _, _)| *known == name || *known == board)
+        .map(|&(_, mapper_id, submapper)| (mapper_id, submapper))
+}
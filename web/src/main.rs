@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use ::nes::cheats::GameGenie;
 use ::nes::joypad::Button;
 use anyhow::anyhow;
 use anyhow::Result;
@@ -22,6 +23,8 @@ struct CNes {
     nes_channel: mpsc::Sender<NesMessage>,
     load_signal: Option<oneshot::Sender<()>>,
     file_reader: Option<FileReader>,
+    // Game Genie codes and whether they are enabled, as sent to the NES
+    genie_codes: Vec<(String, bool)>,
 }
 
 pub enum NesMessage {
@@ -29,6 +32,9 @@ pub enum NesMessage {
     Reset,
     ButtonPress(Button),
     ButtonRelease(Button),
+    GenieAdd(String),
+    GenieEnable(usize, bool),
+    GenieRemove(usize),
     UtilsLoadingFile(Blob),
 }
 
@@ -58,6 +64,9 @@ async fn nes_thread(
                 Reset => nes.reset()?,
                 ButtonPress(btn) => nes.press_btn(btn)?,
                 ButtonRelease(btn) => nes.release_btn(btn)?,
+                GenieAdd(code) => nes.add_genie(&code)?,
+                GenieEnable(idx, enabled) => nes.enable_genie(idx, enabled)?,
+                GenieRemove(idx) => nes.remove_genie(idx)?,
                 _ => unreachable!(),
             }
         } else {
@@ -88,6 +97,7 @@ impl Component for CNes {
             nes_channel: tx,
             load_signal: Some(load_signal_tx),
             file_reader: None,
+            genie_codes: vec![],
         }
     }
 
@@ -111,6 +121,33 @@ impl Component for CNes {
                 self.file_reader = Some(file_reader);
             }
             _ => {
+                // keep the listed codes in step with the NES, refusing invalid codes here
+                let rerender = match &msg {
+                    NesMessage::GenieAdd(code) => match GameGenie::decode(code) {
+                        Ok(genie) if self.genie_codes.iter().all(|(c, _)| *c != genie.code) => {
+                            self.genie_codes.push((genie.code, true));
+                            true
+                        }
+                        Ok(genie) => {
+                            gloo_dialogs::alert(&format!("{} is already added", genie.code));
+                            return false;
+                        }
+                        Err(err) => {
+                            gloo_dialogs::alert(&err.to_string());
+                            return false;
+                        }
+                    },
+                    NesMessage::GenieEnable(idx, enabled) => {
+                        self.genie_codes[*idx].1 = *enabled;
+                        true
+                    }
+                    NesMessage::GenieRemove(idx) => {
+                        self.genie_codes.remove(*idx);
+                        true
+                    }
+                    _ => false,
+                };
+
                 let mut nes_channel = self.nes_channel.clone();
 
                 spawn_local(async move {
@@ -119,6 +156,7 @@ impl Component for CNes {
                         log::error!("NES channel communication error: {}", err);
                     }
                 });
+                return rerender;
             }
         }
         false
@@ -162,6 +200,35 @@ impl Component for CNes {
             Some(NesMessage::UtilsLoadingFile(file.into()))
         });
 
+        // game genie callbacks
+        let add_genie = link.batch_callback(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let code = input.value();
+            input.set_value("");
+            (!code.trim().is_empty()).then(|| NesMessage::GenieAdd(code))
+        });
+        let genie_codes = self
+            .genie_codes
+            .iter()
+            .enumerate()
+            .map(|(idx, (code, enabled))| {
+                let enabled = *enabled;
+                html! {
+                    <li>
+                        <label>
+                            <input type="checkbox"
+                                checked={enabled}
+                                onchange={link.callback(move |_| NesMessage::GenieEnable(idx, !enabled))}/>
+                            { code.clone() }
+                        </label>
+                        <button onclick={link.callback(move |_| NesMessage::GenieRemove(idx))}>
+                            { "X" }
+                        </button>
+                    </li>
+                }
+            })
+            .collect::<Html>();
+
         html! {
             <div class="nes" tabindex="0" {onkeydown} {onkeyup}>
                 <input class="nes-rom-file"
@@ -170,6 +237,12 @@ impl Component for CNes {
                     onchange={load_rom}/>
                 <canvas id="nes-canvas" width=256 height=240>
                 </canvas>
+                <div class="nes-genie">
+                    <input type="text"
+                        placeholder="Game Genie code"
+                        onchange={add_genie}/>
+                    <ul>{ genie_codes }</ul>
+                </div>
                 // NES Buttons
                 <div class="nes-joypad">
                     <div class="nes-joypad-left">
@@ -3,6 +3,7 @@ use std::rc::Rc;
 
 use anyhow::anyhow;
 use anyhow::Result;
+use nes::apu::expansion::SAMPLE_RATE;
 use nes::apu::AudioChannel;
 use wasm_bindgen::JsValue;
 use web_sys::AudioContext;
@@ -22,6 +23,9 @@ pub struct NesAudio {
     p1_gain: GainNode,
     p2_gain: GainNode,
     tri_gain: GainNode,
+    output_gain: GainNode,
+    // when the last queued batch of expansion audio ends
+    samples_end: f64,
 }
 
 impl NesAudio {
@@ -73,6 +77,8 @@ impl NesAudio {
             p1_gain,
             p2_gain,
             tri_gain,
+            output_gain,
+            samples_end: 0.,
         })
     }
 
@@ -150,6 +156,11 @@ impl ::nes::nesaudio::NesAudio for NesAudio {
         }
         Ok(())
     }
+
+    fn play_samples(&mut self, samples: &[f32]) -> Result<()> {
+        self.queue_samples(samples)
+            .map_err(|err| anyhow!("Failed to play expansion audio: {:?}", err))
+    }
 }
 
 fn set_duty_cycle(cx: &AudioContext, pulse: &OscillatorNode, dc: f32) -> Result<()> {
@@ -178,6 +189,23 @@ fn set_duty_cycle(cx: &AudioContext, pulse: &OscillatorNode, dc: f32) -> Result<
     Ok(())
 }
 
+impl NesAudio {
+    fn queue_samples(&mut self, samples: &[f32]) -> Result<(), JsValue> {
+        let buffer = self
+            .context
+            .create_buffer(1, samples.len() as u32, SAMPLE_RATE as f32)?;
+        buffer.copy_to_channel(&mut samples.to_vec(), 0)?;
+        let source = self.context.create_buffer_source()?;
+        source.set_buffer(Some(&buffer));
+        source.connect_with_audio_node(&self.output_gain)?;
+        // play right after the previous batch, or now if we fell behind
+        let start = self.samples_end.max(self.context.current_time());
+        source.start_with_when(start)?;
+        self.samples_end = start + samples.len() as f64 / SAMPLE_RATE as f64;
+        Ok(())
+    }
+}
+
 impl Drop for NesAudio {
     fn drop(&mut self) {
         let _ = self.context.close();
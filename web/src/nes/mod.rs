@@ -1,7 +1,10 @@
 use std::rc::Rc;
 
 use anyhow::anyhow;
+use anyhow::Context;
 use anyhow::Result;
+use nes::archive;
+use nes::cheats;
 use nes::joypad::Button;
 use web_sys::AudioContext;
 
@@ -37,9 +40,36 @@ impl Nes {
     }
 
     pub fn load(&mut self, rom_bytes: &[u8]) -> Result<()> {
+        if archive::is_archive(rom_bytes) {
+            // the page cannot ask which ROM to play yet, so take the first one
+            let names = archive::list(rom_bytes)?;
+            let name = names.first().context("The archive holds no ROM")?;
+            if names.len() > 1 {
+                log::warn!(
+                    "Playing {} from the archive, it also holds {:?}",
+                    name,
+                    &names[1..]
+                );
+            }
+            return self
+                .nes
+                .load(&archive::extract(rom_bytes, Some(name))?.data);
+        }
         self.nes.load(rom_bytes)
     }
 
+    pub fn add_genie(&mut self, code: &str) -> Result<()> {
+        cheats::add_game_genie(&mut self.nes, code).map(|_| ())
+    }
+
+    pub fn enable_genie(&mut self, index: usize, enabled: bool) -> Result<()> {
+        cheats::enable_game_genie(&mut self.nes, index, enabled)
+    }
+
+    pub fn remove_genie(&mut self, index: usize) -> Result<()> {
+        cheats::remove_game_genie(&mut self.nes, index).map(|_| ())
+    }
+
     pub fn press_btn(&mut self, btn: Button) -> Result<()> {
         self.nes.press_btn(btn, true)
     }